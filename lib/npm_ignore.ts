@@ -7,7 +7,7 @@ import { isDeclarationFilePath, toDtsFilePath, toJsFilePath } from "./utils.ts";
 export function getNpmIgnoreText(options: {
   sourceMap?: SourceMapOptions;
   inlineSources?: boolean;
-  testFiles: OutputFile[];
+  testFiles: Pick<OutputFile, "filePath">[];
   declaration: "separate" | "inline" | false;
   declarationMap: boolean | undefined;
   includeScriptModule: boolean | undefined;
@@ -1,6 +1,7 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
 import { assertEquals } from "@std/assert";
+import type { PackageManifest, TransformOutput } from "../transform.ts";
 import { getPackageJson, type GetPackageJsonOptions } from "./package_json.ts";
 
 const versions = {
@@ -10,42 +11,37 @@ const versions = {
 
 Deno.test("single entrypoint", () => {
   const props: GetPackageJsonOptions = {
-    transformOutput: {
-      main: {
-        files: [],
-        dependencies: [{
-          name: "dep",
-          version: "^1.0.0",
-        }],
-        entryPoints: ["mod.ts"],
-      },
-      test: {
-        entryPoints: [],
-        files: [],
-        dependencies: [{
-          name: "test-dep",
-          version: "0.1.0",
-        }, {
-          name: "@deno/shim-deno",
-          version: "~0.1.0",
-        }],
-      },
-      warnings: [],
-      typesDependencies: [],
-      binOnlyFiles: [],
-    },
-    entryPoints: [{
-      name: ".",
-      path: "./mod.ts",
-    }],
+    transformOutput: getTransformOutput({
+      main: "./script/mod.js",
+      module: "./esm/mod.js",
+      types: "./types/mod.d.ts",
+      exports: [{
+        name: ".",
+        types: "./types/mod.d.ts",
+        import: "./esm/mod.js",
+        require: "./script/mod.js",
+      }],
+      imports: [],
+      bin: [],
+      dependencies: [{
+        name: "dep",
+        version: "^1.0.0",
+        kind: "dependency",
+      }, {
+        name: "test-dep",
+        version: "0.1.0",
+        kind: "dev",
+      }, {
+        name: "@deno/shim-deno",
+        version: "~0.1.0",
+        kind: "dev",
+      }],
+    }),
     package: {
       name: "package",
       version: "0.1.0",
     },
     testEnabled: true,
-    includeEsModule: true,
-    includeScriptModule: true,
-    includeDeclarations: true,
     includeTsLib: false,
     shims: {
       deno: "dev",
@@ -87,188 +83,71 @@ Deno.test("single entrypoint", () => {
   assertEquals(
     getPackageJson({
       ...props,
-      transformOutput: props.transformOutput,
       testEnabled: false,
-    }),
-    {
-      name: "package",
-      version: "0.1.0",
-      main: "./script/mod.js",
-      module: "./esm/mod.js",
-      types: "./types/mod.d.ts",
-      dependencies: {
-        dep: "^1.0.0",
-      },
-      devDependencies: {
-        "@types/node": versions.nodeTypes,
-      },
-      scripts: {},
-      exports: {
-        ".": {
-          import: {
-            types: "./types/mod.d.ts",
-            default: "./esm/mod.js",
-          },
-          require: {
-            types: "./types/mod.d.ts",
-            default: "./script/mod.js",
-          },
-        },
-      },
-      _generatedBy: "dnt@dev",
-    },
+    }).scripts,
+    {},
   );
+});
 
-  assertEquals(
-    getPackageJson({
-      ...props,
-      testEnabled: false,
-      includeScriptModule: false,
-    }),
-    {
-      name: "package",
-      version: "0.1.0",
-      main: undefined,
+Deno.test("without declarations", () => {
+  const packageJson = getPackageJson({
+    transformOutput: getTransformOutput({
       module: "./esm/mod.js",
-      types: "./types/mod.d.ts",
-      dependencies: {
-        dep: "^1.0.0",
-      },
-      devDependencies: {
-        "@types/node": versions.nodeTypes,
-      },
-      scripts: {},
-      exports: {
-        ".": {
-          import: {
-            types: "./types/mod.d.ts",
-            default: "./esm/mod.js",
-          },
-          require: undefined,
-        },
-      },
-      _generatedBy: "dnt@dev",
-    },
-  );
-
-  assertEquals(
-    getPackageJson({
-      ...props,
-      testEnabled: false,
-      includeEsModule: false,
+      exports: [{
+        name: ".",
+        import: "./esm/mod.js",
+      }],
+      imports: [],
+      bin: [],
+      dependencies: [],
     }),
-    {
+    package: {
       name: "package",
       version: "0.1.0",
-      main: "./script/mod.js",
-      module: undefined,
-      types: "./types/mod.d.ts",
-      dependencies: {
-        dep: "^1.0.0",
-      },
-      devDependencies: {
-        "@types/node": versions.nodeTypes,
-      },
-      scripts: {},
-      _generatedBy: "dnt@dev",
     },
-  );
+    testEnabled: false,
+    includeTsLib: true,
+    shims: {},
+  });
 
-  assertEquals(
-    getPackageJson({
-      ...props,
-      testEnabled: false,
-      includeScriptModule: false,
-      includeDeclarations: false,
-    }),
-    {
-      name: "package",
-      version: "0.1.0",
-      main: undefined,
-      module: "./esm/mod.js",
-      types: undefined,
-      dependencies: {
-        dep: "^1.0.0",
-      },
-      devDependencies: {
-        "@types/node": versions.nodeTypes,
-      },
-      scripts: {},
-      exports: {
-        ".": {
-          import: "./esm/mod.js",
-          require: undefined,
-        },
+  assertEquals(packageJson, {
+    name: "package",
+    version: "0.1.0",
+    main: undefined,
+    module: "./esm/mod.js",
+    types: undefined,
+    exports: {
+      ".": {
+        import: "./esm/mod.js",
+        require: undefined,
       },
-      _generatedBy: "dnt@dev",
     },
-  );
-
-  // tslib
-  assertEquals(
-    getPackageJson({
-      ...props,
-      testEnabled: false,
-      includeScriptModule: false,
-      includeDeclarations: false,
-      includeTsLib: true,
-    }),
-    {
-      name: "package",
-      version: "0.1.0",
-      main: undefined,
-      module: "./esm/mod.js",
-      types: undefined,
-      dependencies: {
-        tslib: versions.tsLib,
-        dep: "^1.0.0",
-      },
-      devDependencies: {
-        "@types/node": versions.nodeTypes,
-      },
-      scripts: {},
-      exports: {
-        ".": {
-          import: "./esm/mod.js",
-          require: undefined,
-        },
-      },
-      _generatedBy: "dnt@dev",
+    scripts: {},
+    dependencies: {
+      tslib: versions.tsLib,
     },
-  );
+    _generatedBy: "dnt@dev",
+  });
 });
 
 Deno.test("exports have default last", () => {
   const props: GetPackageJsonOptions = {
-    transformOutput: {
-      main: {
-        files: [],
-        dependencies: [],
-        entryPoints: ["mod.ts"],
-      },
-      test: {
-        entryPoints: [],
-        files: [],
-        dependencies: [],
-      },
-      warnings: [],
-      typesDependencies: [],
-      binOnlyFiles: [],
-    },
-    entryPoints: [
-      {
+    transformOutput: getTransformOutput({
+      exports: [{
         name: ".",
-        path: "./mod.ts",
-      },
-    ],
+        types: "./types/mod.d.ts",
+        import: "./esm/mod.js",
+        require: "./script/mod.js",
+      }],
+      imports: [],
+      bin: [],
+      dependencies: [],
+    }),
     package: {
       name: "package",
       version: "0.1.0",
     },
     testEnabled: true,
-    includeEsModule: true,
-    includeScriptModule: true,
-    includeDeclarations: true,
     includeTsLib: false,
     shims: {
       deno: "dev",
@@ -284,200 +163,193 @@ Deno.test("exports have default last", () => {
   assertEquals(Object.keys(result.exports["."].require), ["types", "default"]);
 });
 
-Deno.test("multiple entrypoints", () => {
-  const props: GetPackageJsonOptions = {
-    transformOutput: {
-      main: {
-        files: [],
-        dependencies: [{
-          name: "@deno/shim-deno",
-          version: "~0.1.0",
-        }],
-        entryPoints: ["mod.ts", "other.ts"],
-      },
-      test: {
-        entryPoints: [],
-        files: [],
-        dependencies: [],
-      },
-      warnings: [],
-      typesDependencies: [],
-      binOnlyFiles: [],
-    },
-    entryPoints: [{
-      name: ".",
-      path: "./mod.ts",
-    }, {
-      name: "./my-other-entrypoint.js",
-      path: "./other.ts",
-    }],
+Deno.test("specified exports and types override the manifest", () => {
+  const result: any = getPackageJson({
+    transformOutput: getTransformOutput({
+      exports: [{
+        name: ".",
+        types: "./types/mod.d.ts",
+        import: "./esm/mod.js",
+      }, {
+        name: "./sub",
+        types: "./types/sub.d.ts",
+        import: "./esm/sub.js",
+      }],
+      imports: [],
+      bin: [],
+      dependencies: [],
+    }),
     package: {
       name: "package",
       version: "0.1.0",
+      types: "./custom.d.ts",
+      exports: {
+        "./sub": {
+          node: "./node/sub.js",
+        },
+      },
     },
     testEnabled: false,
-    includeEsModule: true,
-    includeScriptModule: true,
-    includeDeclarations: true,
     includeTsLib: false,
-    shims: { deno: true },
-  };
+    shims: {},
+  });
 
-  assertEquals(getPackageJson(props), {
-    name: "package",
-    version: "0.1.0",
-    main: "./script/mod.js",
-    module: "./esm/mod.js",
-    types: "./types/mod.d.ts",
-    dependencies: {
-      "@deno/shim-deno": "~0.1.0",
-    },
-    devDependencies: {
-      "@types/node": versions.nodeTypes,
-    },
-    exports: {
-      ".": {
-        import: {
-          types: "./types/mod.d.ts",
-          default: "./esm/mod.js",
-        },
-        require: {
-          types: "./types/mod.d.ts",
-          default: "./script/mod.js",
-        },
-      },
-      "./my-other-entrypoint.js": {
-        import: {
-          types: "./types/other.d.ts",
-          default: "./esm/other.js",
-        },
-        require: {
-          types: "./types/other.d.ts",
-          default: "./script/other.js",
-        },
+  assertEquals(result.exports, {
+    ".": {
+      import: {
+        types: "./custom.d.ts",
+        default: "./esm/mod.js",
       },
+      require: undefined,
+    },
+    "./sub": {
+      node: "./node/sub.js",
     },
-    scripts: {},
-    _generatedBy: "dnt@dev",
   });
 });
 
-Deno.test("binary entrypoints", () => {
-  const props: GetPackageJsonOptions = {
-    transformOutput: {
-      main: {
-        files: [],
-        dependencies: [{
-          name: "@deno/shim-deno",
-          version: "~0.1.0",
-        }],
-        entryPoints: ["mod.ts", "bin.ts"],
-      },
-      test: {
-        entryPoints: [],
-        files: [],
-        dependencies: [],
-      },
-      warnings: [],
-      typesDependencies: [],
-      binOnlyFiles: [],
-    },
-    entryPoints: [{
-      name: ".",
-      path: "./mod.ts",
-    }, {
-      kind: "bin",
-      name: "my_bin",
-      path: "./bin.ts",
-    }],
+Deno.test("browser platform", () => {
+  const result: any = getPackageJson({
+    transformOutput: getTransformOutput({
+      module: "./esm/mod.js",
+      types: "./types/mod.d.ts",
+      browser: "./esm/mod.js",
+      exports: [{
+        name: ".",
+        types: "./types/mod.d.ts",
+        browser: "./esm/mod.js",
+        import: "./esm/mod.js",
+      }],
+      imports: [],
+      bin: [],
+      dependencies: [],
+    }),
     package: {
       name: "package",
       version: "0.1.0",
     },
     testEnabled: false,
-    includeEsModule: true,
-    includeScriptModule: true,
-    includeDeclarations: true,
     includeTsLib: false,
-    shims: { deno: true },
-  };
+    shims: {},
+  });
 
-  assertEquals(getPackageJson(props), {
-    name: "package",
-    version: "0.1.0",
-    main: "./script/mod.js",
-    module: "./esm/mod.js",
+  assertEquals(result.browser, "./esm/mod.js");
+  assertEquals(Object.keys(result.exports["."]), [
+    "browser",
+    "import",
+    "require",
+  ]);
+  assertEquals(result.exports["."].browser, {
     types: "./types/mod.d.ts",
-    bin: {
-      my_bin: "./esm/bin.js",
-    },
-    dependencies: {
-      "@deno/shim-deno": "~0.1.0",
-    },
-    devDependencies: {
-      "@types/node": versions.nodeTypes,
-    },
-    exports: {
-      ".": {
-        import: {
-          types: "./types/mod.d.ts",
-          default: "./esm/mod.js",
-        },
-        require: {
-          types: "./types/mod.d.ts",
-          default: "./script/mod.js",
-        },
-      },
-    },
-    scripts: {},
-    _generatedBy: "dnt@dev",
+    default: "./esm/mod.js",
   });
 });
 
-Deno.test("peer dependencies", () => {
-  const props: GetPackageJsonOptions = {
-    transformOutput: {
-      main: {
-        files: [],
-        dependencies: [{
-          name: "dep",
-          version: "^1.0.0",
-        }, {
-          name: "peerDep",
-          version: "^2.0.0",
-          peerDependency: true,
+Deno.test("subpath imports", () => {
+  const getImports = (types: string | undefined) =>
+    getPackageJson({
+      transformOutput: getTransformOutput({
+        exports: [],
+        imports: [{
+          name: "#internal/*",
+          target: "./internal/*.js",
+          types,
         }],
-        entryPoints: ["mod.ts"],
+        bin: [],
+        dependencies: [],
+      }),
+      package: {
+        name: "package",
+        version: "0.1.0",
       },
-      test: {
-        entryPoints: [],
-        files: [],
-        dependencies: [{
-          name: "test-dep",
-          version: "0.1.0",
-          // should be ignored
-          peerDependency: true,
-        }, {
-          name: "@deno/shim-deno",
-          version: "~0.1.0",
-        }],
+      testEnabled: false,
+      includeTsLib: false,
+      shims: {},
+    }).imports;
+
+  // the modules resolve them from the package.json in their directory
+  assertEquals(getImports(undefined), undefined);
+  assertEquals(getImports("./types/internal/*.d.ts"), {
+    "#internal/*": {
+      types: "./types/internal/*.d.ts",
+    },
+  });
+});
+
+Deno.test("binary entrypoints", () => {
+  const getBin = (bin: PackageManifest["bin"]) =>
+    getPackageJson({
+      transformOutput: getTransformOutput({
+        exports: [],
+        imports: [],
+        bin,
+        dependencies: [],
+      }),
+      package: {
+        name: "package",
+        version: "0.1.0",
       },
-      warnings: [],
-      typesDependencies: [],
-      binOnlyFiles: [],
+      testEnabled: false,
+      includeTsLib: false,
+      shims: {},
+    }).bin;
+
+  assertEquals(getBin([]), undefined);
+  // a single binary without a name uses the package name as the command
+  assertEquals(getBin([{ path: "./esm/mod.js" }]), "./esm/mod.js");
+  assertEquals(
+    getBin([
+      { name: "cmd", path: "./esm/cmd.js" },
+      { name: "other", path: "./esm/other.js" },
+    ]),
+    {
+      cmd: "./esm/cmd.js",
+      other: "./esm/other.js",
     },
-    entryPoints: [{
-      name: ".",
-      path: "./mod.ts",
-    }],
+  );
+});
+
+Deno.test("dependencies by kind", () => {
+  const props: GetPackageJsonOptions = {
+    transformOutput: getTransformOutput({
+      main: "./script/mod.js",
+      exports: [],
+      imports: [],
+      bin: [],
+      dependencies: [{
+        name: "dep",
+        version: "^1.0.0",
+        kind: "dependency",
+      }, {
+        name: "peerDep",
+        version: "^2.0.0",
+        kind: "peer",
+      }, {
+        name: "plugin",
+        version: "^3.0.0",
+        kind: "optionalPeer",
+      }, {
+        name: "accelerator",
+        version: "^4.0.0",
+        kind: "optional",
+      }, {
+        name: "helper",
+        version: "^5.0.0",
+        kind: "dev",
+      }, {
+        name: "plugin",
+        version: "^3.0.0",
+        kind: "dev",
+      }],
+    }),
     package: {
       name: "package",
       version: "0.1.0",
+      devDependencies: {
+        helper: "^5.1.0",
+      },
     },
     testEnabled: true,
-    includeEsModule: true,
-    includeScriptModule: true,
-    includeDeclarations: true,
     includeTsLib: false,
     shims: {
       deno: "dev",
@@ -492,20 +364,6 @@ Deno.test("peer dependencies", () => {
         name: "package",
         version: "0.1.0",
         main: "./script/mod.js",
-        module: "./esm/mod.js",
-        types: "./types/mod.d.ts",
-        exports: {
-          ".": {
-            import: {
-              types: "./types/mod.d.ts",
-              default: "./esm/mod.js",
-            },
-            require: {
-              types: "./types/mod.d.ts",
-              default: "./script/mod.js",
-            },
-          },
-        },
         scripts: {
           test: "node test_runner.cjs",
         },
@@ -514,11 +372,20 @@ Deno.test("peer dependencies", () => {
         },
         peerDependencies: {
           peerDep: "^2.0.0",
+          plugin: "^3.0.0",
+        },
+        peerDependenciesMeta: {
+          plugin: {
+            optional: true,
+          },
+        },
+        optionalDependencies: {
+          accelerator: "^4.0.0",
         },
         devDependencies: {
           "@types/node": versions.nodeTypes,
-          "test-dep": "0.1.0",
-          "@deno/shim-deno": "~0.1.0",
+          helper: "^5.1.0",
+          plugin: "^3.0.0",
         },
         _generatedBy: "dnt@dev",
       },
@@ -528,106 +395,253 @@ Deno.test("peer dependencies", () => {
   );
 });
 
-Deno.test("types dependencies", () => {
-  const props: GetPackageJsonOptions = {
-    transformOutput: {
-      main: {
-        files: [],
+Deno.test("types node not added when a dependency", () => {
+  const packageJson = getPackageJson({
+    transformOutput: getTransformOutput({
+      exports: [],
+      imports: [],
+      bin: [],
+      dependencies: [{
+        name: "@types/node",
+        version: "^18.0.0",
+        kind: "dependency",
+      }],
+    }),
+    package: {
+      name: "package",
+      version: "0.1.0",
+    },
+    testEnabled: false,
+    includeTsLib: false,
+    shims: {
+      deno: true,
+    },
+  });
+
+  assertEquals(packageJson.dependencies, { "@types/node": "^18.0.0" });
+  assertEquals(packageJson.devDependencies, undefined);
+});
+
+Deno.test("es module, script module, and declaration combinations", () => {
+  // the manifests that the transform outputs for each combination
+  const getPackageJsonWith = (
+    manifest: Pick<PackageManifest, "main" | "module" | "types" | "exports">,
+  ) =>
+    getPackageJson({
+      transformOutput: getTransformOutput({
+        ...manifest,
+        imports: [],
+        bin: [],
         dependencies: [{
-          name: "svg-path-parser",
-          version: "1.1.0",
-        }, {
-          name: "other",
+          name: "dep",
           version: "^1.0.0",
+          kind: "dependency",
         }],
-        entryPoints: ["mod.ts"],
+      }),
+      package: {
+        name: "package",
+        version: "0.1.0",
       },
-      test: {
-        entryPoints: [],
-        files: [],
-        dependencies: [],
+      testEnabled: false,
+      includeTsLib: false,
+      shims: {
+        deno: "dev",
       },
-      warnings: [],
-      typesDependencies: [{
-        name: "@types/svg-path-parser",
-        version: "~1.1.6",
+    });
+  const expected = (props: Record<string, unknown>) => ({
+    name: "package",
+    version: "0.1.0",
+    ...props,
+    dependencies: {
+      dep: "^1.0.0",
+    },
+    devDependencies: {
+      "@types/node": versions.nodeTypes,
+    },
+    scripts: {},
+    _generatedBy: "dnt@dev",
+  });
+
+  // without script modules
+  assertEquals(
+    getPackageJsonWith({
+      module: "./esm/mod.js",
+      types: "./types/mod.d.ts",
+      exports: [{
+        name: ".",
+        types: "./types/mod.d.ts",
+        import: "./esm/mod.js",
+      }],
+    }),
+    expected({
+      main: undefined,
+      module: "./esm/mod.js",
+      types: "./types/mod.d.ts",
+      exports: {
+        ".": {
+          import: {
+            types: "./types/mod.d.ts",
+            default: "./esm/mod.js",
+          },
+          require: undefined,
+        },
+      },
+    }),
+  );
+
+  // without es modules, where the `main` field is enough
+  assertEquals(
+    getPackageJsonWith({
+      main: "./script/mod.js",
+      types: "./types/mod.d.ts",
+      exports: [],
+    }),
+    expected({
+      main: "./script/mod.js",
+      module: undefined,
+      types: "./types/mod.d.ts",
+    }),
+  );
+
+  // without script modules and declarations
+  assertEquals(
+    getPackageJsonWith({
+      module: "./esm/mod.js",
+      exports: [{
+        name: ".",
+        import: "./esm/mod.js",
+      }],
+    }),
+    expected({
+      main: undefined,
+      module: "./esm/mod.js",
+      types: undefined,
+      exports: {
+        ".": {
+          import: "./esm/mod.js",
+          require: undefined,
+        },
+      },
+    }),
+  );
+});
+
+Deno.test("multiple entrypoints", () => {
+  const packageJson = getPackageJson({
+    transformOutput: getTransformOutput({
+      main: "./script/mod.js",
+      module: "./esm/mod.js",
+      types: "./types/mod.d.ts",
+      exports: [{
+        name: ".",
+        types: "./types/mod.d.ts",
+        import: "./esm/mod.js",
+        require: "./script/mod.js",
       }, {
-        // a types package that's already a dependency is not duplicated
-        name: "other",
-        version: "^1.0.0",
+        name: "./my-other-entrypoint.js",
+        types: "./types/other.d.ts",
+        import: "./esm/other.js",
+        require: "./script/other.js",
       }],
-      binOnlyFiles: [],
-    },
-    entryPoints: [{
-      name: ".",
-      path: "./mod.ts",
-    }],
+      imports: [],
+      bin: [],
+      dependencies: [],
+    }),
     package: {
       name: "package",
       version: "0.1.0",
     },
     testEnabled: false,
-    includeEsModule: true,
-    includeScriptModule: true,
-    includeDeclarations: true,
     includeTsLib: false,
     shims: {},
-  };
-
-  const packageJson = getPackageJson(props);
-  assertEquals(packageJson.dependencies, {
-    "svg-path-parser": "1.1.0",
-    "other": "^1.0.0",
   });
-  assertEquals(packageJson.devDependencies, {
-    "@types/svg-path-parser": "~1.1.6",
-  });
-});
 
-Deno.test("binary entrypoint without a name", () => {
-  const props = getBinaryProps({ includeEsModule: true, name: undefined });
-  // the package name is used as the name of the command
-  assertEquals(getPackageJson(props).bin, "./esm/mod.js");
-});
-
-Deno.test("binary entrypoint without an esm output", () => {
-  const props = getBinaryProps({ includeEsModule: false, name: "cmd" });
-  assertEquals(getPackageJson(props).bin, { cmd: "./script/mod.js" });
-});
-
-function getBinaryProps(
-  options: { includeEsModule: boolean; name: string | undefined },
-): GetPackageJsonOptions {
-  return {
-    transformOutput: {
-      main: {
-        files: [],
-        dependencies: [],
-        entryPoints: ["mod.ts"],
+  assertEquals(packageJson.exports, {
+    ".": {
+      import: {
+        types: "./types/mod.d.ts",
+        default: "./esm/mod.js",
       },
-      test: {
-        entryPoints: [],
-        files: [],
-        dependencies: [],
+      require: {
+        types: "./types/mod.d.ts",
+        default: "./script/mod.js",
       },
-      warnings: [],
-      typesDependencies: [],
-      binOnlyFiles: [],
     },
-    entryPoints: [{
-      kind: "bin",
-      name: options.name,
-      path: "./mod.ts",
-    }],
+    "./my-other-entrypoint.js": {
+      import: {
+        types: "./types/other.d.ts",
+        default: "./esm/other.js",
+      },
+      require: {
+        types: "./types/other.d.ts",
+        default: "./script/other.js",
+      },
+    },
+  });
+});
+
+Deno.test("specified dependencies override the manifest", () => {
+  const packageJson = getPackageJson({
+    transformOutput: getTransformOutput({
+      exports: [],
+      imports: [],
+      bin: [],
+      dependencies: [{
+        name: "svg-path-parser",
+        version: "1.1.0",
+        kind: "dependency",
+      }, {
+        name: "@types/svg-path-parser",
+        version: "~1.1.6",
+        kind: "dev",
+      }],
+    }),
     package: {
       name: "package",
       version: "0.1.0",
+      dependencies: {
+        "svg-path-parser": "^1.1.0",
+      },
     },
     testEnabled: false,
-    includeEsModule: options.includeEsModule,
-    includeScriptModule: true,
-    includeDeclarations: false,
     includeTsLib: false,
     shims: {},
+  });
+
+  assertEquals(packageJson.dependencies, {
+    "svg-path-parser": "^1.1.0",
+  });
+  assertEquals(packageJson.devDependencies, {
+    "@types/svg-path-parser": "~1.1.6",
+  });
+});
+
+function getTransformOutput(
+  packageManifest: PackageManifest,
+): TransformOutput {
+  return {
+    main: {
+      files: [],
+      assets: [],
+      typeOnlyDependencies: [],
+      dependencies: [],
+      entryPoints: [],
+      workerEntryPoints: [],
+    },
+    test: {
+      entryPoints: [],
+      workerEntryPoints: [],
+      files: [],
+      assets: [],
+      typeOnlyDependencies: [],
+      dependencies: [],
+    },
+    warnings: [],
+    typesDependencies: [],
+    binOnlyFiles: [],
+    subpathImports: [],
+    packageManifest,
+    mappings: {},
+    graph: { modules: [], edges: [] },
   };
 }
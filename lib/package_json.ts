@@ -1,48 +1,32 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
-import type { EntryPoint, ShimOptions } from "../mod.ts";
-import type { TransformOutput } from "../transform.ts";
+import type { ShimOptions } from "../mod.ts";
+import type {
+  DependencyKind,
+  PackageManifestExport,
+  TransformOutput,
+} from "../transform.ts";
 import type { PackageJson } from "./types.ts";
-import { getDntVersion, toDtsFilePath, toJsFilePath } from "./utils.ts";
+import { getDntVersion } from "./utils.ts";
 
 export interface GetPackageJsonOptions {
   transformOutput: TransformOutput;
-  entryPoints: EntryPoint[];
   package: PackageJson;
-  includeEsModule: boolean | undefined;
-  includeScriptModule: boolean | undefined;
-  includeDeclarations: boolean | undefined;
   includeTsLib: boolean | undefined;
   testEnabled: boolean | undefined;
   shims: ShimOptions;
 }
 
+/** Gets the package.json file from the package manifest of the transform,
+ * along with the specified package.json properties that override it. */
 export function getPackageJson({
   transformOutput,
-  entryPoints,
   package: packageJsonObj,
-  includeEsModule,
-  includeScriptModule,
-  includeDeclarations,
   includeTsLib,
   testEnabled,
   shims,
 }: GetPackageJsonOptions): Record<string, unknown> {
-  const finalEntryPoints = transformOutput
-    .main.entryPoints.map((e, i) => ({
-      name: entryPoints[i].name,
-      kind: entryPoints[i].kind ?? "export",
-      path: toJsFilePath(e),
-      types: toDtsFilePath(e),
-    }));
-  const exports = finalEntryPoints.filter((e) => e.kind === "export").map((
-    e,
-  ) => ({
-    ...e,
-    // only a binary entrypoint may go without a name
-    name: e.name!,
-  }));
-  const binaries = finalEntryPoints.filter((e) => e.kind === "bin");
+  const manifest = transformOutput.packageManifest;
   const dependencies = {
     // typescript helpers library (https://www.npmjs.com/package/tslib)
     ...(includeTsLib
@@ -51,51 +35,39 @@ export function getPackageJson({
       }
       : {}),
     // add dependencies from transform
-    ...Object.fromEntries(
-      transformOutput.main.dependencies
-        .filter((d) => !d.peerDependency)
-        .map((d) => [d.name, d.version]),
-    ),
+    ...getManifestDependencies("dependency"),
     // override with specified dependencies
     ...(packageJsonObj.dependencies ?? {}),
   };
   const peerDependencies = {
     // add dependencies from transform
-    ...Object.fromEntries(
-      transformOutput.main.dependencies
-        .filter((d) => d.peerDependency)
-        .map((d) => [d.name, d.version]),
-    ),
+    ...getManifestDependencies("peer", "optionalPeer"),
     // override with specified dependencies
     ...(packageJsonObj.peerDependencies ?? {}),
   };
-  const testDevDependencies = testEnabled
-    ? ({
-      // add dependencies from transform
-      ...Object.fromEntries(
-        // ignore peer dependencies on this
-        transformOutput.test.dependencies.map((d) => [d.name, d.version]) ??
-          [],
-      ),
-    })
-    : {};
-  const devDependencies = {
-    // packages that provide the type declarations of a dependency
-    // (ex. an `@types/` package specified by an `X-TypeScript-Types` header)
+  const peerDependenciesMeta = {
+    // add dependencies from transform
     ...Object.fromEntries(
-      transformOutput.typesDependencies
-        .filter((d) =>
-          !Object.keys(dependencies).includes(d.name) &&
-          !Object.keys(peerDependencies).includes(d.name)
-        )
-        .map((d) => [d.name, d.version]),
+      Object.keys(getManifestDependencies("optionalPeer"))
+        .map((name) => [name, { optional: true }]),
     ),
+    // override with specified dependencies
+    ...(packageJsonObj.peerDependenciesMeta ?? {}),
+  };
+  const optionalDependencies = {
+    // add dependencies from transform
+    ...getManifestDependencies("optional"),
+    // override with specified dependencies
+    ...(packageJsonObj.optionalDependencies ?? {}),
+  };
+  const devDependencies = {
     ...(shouldIncludeTypesNode()
       ? {
         "@types/node": "^20.9.0",
       }
       : {}),
-    ...testDevDependencies,
+    // add dependencies from transform
+    ...getManifestDependencies("dev"),
     // override with specified dependencies
     ...(packageJsonObj.devDependencies ?? {}),
   };
@@ -106,14 +78,13 @@ export function getPackageJson({
       ...(packageJsonObj.scripts ?? {}),
     })
     : packageJsonObj.scripts ?? {};
-  const mainExport = exports.length > 0
-    ? {
-      module: includeEsModule ? `./esm/${exports[0].path}` : undefined,
-      main: includeScriptModule ? `./script/${exports[0].path}` : undefined,
-      types: includeDeclarations ? `./types/${exports[0].types}` : undefined,
-    }
-    : {};
-  const binaryExport = binaries.length > 0 ? { bin: getBin() } : {};
+  const mainExport = {
+    module: manifest.module,
+    main: manifest.main,
+    types: manifest.types,
+    ...(manifest.browser != null ? { browser: manifest.browser } : {}),
+  };
+  const binaryExport = manifest.bin.length > 0 ? { bin: getBin() } : {};
 
   const final: Record<string, unknown> = {
     ...mainExport,
@@ -122,57 +93,72 @@ export function getPackageJson({
     scripts: {},
     ...deleteEmptyKeys({
       exports: {
-        ...(includeEsModule || exports.length > 1
-          ? {
-            ...(Object.fromEntries(exports.map((e) => {
-              return [e.name, {
-                import: includeEsModule
-                  ? getPathOrTypesObject(`./esm/${e.path}`)
-                  : undefined,
-                require: includeScriptModule
-                  ? getPathOrTypesObject(`./script/${e.path}`)
-                  : undefined,
-                ...(packageJsonObj.exports?.[e.name] ?? {}),
-              }];
-
-              function getPathOrTypesObject(path: string) {
-                if (includeDeclarations) {
-                  return {
-                    // "types" must always be first and "default" last
-                    types:
-                      (e.name === "." ? packageJsonObj.types : undefined) ??
-                        `./types/${e.types}`,
-                    default: path,
-                  };
-                } else {
-                  return path;
-                }
-              }
-            }))),
-          }
-          : {}),
+        ...Object.fromEntries(manifest.exports.map((e) => {
+          return [e.name, {
+            // bundlers use the first condition that matches
+            ...(e.browser != null
+              ? { browser: getConditionValue(e, e.browser) }
+              : {}),
+            import: getConditionValue(e, e.import),
+            require: getConditionValue(e, e.require),
+            ...(packageJsonObj.exports?.[e.name] ?? {}),
+          }];
+        })),
         // allow someone to override
         ...(packageJsonObj.exports ?? {}),
       },
+      // the modules resolve these from the package.json in their directory,
+      // so only the separate declaration files resolve them from here
+      imports: {
+        ...Object.fromEntries(
+          manifest.imports
+            .filter((i) => i.types != null)
+            .map((i) => [i.name, { types: i.types }]),
+        ),
+        // allow someone to override
+        ...(packageJsonObj.imports ?? {}),
+      },
       scripts,
       dependencies,
       peerDependencies,
+      peerDependenciesMeta,
+      optionalDependencies,
       devDependencies,
     }),
     _generatedBy: `dnt@${getDntVersion()}`,
   };
   return sortObject(final);
 
-  function getBin() {
-    // a single binary without a name uses the package name as the command
-    if (binaries.length === 1 && binaries[0].name == null) {
-      return getBinPath(binaries[0]);
+  function getConditionValue(
+    e: PackageManifestExport,
+    path: string | undefined,
+  ) {
+    if (path == null || e.types == null) {
+      return path;
     }
-    return Object.fromEntries(binaries.map((b) => [b.name, getBinPath(b)]));
+    return {
+      // "types" must always be first and "default" last
+      types: (e.name === "." ? packageJsonObj.types : undefined) ?? e.types,
+      default: path,
+    };
+  }
+
+  function getManifestDependencies(...kinds: DependencyKind[]) {
+    return Object.fromEntries(
+      manifest.dependencies
+        .filter((d) => kinds.includes(d.kind))
+        .map((d) => [d.name, d.version]),
+    );
   }
 
-  function getBinPath(binary: { path: string }) {
-    return includeEsModule ? `./esm/${binary.path}` : `./script/${binary.path}`;
+  function getBin() {
+    // a single binary without a name uses the package name as the command
+    if (manifest.bin.length === 1 && manifest.bin[0].name == null) {
+      return manifest.bin[0].path;
+    }
+    return Object.fromEntries(
+      manifest.bin.map((b) => [b.name, b.path]),
+    );
   }
 
   function shouldIncludeTypesNode() {
@@ -222,12 +208,16 @@ function sortObject(obj: Record<string, unknown>) {
     "module",
     "types",
     "typings",
+    "browser",
     "exports",
+    "imports",
     "scripts",
   ];
   const lowPrecedence = [
     "dependencies",
     "peerDependencies",
+    "peerDependenciesMeta",
+    "optionalDependencies",
     "devDependencies",
     "_generatedBy",
   ];
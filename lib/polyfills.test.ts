@@ -112,3 +112,35 @@ Deno.test("resolveUseImportMetaPolyfill - esm only respects an explicit override
     true,
   );
 });
+
+Deno.test("resolveUseImportMetaPolyfill - browser platform only when explicit", () => {
+  assertEquals(
+    resolveUseImportMetaPolyfill({
+      polyfills: {},
+      target: "ES2021",
+      emitScriptModule: false,
+      platform: "browser",
+    }),
+    false,
+  );
+  assertEquals(
+    resolveUseImportMetaPolyfill({
+      polyfills: { importMeta: true },
+      target: "ES2021",
+      emitScriptModule: true,
+      platform: "browser",
+    }),
+    true,
+  );
+  assertThrows(
+    () =>
+      resolveUseImportMetaPolyfill({
+        polyfills: {},
+        target: "ES2021",
+        emitScriptModule: true,
+        platform: "browser",
+      }),
+    Error,
+    "A script module cannot be emitted for the 'browser' platform",
+  );
+});
@@ -1,5 +1,6 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
+import type { Platform } from "../transform.ts";
 import type { PolyfillName, PolyfillOptions, ScriptTarget } from "./types.ts";
 
 /** Every polyfill dnt knows how to apply. */
@@ -49,14 +50,27 @@ export function resolvePolyfillOptions(
  * overrides and the rest of the build options.
  *
  * `import.meta` is a syntax error in CommonJS, so the polyfill is required
- * whenever a script module is emitted regardless of the target.
+ * whenever a script module is emitted regardless of the target. The polyfill
+ * is implemented with Node.js modules, so it's only used for the browser
+ * platform when explicitly enabled.
  */
 export function resolveUseImportMetaPolyfill(options: {
   polyfills: Record<string, boolean>;
   target: ScriptTarget;
   emitScriptModule: boolean;
+  platform?: Platform;
 }): boolean {
   const explicit = options.polyfills["importMeta"];
+  if (options.platform === "browser") {
+    if (options.emitScriptModule && explicit !== true) {
+      throw new Error(
+        "A script module cannot be emitted for the 'browser' platform because " +
+          "`import.meta` is not valid CommonJS and the 'importMeta' polyfill " +
+          "only works on Node.js. Set the 'scriptModule' build option to false.",
+      );
+    }
+    return explicit ?? false;
+  }
   if (options.emitScriptModule) {
     if (explicit === false) {
       throw new Error(
@@ -239,7 +239,6 @@ function getWebSocketShim(): Shim {
     typesPackage: {
       name: "@types/ws",
       version: "^8.5.4",
-      peerDependency: false,
     },
     globalNames: [{
       name: "WebSocket",
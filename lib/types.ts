@@ -34,6 +34,9 @@ export interface PackageJson {
   dependencies?: { [packageName: string]: string };
   devDependencies?: { [packageName: string]: string };
   peerDependencies?: { [packageName: string]: string };
+  peerDependenciesMeta?: {
+    [packageName: string]: { optional?: boolean };
+  };
   bundleDependencies?: { [packageName: string]: string };
   optionalDependencies?: { [packageName: string]: string };
   engines?: { [engineName: string]: string };
@@ -29,6 +29,10 @@ import type {
 } from "./lib/types.ts";
 import { glob, runNpmCommand, standardizePath } from "./lib/utils.ts";
 import {
+  chainSourceMaps,
+  type ConfigPackage,
+  type Platform,
+  type SourceMaps,
   type SpecifierMappings,
   transform,
   type TransformOutput,
@@ -45,6 +49,7 @@ export type {
 } from "./lib/types.ts";
 export type { JsxEmit, LibName, SourceMapOptions } from "./lib/compiler.ts";
 export type { ShimOptions } from "./lib/shims.ts";
+export type { Platform } from "./transform.ts";
 
 export interface EntryPoint {
   /**
@@ -63,8 +68,20 @@ export interface EntryPoint {
 }
 
 export interface BuildOptions {
-  /** Entrypoint(s) to the Deno module. Ex. `./mod.ts` */
-  entryPoints: (string | EntryPoint)[];
+  /** Entrypoint(s) to the Deno module. Ex. `./mod.ts`
+   *
+   * This is required unless `entryPointsFromConfig` is set, in which case
+   * only binary entrypoints need to be specified.
+   */
+  entryPoints?: (string | EntryPoint)[];
+  /** Uses the `exports` of the deno.json as the export entrypoints, along
+   * with its `name` and `version` for the package.json when they aren't
+   * specified in `package`.
+   *
+   * Any non-binary `entryPoints` that are specified must be the same modules
+   * as the exports.
+   */
+  entryPointsFromConfig?: boolean;
   /** Directory to output to. */
   outDir: string;
   /** Shims to use. */
@@ -115,13 +132,22 @@ export interface BuildOptions {
    */
   declarationMap?: boolean;
   /** Include a CommonJS or UMD module.
-   * @default "cjs"
+   * @default "cjs", or `false` for the `"browser"` platform
    */
   scriptModule?: "cjs" | "umd" | false;
   /** Whether to emit an ES module.
    * @default true
    */
   esModule?: boolean;
+  /** Platform that the package runs on.
+   *
+   * The `"browser"` platform resolves packages with the `browser` condition,
+   * doesn't use the Node.js only `importMeta` polyfill, adds a `browser`
+   * condition to the package.json `exports`, and warns about imports of
+   * Node.js built-in modules and usages of the `Deno` global.
+   * @default "node"
+   */
+  platform?: Platform;
   /** Explicitly enables or disables polyfills, overriding what
    * `compilerOptions.target` implies.
    *
@@ -207,8 +233,24 @@ export interface BuildOptions {
    * ```
    */
   mappings?: SpecifierMappings;
-  /** Package.json output. You may override dependencies and dev dependencies in here. */
-  package: PackageJson;
+  /** Additional conditions to use when resolving the `exports` and `imports`
+   * of packages (ex. `["react-server"]`).
+   */
+  conditions?: string[];
+  /** Files imported with a `type` of `"text"` or `"bytes"` that are at most
+   * this many bytes are inlined in the output. Larger files are copied to the
+   * output and read at runtime.
+   * @default 4096
+   */
+  assetInlineLimit?: number;
+  /** Package.json output. You may override dependencies and dev dependencies in here.
+   *
+   * The `name` and `version` may be left out when they're read from the
+   * deno.json with `entryPointsFromConfig`.
+   */
+  package:
+    & Omit<PackageJson, "name" | "version">
+    & Partial<Pick<PackageJson, "name" | "version">>;
   /** Path or url to a deno.json.
    *
    * When not specified, a deno.json is auto-discovered by searching upwards
@@ -236,6 +278,27 @@ export interface BuildOptions {
    * Leave this undefined to use the `lock.frozen` setting in the deno.json file.
    */
   frozenLockfile?: boolean;
+  /** Uses the versions that the deno lock file locks the `npm:` and `jsr:`
+   * specifiers to for the versions in the package.json, either exactly
+   * (ex. `1.2.3`) or as a caret range (ex. `^1.2.3`), rather than the version
+   * requirements in the specifiers.
+   *
+   * A warning is output for a specifier whose version requirement doesn't
+   * match its locked version or allows versions far from it (ex. `npm:pkg@5`
+   * locked at `5.3.0`).
+   */
+  lockedDependencyVersions?: "exact" | "caret";
+  /** Path to a directory of npm packuments laid out like Deno's npm cache
+   * (ex. `<dir>/@scope/name/registry.json`).
+   *
+   * When specified, the build errors when the name or version range of a
+   * dependency doesn't resolve in it rather than when installing.
+   */
+  npmRegistryDir?: string;
+  /** Pins each dependency in the package.json to the exact version its
+   * range resolves to in `npmRegistryDir`.
+   */
+  pinNpmVersions?: boolean;
   /** Package manager used to install dependencies and run npm scripts.
    * This also can be an absolute path to the executable file of package manager.
    * @default "npm"
@@ -262,6 +325,7 @@ export interface BuildOptions {
      * Use source maps from the canonical typescript to ESM/CommonJS emit.
      *
      * Specify `true` to include separate files or `"inline"` to inline the source map in the same file.
+     * The maps lead back to the original Deno modules rather than the transformed ones.
      * @remarks Using this option will cause your sources to be included in the npm package.
      * @default false
      */
@@ -327,9 +391,6 @@ export interface BuildOptions {
 
 /** Builds the specified Deno module to an npm package using the TypeScript compiler. */
 export async function build(options: BuildOptions): Promise<void> {
-  if (options.scriptModule === false && options.esModule === false) {
-    throw new Error("`scriptModule` and `esModule` cannot both be `false`");
-  }
   // the directory that all the relative paths in the options resolve from
   const cwd = standardizePath(options.cwd ?? ".", Deno.cwd());
   // set defaults
@@ -337,7 +398,8 @@ export async function build(options: BuildOptions): Promise<void> {
     ...options,
     outDir: standardizePath(options.outDir, cwd),
     entryPoints: options.entryPoints,
-    scriptModule: options.scriptModule ?? "cjs",
+    scriptModule: options.scriptModule ??
+      (options.platform === "browser" ? false : "cjs"),
     esModule: options.esModule ?? true,
     typeCheck: options.typeCheck ?? "single",
     test: options.test ?? true,
@@ -345,6 +407,9 @@ export async function build(options: BuildOptions): Promise<void> {
       ? "inline"
       : options.declaration ?? "inline",
   };
+  if (options.scriptModule === false && options.esModule === false) {
+    throw new Error("`scriptModule` and `esModule` cannot both be `false`");
+  }
   if (
     options.declaration === "separate" && options.scriptModule !== false &&
     options.esModule !== false
@@ -366,6 +431,14 @@ export async function build(options: BuildOptions): Promise<void> {
         `'declaration' to be enabled and 'skipSourceOutput' to be 'false'.`,
     );
   }
+  // the maps that the compiler emits are chained with the ones from the
+  // transform so they lead back to the original modules
+  const sourceMaps: SourceMaps =
+    options.compilerOptions?.sourceMap || declarationMap
+      ? options.compilerOptions?.inlineSources
+        ? "withSourcesContent"
+        : "enabled"
+      : "none";
   const packageManager = options.packageManager ?? "npm";
   const scriptTarget = options.compilerOptions?.target ?? "ES2021";
   const polyfills = resolvePolyfillOptions(options.polyfills);
@@ -375,8 +448,9 @@ export async function build(options: BuildOptions): Promise<void> {
     polyfills,
     target: scriptTarget,
     emitScriptModule: options.scriptModule !== false,
+    platform: options.platform,
   });
-  const entryPoints: EntryPoint[] = options.entryPoints.map((e, i) => {
+  let entryPoints: EntryPoint[] = (options.entryPoints ?? []).map((e, i) => {
     if (typeof e === "string") {
       return {
         name: i === 0 ? "." : e.replace(/\.tsx?$/i, ".js"),
@@ -398,6 +472,9 @@ export async function build(options: BuildOptions): Promise<void> {
 
   log("Transforming...");
   const transformOutput = await transformEntryPoints();
+  if (transformOutput.configPackage != null) {
+    entryPoints = getConfigEntryPoints(transformOutput.configPackage);
+  }
   // a binary is only ever run by node, so its modules aren't part of the
   // script output when nothing else uses them
   const binOnlyFiles = new Set(
@@ -415,13 +492,28 @@ export async function build(options: BuildOptions): Promise<void> {
   }
 
   const createdDirectories = new Set<string>();
-  const writeFile = (filePath: string, fileText: string) => {
+  const writeFile = (filePath: string, data: string | Uint8Array) => {
     const dir = path.dirname(filePath);
     if (!createdDirectories.has(dir)) {
       Deno.mkdirSync(dir, { recursive: true });
       createdDirectories.add(dir);
     }
-    Deno.writeTextFileSync(filePath, fileText);
+    if (typeof data === "string") {
+      Deno.writeTextFileSync(filePath, data);
+    } else {
+      Deno.writeFileSync(filePath, data);
+    }
+  };
+  // the compiler only emits the modules, so the assets are copied beside them
+  const writeAssets = (outDir: string) => {
+    for (
+      const asset of [
+        ...transformOutput.main.assets,
+        ...transformOutput.test.assets,
+      ]
+    ) {
+      writeFile(path.join(outDir, asset.filePath), asset.bytes);
+    }
   };
 
   createPackageJson();
@@ -505,6 +597,13 @@ export async function build(options: BuildOptions): Promise<void> {
       skipLibCheck: options.compilerOptions?.skipLibCheck ?? true,
       useUnknownInCatchVariables:
         options.compilerOptions?.useUnknownInCatchVariables ?? false,
+      // resolve the kept subpath imports to the sources for type checking
+      paths: Object.fromEntries(
+        transformOutput.subpathImports.map((subpathImport) => [
+          subpathImport.name,
+          [path.join(srcOutDir, subpathImport.target)],
+        ]),
+      ),
     },
   });
 
@@ -515,6 +614,8 @@ export async function build(options: BuildOptions): Promise<void> {
     })).filter((p) => p.kind === "bin").map((p) => p.path),
   );
 
+  // source map of each output file keyed by the url it's compiled from
+  const outputSourceMaps: Record<string, string> = {};
   for (
     const outputFile of [
       ...transformOutput.main.files,
@@ -526,11 +627,22 @@ export async function build(options: BuildOptions): Promise<void> {
       "src",
       outputFile.filePath,
     );
+    const addsShebang = binaryEntryPointPaths.has(outputFile.filePath) &&
+      !outputFile.fileText.startsWith("#!");
     const outputFileText = binaryEntryPointPaths.has(outputFile.filePath)
       ? `#!/usr/bin/env node\n${
         outputFile.fileText.replace(/^#![^\n\r]*\r?\n?/, "")
       }`
       : outputFile.fileText;
+    if (outputFile.sourceMap != null) {
+      const sourceMap = JSON.parse(outputFile.sourceMap);
+      if (addsShebang) {
+        // an empty first line shifts the mappings below the shebang
+        sourceMap.mappings = ";" + sourceMap.mappings;
+      }
+      outputSourceMaps[path.toFileUrl(path.resolve(outputFilePath)).href] =
+        JSON.stringify(sourceMap);
+    }
     const sourceFile = project.createSourceFile(
       outputFilePath,
       outputFileText,
@@ -557,6 +669,9 @@ export async function build(options: BuildOptions): Promise<void> {
       writeFile(outputFilePath, outputFileText);
     }
   }
+  if (!options.skipSourceOutput) {
+    writeAssets(srcOutDir);
+  }
 
   let program = getProgramAndMaybeTypeCheck("ESM");
 
@@ -584,8 +699,9 @@ export async function build(options: BuildOptions): Promise<void> {
     });
     writeFile(
       path.join(esmOutDir, "package.json"),
-      `{\n  "type": "module"\n}\n`,
+      getScopePackageJsonText("module"),
     );
+    writeAssets(esmOutDir);
   }
 
   // emit the script files
@@ -614,8 +730,9 @@ export async function build(options: BuildOptions): Promise<void> {
     });
     writeFile(
       path.join(scriptOutDir, "package.json"),
-      `{\n  "type": "commonjs"\n}\n`,
+      getScopePackageJsonText("commonjs"),
     );
+    writeAssets(scriptOutDir);
   }
 
   // ensure this is done before running tests
@@ -645,6 +762,9 @@ export async function build(options: BuildOptions): Promise<void> {
     const emitResult = program.emit(
       undefined,
       (filePath, data, writeByteOrderMark) => {
+        if (sourceMaps !== "none") {
+          data = chainEmittedSourceMap(filePath, data);
+        }
         if (writeByteOrderMark) {
           data = "\uFEFF" + data;
         }
@@ -661,6 +781,30 @@ export async function build(options: BuildOptions): Promise<void> {
     }
   }
 
+  function chainEmittedSourceMap(filePath: string, data: string) {
+    const fileUrl = path.toFileUrl(path.resolve(filePath)).href;
+    if (filePath.endsWith(".map")) {
+      return chainSourceMaps(data, fileUrl, outputSourceMaps);
+    }
+    // an inline source map is in a comment at the end of the file
+    return data.replace(
+      /(\/\/# sourceMappingURL=data:application\/json;base64,)(\S+)\s*$/,
+      (_, prefix: string, base64: string) => {
+        const sourceMap = new TextDecoder().decode(
+          Uint8Array.from(atob(base64), (c) => c.charCodeAt(0)),
+        );
+        const chained = new TextEncoder().encode(
+          chainSourceMaps(sourceMap, fileUrl, outputSourceMaps),
+        );
+        let binary = "";
+        for (const byte of chained) {
+          binary += String.fromCharCode(byte);
+        }
+        return prefix + btoa(binary);
+      },
+    );
+  }
+
   function getProgramAndMaybeTypeCheck(current: "ESM" | "script") {
     // When creating the program and type checking, we need to ensure that
     // the cwd is the directory that contains the node_modules directory
@@ -741,14 +885,19 @@ export async function build(options: BuildOptions): Promise<void> {
       );
     }
 
+    const configPackage = transformOutput.configPackage;
+    const name = options.package.name ?? configPackage?.name;
+    const version = options.package.version ?? configPackage?.version;
+    if (name == null || version == null) {
+      throw new Error(
+        "The package.json `name` and `version` must be specified in the " +
+          "`package` option or in the deno.json with `entryPointsFromConfig`.",
+      );
+    }
     const packageJsonObj = getPackageJson({
-      entryPoints,
       transformOutput,
-      package: options.package,
+      package: { ...options.package, name, version },
       testEnabled: options.test,
-      includeEsModule: options.esModule !== false,
-      includeScriptModule: options.scriptModule !== false,
-      includeDeclarations: options.declaration === "separate",
       includeTsLib: options.compilerOptions?.importHelpers,
       shims: options.shims,
     });
@@ -758,6 +907,17 @@ export async function build(options: BuildOptions): Promise<void> {
     );
   }
 
+  function getScopePackageJsonText(type: "module" | "commonjs") {
+    // the modules resolve the subpath imports from the package.json
+    // of their directory, so the imports need to be in here
+    const imports = transformOutput.packageManifest.imports;
+    const packageJson = imports.length === 0 ? { type } : {
+      type,
+      imports: Object.fromEntries(imports.map((i) => [i.name, i.target])),
+    };
+    return JSON.stringify(packageJson, undefined, 2) + "\n";
+  }
+
   function createNpmIgnore() {
     const fileText = getNpmIgnoreText({
       sourceMap: options.compilerOptions?.sourceMap,
@@ -790,20 +950,68 @@ export async function build(options: BuildOptions): Promise<void> {
     const { shims, testShims } = shimOptionsToTransformShims(options.shims);
     return transform({
       entryPoints: entryPoints.map((e) => e.path),
+      entryPointsFromConfig: options.entryPointsFromConfig,
       binEntryPoints: getBinOnlyEntryPointPaths(),
       testEntryPoints: options.test ? await getTestEntryPoints() : [],
+      packageManifest: {
+        entryPoints: entryPoints.map((e) => ({
+          kind: e.kind ?? "export",
+          name: e.name,
+          path: e.path,
+        })),
+        esModuleDir: options.esModule !== false ? "./esm" : undefined,
+        scriptModuleDir: options.scriptModule !== false
+          ? "./script"
+          : undefined,
+        typesDir: options.declaration === "separate" ? "./types" : undefined,
+        specifiedDependencies: Object.keys({
+          ...options.package.dependencies,
+          ...options.package.peerDependencies,
+        }),
+      },
       shims,
       testShims,
       mappings: options.mappings,
       target: scriptTarget,
       polyfills,
+      platform: options.platform,
+      conditions: options.conditions,
+      assetInlineLimit: options.assetInlineLimit,
+      sourceMaps,
       importMap: options.importMap,
       configFile: options.configFile,
       frozenLockfile: options.frozenLockfile,
+      lockedDependencyVersions: options.lockedDependencyVersions,
+      npmRegistryDir: options.npmRegistryDir,
+      pinNpmVersions: options.pinNpmVersions,
       cwd,
     });
   }
 
+  /** Gets entrypoints in the order of the transformed ones, which are the
+   * exports of the config file followed by the binary entrypoints. */
+  function getConfigEntryPoints(configPackage: ConfigPackage): EntryPoint[] {
+    const outputPaths = transformOutput.main.entryPoints;
+    const binEntryPoints = entryPoints.filter((e) => e.kind === "bin");
+    if (
+      outputPaths.length !==
+        configPackage.exports.length + binEntryPoints.length
+    ) {
+      throw new Error(
+        "A binary entrypoint can't also be one of the exports of the " +
+          "deno.json when using `entryPointsFromConfig`.",
+      );
+    }
+    return [
+      ...configPackage.exports.map((name, i) => ({
+        kind: "export" as const,
+        name,
+        path: outputPaths[i],
+      })),
+      ...binEntryPoints,
+    ];
+  }
+
   function getBinOnlyEntryPointPaths() {
     const exportPaths = new Set(
       entryPoints.filter((e) => (e.kind ?? "export") !== "bin").map((e) =>
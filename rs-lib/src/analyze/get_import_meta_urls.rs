@@ -0,0 +1,124 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use deno_ast::swc::ast::MetaPropKind;
+use deno_ast::swc::common::SyntaxContext;
+use deno_ast::view::*;
+use deno_ast::SourceRange;
+use deno_ast::SourceRanged;
+use deno_graph::Position;
+
+/// A `new URL("./file", import.meta.url)` expression.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImportMetaUrl {
+  /// The string literal that's resolved relative to the module.
+  pub specifier: String,
+  /// Range of the string literal's text without the quotes.
+  pub specifier_range: SourceRange,
+  pub position: Position,
+  /// If it's the script of a `new Worker(...)`.
+  pub is_worker: bool,
+}
+
+/// Gets the `new URL(<string literal>, import.meta.url)` expressions that
+/// reference files relative to the module.
+///
+/// The program must have been parsed with scope analysis.
+pub fn get_import_meta_urls(
+  program: Program,
+  unresolved_context: SyntaxContext,
+) -> Vec<ImportMetaUrl> {
+  let mut results = Vec::new();
+  visit_children(program.into(), program, unresolved_context, &mut results);
+  results
+}
+
+fn visit_children(
+  node: Node,
+  program: Program,
+  unresolved_context: SyntaxContext,
+  results: &mut Vec<ImportMetaUrl>,
+) {
+  if let Node::NewExpr(expr) = node {
+    if let Some(specifier) = get_specifier(expr, unresolved_context) {
+      results.push(ImportMetaUrl {
+        specifier: specifier.value().to_string_lossy().into_owned(),
+        specifier_range: SourceRange::new(
+          specifier.start() + 1,
+          specifier.end() - 1,
+        ),
+        position: Position {
+          line: expr.start_line_fast(program),
+          character: expr.start_column_fast(program),
+        },
+        is_worker: is_worker_script(expr, unresolved_context),
+      });
+    }
+  }
+
+  for child in node.children() {
+    visit_children(child, program, unresolved_context, results);
+  }
+}
+
+fn get_specifier<'a>(
+  expr: &NewExpr<'a>,
+  unresolved_context: SyntaxContext,
+) -> Option<&'a Str<'a>> {
+  if !is_global_ident(expr.callee, "URL", unresolved_context) {
+    return None;
+  }
+  let [specifier_arg, base_arg] = expr.args? else {
+    return None;
+  };
+  if specifier_arg.inner.spread.is_some() || base_arg.inner.spread.is_some() {
+    return None;
+  }
+  let Expr::Lit(Lit::Str(specifier)) = specifier_arg.expr else {
+    return None;
+  };
+  if !is_import_meta_url(base_arg.expr) {
+    return None;
+  }
+  Some(specifier)
+}
+
+/// Gets if the expression is the first argument of `new Worker(...)`.
+fn is_worker_script(expr: &NewExpr, unresolved_context: SyntaxContext) -> bool {
+  let Node::ExprOrSpread(arg) = expr.parent() else {
+    return false;
+  };
+  let Node::NewExpr(parent) = arg.parent() else {
+    return false;
+  };
+  is_global_ident(parent.callee, "Worker", unresolved_context)
+    && parent
+      .args
+      .and_then(|args| args.first())
+      .is_some_and(|first| first.range() == arg.range())
+}
+
+/// Gets if the expression refers to the global with the provided name
+/// rather than a declaration that shadows it.
+fn is_global_ident(
+  expr: Expr,
+  name: &str,
+  unresolved_context: SyntaxContext,
+) -> bool {
+  match expr {
+    Expr::Ident(ident) => {
+      ident.sym() == name && ident.ctxt() == unresolved_context
+    }
+    _ => false,
+  }
+}
+
+fn is_import_meta_url(expr: Expr) -> bool {
+  let Expr::Member(member) = expr else {
+    return false;
+  };
+  let Expr::MetaProp(meta) = member.obj else {
+    return false;
+  };
+  meta.inner.kind == MetaPropKind::ImportMeta
+    && matches!(member.prop, MemberProp::Ident(ident) if ident.sym() == "url")
+}
@@ -0,0 +1,129 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use deno_ast::swc::ast::Id;
+use deno_ast::view::*;
+
+use super::is_only_used_as_type;
+
+/// Gets the module specifiers of the static imports and re-exports that are
+/// kept in the emitted JavaScript, which excludes the ones that TypeScript
+/// elides because they're only used in type positions.
+///
+/// The program must have been parsed with scope analysis.
+pub fn get_runtime_import_specifiers(program: Program) -> HashSet<String> {
+  let mut results = HashSet::new();
+  let Program::Module(module) = program else {
+    return results;
+  };
+
+  let mut imported_ids: HashMap<Id, String> = HashMap::new();
+  for item in module.body.iter() {
+    let ModuleItem::ModuleDecl(decl) = item else {
+      continue;
+    };
+    match decl {
+      ModuleDecl::Import(import) => {
+        if import.type_only() {
+          continue;
+        }
+        let specifier = str_value(import.src);
+        if import.specifiers.is_empty() {
+          // side effect import
+          results.insert(specifier);
+          continue;
+        }
+        for import_specifier in import.specifiers.iter() {
+          let local = match import_specifier {
+            ImportSpecifier::Named(named) => {
+              if named.is_type_only() {
+                continue;
+              }
+              named.local
+            }
+            ImportSpecifier::Default(default) => default.local,
+            ImportSpecifier::Namespace(namespace) => namespace.local,
+          };
+          imported_ids.insert(local.inner.to_id(), specifier.clone());
+        }
+      }
+      ModuleDecl::ExportNamed(export) => {
+        let Some(src) = export.src else {
+          continue;
+        };
+        let is_runtime = !export.type_only()
+          && export.specifiers.iter().any(|s| match s {
+            ExportSpecifier::Named(named) => !named.is_type_only(),
+            ExportSpecifier::Default(_) | ExportSpecifier::Namespace(_) => true,
+          });
+        if is_runtime {
+          results.insert(str_value(src));
+        }
+      }
+      ModuleDecl::ExportAll(export) => {
+        if !export.type_only() {
+          results.insert(str_value(export.src));
+        }
+      }
+      _ => {}
+    }
+  }
+
+  if !imported_ids.is_empty() {
+    let mut type_only_ids = HashSet::new();
+    let mut runtime_ids = HashSet::new();
+    visit_children(
+      program.into(),
+      &imported_ids,
+      &mut type_only_ids,
+      &mut runtime_ids,
+    );
+    for (id, specifier) in imported_ids {
+      // keep an import that isn't used at all, which is only elided when
+      // the compiler options say so
+      if runtime_ids.contains(&id) || !type_only_ids.contains(&id) {
+        results.insert(specifier);
+      }
+    }
+  }
+  results
+}
+
+fn visit_children(
+  node: Node,
+  imported_ids: &HashMap<Id, String>,
+  type_only_ids: &mut HashSet<Id>,
+  runtime_ids: &mut HashSet<Id>,
+) {
+  if let Node::Ident(ident) = node {
+    let id = ident.inner.to_id();
+    if imported_ids.contains_key(&id) && !is_import_specifier(node) {
+      if is_only_used_as_type(node) {
+        type_only_ids.insert(id);
+      } else {
+        runtime_ids.insert(id);
+      }
+    }
+  }
+
+  for child in node.children() {
+    visit_children(child, imported_ids, type_only_ids, runtime_ids);
+  }
+}
+
+fn str_value(str: &Str) -> String {
+  str.value().to_string_lossy().into_owned()
+}
+
+fn is_import_specifier(node: Node) -> bool {
+  matches!(
+    node.parent(),
+    Some(
+      Node::ImportNamedSpecifier(_)
+        | Node::ImportDefaultSpecifier(_)
+        | Node::ImportStarAsSpecifier(_)
+    )
+  )
+}
@@ -0,0 +1,48 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::HashSet;
+
+use deno_ast::swc::common::SyntaxContext;
+use deno_ast::view::*;
+
+use super::is_in_type;
+
+/// Gets if the program uses the global at runtime, which excludes type
+/// positions and feature checks (ex. `typeof Deno !== "undefined"`).
+///
+/// The program must have been parsed with scope analysis.
+pub fn has_global_usage(
+  program: Program,
+  unresolved_context: SyntaxContext,
+  top_level_decls: &HashSet<String>,
+  global_name: &str,
+) -> bool {
+  if top_level_decls.contains(global_name) {
+    return false;
+  }
+  return visit_children(program.into(), unresolved_context, global_name);
+
+  fn visit_children(
+    node: Node,
+    unresolved_context: SyntaxContext,
+    global_name: &str,
+  ) -> bool {
+    if let Node::Ident(ident) = node {
+      return ident.ctxt() == unresolved_context
+        && ident.sym() == global_name
+        && !is_in_type(node)
+        && !is_typeof_operand(node);
+    }
+    node
+      .children()
+      .into_iter()
+      .any(|child| visit_children(child, unresolved_context, global_name))
+  }
+}
+
+fn is_typeof_operand(node: Node) -> bool {
+  match node.parent() {
+    Some(Node::UnaryExpr(expr)) => expr.op() == UnaryOp::TypeOf,
+    _ => false,
+  }
+}
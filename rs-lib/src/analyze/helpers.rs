@@ -3,7 +3,18 @@
 use deno_ast::view::*;
 use deno_ast::SourceRanged;
 
-pub fn is_in_type(mut node: Node) -> bool {
+pub fn is_in_type(node: Node) -> bool {
+  is_in_type_inner(node, false)
+}
+
+/// Gets if the node is only used as a type, which unlike [`is_in_type`]
+/// counts the expressions that only wrap a value (ex. `x!` or `x as const`)
+/// and parameter properties as runtime code.
+pub fn is_only_used_as_type(node: Node) -> bool {
+  is_in_type_inner(node, true)
+}
+
+fn is_in_type_inner(mut node: Node, value_wrappers_are_runtime: bool) -> bool {
   // todo: add unit tests and investigate if there's something in swc that does this?
   while let Some(parent) = node.parent() {
     let is_type = match parent {
@@ -125,10 +136,12 @@ pub fn is_in_type(mut node: Node) -> bool {
       | Node::TsNamespaceDecl(_)
       | Node::TsNamespaceExportDecl(_)
       | Node::TsInstantiation(_) => Some(false),
+      Node::TsConstAssertion(_)
+      | Node::TsNonNullExpr(_)
+      | Node::TsParamProp(_) => Some(!value_wrappers_are_runtime),
       Node::TsArrayType(_)
       | Node::TsCallSignatureDecl(_)
       | Node::TsConditionalType(_)
-      | Node::TsConstAssertion(_)
       | Node::TsConstructSignatureDecl(_)
       | Node::TsConstructorType(_)
       | Node::TsExprWithTypeArgs(_)
@@ -146,9 +159,7 @@ pub fn is_in_type(mut node: Node) -> bool {
       | Node::TsLitType(_)
       | Node::TsMappedType(_)
       | Node::TsMethodSignature(_)
-      | Node::TsNonNullExpr(_)
       | Node::TsOptionalType(_)
-      | Node::TsParamProp(_)
       | Node::TsParenthesizedType(_)
       | Node::TsPropertySignature(_)
       | Node::TsQualifiedName(_)
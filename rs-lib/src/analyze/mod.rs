@@ -1,9 +1,15 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
 mod get_ignore_line_indexes;
+mod get_import_meta_urls;
+mod get_runtime_import_specifiers;
 mod get_top_level_decls;
+mod has_global_usage;
 mod helpers;
 
 pub use get_ignore_line_indexes::*;
+pub use get_import_meta_urls::*;
+pub use get_runtime_import_specifiers::*;
 pub use get_top_level_decls::*;
+pub use has_global_usage::*;
 pub use helpers::*;
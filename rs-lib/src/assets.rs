@@ -0,0 +1,316 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::BTreeMap;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use anyhow::Result;
+use deno_ast::ModuleSpecifier;
+use deno_graph::Module;
+use deno_path_util::url_to_file_path;
+
+use crate::graph::display_specifier;
+use crate::graph::ModuleGraph;
+use crate::utils::get_relative_path;
+use crate::utils::with_extension;
+use crate::Platform;
+
+/// How a file is imported when it's imported with a `type` attribute of
+/// `"text"` or `"bytes"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetKind {
+  /// Imported as a string.
+  Text,
+  /// Imported as a `Uint8Array`.
+  Bytes,
+}
+
+impl AssetKind {
+  fn from_attribute_type(value: &str) -> Option<Self> {
+    match value {
+      "text" => Some(Self::Text),
+      "bytes" => Some(Self::Bytes),
+      _ => None,
+    }
+  }
+
+  fn as_attribute_type(&self) -> &'static str {
+    match self {
+      Self::Text => "text",
+      Self::Bytes => "bytes",
+    }
+  }
+}
+
+/// Gets how each file that's imported as text or bytes is imported.
+pub fn get_asset_kinds<'a>(
+  module_graph: &ModuleGraph,
+  modules: impl Iterator<Item = &'a &'a Module>,
+) -> Result<BTreeMap<ModuleSpecifier, AssetKind>> {
+  let mut kinds = BTreeMap::new();
+  for module in modules.filter_map(|m| m.js()) {
+    for dep in module.dependencies.values() {
+      let Some(specifier) = dep.get_code() else {
+        continue;
+      };
+      let specifier = module_graph.resolve(specifier);
+      for import in dep.imports.iter() {
+        let Some(kind) = import
+          .attributes
+          .get("type")
+          .and_then(AssetKind::from_attribute_type)
+        else {
+          continue;
+        };
+        if !matches!(
+          module_graph.try_get(specifier),
+          Some(Module::External(external)) if external.was_asset_load
+        ) {
+          bail!(
+            "{} is imported with a type of \"{}\" and also as a module, which isn't supported.\n    at {}",
+            display_specifier(specifier),
+            kind.as_attribute_type(),
+            import.specifier_range,
+          );
+        }
+        match kinds.insert(specifier.clone(), kind) {
+          Some(previous_kind) if previous_kind != kind => bail!(
+            "{} is imported with a type of both \"text\" and \"bytes\", which isn't supported.\n    at {}",
+            display_specifier(specifier),
+            import.specifier_range,
+          ),
+          _ => {}
+        }
+      }
+    }
+  }
+  Ok(kinds)
+}
+
+/// Gets the path of the copy of a file that's imported as text or bytes
+/// based on the path of the module that's generated for it.
+pub fn get_asset_file_path(
+  specifier: &ModuleSpecifier,
+  module_file_path: &Path,
+) -> PathBuf {
+  let file_path = with_extension(module_file_path, "");
+  // ex. the module for `data.json` is `data.ts`
+  match Path::new(specifier.path()).extension() {
+    Some(ext) if file_path.extension() != Some(ext) => {
+      with_extension(&file_path, &ext.to_string_lossy())
+    }
+    _ => file_path,
+  }
+}
+
+/// Gets the output path of a file that a module references with
+/// `new URL("./file", import.meta.url)`, which keeps its location relative
+/// to the module's output path.
+///
+/// Returns `None` when that's outside the output directory.
+pub fn get_referenced_file_path(
+  referrer: &ModuleSpecifier,
+  referrer_file_path: &Path,
+  specifier: &ModuleSpecifier,
+) -> Option<PathBuf> {
+  let referrer_dir = url_to_file_path(referrer).ok()?.parent()?.to_owned();
+  let relative_path =
+    pathdiff::diff_paths(url_to_file_path(specifier).ok()?, referrer_dir)?;
+  let mut file_path = PathBuf::new();
+  for component in referrer_file_path
+    .parent()?
+    .components()
+    .chain(relative_path.components())
+  {
+    match component {
+      Component::Normal(name) => file_path.push(name),
+      Component::ParentDir => {
+        if !file_path.pop() {
+          return None;
+        }
+      }
+      Component::CurDir => {}
+      Component::Prefix(_) | Component::RootDir => return None,
+    }
+  }
+  Some(file_path)
+}
+
+pub struct AssetModuleOptions<'a> {
+  pub kind: AssetKind,
+  pub bytes: &'a [u8],
+  pub module_file_path: &'a Path,
+  /// Path of the copy of the file or `None` to inline its content in
+  /// the module.
+  pub asset_file_path: Option<&'a Path>,
+  pub platform: Platform,
+}
+
+/// Gets the text of the TypeScript module that has the file's content
+/// as its default export.
+pub fn get_asset_module_text(options: AssetModuleOptions) -> String {
+  let Some(asset_file_path) = options.asset_file_path else {
+    let value = match options.kind {
+      AssetKind::Text => {
+        serde_json::to_string(&String::from_utf8_lossy(options.bytes)).unwrap()
+      }
+      AssetKind::Bytes => format!(
+        "Uint8Array.from(atob(\"{}\"), (c) => c.charCodeAt(0))",
+        base64::encode(options.bytes)
+      ),
+    };
+    return get_default_export_text(options.kind, &value);
+  };
+
+  let url = format!(
+    "new URL(\"./{}\", import.meta.url)",
+    get_relative_path(options.module_file_path, asset_file_path)
+      .to_string_lossy()
+      .replace('\\', "/")
+  );
+  match options.platform {
+    Platform::Node => {
+      let value = match options.kind {
+        AssetKind::Text => format!("readFileSync({url}, \"utf8\")"),
+        AssetKind::Bytes => format!("new Uint8Array(readFileSync({url}))"),
+      };
+      format!(
+        "import {{ readFileSync }} from \"node:fs\";\n\n{}",
+        get_default_export_text(options.kind, &value)
+      )
+    }
+    Platform::Browser => {
+      let value = match options.kind {
+        AssetKind::Text => format!("await (await fetch({url})).text()"),
+        AssetKind::Bytes => {
+          format!("new Uint8Array(await (await fetch({url})).arrayBuffer())")
+        }
+      };
+      get_default_export_text(options.kind, &value)
+    }
+  }
+}
+
+fn get_default_export_text(kind: AssetKind, value: &str) -> String {
+  // the type is explicit so that the declaration file doesn't have the
+  // content as a string literal type
+  match kind {
+    AssetKind::Text => {
+      format!("const text: string = {value};\nexport default text;\n")
+    }
+    AssetKind::Bytes => {
+      format!("const bytes: Uint8Array = {value};\nexport default bytes;\n")
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_get_asset_file_path() {
+    let run_test = |specifier: &str, module_file_path: &str| {
+      get_asset_file_path(
+        &ModuleSpecifier::parse(specifier).unwrap(),
+        Path::new(module_file_path),
+      )
+    };
+    assert_eq!(
+      run_test("file:///data.txt", "data.txt.ts"),
+      PathBuf::from("data.txt")
+    );
+    assert_eq!(
+      run_test("file:///data.json", "data.ts"),
+      PathBuf::from("data.json")
+    );
+    assert_eq!(
+      run_test("file:///data.json", "data_2.ts"),
+      PathBuf::from("data_2.json")
+    );
+    assert_eq!(
+      run_test("https://localhost/data", "deps/localhost/data.ts"),
+      PathBuf::from("deps/localhost/data")
+    );
+  }
+
+  #[test]
+  fn test_get_referenced_file_path() {
+    let run_test =
+      |referrer: &str, referrer_file_path: &str, specifier: &str| {
+        get_referenced_file_path(
+          &ModuleSpecifier::parse(referrer).unwrap(),
+          Path::new(referrer_file_path),
+          &ModuleSpecifier::parse(specifier).unwrap(),
+        )
+      };
+    assert_eq!(
+      run_test(
+        "file:///project/src/mod.ts",
+        "mod.ts",
+        "file:///project/src/templates/page.html"
+      ),
+      Some(PathBuf::from("templates/page.html"))
+    );
+    assert_eq!(
+      run_test(
+        "file:///project/src/sub/mod.ts",
+        "sub/mod.ts",
+        "file:///project/src/data.json"
+      ),
+      Some(PathBuf::from("data.json"))
+    );
+    // outside the output directory
+    assert_eq!(
+      run_test(
+        "file:///project/src/mod.ts",
+        "mod.ts",
+        "file:///project/README.md"
+      ),
+      None
+    );
+  }
+
+  #[test]
+  fn test_get_asset_module_text() {
+    let run_test = |kind: AssetKind, inline: bool, platform: Platform| {
+      get_asset_module_text(AssetModuleOptions {
+        kind,
+        bytes: b"a\"b",
+        module_file_path: Path::new("data/a.txt.ts"),
+        asset_file_path: (!inline).then_some(Path::new("data/a.txt")),
+        platform,
+      })
+    };
+    assert_eq!(
+      run_test(AssetKind::Text, true, Platform::Node),
+      "const text: string = \"a\\\"b\";\nexport default text;\n"
+    );
+    assert_eq!(
+      run_test(AssetKind::Bytes, true, Platform::Node),
+      concat!(
+        "const bytes: Uint8Array = Uint8Array.from(atob(\"YSJi\"), (c) => c.charCodeAt(0));\n",
+        "export default bytes;\n"
+      )
+    );
+    assert_eq!(
+      run_test(AssetKind::Text, false, Platform::Node),
+      concat!(
+        "import { readFileSync } from \"node:fs\";\n\n",
+        "const text: string = readFileSync(new URL(\"./a.txt\", import.meta.url), \"utf8\");\n",
+        "export default text;\n"
+      )
+    );
+    assert_eq!(
+      run_test(AssetKind::Bytes, false, Platform::Browser),
+      concat!(
+        "const bytes: Uint8Array = new Uint8Array(await (await fetch(new URL(\"./a.txt\", import.meta.url))).arrayBuffer());\n",
+        "export default bytes;\n"
+      )
+    );
+  }
+}
@@ -0,0 +1,193 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use deno_ast::ModuleSpecifier;
+use deno_config::deno_json::ConfigFile;
+
+use crate::graph::display_specifier;
+
+/// The package that a deno.json describes for publishing to JSR.
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigPackage {
+  pub name: Option<String>,
+  pub version: Option<String>,
+  /// Names of the exports (ex. `.` or `./sub`), which are in the same order
+  /// as the entry points of the main environment.
+  pub exports: Vec<String>,
+}
+
+/// Gets the package of the config file along with the module that each of
+/// its exports resolves to.
+pub(crate) fn resolve_config_package(
+  config_file: &ConfigFile,
+) -> Result<(ConfigPackage, Vec<ModuleSpecifier>)> {
+  let mut exports = Vec::new();
+  let mut export_specifiers = Vec::new();
+  for (key, value) in config_file.to_exports_config()?.into_map() {
+    let specifier = config_file.specifier.join(&value).with_context(|| {
+      format!(
+        "Failed resolving export \"{}\" of {}",
+        key,
+        display_specifier(&config_file.specifier)
+      )
+    })?;
+    exports.push(key);
+    export_specifiers.push(specifier);
+  }
+  Ok((
+    ConfigPackage {
+      name: config_file.json.name.clone(),
+      version: config_file.json.version.clone(),
+      exports,
+    },
+    export_specifiers,
+  ))
+}
+
+/// Gets the entry points for the exports of the config file, which are
+/// followed by any binary entry point that isn't exported.
+///
+/// Provided entry points that aren't only used as a binary must be the same
+/// modules as the exports, and the config file must have some exports.
+pub(crate) fn get_config_entry_points(
+  config_specifier: &ModuleSpecifier,
+  package: &ConfigPackage,
+  export_specifiers: &[ModuleSpecifier],
+  entry_points: &[ModuleSpecifier],
+  bin_entry_points: &[ModuleSpecifier],
+) -> Result<Vec<ModuleSpecifier>> {
+  if export_specifiers.is_empty() {
+    bail!(
+      "Deriving the entry points from the config file requires `exports` in {}",
+      display_specifier(config_specifier),
+    );
+  }
+  let mut differences = Vec::new();
+  for entry_point in entry_points {
+    if !bin_entry_points.contains(entry_point)
+      && !export_specifiers.contains(entry_point)
+    {
+      differences.push(format!(
+        "{} is an entry point, but not an export",
+        display_specifier(entry_point)
+      ));
+    }
+  }
+  // the provided entry points only need to agree with the exports when
+  // there are some that aren't binaries
+  if entry_points.iter().any(|e| !bin_entry_points.contains(e)) {
+    for (name, specifier) in package.exports.iter().zip(export_specifiers) {
+      if !entry_points.contains(specifier) {
+        differences.push(format!(
+          "{} is the \"{}\" export, but not an entry point",
+          display_specifier(specifier),
+          name,
+        ));
+      }
+    }
+  }
+  if !differences.is_empty() {
+    bail!(
+      "The entry points don't match the exports of {}:\n  * {}",
+      display_specifier(config_specifier),
+      differences.join("\n  * "),
+    );
+  }
+
+  let mut result = export_specifiers.to_vec();
+  for entry_point in bin_entry_points {
+    if !result.contains(entry_point) {
+      result.push(entry_point.clone());
+    }
+  }
+  Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_get_config_entry_points() {
+    let config = specifier("deno.json");
+    let package = ConfigPackage {
+      name: Some("@scope/pkg".to_string()),
+      version: None,
+      exports: vec![".".to_string(), "./sub".to_string()],
+    };
+    let exports = vec![specifier("mod.ts"), specifier("sub.ts")];
+    let get = |entry_points: &[&str], bin_entry_points: &[&str]| {
+      get_config_entry_points(
+        &config,
+        &package,
+        &exports,
+        &entry_points
+          .iter()
+          .map(|e| specifier(e))
+          .collect::<Vec<_>>(),
+        &bin_entry_points
+          .iter()
+          .map(|e| specifier(e))
+          .collect::<Vec<_>>(),
+      )
+      .map_err(|err| err.to_string())
+    };
+
+    assert_eq!(get(&[], &[]), Ok(exports.clone()));
+    // same modules in a different order
+    assert_eq!(get(&["sub.ts", "mod.ts"], &[]), Ok(exports.clone()));
+    assert_eq!(
+      get(&["cli.ts"], &["cli.ts"]),
+      Ok(vec![
+        specifier("mod.ts"),
+        specifier("sub.ts"),
+        specifier("cli.ts")
+      ])
+    );
+    assert_eq!(
+      get(&["mod.ts", "other.ts"], &[]),
+      Err(
+        concat!(
+          "The entry points don't match the exports of file:///deno.json:\n",
+          "  * file:///other.ts is an entry point, but not an export\n",
+          "  * file:///sub.ts is the \"./sub\" export, but not an entry point",
+        )
+        .to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn test_get_config_entry_points_no_exports() {
+    let package = ConfigPackage {
+      name: Some("@scope/pkg".to_string()),
+      version: None,
+      exports: Vec::new(),
+    };
+    // a package without exports isn't made from only its binary
+    assert_eq!(
+      get_config_entry_points(
+        &specifier("deno.json"),
+        &package,
+        &[],
+        &[specifier("cli.ts")],
+        &[specifier("cli.ts")],
+      )
+      .map_err(|err| err.to_string()),
+      Err(
+        "Deriving the entry points from the config file requires `exports` in file:///deno.json"
+          .to_string()
+      )
+    );
+  }
+
+  fn specifier(path: &str) -> ModuleSpecifier {
+    ModuleSpecifier::parse(&format!("file:///{path}")).unwrap()
+  }
+}
@@ -0,0 +1,304 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::Result;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::Dependency;
+use crate::DependencyKind;
+use crate::TransformOutput;
+
+/// File in the output directory that lists the files and dependencies
+/// emitted to it, which is how the files that a later emit no longer
+/// outputs are found.
+pub const MANIFEST_FILE_NAME: &str = ".dnt-manifest.json";
+
+#[sys_traits::auto_impl]
+pub trait EmitSys:
+  sys_traits::FsCreateDirAll
+  + sys_traits::FsRead
+  + sys_traits::FsReadDir
+  + sys_traits::FsRemoveDir
+  + sys_traits::FsRemoveFile
+  + sys_traits::FsWrite
+{
+}
+
+/// What emitting the output did, with the paths relative to the output
+/// directory.
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct EmitResult {
+  /// Files that were written because they're new or their content changed.
+  pub written_files: Vec<PathBuf>,
+  /// Files that already had the same content, so they weren't written.
+  pub unchanged_files: Vec<PathBuf>,
+  /// Files that a previous emit wrote, but that aren't in the output.
+  pub removed_files: Vec<PathBuf>,
+}
+
+pub fn emit_output(
+  sys: &impl EmitSys,
+  output: &TransformOutput,
+  out_dir: &Path,
+) -> Result<EmitResult> {
+  let previous_files = EmitManifest::read(sys, out_dir)
+    .map(|manifest| manifest.files)
+    .unwrap_or_default();
+
+  let mut result = EmitResult::default();
+  let mut emitted_files = HashSet::new();
+  for (file_path, content) in get_output_contents(output) {
+    let full_path = out_dir.join(&file_path);
+    let is_unchanged = sys
+      .fs_read(&full_path)
+      .is_ok_and(|existing| existing.as_ref() == content.as_ref());
+    if is_unchanged {
+      result.unchanged_files.push(file_path.clone());
+    } else {
+      if let Some(parent) = full_path.parent() {
+        sys.fs_create_dir_all(parent).with_context(|| {
+          format!("Failed creating directory {}", parent.display())
+        })?;
+      }
+      sys
+        .fs_write(&full_path, &content)
+        .with_context(|| format!("Failed writing {}", full_path.display()))?;
+      result.written_files.push(file_path.clone());
+    }
+    emitted_files.insert(file_path);
+  }
+
+  for file_path in previous_files {
+    if emitted_files.contains(&file_path) {
+      continue;
+    }
+    let full_path = out_dir.join(&file_path);
+    match sys.fs_remove_file(&full_path) {
+      Ok(()) => {}
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+      Err(err) => {
+        return Err(err)
+          .with_context(|| format!("Failed removing {}", full_path.display()))
+      }
+    }
+    remove_empty_dirs(sys, out_dir, &full_path);
+    result.removed_files.push(file_path);
+  }
+  result.removed_files.sort();
+
+  let mut files = emitted_files.into_iter().collect::<Vec<_>>();
+  files.sort();
+  let manifest = EmitManifest {
+    files,
+    dependencies: output.package_manifest.dependencies.clone(),
+  };
+  let manifest_path = out_dir.join(MANIFEST_FILE_NAME);
+  sys
+    .fs_create_dir_all(out_dir)
+    .and_then(|()| sys.fs_write(&manifest_path, manifest.to_json().to_string()))
+    .with_context(|| format!("Failed writing {}", manifest_path.display()))?;
+
+  Ok(result)
+}
+
+/// What the last emit wrote to the output directory.
+pub struct EmitManifest {
+  /// Paths of the files relative to the output directory.
+  pub files: Vec<PathBuf>,
+  /// Dependencies of the output's package manifest.
+  pub dependencies: Vec<Dependency>,
+}
+
+impl EmitManifest {
+  /// Reads the manifest of the output directory, which is `None` when
+  /// nothing was emitted to it or the manifest can't be read.
+  pub fn read(sys: &impl sys_traits::FsRead, out_dir: &Path) -> Option<Self> {
+    let text = sys
+      .fs_read_to_string(out_dir.join(MANIFEST_FILE_NAME))
+      .ok()?;
+    Self::from_json(&serde_json::from_str(&text).ok()?)
+  }
+
+  fn from_json(value: &Value) -> Option<Self> {
+    let files = value
+      .get("files")?
+      .as_array()?
+      .iter()
+      .map(|file| file.as_str().map(PathBuf::from))
+      // never touch anything outside the output directory
+      .filter(|file_path| {
+        file_path.as_ref().is_none_or(|file_path| {
+          file_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        })
+      })
+      .collect::<Option<Vec<_>>>()?;
+    let dependencies = match value.get("dependencies") {
+      Some(dependencies) => dependencies
+        .as_array()?
+        .iter()
+        .map(|dependency| {
+          Some(Dependency {
+            name: dependency.get("name")?.as_str()?.to_string(),
+            version: dependency.get("version")?.as_str()?.to_string(),
+            kind: parse_dependency_kind(dependency.get("kind")?.as_str()?)?,
+          })
+        })
+        .collect::<Option<Vec<_>>>()?,
+      None => Vec::new(),
+    };
+    Some(Self {
+      files,
+      dependencies,
+    })
+  }
+
+  fn to_json(&self) -> Value {
+    json!({
+      "files": self
+        .files
+        .iter()
+        .map(|file_path| file_path.to_string_lossy().replace('\\', "/"))
+        .collect::<Vec<_>>(),
+      "dependencies": self
+        .dependencies
+        .iter()
+        .map(|dependency| json!({
+          "name": dependency.name,
+          "version": dependency.version,
+          "kind": dependency_kind_name(dependency.kind),
+        }))
+        .collect::<Vec<_>>(),
+    })
+  }
+}
+
+/// Gets the name of the kind, which is the same as in the serialized
+/// output.
+fn dependency_kind_name(kind: DependencyKind) -> &'static str {
+  match kind {
+    DependencyKind::Dependency => "dependency",
+    DependencyKind::Dev => "dev",
+    DependencyKind::Optional => "optional",
+    DependencyKind::OptionalPeer => "optionalPeer",
+    DependencyKind::Peer => "peer",
+  }
+}
+
+fn parse_dependency_kind(name: &str) -> Option<DependencyKind> {
+  match name {
+    "dependency" => Some(DependencyKind::Dependency),
+    "dev" => Some(DependencyKind::Dev),
+    "optional" => Some(DependencyKind::Optional),
+    "optionalPeer" => Some(DependencyKind::OptionalPeer),
+    "peer" => Some(DependencyKind::Peer),
+    _ => None,
+  }
+}
+
+/// Gets the path and content of each file that's emitted for the output.
+///
+/// A file with a source map gets a `sourceMappingURL` comment that points
+/// at the map emitted beside it.
+pub fn get_output_contents(
+  output: &TransformOutput,
+) -> impl Iterator<Item = (PathBuf, Cow<'_, [u8]>)> {
+  let files = output
+    .main
+    .files
+    .iter()
+    .chain(output.test.files.iter())
+    .flat_map(|file| {
+      let Some(source_map) = &file.source_map else {
+        return vec![(
+          file.file_path.clone(),
+          Cow::Borrowed(file.file_text.as_bytes()),
+        )];
+      };
+      let mut map_file_path = file.file_path.clone().into_os_string();
+      map_file_path.push(".map");
+      let map_file_path = PathBuf::from(map_file_path);
+      let file_text = format!(
+        "{}\n//# sourceMappingURL={}",
+        file.file_text,
+        map_file_path.file_name().unwrap().to_string_lossy()
+      );
+      vec![
+        (file.file_path.clone(), Cow::Owned(file_text.into_bytes())),
+        (map_file_path, Cow::Borrowed(source_map.as_bytes())),
+      ]
+    });
+  let assets = output
+    .main
+    .assets
+    .iter()
+    .chain(output.test.assets.iter())
+    .map(|asset| {
+      (
+        asset.file_path.clone(),
+        Cow::Borrowed(asset.bytes.as_slice()),
+      )
+    });
+  files.chain(assets)
+}
+
+/// Removes the directories of a removed file that are now empty, up to the
+/// output directory.
+fn remove_empty_dirs(sys: &impl EmitSys, out_dir: &Path, file_path: &Path) {
+  let mut dir = file_path.parent();
+  while let Some(current) = dir {
+    if current == out_dir || !current.starts_with(out_dir) {
+      break;
+    }
+    let is_empty = sys
+      .fs_read_dir(current)
+      .is_ok_and(|mut entries| entries.next().is_none());
+    if !is_empty || sys.fs_remove_dir(current).is_err() {
+      break;
+    }
+    dir = current.parent();
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn manifest_json() {
+    let manifest = EmitManifest {
+      files: vec![PathBuf::from("a.ts"), PathBuf::from("b/c.ts")],
+      dependencies: vec![Dependency {
+        name: "a".to_string(),
+        version: "^1.0.0".to_string(),
+        kind: DependencyKind::OptionalPeer,
+      }],
+    };
+    let value = manifest.to_json();
+    let parsed = EmitManifest::from_json(&value).unwrap();
+    assert_eq!(parsed.files, manifest.files);
+    assert_eq!(parsed.dependencies, manifest.dependencies);
+    assert!(EmitManifest::from_json(&json!({ "files": [1] })).is_none());
+  }
+
+  #[test]
+  fn manifest_outside_out_dir() {
+    let value = json!({ "files": ["a.ts", "../b.ts", "/c.ts", "d/e.ts"] });
+    assert_eq!(
+      EmitManifest::from_json(&value).unwrap().files,
+      vec![PathBuf::from("a.ts"), PathBuf::from("d/e.ts")]
+    );
+  }
+}
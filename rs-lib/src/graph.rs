@@ -2,11 +2,15 @@
 
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt::Write;
 use std::rc::Rc;
+use std::sync::Arc;
 
+use crate::analyze::get_import_meta_urls;
 use crate::loader::get_all_specifier_mappers;
 use crate::loader::SourceLoader;
+use crate::locked_versions::LockedDependencyVersions;
 use crate::parser::ScopeAnalysisParser;
 use crate::specifiers::get_specifiers;
 use crate::specifiers::Specifiers;
@@ -14,6 +18,7 @@ use crate::MappedSpecifier;
 use crate::PackageMappedSpecifier;
 
 use anyhow::bail;
+use anyhow::Context;
 use anyhow::Result;
 use deno_ast::ModuleSpecifier;
 use deno_ast::ParseDiagnostic;
@@ -22,6 +27,11 @@ use deno_graph::ast::CapturingModuleAnalyzer;
 use deno_graph::ast::EsParser;
 use deno_graph::ast::ParseOptions;
 use deno_graph::ast::ParsedSourceStore;
+use deno_graph::ast::ParserModuleAnalyzer;
+use deno_graph::source::CacheSetting;
+use deno_graph::source::LoadOptions;
+use deno_graph::source::LoadResponse;
+use deno_graph::source::Loader;
 use deno_graph::source::NullModuleInfoCacher;
 use deno_graph::source::ResolutionKind;
 use deno_graph::source::ResolveError;
@@ -29,6 +39,7 @@ use deno_graph::source::Resolver;
 use deno_graph::JsModule;
 use deno_graph::Module;
 use deno_graph::ModuleGraphError;
+use deno_graph::PositionRange;
 use deno_graph::Range;
 use deno_resolver::deno_json::CompilerOptionsResolver;
 use deno_resolver::deno_json::JsxImportSourceConfigResolver;
@@ -36,6 +47,7 @@ use deno_resolver::factory::WorkspaceFactorySys;
 use deno_resolver::graph::DefaultDenoResolverRc;
 use deno_resolver::npm::DenoInNpmPackageChecker;
 use deno_semver::jsr::JsrPackageReqReference;
+use deno_semver::package::PackageReq;
 use deno_semver::VersionRange;
 use deno_semver::VersionReq;
 use sys_traits::impls::RealSys;
@@ -48,18 +60,43 @@ pub struct ModuleGraphOptions<'a, TSys: WorkspaceFactorySys> {
   pub specifier_mappings: &'a HashMap<ModuleSpecifier, MappedSpecifier>,
   /// Original key of each mapping, which is what the user specified.
   pub specifier_mapping_keys: &'a HashMap<ModuleSpecifier, String>,
+  /// Package mappings that don't need to be found in the graph (ex. the
+  /// exports of the other members of a workspace).
+  pub optional_specifier_mappings: &'a HashSet<ModuleSpecifier>,
   pub compiler_options_resolver: Rc<CompilerOptionsResolver>,
   pub cjs_tracker:
     Rc<deno_resolver::cjs::CjsTracker<DenoInNpmPackageChecker, TSys>>,
   /// The project's deno lockfile, used to lock module versions and verify
   /// remote module checksums while building the graph.
   pub maybe_lockfile: Option<deno_resolver::lockfile::LockfileLockRc<TSys>>,
+  /// Derives the versions of the dependencies from the lockfile.
+  pub locked_dependency_versions: Option<LockedDependencyVersions>,
 }
 
 /// Wrapper around deno_graph::ModuleGraph.
 pub struct ModuleGraph {
   graph: deno_graph::ModuleGraph,
-  capturing_analyzer: CapturingModuleAnalyzer,
+  /// Modules parsed while building the graph, which are kept in a map
+  /// rather than the analyzer's store so the graph can be shared between
+  /// threads.
+  parsed_sources: HashMap<ModuleSpecifier, ParsedSource>,
+  /// Content of the files imported as text or bytes, which the graph
+  /// doesn't keep.
+  assets: HashMap<ModuleSpecifier, Arc<[u8]>>,
+  referenced_files: Vec<ReferencedFile>,
+  /// Scripts of the workers that each module creates with
+  /// `new Worker(new URL("./worker.ts", import.meta.url))`.
+  workers: HashMap<ModuleSpecifier, Vec<ModuleSpecifier>>,
+}
+
+/// A file that a local module references with
+/// `new URL("./file", import.meta.url)`.
+pub struct ReferencedFile {
+  pub referrer: ModuleSpecifier,
+  pub specifier: ModuleSpecifier,
+  pub range: Range,
+  /// Content of the file or `None` when it wasn't found.
+  pub content: Option<Arc<[u8]>>,
 }
 
 impl ModuleGraph {
@@ -70,11 +107,14 @@ impl ModuleGraph {
     let loader = options.loader;
     let jsr_specifier_mappings =
       JsrSpecifierMappings::new(options.specifier_mappings);
+    let prefix_specifier_mappings =
+      PrefixSpecifierMappings::new(options.specifier_mappings);
     let loader = SourceLoader::new(
       loader,
       get_all_specifier_mappers(),
       options.specifier_mappings,
       &jsr_specifier_mappings,
+      &prefix_specifier_mappings,
     );
     let scoped_jsx_import_source_config =
       JsxImportSourceConfigResolver::from_compiler_options_resolver(
@@ -103,39 +143,46 @@ impl ModuleGraph {
       inner: &graph_resolver,
       mappings: &jsr_specifier_mappings,
     };
-    graph
-      .build(
-        options
-          .entry_points
-          .iter()
-          .chain(options.test_entry_points.iter())
-          .map(|s| s.to_owned())
-          .collect(),
-        Vec::new(),
-        &loader,
-        deno_graph::BuildOptions {
-          is_dynamic: false,
-          skip_dynamic_deps: false,
-          resolver: Some(&graph_resolver),
-          locker: locker
-            .as_mut()
-            .map(|l| l as &mut dyn deno_graph::source::Locker),
-          module_analyzer: &capturing_analyzer,
-          module_info_cacher: &NullModuleInfoCacher,
-          reporter: None,
-          npm_resolver: None,
-          file_system: &RealSys,
-          jsr_url_provider: Default::default(),
-          jsr_version_resolver: Default::default(),
-          jsr_metadata_store: None,
-          executor: Default::default(),
-          passthrough_jsr_specifiers: false,
-          unstable_bytes_imports: false,
-          unstable_text_imports: false,
-          unstable_css_imports: false,
-        },
-      )
-      .await;
+    let mut roots = options
+      .entry_points
+      .iter()
+      .chain(options.test_entry_points.iter())
+      .map(|s| s.to_owned())
+      .collect::<Vec<_>>();
+    let mut workers = HashMap::new();
+    // the scripts of workers aren't imported, so they're added as roots
+    // until there aren't any new ones
+    while !roots.is_empty() {
+      graph
+        .build(
+          roots,
+          Vec::new(),
+          &loader,
+          deno_graph::BuildOptions {
+            is_dynamic: false,
+            skip_dynamic_deps: false,
+            resolver: Some(&graph_resolver),
+            locker: locker
+              .as_mut()
+              .map(|l| l as &mut dyn deno_graph::source::Locker),
+            module_analyzer: &capturing_analyzer,
+            module_info_cacher: &NullModuleInfoCacher,
+            reporter: None,
+            npm_resolver: None,
+            file_system: &RealSys,
+            jsr_url_provider: Default::default(),
+            jsr_version_resolver: Default::default(),
+            jsr_metadata_store: None,
+            executor: Default::default(),
+            passthrough_jsr_specifiers: false,
+            unstable_bytes_imports: true,
+            unstable_text_imports: true,
+            unstable_css_imports: false,
+          },
+        )
+        .await;
+      roots = find_workers(&graph, &capturing_analyzer, &mut workers);
+    }
 
     let mut error_message = String::new();
     for error in graph.module_errors() {
@@ -191,10 +238,24 @@ impl ModuleGraph {
         .map_err(|err| anyhow::anyhow!("{}", err))?;
     }
 
-    let graph = Self {
+    let assets = load_assets(&graph, &loader).await?;
+    let parsed_sources = graph
+      .modules()
+      .filter_map(|module| module.js())
+      .filter_map(|module| {
+        let parsed_source =
+          capturing_analyzer.get_parsed_source(&module.specifier)?;
+        Some((module.specifier.clone(), parsed_source))
+      })
+      .collect();
+    let mut graph = Self {
       graph,
-      capturing_analyzer,
+      parsed_sources,
+      assets,
+      referenced_files: Vec::new(),
+      workers,
     };
+    graph.referenced_files = load_referenced_files(&graph, &loader).await?;
 
     let loader_specifiers = loader.into_specifiers();
 
@@ -226,8 +287,16 @@ impl ModuleGraph {
       &jsr_specifier_mappings,
       &graph,
       graph.all_modules(),
+      options.maybe_lockfile.as_ref().map(|l| l.lock()).as_deref(),
+      options.locked_dependency_versions,
     )?;
 
+    let mapped_specifiers = specifiers
+      .main
+      .mapped
+      .keys()
+      .chain(specifiers.test.mapped.keys())
+      .collect::<Vec<_>>();
     let not_found_package_specifiers = options
       .specifier_mappings
       .iter()
@@ -235,15 +304,11 @@ impl ModuleGraph {
         MappedSpecifier::Package(_) => Some(k),
         MappedSpecifier::Module(_) => None,
       })
+      .filter(|s| !options.optional_specifier_mappings.contains(s))
       .filter(|s| {
-        !jsr_specifier_mappings.was_found(
-          s,
-          specifiers
-            .main
-            .mapped
-            .keys()
-            .chain(specifiers.test.mapped.keys()),
-        )
+        !jsr_specifier_mappings.was_found(s, mapped_specifiers.iter().copied())
+          && !prefix_specifier_mappings
+            .was_found(s, mapped_specifiers.iter().copied())
       })
       .collect::<Vec<_>>();
     if !not_found_package_specifiers.is_empty() {
@@ -280,16 +345,33 @@ impl ModuleGraph {
     self.graph.get(specifier)
   }
 
+  /// Gets the content of a file that's imported as text or bytes.
+  pub fn get_asset(&self, specifier: &ModuleSpecifier) -> &[u8] {
+    self.assets.get(specifier).unwrap_or_else(|| {
+      panic!("dnt bug - Did not find asset: {specifier}");
+    })
+  }
+
+  /// Gets the scripts of the workers that the module creates.
+  pub fn get_workers(&self, specifier: &ModuleSpecifier) -> &[ModuleSpecifier] {
+    self
+      .workers
+      .get(specifier)
+      .map(|workers| workers.as_slice())
+      .unwrap_or_default()
+  }
+
+  pub fn referenced_files(&self) -> &[ReferencedFile] {
+    &self.referenced_files
+  }
+
   pub fn get_parsed_source(
     &self,
     js_module: &JsModule,
   ) -> Result<ParsedSource, ParseDiagnostic> {
-    match self
-      .capturing_analyzer
-      .get_parsed_source(&js_module.specifier)
-    {
-      Some(parsed_source) => Ok(parsed_source),
-      None => self.capturing_analyzer.parse_program(ParseOptions {
+    match self.parsed_sources.get(&js_module.specifier) {
+      Some(parsed_source) => Ok(parsed_source.clone()),
+      None => ScopeAnalysisParser.parse_program(ParseOptions {
         specifier: &js_module.specifier,
         source: js_module.source.text.clone(),
         media_type: js_module.media_type,
@@ -298,6 +380,102 @@ impl ModuleGraph {
     }
   }
 
+  /// Replaces the text of a module that changed since the graph was built.
+  ///
+  /// This is only done when the module still has the same dependencies at
+  /// the same positions, so that the rest of the graph stays the same, and
+  /// returns `false` when the graph needs to be built again instead.
+  pub fn try_update_module_text(
+    &mut self,
+    specifier: &ModuleSpecifier,
+    text: Arc<str>,
+  ) -> bool {
+    let Some(Module::Js(module)) = self.graph.get(specifier) else {
+      return false;
+    };
+    // which declaration file provides the types depends on its text
+    if module.media_type.is_declaration() {
+      return false;
+    }
+    let Ok(previous) = self.get_parsed_source(module) else {
+      return false;
+    };
+    let Ok(parsed_source) = ScopeAnalysisParser.parse_program(ParseOptions {
+      specifier,
+      source: text,
+      media_type: module.media_type,
+      scope_analysis: false,
+    }) else {
+      return false;
+    };
+    let get_dependencies = |parsed_source: &ParsedSource| {
+      let import_meta_urls = parsed_source.with_view(|program| {
+        get_import_meta_urls(program, parsed_source.unresolved_context())
+      });
+      (
+        ParserModuleAnalyzer::module_info(parsed_source),
+        import_meta_urls,
+      )
+    };
+    if get_dependencies(&previous) != get_dependencies(&parsed_source) {
+      return false;
+    }
+    self.parsed_sources.insert(specifier.clone(), parsed_source);
+    true
+  }
+
+  /// Gets the modules along with the ones that import them, directly or
+  /// through other modules.
+  pub fn with_dependents(
+    &self,
+    specifiers: impl IntoIterator<Item = ModuleSpecifier>,
+  ) -> HashSet<ModuleSpecifier> {
+    let mut dependents: HashMap<&ModuleSpecifier, Vec<&ModuleSpecifier>> =
+      HashMap::new();
+    for module in self.graph.modules() {
+      let Module::Js(module) = module else {
+        continue;
+      };
+      let dependencies = module
+        .dependencies
+        .values()
+        .flat_map(|dependency| {
+          [
+            dependency.maybe_code.maybe_specifier(),
+            dependency.maybe_type.maybe_specifier(),
+          ]
+        })
+        .chain(
+          module
+            .maybe_types_dependency
+            .iter()
+            .map(|types| types.dependency.maybe_specifier()),
+        )
+        .flatten()
+        .chain(self.get_workers(&module.specifier));
+      for dependency in dependencies {
+        dependents
+          .entry(self.resolve(dependency))
+          .or_default()
+          .push(&module.specifier);
+      }
+    }
+    let mut found = HashSet::new();
+    let mut pending = specifiers.into_iter().collect::<Vec<_>>();
+    while let Some(specifier) = pending.pop() {
+      if let Some(referrers) = dependents.get(&specifier) {
+        pending.extend(
+          referrers
+            .iter()
+            .filter(|referrer| !found.contains(**referrer))
+            .map(|referrer| (*referrer).clone()),
+        );
+      }
+      found.insert(specifier);
+    }
+    found
+  }
+
   pub fn resolve_dependency(
     &self,
     value: &str,
@@ -330,6 +508,153 @@ impl ModuleGraph {
   }
 }
 
+/// Loads the files that are only imported as text or bytes, since the graph
+/// doesn't load their content.
+async fn load_assets(
+  graph: &deno_graph::ModuleGraph,
+  loader: &SourceLoader<'_>,
+) -> Result<HashMap<ModuleSpecifier, Arc<[u8]>>> {
+  let mut assets = HashMap::new();
+  for module in graph.modules() {
+    let Module::External(module) = module else {
+      continue;
+    };
+    if !module.was_asset_load {
+      continue;
+    }
+    let response = loader
+      .load(
+        &module.specifier,
+        LoadOptions {
+          in_dynamic_branch: false,
+          was_dynamic_root: false,
+          cache_setting: CacheSetting::Use,
+          maybe_checksum: None,
+        },
+      )
+      .await
+      .with_context(|| format!("Failed loading {}", module.specifier))?;
+    match response {
+      Some(LoadResponse::Module { content, .. }) => {
+        assets.insert(module.specifier.clone(), content);
+      }
+      _ => bail!("Failed loading {}", module.specifier),
+    }
+  }
+  Ok(assets)
+}
+
+/// Finds the scripts of the workers created by the modules that haven't been
+/// searched yet, returning the ones that aren't in the graph.
+fn find_workers(
+  graph: &deno_graph::ModuleGraph,
+  capturing_analyzer: &CapturingModuleAnalyzer,
+  workers: &mut HashMap<ModuleSpecifier, Vec<ModuleSpecifier>>,
+) -> Vec<ModuleSpecifier> {
+  let mut new_roots = Vec::new();
+  for module in graph.modules() {
+    let Module::Js(module) = module else {
+      continue;
+    };
+    if module.media_type.is_declaration()
+      || workers.contains_key(&module.specifier)
+    {
+      continue;
+    }
+    let Some(parsed_source) =
+      capturing_analyzer.get_parsed_source(&module.specifier)
+    else {
+      continue;
+    };
+    let module_workers = parsed_source
+      .with_view(|program| {
+        get_import_meta_urls(program, parsed_source.unresolved_context())
+      })
+      .into_iter()
+      .filter(|import_meta_url| import_meta_url.is_worker)
+      .filter_map(|import_meta_url| {
+        module.specifier.join(&import_meta_url.specifier).ok()
+      })
+      .collect::<Vec<_>>();
+    for worker in module_workers.iter() {
+      if graph.get(worker).is_none() && !new_roots.contains(worker) {
+        new_roots.push(worker.clone());
+      }
+    }
+    workers.insert(module.specifier.clone(), module_workers);
+  }
+  new_roots
+}
+
+/// Loads the files that the local modules reference with
+/// `new URL("./file", import.meta.url)`, which aren't in the graph.
+async fn load_referenced_files(
+  graph: &ModuleGraph,
+  loader: &SourceLoader<'_>,
+) -> Result<Vec<ReferencedFile>> {
+  let mut contents: HashMap<ModuleSpecifier, Option<Arc<[u8]>>> =
+    HashMap::new();
+  let mut referenced_files = Vec::new();
+  for module in graph.all_modules() {
+    let Module::Js(module) = module else {
+      continue;
+    };
+    if module.specifier.scheme() != "file" {
+      continue;
+    }
+    let parsed_source = graph.get_parsed_source(module)?;
+    let import_meta_urls = parsed_source.with_view(|program| {
+      get_import_meta_urls(program, parsed_source.unresolved_context())
+    });
+    for import_meta_url in import_meta_urls {
+      let Ok(specifier) = module.specifier.join(&import_meta_url.specifier)
+      else {
+        continue;
+      };
+      // modules are already in the output
+      if specifier.scheme() != "file" || graph.try_get(&specifier).is_some() {
+        continue;
+      }
+      let content = match contents.get(&specifier) {
+        Some(content) => content.clone(),
+        None => {
+          let response = loader
+            .load(
+              &specifier,
+              LoadOptions {
+                in_dynamic_branch: false,
+                was_dynamic_root: false,
+                cache_setting: CacheSetting::Use,
+                maybe_checksum: None,
+              },
+            )
+            .await;
+          let content = match response {
+            Ok(Some(LoadResponse::Module { content, .. })) => Some(content),
+            _ => None,
+          };
+          contents.insert(specifier.clone(), content.clone());
+          content
+        }
+      };
+      referenced_files.push(ReferencedFile {
+        referrer: module.specifier.clone(),
+        specifier,
+        range: Range {
+          specifier: module.specifier.clone(),
+          range: PositionRange {
+            start: import_meta_url.position,
+            end: import_meta_url.position,
+          },
+          resolution_mode: None,
+        },
+        content,
+      });
+    }
+  }
+  Ok(referenced_files)
+}
+
 /// Resolves `jsr:` specifiers that the user has provided a mapping for to
 /// [`MAPPED_JSR_SCHEME`] so that they make it to the loader.
 ///
@@ -519,6 +844,27 @@ impl JsrSpecifierMappings {
     }
   }
 
+  /// Package requirement of a specifier in the module graph whose mapping
+  /// gets its version from the specifier.
+  pub fn specifier_package_req(
+    &self,
+    specifier: &ModuleSpecifier,
+  ) -> Option<PackageReq> {
+    let specifier = from_graph_specifier(specifier)?;
+    let mapping = self
+      .by_name_and_sub_path
+      .get(&jsr_mapping_key(&specifier)?)?;
+    match mapping {
+      MappedSpecifier::Package(package) if package.version.is_none() => Some(
+        JsrPackageReqReference::from_specifier(&specifier)
+          .ok()?
+          .into_inner()
+          .req,
+      ),
+      _ => None,
+    }
+  }
+
   /// Version requirement a specifier in the module graph provides for its
   /// mapping, which is [`None`] when the mapping specifies its own version or
   /// the requirement is not a single range of versions.
@@ -543,6 +889,95 @@ impl JsrSpecifierMappings {
   }
 }
 
+/// The user's mappings whose key ends in a slash, which apply to every
+/// specifier under that directory that isn't mapped on its own.
+#[derive(Debug, Default)]
+pub struct PrefixSpecifierMappings {
+  /// Sorted longest prefix first so that the most specific one is found.
+  prefixes: Vec<(ModuleSpecifier, PackageMappedSpecifier)>,
+}
+
+impl PrefixSpecifierMappings {
+  pub fn new(mappings: &HashMap<ModuleSpecifier, MappedSpecifier>) -> Self {
+    let mut prefixes = mappings
+      .iter()
+      .filter(|(specifier, _)| is_prefix_mapping_key(specifier))
+      .filter_map(|(specifier, mapping)| match mapping {
+        MappedSpecifier::Package(package) => {
+          Some((specifier.clone(), package.clone()))
+        }
+        MappedSpecifier::Module(_) => None,
+      })
+      .collect::<Vec<_>>();
+    prefixes.sort_by(|a, b| {
+      b.0
+        .as_str()
+        .len()
+        .cmp(&a.0.as_str().len())
+        .then(a.0.cmp(&b.0))
+    });
+    Self { prefixes }
+  }
+
+  /// Gets the package a specifier under one of the prefixes maps to, which
+  /// has the rest of the specifier's path filled into its sub path.
+  pub fn get(
+    &self,
+    specifier: &ModuleSpecifier,
+  ) -> Option<PackageMappedSpecifier> {
+    self.prefixes.iter().find_map(|(prefix, package)| {
+      let remainder = prefix_remainder(prefix, specifier)?;
+      let mut package = package.clone();
+      package.sub_path = Some(match package.sub_path.as_deref() {
+        Some(template) if template.contains('*') => {
+          template.replacen('*', remainder, 1)
+        }
+        Some(sub_path) => {
+          format!("{}/{}", sub_path.trim_end_matches('/'), remainder)
+        }
+        None => remainder.to_string(),
+      });
+      Some(package)
+    })
+  }
+
+  /// Whether the graph contained a specifier under the provided prefix
+  /// mapping, which is `false` for a mapping that isn't a prefix.
+  pub fn was_found<'a>(
+    &self,
+    mapping_specifier: &ModuleSpecifier,
+    mut graph_specifiers: impl Iterator<Item = &'a ModuleSpecifier>,
+  ) -> bool {
+    is_prefix_mapping_key(mapping_specifier)
+      && graph_specifiers
+        .any(|s| prefix_remainder(mapping_specifier, s).is_some())
+  }
+}
+
+/// Whether a mapping applies to everything under a directory rather than a
+/// single module (ex. `https://example.com/lib@1.0/`).
+pub fn is_prefix_mapping_key(specifier: &ModuleSpecifier) -> bool {
+  specifier.scheme() != "jsr" && specifier.path().ends_with('/')
+}
+
+/// Gets the path of the specifier relative to the prefix, without its
+/// query or fragment.
+fn prefix_remainder<'a>(
+  prefix: &ModuleSpecifier,
+  specifier: &'a ModuleSpecifier,
+) -> Option<&'a str> {
+  let remainder = specifier.as_str().strip_prefix(prefix.as_str())?;
+  let remainder = match remainder.find(['?', '#']) {
+    Some(index) => &remainder[..index],
+    None => remainder,
+  };
+  if remainder.is_empty() {
+    None
+  } else {
+    Some(remainder)
+  }
+}
+
 /// Specifier to show the user, which hides the scheme mapped `jsr:` specifiers
 /// have within the module graph.
 pub fn display_specifier(specifier: &ModuleSpecifier) -> String {
@@ -607,6 +1042,7 @@ fn format_specifiers_for_message(
 #[cfg(test)]
 mod test {
   use super::*;
+  use crate::DependencyKind;
 
   #[test]
   fn test_jsr_mappings_graph_specifier() {
@@ -766,7 +1202,7 @@ mod test {
               name: name.to_string(),
               version: version.map(ToOwned::to_owned),
               sub_path: None,
-              peer_dependency: false,
+              kind: DependencyKind::Dependency,
             },
           )
         })
@@ -792,6 +1228,93 @@ mod test {
     }
   }
 
+  #[test]
+  fn test_prefix_mappings_get() {
+    let mappings = PrefixSpecifierMappings::new(&HashMap::from([
+      (
+        parse("https://localhost/lib@1.0/"),
+        package_mapping("lib", None),
+      ),
+      // the most specific prefix wins
+      (
+        parse("https://localhost/lib@1.0/nested/"),
+        package_mapping("nested", Some("dist")),
+      ),
+      (
+        parse("https://localhost/template/"),
+        package_mapping("template", Some("esm/*/index.js")),
+      ),
+      // only a key ending in a slash is a prefix
+      (
+        parse("https://localhost/exact.ts"),
+        package_mapping("exact", None),
+      ),
+    ]));
+
+    run_test(
+      &mappings,
+      "https://localhost/lib@1.0/fs/mod.ts",
+      Some(("lib", "fs/mod.ts")),
+    );
+    run_test(
+      &mappings,
+      "https://localhost/lib@1.0/nested/mod.ts?query",
+      Some(("nested", "dist/mod.ts")),
+    );
+    run_test(
+      &mappings,
+      "https://localhost/template/hooks",
+      Some(("template", "esm/hooks/index.js")),
+    );
+    run_test(&mappings, "https://localhost/lib@1.0/", None);
+    run_test(&mappings, "https://localhost/lib@2.0/mod.ts", None);
+    run_test(&mappings, "https://localhost/exact.ts/other.ts", None);
+
+    fn run_test(
+      mappings: &PrefixSpecifierMappings,
+      specifier: &str,
+      expected: Option<(&str, &str)>,
+    ) {
+      let package = mappings.get(&parse(specifier));
+      assert_eq!(
+        package
+          .as_ref()
+          .map(|p| (p.name.as_str(), p.sub_path.as_deref().unwrap())),
+        expected,
+      );
+    }
+  }
+
+  #[test]
+  fn test_prefix_mappings_was_found() {
+    let mappings = PrefixSpecifierMappings::new(&HashMap::from([(
+      parse("https://localhost/lib/"),
+      package_mapping("lib", None),
+    )]));
+    assert!(mappings.was_found(
+      &parse("https://localhost/lib/"),
+      [parse("https://localhost/lib/sub/mod.ts")].iter()
+    ));
+    assert!(!mappings.was_found(
+      &parse("https://localhost/lib/"),
+      [parse("https://localhost/other/mod.ts")].iter()
+    ));
+    // not a prefix, so it's left for the exact match
+    assert!(!mappings.was_found(
+      &parse("https://localhost/lib/mod.ts"),
+      [parse("https://localhost/lib/mod.ts")].iter()
+    ));
+  }
+
+  fn package_mapping(name: &str, sub_path: Option<&str>) -> MappedSpecifier {
+    MappedSpecifier::Package(PackageMappedSpecifier {
+      name: name.to_string(),
+      version: Some("1.0.0".to_string()),
+      sub_path: sub_path.map(ToOwned::to_owned),
+      kind: DependencyKind::Dependency,
+    })
+  }
+
   fn mappings() -> JsrSpecifierMappings {
     JsrSpecifierMappings::new(&HashMap::from([
       (parse("jsr:@scope/name"), mapping(None)),
@@ -805,7 +1328,7 @@ mod test {
       name: "package".to_string(),
       version: version.map(ToOwned::to_owned),
       sub_path: None,
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     })
   }
 
@@ -0,0 +1,263 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::Result;
+use deno_ast::ModuleSpecifier;
+use deno_ast::TextChange;
+use deno_graph::source::LoaderChecksum;
+use serde_json::json;
+use serde_json::Value;
+
+/// Bumped when the format of the cache file or what's stored in it changes.
+const CACHE_VERSION: u64 = 1;
+
+/// What analyzing a module found, which is reused by later transforms
+/// while nothing that affects the module changes.
+#[derive(Clone, Debug, Default)]
+pub struct ModuleAnalysis {
+  pub text_changes: Vec<TextChange>,
+  /// Names of the polyfills the module needs.
+  pub polyfills: Vec<String>,
+  pub used_shim: bool,
+  pub warnings: Vec<String>,
+}
+
+/// Analyses of the modules from the last transform, which are persisted
+/// to a file in the cache directory or only kept in memory.
+#[derive(Default)]
+pub struct IncrementalCache {
+  file_path: Option<PathBuf>,
+  previous: HashMap<String, (String, ModuleAnalysis)>,
+  current: HashMap<String, (String, ModuleAnalysis)>,
+}
+
+impl IncrementalCache {
+  /// Loads the cache from the directory, which starts out empty when
+  /// there's no cache file or it can't be read.
+  pub fn load(sys: &impl sys_traits::FsRead, dir_path: &Path) -> Self {
+    let file_path = dir_path.join("modules.json");
+    let previous = sys
+      .fs_read_to_string(&file_path)
+      .ok()
+      .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+      .and_then(|value| parse_entries(&value))
+      .unwrap_or_default();
+    Self {
+      file_path: Some(file_path),
+      previous,
+      current: HashMap::new(),
+    }
+  }
+
+  /// Gets the analysis of the module when it was stored with the same key.
+  pub fn get(
+    &self,
+    specifier: &ModuleSpecifier,
+    key: &str,
+  ) -> Option<&ModuleAnalysis> {
+    let (previous_key, analysis) = self.previous.get(specifier.as_str())?;
+    (previous_key == key).then_some(analysis)
+  }
+
+  /// Stores the analysis of a module in this transform, which is what
+  /// gets saved.
+  pub fn insert(
+    &mut self,
+    specifier: &ModuleSpecifier,
+    key: String,
+    analysis: ModuleAnalysis,
+  ) {
+    self.current.insert(specifier.to_string(), (key, analysis));
+  }
+
+  /// Makes the analyses of this transform's modules the ones the next
+  /// transform reuses, which drops the ones of modules that are no longer
+  /// in the output, and writes them to the cache file when there is one.
+  pub fn finish(
+    &mut self,
+    sys: &impl deno_path_util::fs::AtomicWriteFileWithRetriesSys,
+  ) -> Result<()> {
+    self.previous = std::mem::take(&mut self.current);
+    let Some(file_path) = &self.file_path else {
+      return Ok(());
+    };
+    let modules = self
+      .previous
+      .iter()
+      .map(|(specifier, (key, analysis))| {
+        (
+          specifier.clone(),
+          json!({
+            "key": key,
+            "textChanges": analysis
+              .text_changes
+              .iter()
+              .map(|c| json!([c.range.start, c.range.end, c.new_text]))
+              .collect::<Vec<_>>(),
+            "polyfills": analysis.polyfills,
+            "usedShim": analysis.used_shim,
+            "warnings": analysis.warnings,
+          }),
+        )
+      })
+      .collect::<serde_json::Map<_, _>>();
+    let text = json!({
+      "version": CACHE_VERSION,
+      "modules": modules,
+    })
+    .to_string();
+    deno_path_util::fs::atomic_write_file_with_retries(
+      sys,
+      file_path,
+      text.as_bytes(),
+      0o644,
+    )
+    .with_context(|| {
+      format!("Failed writing cache file {}", file_path.display())
+    })
+  }
+}
+
+/// Gets the key of a module's analysis from the things that affect it.
+pub fn get_cache_key<'a>(parts: impl Iterator<Item = &'a str>) -> String {
+  let mut text = String::new();
+  for part in parts {
+    // the length prefix keeps the parts from running together
+    text.push_str(&format!("{}:{part}\n", part.len()));
+  }
+  LoaderChecksum::r#gen(text.as_bytes())
+}
+
+fn parse_entries(
+  value: &Value,
+) -> Option<HashMap<String, (String, ModuleAnalysis)>> {
+  if value.get("version")?.as_u64()? != CACHE_VERSION {
+    return None;
+  }
+  let mut entries = HashMap::new();
+  for (specifier, entry) in value.get("modules")?.as_object()? {
+    let text_changes = entry
+      .get("textChanges")?
+      .as_array()?
+      .iter()
+      .map(|change| {
+        let [start, end, new_text] = change.as_array()?.as_slice() else {
+          return None;
+        };
+        Some(TextChange {
+          range: start.as_u64()? as usize..end.as_u64()? as usize,
+          new_text: new_text.as_str()?.to_string(),
+        })
+      })
+      .collect::<Option<Vec<_>>>()?;
+    let analysis = ModuleAnalysis {
+      text_changes,
+      polyfills: get_strings(entry.get("polyfills")?)?,
+      used_shim: entry.get("usedShim")?.as_bool()?,
+      warnings: get_strings(entry.get("warnings")?)?,
+    };
+    let key = entry.get("key")?.as_str()?.to_string();
+    entries.insert(specifier.clone(), (key, analysis));
+  }
+  Some(entries)
+}
+
+fn get_strings(value: &Value) -> Option<Vec<String>> {
+  value
+    .as_array()?
+    .iter()
+    .map(|v| v.as_str().map(ToOwned::to_owned))
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+  use sys_traits::impls::InMemorySys;
+  use sys_traits::FsMetadata;
+  use sys_traits::FsWrite;
+
+  use super::*;
+
+  #[test]
+  fn save_and_load() {
+    let sys = InMemorySys::default();
+    let dir_path = PathBuf::from("/cache");
+    let specifier = ModuleSpecifier::parse("file:///mod.ts").unwrap();
+    let other_specifier = ModuleSpecifier::parse("file:///other.ts").unwrap();
+    let mut cache = IncrementalCache::load(&sys, &dir_path);
+    assert!(cache.get(&specifier, "key").is_none());
+    cache.insert(
+      &specifier,
+      "key".to_string(),
+      ModuleAnalysis {
+        text_changes: vec![TextChange {
+          range: 1..2,
+          new_text: "a".to_string(),
+        }],
+        polyfills: vec!["objectHasOwn".to_string()],
+        used_shim: true,
+        warnings: vec!["warning".to_string()],
+      },
+    );
+    cache.insert(&other_specifier, "key".to_string(), Default::default());
+    cache.finish(&sys).unwrap();
+
+    let mut cache = IncrementalCache::load(&sys, &dir_path);
+    // a different key means something affecting the module changed
+    assert!(cache.get(&other_specifier, "other").is_none());
+    let analysis = cache.get(&specifier, "key").unwrap().clone();
+    assert_eq!(analysis.text_changes.len(), 1);
+    assert_eq!(analysis.text_changes[0].range, 1..2);
+    assert_eq!(analysis.text_changes[0].new_text, "a");
+    assert_eq!(analysis.polyfills, vec!["objectHasOwn".to_string()]);
+    assert!(analysis.used_shim);
+    assert_eq!(analysis.warnings, vec!["warning".to_string()]);
+    cache.insert(&specifier, "key".to_string(), analysis);
+    cache.finish(&sys).unwrap();
+
+    // only the modules of the last transform are kept
+    let cache = IncrementalCache::load(&sys, &dir_path);
+    assert!(cache.get(&other_specifier, "key").is_none());
+    assert!(cache.get(&specifier, "key").is_some());
+
+    // an unreadable cache is ignored
+    sys
+      .fs_write(dir_path.join("modules.json"), "{ invalid")
+      .unwrap();
+    let cache = IncrementalCache::load(&sys, &dir_path);
+    assert!(cache.get(&specifier, "key").is_none());
+  }
+
+  #[test]
+  fn in_memory() {
+    let sys = InMemorySys::default();
+    let specifier = ModuleSpecifier::parse("file:///mod.ts").unwrap();
+    let mut cache = IncrementalCache::default();
+    cache.insert(&specifier, "key".to_string(), Default::default());
+    assert!(cache.get(&specifier, "key").is_none());
+    cache.finish(&sys).unwrap();
+    assert!(cache.get(&specifier, "key").is_some());
+    assert!(!sys.fs_exists_no_err("/cache/modules.json"));
+
+    // not storing the module again drops it
+    cache.finish(&sys).unwrap();
+    assert!(cache.get(&specifier, "key").is_none());
+  }
+
+  #[test]
+  fn cache_key() {
+    assert_eq!(
+      get_cache_key(["a", "b"].into_iter()),
+      get_cache_key(["a", "b"].into_iter())
+    );
+    assert_ne!(
+      get_cache_key(["a", "b"].into_iter()),
+      get_cache_key(["ab"].into_iter())
+    );
+  }
+}
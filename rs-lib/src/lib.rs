@@ -4,7 +4,9 @@
 #![deny(clippy::disallowed_methods)]
 #![deny(clippy::disallowed_types)]
 
+use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::path::Path;
@@ -12,16 +14,25 @@ use std::path::PathBuf;
 use std::rc::Rc;
 
 use analyze::get_top_level_decls;
+use analyze::has_global_usage;
 use anyhow::Context;
 use anyhow::Result;
+use assets::get_asset_file_path;
+use assets::get_asset_module_text;
+use assets::get_referenced_file_path;
+use assets::AssetModuleOptions;
 
+use crate::graph::display_specifier;
 use crate::loader::get_all_specifier_mappers;
 use crate::loader::get_types_package_for_specifier;
 use analyze::get_ignore_line_indexes;
+use analyze::get_import_meta_urls;
 use anyhow::bail;
 use deno_ast::apply_text_changes;
+use deno_ast::ParsedSource;
 use deno_ast::TextChange;
 use deno_cache_dir::file_fetcher::NullBlobStore;
+use deno_graph::JsModule;
 use deno_graph::Module;
 use deno_resolver::deno_json::CompilerOptionsOverrides;
 use deno_resolver::factory::ConfigDiscoveryOption;
@@ -38,18 +49,41 @@ use deno_resolver::workspace::SpecifiedImportMap;
 use deno_resolver::NodeResolverOptions;
 use deno_semver::npm::NpmPackageReqReference;
 use graph::ModuleGraphOptions;
-use mappings::Mappings;
+use incremental_cache::get_cache_key;
+use incremental_cache::IncrementalCache;
+use incremental_cache::ModuleAnalysis;
+pub use mappings::Mappings;
 use mappings::SYNTHETIC_SPECIFIERS;
 use mappings::SYNTHETIC_TEST_SPECIFIERS;
 use node_resolver::analyze::NodeCodeTranslatorMode;
 use node_resolver::NodeConditionOptions;
+use node_resolver::DENO_SUPPORTED_BUILTIN_NODE_MODULES;
+pub use output_dir_diff::DiffOutputDirSys;
+pub use output_dir_diff::ModifiedFile;
+pub use output_dir_diff::OutputDirDiff;
+use output_graph::get_output_graph;
+use output_graph::GetOutputGraphOptions;
+pub use output_graph::OutputGraph;
+pub use output_graph::OutputGraphEdge;
+pub use output_graph::OutputGraphEdgeKind;
+pub use output_graph::OutputGraphEnvironment;
+pub use output_graph::OutputGraphModule;
 use polyfills::build_polyfill_file;
 use polyfills::polyfills_for_target;
 use polyfills::Polyfill;
 pub use polyfills::PolyfillOverrides;
+use source_map::apply_text_changes_with_source_map;
+pub use source_map::chain_source_maps;
+use source_map::update_source_map_for_insert;
+pub use source_map::SourceMaps;
+use specifiers::format_import_chain;
+use specifiers::EnvironmentSpecifiers;
+use specifiers::ImportChains;
 use specifiers::Specifiers;
 use utils::get_relative_specifier;
+use utils::map_in_parallel;
 use utils::prepend_statement_to_text;
+use utils::with_extension;
 use visitors::fill_polyfills;
 use visitors::get_deno_comment_directive_text_changes;
 use visitors::get_global_text_changes;
@@ -57,43 +91,148 @@ use visitors::get_import_exports_text_changes;
 use visitors::FillPolyfillsParams;
 use visitors::GetGlobalTextChangesParams;
 use visitors::GetImportExportsTextChangesParams;
+use wasm_module::get_wasm_module_wrapper_text;
+use workspace::WorkspaceSiblings;
 
+pub use config_package::ConfigPackage;
+pub use deno_ast::MediaType;
 pub use deno_ast::ModuleSpecifier;
 pub use deno_graph::source::CacheSetting;
 pub use deno_graph::source::LoadError;
 pub use deno_graph::source::LoaderChecksum;
-
+pub use emit::EmitResult;
+pub use emit::EmitSys;
+pub use locked_versions::LockedDependencyVersions;
+pub use npm_package_info::LocalNpmPackageInfoProvider;
+pub use npm_package_info::NpmPackageInfo;
+pub use npm_package_info::NpmPackageInfoProvider;
+pub use package_manifest::PackageManifest;
+pub use package_manifest::PackageManifestBin;
+pub use package_manifest::PackageManifestEntryPoint;
+pub use package_manifest::PackageManifestEntryPointKind;
+pub use package_manifest::PackageManifestExport;
+pub use package_manifest::PackageManifestImport;
+pub use package_manifest::PackageManifestOptions;
+pub use subpath_imports::SubpathImport;
+pub use transformer::TransformDiff;
+pub use transformer::Transformer;
+pub use workspace::transform_workspace;
+pub use workspace::TransformWorkspaceMemberOutput;
+pub use workspace::TransformWorkspaceOptions;
+
+use crate::config_package::get_config_entry_points;
+use crate::config_package::resolve_config_package;
 use crate::declaration_file_resolution::TypesDependency;
+use crate::package_manifest::get_package_manifest;
+use crate::package_manifest::GetPackageManifestOptions;
+use crate::subpath_imports::get_preserved_subpath_imports;
+use crate::subpath_imports::PackageJsonImports;
+use crate::subpath_imports::PreservedSubpathImports;
 use crate::utils::strip_bom;
 
 mod analyze;
+mod assets;
+mod config_package;
 mod declaration_file_resolution;
+mod emit;
 mod graph;
+mod incremental_cache;
 mod loader;
+mod locked_versions;
 mod mappings;
+mod npm_package_info;
+mod output_dir_diff;
+mod output_graph;
+mod package_manifest;
 mod parser;
 mod polyfills;
+mod source_map;
 mod specifiers;
+mod subpath_imports;
+mod transformer;
 mod utils;
 mod visitors;
+mod wasm_module;
+mod workspace;
 
 #[cfg_attr(feature = "serialization", derive(serde::Serialize))]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct OutputFile {
   pub file_path: PathBuf,
   pub file_text: String,
+  /// Module the file was transformed from, which is a `dnt://` specifier
+  /// for the files that dnt generates.
+  pub specifier: ModuleSpecifier,
+  /// Media type of the module the file was transformed from.
+  pub media_type: MediaType,
+  pub kind: OutputFileKind,
+  /// SHA-256 checksum of the file's text.
+  pub content_hash: String,
+  /// Source map from the file to the module it was transformed from when
+  /// source maps are enabled.
+  pub source_map: Option<String>,
 }
 
+/// Where an output file comes from.
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFileKind {
+  Local,
+  /// A remote module, which is in the `deps` directory.
+  Remote,
+  /// A file that dnt generates (ex. the polyfills or shims).
+  Synthetic,
+  /// A declaration file that provides the types of another module.
+  Types,
+}
+
+/// A file that's copied to the output as-is (ex. a Wasm binary).
 #[cfg_attr(feature = "serialization", derive(serde::Serialize))]
-#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OutputAsset {
+  pub file_path: PathBuf,
+  #[cfg_attr(feature = "serialization", serde(with = "serde_bytes"))]
+  pub bytes: Vec<u8>,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct Dependency {
   pub name: String,
   pub version: String,
   #[serde(default)]
-  pub peer_dependency: bool,
+  pub kind: DependencyKind,
+}
+
+// make sure to update `DependencyKind` in the TS code when changing the names on this
+/// Where a dependency is listed in the package.json file.
+///
+/// The variants are ordered by precedence, so when a package is depended on
+/// with different kinds, the greatest one is used. A package that's required
+/// somewhere outranks the kinds that npm doesn't always install, so it's
+/// only demoted from `dependencies` to a required peer dependency.
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DependencyKind {
+  /// In `devDependencies`.
+  Dev,
+  /// In `optionalDependencies` (ex. a native accelerator).
+  Optional,
+  /// In `peerDependencies` and marked optional in `peerDependenciesMeta`
+  /// (ex. a plugin that can be swapped out).
+  OptionalPeer,
+  /// In `dependencies`.
+  #[default]
+  Dependency,
+  /// In `peerDependencies`.
+  Peer,
 }
 
 #[cfg_attr(feature = "serialization", derive(serde::Serialize))]
@@ -114,6 +253,66 @@ pub struct TransformOutput {
   pub types_dependencies: Vec<Dependency>,
   /// Output files that are only reachable from a binary entry point.
   pub bin_only_files: Vec<PathBuf>,
+  /// Name, version, and export names from the config file when the entry
+  /// points are derived from its exports.
+  pub config_package: Option<ConfigPackage>,
+  /// `#` prefixed subpath imports of the package.json file that are kept
+  /// in the output because they import local modules.
+  pub subpath_imports: Vec<SubpathImport>,
+  /// The `exports`, `imports`, `bin`, and dependencies of the package.json
+  /// file.
+  pub package_manifest: PackageManifest,
+  /// Output file paths of the modules, which can be looked up by
+  /// specifier or by path.
+  pub mappings: Mappings,
+  /// Modules in the output and the dependencies between them.
+  pub graph: OutputGraph,
+  #[cfg_attr(feature = "serialization", serde(skip))]
+  import_chains: ImportChains,
+  /// Local files that the output was transformed from.
+  #[cfg_attr(feature = "serialization", serde(skip))]
+  source_file_paths: HashSet<PathBuf>,
+}
+
+impl TransformOutput {
+  /// Gets the shortest chain of imports from an entry point to the module
+  /// that's output at the path, which explains why it's in the output.
+  ///
+  /// The chain starts with the entry point and ends with the module.
+  pub fn get_import_chain(
+    &self,
+    file_path: &Path,
+  ) -> Option<Vec<ModuleSpecifier>> {
+    self
+      .import_chains
+      .get(self.mappings.try_get_specifier(file_path)?)
+  }
+
+  /// Gets the shortest chain of imports from an entry point to a module
+  /// that's mapped to the dependency with the provided name.
+  pub fn get_dependency_import_chain(
+    &self,
+    name: &str,
+  ) -> Option<Vec<ModuleSpecifier>> {
+    self.import_chains.get_for_package(name)
+  }
+
+  /// Writes the files and assets to the output directory, skipping the
+  /// files whose content is unchanged and removing the files that a
+  /// previous emit wrote but that aren't in this output.
+  pub fn emit(&self, sys: &impl EmitSys, out_dir: &Path) -> Result<EmitResult> {
+    emit::emit_output(sys, self, out_dir)
+  }
+
+  /// Compares the output with an existing output directory without
+  /// writing anything, which shows how emitting it there would change it.
+  pub fn diff_output_dir(
+    &self,
+    sys: &impl DiffOutputDirSys,
+    out_dir: &Path,
+  ) -> Result<OutputDirDiff> {
+    output_dir_diff::diff_output_dir(sys, self, out_dir)
+  }
 }
 
 #[cfg_attr(feature = "serialization", derive(serde::Serialize))]
@@ -121,8 +320,16 @@ pub struct TransformOutput {
 #[derive(Debug, PartialEq, Eq, Default)]
 pub struct TransformOutputEnvironment {
   pub entry_points: Vec<PathBuf>,
+  /// Output paths of the scripts of the workers that the modules create,
+  /// which are run on their own like an entry point.
+  pub worker_entry_points: Vec<PathBuf>,
   pub files: Vec<OutputFile>,
+  /// Files that aren't modules, which go beside the emitted modules.
+  pub assets: Vec<OutputAsset>,
   pub dependencies: Vec<Dependency>,
+  /// Dependencies that are only imported for their types (ex. with
+  /// `import type`), so the emitted JavaScript never imports them.
+  pub type_only_dependencies: Vec<Dependency>,
 }
 
 #[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
@@ -136,6 +343,7 @@ pub enum MappedSpecifier {
   Module(ModuleSpecifier),
 }
 
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
 #[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -146,10 +354,13 @@ pub struct PackageMappedSpecifier {
   /// dependency (ex. Node modules like "fs")
   pub version: Option<String>,
   /// Sub path of the npm package to use in the module specifier.
+  ///
+  /// For a mapping of a directory, this is a template that the path of
+  /// each module under the directory is appended to or replaces a `*` in.
   pub sub_path: Option<String>,
-  /// If this is suggested to be a peer dependency.
+  /// Where the dependency is listed in the package.json file.
   #[serde(default)]
-  pub peer_dependency: bool,
+  pub kind: DependencyKind,
 }
 
 impl PackageMappedSpecifier {
@@ -158,7 +369,7 @@ impl PackageMappedSpecifier {
       name: npm_specifier.req().name.to_string(),
       version: Some(npm_specifier.req().version_req.version_text().to_string()),
       sub_path: npm_specifier.sub_path().map(|s| s.to_string()),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }
   }
 
@@ -262,18 +473,44 @@ pub enum ScriptTarget {
   Latest = 11,
 }
 
+// make sure to update `Platform` in the TS code when changing the names on this
+/// Platform that the output runs on.
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Platform {
+  #[default]
+  Node,
+  /// Resolves npm packages with the `browser` condition and prefers the
+  /// `browser` field of their package.json files.
+  Browser,
+}
+
+#[derive(Clone)]
 pub struct TransformOptions {
   pub entry_points: Vec<ModuleSpecifier>,
+  /// Uses the `exports` of the discovered config file as the entry points.
+  ///
+  /// Any provided entry points that aren't only binaries must be the same
+  /// modules as the exports.
+  pub entry_points_from_config: bool,
   /// Entry points that are only used as an npm binary, which is a subset
   /// of the entry points.
   pub bin_entry_points: Vec<ModuleSpecifier>,
   pub test_entry_points: Vec<ModuleSpecifier>,
+  /// Layout of the emitted package that the output's package manifest
+  /// describes.
+  pub package_manifest: PackageManifestOptions,
   pub shims: Vec<Shim>,
   pub test_shims: Vec<Shim>,
   /// Maps specifiers to an npm package or module.
   ///
   /// A key may be a url or a bare specifier that resolves via the config
   /// file's import map (ex. `my-lib`).
+  ///
+  /// A key ending in a slash maps every module under that directory to a
+  /// package, with the rest of the module's path appended to the sub path
+  /// (or filled in for a `*` in it).
   pub specifier_mappings: HashMap<String, MappedSpecifier>,
   /// Version of ECMAScript that the final code will target.
   /// This controls whether certain polyfills should occur.
@@ -281,6 +518,20 @@ pub struct TransformOptions {
   /// Explicitly enables or disables polyfills by name, taking precedence
   /// over what `target` implies.
   pub polyfills: PolyfillOverrides,
+  pub platform: Platform,
+  /// Additional conditions to use when resolving the `exports` and `imports`
+  /// of npm packages (ex. `worker` or `react-server`).
+  pub conditions: Vec<String>,
+  /// Files imported as text or bytes that are at most this many bytes
+  /// are inlined in the module that's generated for them. Larger files are
+  /// copied to the output and read at runtime.
+  pub asset_inline_limit: usize,
+  /// Generates a source map from each transformed module's output back to
+  /// its original specifier.
+  pub source_maps: SourceMaps,
+  /// Directory of a cache of the analysis of each module, which a later
+  /// transform reuses for the modules where nothing affecting them changed.
+  pub incremental_cache_dir: Option<PathBuf>,
   pub config_file: Option<ModuleSpecifier>,
   /// Disables auto-discovering a config file based on the entry points
   /// when no config file or import map is provided.
@@ -293,6 +544,19 @@ pub struct TransformOptions {
   ///
   /// Leave this `None` to use the `lock.frozen` setting in the deno.json file.
   pub frozen_lockfile: Option<bool>,
+  /// Uses the versions that the deno lockfile locks the `npm:` and `jsr:`
+  /// specifiers to for the versions of the dependencies, rather than the
+  /// version requirements written in the specifiers.
+  ///
+  /// Leave this `None` to use the version requirements.
+  pub locked_dependency_versions: Option<LockedDependencyVersions>,
+  /// Provides npm package info used to check that the name and version
+  /// range of each dependency in the output resolves.
+  pub npm_package_info_provider: Option<Rc<dyn NpmPackageInfoProvider>>,
+  /// Pins each dependency to the exact version its range resolves to.
+  ///
+  /// This requires an npm package info provider.
+  pub pin_npm_versions: bool,
   /// Directory that a config file, `deno.lock`, and `node_modules` directory
   /// are discovered relative to.
   pub cwd: PathBuf,
@@ -326,6 +590,7 @@ struct EnvironmentContext<'a> {
   environment: TransformOutputEnvironment,
   searching_polyfills: Vec<Box<dyn Polyfill>>,
   found_polyfills: Vec<Box<dyn Polyfill>>,
+  polyfill_file_specifier: &'a ModuleSpecifier,
   shim_file_specifier: &'a ModuleSpecifier,
   shim_global_names: HashSet<&'a str>,
   shims: &'a Vec<Shim>,
@@ -337,10 +602,84 @@ pub async fn transform(
   http_client: impl deno_cache_dir::file_fetcher::HttpClient + Clone + 'static,
   options: TransformOptions,
 ) -> Result<TransformOutput> {
-  if options.entry_points.is_empty() {
+  let mut incremental_cache = options
+    .incremental_cache_dir
+    .as_ref()
+    .map(|dir_path| IncrementalCache::load(&sys, dir_path));
+  transform_with_siblings(
+    sys,
+    http_client,
+    options,
+    &WorkspaceSiblings::default(),
+    incremental_cache.as_mut(),
+  )
+  .await
+}
+
+/// Transforms with the exports of the other members of the workspace mapped
+/// to their packages.
+async fn transform_with_siblings<TSys: WorkspaceFactorySys>(
+  sys: TSys,
+  http_client: impl deno_cache_dir::file_fetcher::HttpClient + Clone + 'static,
+  options: TransformOptions,
+  siblings: &WorkspaceSiblings,
+  incremental_cache: Option<&mut IncrementalCache>,
+) -> Result<TransformOutput> {
+  let context =
+    build_transform_context(sys.clone(), http_client, &options, siblings)
+      .await?;
+  transform_with_context(
+    &context,
+    &sys,
+    &options,
+    &mut HashMap::new(),
+    incremental_cache,
+  )
+  .await
+}
+
+/// The module graph and what's derived from it, which is everything the
+/// output is built from other than the modules' text.
+///
+/// A [`Transformer`] keeps this so a change to a module's text only
+/// transforms the modules it affects.
+struct TransformContext {
+  /// Entry points, which may have been derived from the config file.
+  entry_points: Vec<ModuleSpecifier>,
+  module_graph: crate::graph::ModuleGraph,
+  specifiers: Specifiers,
+  mappings: Mappings,
+  preserved_subpath_imports: PreservedSubpathImports,
+  package_specifier_mappings: HashMap<ModuleSpecifier, String>,
+  worker_specifiers: HashSet<ModuleSpecifier>,
+  main_dependencies: Vec<Dependency>,
+  main_type_only_dependencies: Vec<Dependency>,
+  test_dependencies: Vec<Dependency>,
+  types_dependencies: Vec<Dependency>,
+  bin_only_files: Vec<PathBuf>,
+  config_package: Option<ConfigPackage>,
+  discovered_config_file: Option<PathBuf>,
+  graph: OutputGraph,
+  import_chains: ImportChains,
+  warnings: Vec<String>,
+  source_file_paths: HashSet<PathBuf>,
+}
+
+/// Builds the module graph and everything derived from it.
+async fn build_transform_context(
+  sys: impl WorkspaceFactorySys,
+  http_client: impl deno_cache_dir::file_fetcher::HttpClient + Clone + 'static,
+  options: &TransformOptions,
+  siblings: &WorkspaceSiblings,
+) -> Result<TransformContext> {
+  if options.entry_points.is_empty() && !options.entry_points_from_config {
     anyhow::bail!("at least one entry point must be specified");
   }
+  if options.pin_npm_versions && options.npm_package_info_provider.is_none() {
+    anyhow::bail!("pinning npm versions requires an npm package info provider");
+  }
 
+  let mut entry_points = options.entry_points.clone();
   let paths = options
     .entry_points
     .iter()
@@ -373,26 +712,28 @@ pub async fn transform(
 
   let factory = deno_resolver::factory::WorkspaceFactory::new(
     sys,
-    options.cwd,
-    WorkspaceFactoryOptions {
-      additional_config_file_names: &[],
-      config_discovery,
-      is_package_manager_subcommand: false,
-      // force no node_modules directory so that we resolve package.json deps as npm specifiers
-      node_modules_dir: Some(deno_config::deno_json::NodeModulesDirMode::None),
-      node_modules_linker: None,
-      no_npm: false,
-      import_npm_lockfile: false,
-      npm_process_state: None,
-      root_node_modules_dir_override: None,
-      vendor: None,
-      frozen_lockfile: options.frozen_lockfile,
-      lock_arg: None,
-      lockfile_skip_write: true,
-      maybe_custom_deno_dir_root: None,
-      no_lock: false,
-    },
+    options.cwd.clone(),
+    workspace_factory_options(config_discovery, options.frozen_lockfile),
   );
+  let config_package = if options.entry_points_from_config {
+    let Some(config_file) =
+      factory.workspace_directory()?.member_or_root_deno_json()
+    else {
+      bail!("Deriving the entry points from the config file requires a deno.json file");
+    };
+    let (config_package, export_specifiers) =
+      resolve_config_package(config_file)?;
+    entry_points = get_config_entry_points(
+      &config_file.specifier,
+      &config_package,
+      &export_specifiers,
+      &options.entry_points,
+      &options.bin_entry_points,
+    )?;
+    Some(config_package)
+  } else {
+    None
+  };
   let discovered_config_file = if is_auto_discovering {
     factory
       .workspace_directory()?
@@ -412,8 +753,8 @@ pub async fn transform(
       cache_setting: deno_cache_dir::file_fetcher::CacheSetting::Use,
     },
   );
-  let maybe_import_map_file = match options.import_map {
-    Some(url) => Some(file_fetcher.fetch_bypass_permissions(&url).await?),
+  let maybe_import_map_file = match &options.import_map {
+    Some(url) => Some(file_fetcher.fetch_bypass_permissions(url).await?),
     None => None,
   };
 
@@ -426,11 +767,15 @@ pub async fn transform(
       node_resolver_options: NodeResolverOptions {
         typescript_version: None,
         conditions: NodeConditionOptions {
-          conditions: Vec::new(),
+          conditions: options
+            .conditions
+            .iter()
+            .map(|c| Cow::Owned(c.clone()))
+            .collect(),
           import_conditions_override: None,
           require_conditions_override: None,
         },
-        is_browser_platform: false,
+        is_browser_platform: options.platform == Platform::Browser,
         bundle_mode: false,
       },
       node_resolution_cache: None,
@@ -459,21 +804,30 @@ pub async fn transform(
   );
   let deno_resolver = resolver_factory.deno_resolver().await?;
   let cjs_tracker = resolver_factory.cjs_tracker()?.clone();
-  let (specifier_mappings, specifier_mapping_keys) =
+  let (mut specifier_mappings, specifier_mapping_keys) =
     resolve_specifier_mappings(
-      options.specifier_mappings,
+      options.specifier_mappings.clone(),
       &deno_resolver,
       &cjs_tracker,
-      options
-        .entry_points
-        .iter()
-        .chain(options.test_entry_points.iter()),
+      entry_points.iter().chain(options.test_entry_points.iter()),
     )?;
+  // a mapping that's provided takes precedence over a sibling's export
+  let mut optional_specifier_mappings = HashSet::new();
+  for (specifier, mapped) in siblings.mappings.iter() {
+    if !specifier_mappings.contains_key(specifier) {
+      specifier_mappings
+        .insert(specifier.clone(), MappedSpecifier::Package(mapped.clone()));
+      optional_specifier_mappings.insert(specifier.clone());
+    }
+  }
   let maybe_lockfile = resolver_factory
     .workspace_factory()
     .maybe_lockfile(&NullNpmPackageInfoProvider)
     .await?
     .cloned();
+  if options.locked_dependency_versions.is_some() && maybe_lockfile.is_none() {
+    bail!("Deriving the dependency versions from the lockfile requires a deno.lock file");
+  }
 
   let file_fetcher = Rc::new(file_fetcher);
   let loader = Rc::new(DenoGraphLoader::new(
@@ -494,8 +848,7 @@ pub async fn transform(
 
   let (module_graph, specifiers) =
     crate::graph::ModuleGraph::build_with_specifiers(ModuleGraphOptions {
-      entry_points: options
-        .entry_points
+      entry_points: entry_points
         .iter()
         .cloned()
         .chain(options.shims.iter().filter_map(|s| s.maybe_specifier()))
@@ -513,6 +866,7 @@ pub async fn transform(
         .collect(),
       specifier_mappings: &specifier_mappings,
       specifier_mapping_keys: &specifier_mapping_keys,
+      optional_specifier_mappings: &optional_specifier_mappings,
       loader,
       resolver: deno_resolver.clone(),
       compiler_options_resolver: resolver_factory
@@ -520,10 +874,30 @@ pub async fn transform(
         .clone(),
       cjs_tracker,
       maybe_lockfile,
+      locked_dependency_versions: options.locked_dependency_versions,
     })
     .await?;
 
+  siblings.ensure_not_imported(&specifiers.local)?;
+
   let mappings = Mappings::new(&module_graph, &specifiers)?;
+  let package_json_imports = match resolver_factory
+    .workspace_factory()
+    .workspace_directory()?
+    .member_or_root_pkg_json()
+  {
+    Some(pkg_json) => PackageJsonImports::new(
+      &deno_path_util::url_from_file_path(&pkg_json.path)?,
+      pkg_json.imports.as_ref(),
+    ),
+    None => PackageJsonImports::default(),
+  };
+  let preserved_subpath_imports = get_preserved_subpath_imports(
+    &package_json_imports,
+    &module_graph,
+    &mappings,
+    &specifiers.local,
+  )?;
   let all_package_specifier_mappings: HashMap<ModuleSpecifier, String> =
     specifiers
       .main
@@ -537,13 +911,40 @@ pub async fn transform(
     module_graph: &module_graph,
     mappings: &mappings,
     specifiers: &specifiers,
-    entry_points: &options.entry_points,
+    entry_points: &entry_points,
     bin_entry_points: &options.bin_entry_points,
     test_entry_points: &options.test_entry_points,
     shims: &options.shims,
     test_shims: &options.test_shims,
   });
-  let mut warnings = get_declaration_warnings(&specifiers);
+  let graph = get_output_graph(GetOutputGraphOptions {
+    module_graph: &module_graph,
+    mappings: &mappings,
+    specifiers: &specifiers,
+    bin_only_files: &bin_only_files,
+  });
+  let import_chains = ImportChains::new(
+    &module_graph,
+    &entry_points
+      .iter()
+      .chain(options.test_entry_points.iter())
+      .cloned()
+      .chain(options.shims.iter().filter_map(|s| s.maybe_specifier()))
+      .chain(
+        options
+          .test_shims
+          .iter()
+          .filter_map(|s| s.maybe_specifier()),
+      )
+      .collect::<Vec<_>>(),
+    &specifiers,
+  );
+  let mut warnings = specifiers
+    .warnings
+    .iter()
+    .map(|warning| warning.display(&import_chains))
+    .collect::<Vec<_>>();
+  warnings.extend(get_declaration_warnings(&specifiers, &import_chains));
   let mut types_dependencies =
     get_types_dependencies(&specifiers, &file_fetcher, &mut warnings).await;
   // the type declarations of a shim are needed to type check the output
@@ -551,21 +952,85 @@ pub async fn transform(
   types_dependencies.extend(get_shim_types_packages(
     options.shims.iter().chain(options.test_shims.iter()),
   ));
+
+  let worker_specifiers = specifiers
+    .local
+    .iter()
+    .chain(specifiers.remote.iter())
+    .flat_map(|specifier| module_graph.get_workers(specifier))
+    .map(|specifier| module_graph.resolve(specifier).clone())
+    .collect();
+  let (main_dependencies, main_type_only_dependencies) =
+    get_environment_dependencies(specifiers.main.clone());
+  let test_dependencies = get_dependencies(specifiers.test.mapped.clone());
+  let source_file_paths = module_graph
+    .all_modules()
+    .map(|module| module.specifier())
+    .chain(module_graph.referenced_files().iter().map(|f| &f.specifier))
+    .filter_map(|specifier| deno_path_util::url_to_file_path(specifier).ok())
+    .collect();
+
+  Ok(TransformContext {
+    entry_points,
+    module_graph,
+    specifiers,
+    mappings,
+    preserved_subpath_imports,
+    package_specifier_mappings: all_package_specifier_mappings,
+    worker_specifiers,
+    main_dependencies,
+    main_type_only_dependencies,
+    test_dependencies,
+    types_dependencies,
+    bin_only_files,
+    config_package,
+    discovered_config_file,
+    graph,
+    import_chains,
+    warnings,
+    source_file_paths,
+  })
+}
+
+/// Builds the output, reusing the results of the JS modules that are in
+/// `transformed_modules` and storing the results of the ones that aren't.
+async fn transform_with_context(
+  context: &TransformContext,
+  sys: &impl deno_path_util::fs::AtomicWriteFileWithRetriesSys,
+  options: &TransformOptions,
+  transformed_modules: &mut HashMap<ModuleSpecifier, TransformedJsModule>,
+  mut incremental_cache: Option<&mut IncrementalCache>,
+) -> Result<TransformOutput> {
+  let TransformContext {
+    module_graph,
+    specifiers,
+    mappings,
+    preserved_subpath_imports,
+    package_specifier_mappings,
+    worker_specifiers,
+    import_chains,
+    ..
+  } = context;
+  let mut warnings = context.warnings.clone();
+  let mut types_dependencies = context.types_dependencies.clone();
   let mut main_env_context = EnvironmentContext {
     environment: TransformOutputEnvironment {
-      entry_points: options
+      entry_points: context
         .entry_points
         .iter()
         .map(|p| mappings.get_file_path(p).to_owned())
         .collect(),
-      dependencies: get_dependencies(specifiers.main.mapped),
+      dependencies: context.main_dependencies.clone(),
+      type_only_dependencies: context.main_type_only_dependencies.clone(),
       ..Default::default()
     },
     searching_polyfills: polyfills_for_target(
       options.target,
+      options.platform,
       &options.polyfills,
     ),
     found_polyfills: Default::default(),
+    polyfill_file_specifier: &SYNTHETIC_SPECIFIERS.polyfills,
     shim_file_specifier: &SYNTHETIC_SPECIFIERS.shims,
     shim_global_names: options
       .shims
@@ -582,14 +1047,16 @@ pub async fn transform(
         .iter()
         .map(|p| mappings.get_file_path(p).to_owned())
         .collect(),
-      dependencies: get_dependencies(specifiers.test.mapped),
+      dependencies: context.test_dependencies.clone(),
       ..Default::default()
     },
     searching_polyfills: polyfills_for_target(
       options.target,
+      options.platform,
       &options.polyfills,
     ),
     found_polyfills: Default::default(),
+    polyfill_file_specifier: &SYNTHETIC_TEST_SPECIFIERS.polyfills,
     shim_file_specifier: &SYNTHETIC_TEST_SPECIFIERS.shims,
     shim_global_names: options
       .test_shims
@@ -600,11 +1067,81 @@ pub async fn transform(
     used_shim: false,
   };
 
-  for specifier in specifiers
+  let output_modules = specifiers
     .local
     .iter()
-    .chain(specifiers.remote.iter())
-    .chain(specifiers.types.values().map(|d| &d.selected.specifier))
+    .map(|s| (s, OutputFileKind::Local))
+    .chain(
+      specifiers
+        .remote
+        .iter()
+        .map(|s| (s, OutputFileKind::Remote)),
+    )
+    .chain(
+      specifiers
+        .types
+        .values()
+        .map(|d| (&d.selected.specifier, OutputFileKind::Types)),
+    )
+    .collect::<Vec<_>>();
+
+  // the js modules are transformed independently of each other, so that's
+  // done up front in parallel and the results are merged below in order,
+  // which skips the modules whose result from a previous transform is kept
+  let main_shim_params = (
+    main_env_context.shim_file_specifier,
+    &main_env_context.shim_global_names,
+  );
+  let test_shim_params = (
+    test_env_context.shim_file_specifier,
+    &test_env_context.shim_global_names,
+  );
+  // the options aren't shared between threads
+  let (target, platform, polyfills, source_maps) = (
+    options.target,
+    options.platform,
+    &options.polyfills,
+    options.source_maps,
+  );
+  let transformed_js_modules =
+    map_in_parallel(&output_modules, |(specifier, _)| {
+      let Module::Js(module) = module_graph.get(specifier) else {
+        return Ok(None);
+      };
+      if transformed_modules.contains_key(*specifier) {
+        return Ok(None);
+      }
+      let is_test = specifiers.test_modules.contains(*specifier);
+      let (shim_file_specifier, shim_global_names) = if is_test {
+        test_shim_params
+      } else {
+        main_shim_params
+      };
+      transform_js_module(
+        &AnalyzeJsModuleParams {
+          specifier,
+          module,
+          parsed_source: &module_graph.get_parsed_source(module)?,
+          module_graph,
+          mappings,
+          is_test,
+          shim_file_specifier,
+          shim_global_names,
+          package_specifier_mappings,
+          preserved_subpath_imports,
+          import_chains,
+          target,
+          platform,
+          polyfills,
+        },
+        incremental_cache.as_deref(),
+        source_maps,
+      )
+      .map(Some)
+    });
+
+  for ((specifier, kind), transformed_js_module) in
+    output_modules.into_iter().zip(transformed_js_modules)
   {
     let module = module_graph.get(specifier);
     let env_context = if specifiers.test_modules.contains(specifier) {
@@ -613,129 +1150,313 @@ pub async fn transform(
       &mut main_env_context
     };
 
-    let file_text = match module {
-      Module::Js(module) => {
-        let parsed_source = module_graph.get_parsed_source(module)?;
-        let text_changes = parsed_source
-          .with_view(|program| -> Result<Vec<TextChange>> {
-            let ignore_line_indexes = get_ignore_line_indexes(
-              parsed_source.specifier().as_str(),
-              program,
-            );
-            let top_level_decls =
-              get_top_level_decls(program, parsed_source.top_level_context());
-            warnings.extend(ignore_line_indexes.warnings);
-
-            fill_polyfills(&mut FillPolyfillsParams {
-              found_polyfills: &mut env_context.found_polyfills,
-              searching_polyfills: &mut env_context.searching_polyfills,
-              program,
-              unresolved_context: parsed_source.unresolved_context(),
-              top_level_decls: &top_level_decls,
-            });
+    if worker_specifiers.contains(specifier) {
+      let file_path = mappings.get_file_path(specifier);
+      if !env_context.environment.entry_points.contains(file_path) {
+        env_context
+          .environment
+          .worker_entry_points
+          .push(file_path.to_owned());
+      }
+    }
 
-            let mut text_changes = Vec::new();
-
-            // shim changes
-            {
-              let shim_relative_specifier = get_relative_specifier(
-                mappings.get_file_path(specifier),
-                mappings.get_file_path(env_context.shim_file_specifier),
-              );
-              let result =
-                get_global_text_changes(&GetGlobalTextChangesParams {
-                  program,
-                  unresolved_context: parsed_source.unresolved_context(),
-                  shim_specifier: &shim_relative_specifier,
-                  shim_global_names: &env_context.shim_global_names,
-                  ignore_line_indexes: &ignore_line_indexes.line_indexes,
-                  top_level_decls: &top_level_decls,
-                });
-              text_changes.extend(result.text_changes);
-              if result.imported_shim {
-                env_context.used_shim = true;
-              }
-            }
-
-            text_changes
-              .extend(get_deno_comment_directive_text_changes(program));
-            text_changes.extend(get_import_exports_text_changes(
-              &GetImportExportsTextChangesParams {
-                specifier,
-                module_graph: &module_graph,
-                mappings: &mappings,
-                program,
-                package_specifier_mappings: &all_package_specifier_mappings,
-              },
-            )?);
-
-            Ok(text_changes)
-          })
-          .with_context(|| {
-            format!(
-              "Issue getting text changes from {}",
-              parsed_source.specifier()
-            )
-          })?;
-
-        apply_text_changes(parsed_source.text(), text_changes)
+    let mut source_map = None;
+    let file_text = match module {
+      Module::Js(_) => {
+        if let Some(transformed) = transformed_js_module? {
+          transformed_modules.insert(specifier.clone(), transformed);
+        }
+        let transformed = transformed_modules
+          .get(specifier)
+          .expect("dnt bug - js module wasn't transformed");
+        warnings.extend(transformed.warnings.iter().cloned());
+        if transformed.used_shim {
+          env_context.used_shim = true;
+        }
+        for name in &transformed.polyfills {
+          if let Some(index) = env_context
+            .searching_polyfills
+            .iter()
+            .position(|p| p.name() == *name)
+          {
+            let polyfill = env_context.searching_polyfills.remove(index);
+            env_context.found_polyfills.push(polyfill);
+          }
+        }
+        if let (Some(cache), Some((key, analysis))) =
+          (incremental_cache.as_deref_mut(), &transformed.cache_entry)
+        {
+          cache.insert(specifier, key.clone(), analysis.clone());
+        }
+        source_map = transformed.source_map.clone();
+        transformed.file_text.clone()
       }
       Module::Json(module) => {
         format!("export default {};", strip_bom(&module.source.text).trim(),)
       }
-      Module::Node(_)
-      | Module::Npm(_)
-      | Module::External(_)
-      | Module::Wasm(_) => {
+      Module::Wasm(module) => {
+        let file_path = mappings.get_file_path(specifier);
+        let asset_file_path = with_extension(file_path, "wasm");
+        let wrapper_text = get_wasm_module_wrapper_text(
+          module,
+          file_path,
+          &asset_file_path,
+          options.platform,
+        )?;
+        let mut generated_module_params = TransformGeneratedModuleParams {
+          specifier,
+          module_graph,
+          mappings,
+          package_specifier_mappings,
+          preserved_subpath_imports,
+          env_context,
+        };
+        let declaration_text = transform_generated_module(
+          &mut generated_module_params,
+          module.source_dts.to_string(),
+          MediaType::Dts,
+        )?;
+        let wrapper_text = transform_generated_module(
+          &mut generated_module_params,
+          wrapper_text,
+          MediaType::JavaScript,
+        )?;
+        env_context.environment.files.push(OutputFile {
+          file_path: with_extension(file_path, "d.ts"),
+          file_text: declaration_text,
+          source_map: None,
+          specifier: specifier.clone(),
+          media_type: MediaType::Wasm,
+          kind,
+          content_hash: String::new(),
+        });
+        env_context.environment.assets.push(OutputAsset {
+          file_path: asset_file_path,
+          bytes: module.source.to_vec(),
+        });
+        wrapper_text
+      }
+      Module::External(_) if specifiers.assets.contains_key(specifier) => {
+        let file_path = mappings.get_file_path(specifier);
+        let bytes = module_graph.get_asset(specifier);
+        let asset_file_path = (bytes.len() > options.asset_inline_limit)
+          .then(|| get_asset_file_path(specifier, file_path));
+        let module_text = get_asset_module_text(AssetModuleOptions {
+          kind: specifiers.assets[specifier],
+          bytes,
+          module_file_path: file_path,
+          asset_file_path: asset_file_path.as_deref(),
+          platform: options.platform,
+        });
+        match asset_file_path {
+          Some(asset_file_path) => {
+            env_context.environment.assets.push(OutputAsset {
+              file_path: asset_file_path,
+              bytes: bytes.to_vec(),
+            });
+            transform_generated_module(
+              &mut TransformGeneratedModuleParams {
+                specifier,
+                module_graph,
+                mappings,
+                package_specifier_mappings,
+                preserved_subpath_imports,
+                env_context,
+              },
+              module_text,
+              MediaType::TypeScript,
+            )?
+          }
+          None => module_text,
+        }
+      }
+      Module::Node(_) | Module::Npm(_) | Module::External(_) => {
         bail!("Not implemented module kind for {}", module.specifier())
       }
     };
 
     let file_path = mappings.get_file_path(specifier).to_owned();
+    let media_type = match module {
+      Module::Js(module) => module.media_type,
+      Module::Json(module) => module.media_type,
+      _ => MediaType::from_specifier(specifier),
+    };
     env_context.environment.files.push(OutputFile {
       file_path,
       file_text,
+      source_map,
+      specifier: specifier.clone(),
+      media_type,
+      kind,
+      content_hash: String::new(),
+    });
+  }
+
+  // copy the files that the local modules read with
+  // `new URL("./file", import.meta.url)` to the same relative location
+  let mut referenced_file_paths = HashSet::new();
+  for referenced_file in module_graph.referenced_files() {
+    let referrer = &referenced_file.referrer;
+    if !specifiers.local.contains(referrer) {
+      continue;
+    }
+    let Some(content) = &referenced_file.content else {
+      warnings.push(format!(
+        "{} references {} with `new URL`, but it wasn't found.\n    at {}",
+        display_specifier(referrer),
+        display_specifier(&referenced_file.specifier),
+        referenced_file.range,
+      ));
+      continue;
+    };
+    let Some(file_path) = get_referenced_file_path(
+      referrer,
+      mappings.get_file_path(referrer),
+      &referenced_file.specifier,
+    ) else {
+      warnings.push(format!(
+        "{} references {} with `new URL`, but it's outside the output directory so it wasn't copied.\n    at {}",
+        display_specifier(referrer),
+        display_specifier(&referenced_file.specifier),
+        referenced_file.range,
+      ));
+      continue;
+    };
+    if !referenced_file_paths.insert(file_path.clone()) {
+      continue;
+    }
+    let env_context = if specifiers.test_modules.contains(referrer) {
+      &mut test_env_context
+    } else {
+      &mut main_env_context
+    };
+    env_context.environment.assets.push(OutputAsset {
+      file_path,
+      bytes: content.to_vec(),
     });
   }
 
+  if let Some(cache) = incremental_cache {
+    cache.finish(sys)?;
+  }
+
   check_add_polyfill_file_to_environment(
     &mut main_env_context,
     mappings.get_file_path(&SYNTHETIC_SPECIFIERS.polyfills),
-  );
+  )?;
   check_add_polyfill_file_to_environment(
     &mut test_env_context,
     mappings.get_file_path(&SYNTHETIC_TEST_SPECIFIERS.polyfills),
-  );
+  )?;
   check_add_shim_file_to_environment(
     &mut main_env_context,
     mappings.get_file_path(&SYNTHETIC_SPECIFIERS.shims),
-    &mappings,
+    mappings,
   );
   check_add_shim_file_to_environment(
     &mut test_env_context,
     mappings.get_file_path(&SYNTHETIC_TEST_SPECIFIERS.shims),
-    &mappings,
+    mappings,
   );
 
   // Remove any dependencies from the test environment that
   // are found in the main environment. Only check for exact
   // matches in order to cause an npm install error if there
   // are two dependencies with the same name, but different versions.
-  test_env_context
+  test_env_context.environment.dependencies.retain(|d| {
+    !main_env_context.environment.dependencies.contains(d)
+      && !main_env_context
+        .environment
+        .type_only_dependencies
+        .contains(d)
+  });
+
+  if let Some(provider) = &options.npm_package_info_provider {
+    npm_package_info::resolve_dependency_versions(
+      provider.as_ref(),
+      main_env_context
+        .environment
+        .dependencies
+        .iter_mut()
+        .chain(
+          main_env_context
+            .environment
+            .type_only_dependencies
+            .iter_mut(),
+        )
+        .chain(test_env_context.environment.dependencies.iter_mut())
+        .chain(types_dependencies.iter_mut()),
+      options.pin_npm_versions,
+    )
+    .await?;
+  }
+
+  let package_manifest = get_package_manifest(
+    GetPackageManifestOptions {
+      options: &options.package_manifest,
+      entry_points: &context.entry_points,
+      bin_entry_points: &options.bin_entry_points,
+      config_package: context.config_package.as_ref(),
+      main: &main_env_context.environment,
+      test: &test_env_context.environment,
+      types_dependencies: &types_dependencies,
+      subpath_imports: &preserved_subpath_imports.imports,
+      platform: options.platform,
+    },
+    |specifier| mappings.get_file_path(specifier).clone(),
+  )?;
+
+  for file in main_env_context
     .environment
-    .dependencies
-    .retain(|d| !main_env_context.environment.dependencies.contains(d));
+    .files
+    .iter_mut()
+    .chain(test_env_context.environment.files.iter_mut())
+  {
+    file.content_hash = LoaderChecksum::r#gen(file.file_text.as_bytes());
+  }
 
   Ok(TransformOutput {
     main: main_env_context.environment,
     test: test_env_context.environment,
     warnings,
-    discovered_config_file,
+    discovered_config_file: context.discovered_config_file.clone(),
     types_dependencies,
-    bin_only_files,
+    bin_only_files: context.bin_only_files.clone(),
+    config_package: context.config_package.clone(),
+    subpath_imports: preserved_subpath_imports.imports.clone(),
+    package_manifest,
+    mappings: mappings.clone(),
+    graph: context.graph.clone(),
+    import_chains: import_chains.clone(),
+    source_file_paths: context.source_file_paths.clone(),
   })
 }
 
+/// Options of the workspace factory that discovers the config files.
+fn workspace_factory_options(
+  config_discovery: ConfigDiscoveryOption,
+  frozen_lockfile: Option<bool>,
+) -> WorkspaceFactoryOptions {
+  WorkspaceFactoryOptions {
+    additional_config_file_names: &[],
+    config_discovery,
+    is_package_manager_subcommand: false,
+    // force no node_modules directory so that we resolve package.json deps as npm specifiers
+    node_modules_dir: Some(deno_config::deno_json::NodeModulesDirMode::None),
+    node_modules_linker: None,
+    no_npm: false,
+    import_npm_lockfile: false,
+    npm_process_state: None,
+    root_node_modules_dir_override: None,
+    vendor: None,
+    frozen_lockfile,
+    lock_arg: None,
+    lockfile_skip_write: true,
+    maybe_custom_deno_dir_root: None,
+    no_lock: false,
+  }
+}
+
 /// Resolves the specifier mapping keys, which may be bare specifiers that
 /// resolve via the config file's import map (ex. `my-lib`).
 ///
@@ -761,6 +1482,14 @@ fn resolve_specifier_mappings<'a, TSys: deno_resolver::DenoResolverSys>(
         resolve_mapping_key(&key, resolver, cjs_tracker, referrers.clone())?
       }
     };
+    if graph::is_prefix_mapping_key(&specifier)
+      && matches!(value, MappedSpecifier::Module(_))
+    {
+      bail!(
+        "The mapping \"{}\" ends with a slash, so it must map to a package rather than a module",
+        key,
+      );
+    }
     if let Some(previous_key) = keys.insert(specifier.clone(), key.clone()) {
       bail!(
         "The mappings \"{}\" and \"{}\" both resolved to {}",
@@ -840,26 +1569,351 @@ fn get_shim_types_packages<'a>(
   packages
 }
 
+struct AnalyzeJsModuleParams<'a> {
+  specifier: &'a ModuleSpecifier,
+  module: &'a JsModule,
+  parsed_source: &'a ParsedSource,
+  module_graph: &'a crate::graph::ModuleGraph,
+  mappings: &'a Mappings,
+  is_test: bool,
+  shim_file_specifier: &'a ModuleSpecifier,
+  shim_global_names: &'a HashSet<&'a str>,
+  package_specifier_mappings: &'a HashMap<ModuleSpecifier, String>,
+  preserved_subpath_imports: &'a PreservedSubpathImports,
+  import_chains: &'a ImportChains,
+  target: ScriptTarget,
+  platform: Platform,
+  polyfills: &'a PolyfillOverrides,
+}
+
+/// A JS module's output along with what it found.
+struct TransformedJsModule {
+  file_text: String,
+  source_map: Option<String>,
+  /// Names of the polyfills the module needs.
+  polyfills: Vec<String>,
+  used_shim: bool,
+  warnings: Vec<String>,
+  /// Key and analysis to store in the incremental cache for the module.
+  cache_entry: Option<(String, ModuleAnalysis)>,
+}
+
+/// Transforms a JS module, reusing its analysis from the incremental cache
+/// when nothing affecting it changed.
+///
+/// This doesn't depend on the other modules' output so that it can run for
+/// each module in parallel.
+fn transform_js_module(
+  params: &AnalyzeJsModuleParams,
+  incremental_cache: Option<&IncrementalCache>,
+  source_maps: SourceMaps,
+) -> Result<TransformedJsModule> {
+  let (analysis, cache_entry) = match incremental_cache {
+    Some(cache) => {
+      let key = get_module_cache_key(params);
+      let analysis = match cache.get(params.specifier, &key) {
+        Some(analysis) => analysis.clone(),
+        None => analyze_js_module(params)?,
+      };
+      let cache_entry = (key, analysis.clone());
+      (analysis, Some(cache_entry))
+    }
+    None => (analyze_js_module(params)?, None),
+  };
+  let (file_text, source_map) = apply_text_changes_with_source_map(
+    &params.module.specifier,
+    params.parsed_source.text(),
+    analysis.text_changes,
+    source_maps,
+  );
+  Ok(TransformedJsModule {
+    file_text,
+    source_map,
+    polyfills: analysis.polyfills,
+    used_shim: analysis.used_shim,
+    warnings: analysis.warnings,
+    cache_entry,
+  })
+}
+
+/// Gets the text changes, polyfills, shim usage, and warnings of a module.
+///
+/// This only depends on what's in the module's cache key so that the
+/// analysis can be reused by a later transform.
+fn analyze_js_module(params: &AnalyzeJsModuleParams) -> Result<ModuleAnalysis> {
+  let specifier = params.specifier;
+  let parsed_source = params.parsed_source;
+  parsed_source
+    .with_view(|program| -> Result<ModuleAnalysis> {
+      let mut analysis = ModuleAnalysis::default();
+      let ignore_line_indexes =
+        get_ignore_line_indexes(parsed_source.specifier().as_str(), program);
+      let top_level_decls =
+        get_top_level_decls(program, parsed_source.top_level_context());
+      analysis.warnings.extend(ignore_line_indexes.warnings);
+
+      // search for all the polyfills rather than only the ones the other
+      // modules haven't used so the result doesn't depend on them
+      let mut found_polyfills = Vec::new();
+      fill_polyfills(&mut FillPolyfillsParams {
+        found_polyfills: &mut found_polyfills,
+        searching_polyfills: &mut polyfills_for_target(
+          params.target,
+          params.platform,
+          params.polyfills,
+        ),
+        program,
+        unresolved_context: parsed_source.unresolved_context(),
+        top_level_decls: &top_level_decls,
+      });
+      analysis.polyfills = found_polyfills
+        .iter()
+        .map(|p| p.name().to_string())
+        .collect();
+
+      // shim changes
+      {
+        let shim_relative_specifier = get_relative_specifier(
+          params.mappings.get_file_path(specifier),
+          params.mappings.get_file_path(params.shim_file_specifier),
+        );
+        let result = get_global_text_changes(&GetGlobalTextChangesParams {
+          program,
+          unresolved_context: parsed_source.unresolved_context(),
+          shim_specifier: &shim_relative_specifier,
+          shim_global_names: params.shim_global_names,
+          ignore_line_indexes: &ignore_line_indexes.line_indexes,
+          top_level_decls: &top_level_decls,
+        });
+        analysis.text_changes.extend(result.text_changes);
+        analysis.used_shim = result.imported_shim;
+      }
+
+      if params.platform == Platform::Browser
+        && !params.is_test
+        && !params.module.media_type.is_declaration()
+      {
+        analysis.warnings.extend(get_browser_platform_warnings(
+          params.module,
+          params.package_specifier_mappings,
+          params.import_chains,
+          has_global_usage(
+            program,
+            parsed_source.unresolved_context(),
+            &top_level_decls,
+            "Deno",
+          ),
+        ));
+      }
+
+      // point the workers at their output
+      for import_meta_url in
+        get_import_meta_urls(program, parsed_source.unresolved_context())
+      {
+        if !import_meta_url.is_worker {
+          continue;
+        }
+        let Ok(worker_specifier) = specifier.join(&import_meta_url.specifier)
+        else {
+          continue;
+        };
+        // a worker that's mapped to a package isn't in the output
+        let Some(worker_file_path) = params
+          .mappings
+          .try_get_file_path(params.module_graph.resolve(&worker_specifier))
+        else {
+          analysis.warnings.push(format!(
+            "{} creates a worker with {}, but it's mapped to a package so its script isn't in the output.\n    at {}",
+            display_specifier(specifier),
+            display_specifier(&worker_specifier),
+            deno_graph::Range {
+              specifier: specifier.clone(),
+              range: deno_graph::PositionRange {
+                start: import_meta_url.position,
+                end: import_meta_url.position,
+              },
+              resolution_mode: None,
+            },
+          ));
+          continue;
+        };
+        analysis.text_changes.push(TextChange {
+          range: import_meta_url
+            .specifier_range
+            .as_byte_range(parsed_source.text_info_lazy().range().start),
+          new_text: get_relative_specifier(
+            params.mappings.get_file_path(specifier),
+            worker_file_path,
+          ),
+        });
+      }
+
+      analysis
+        .text_changes
+        .extend(get_deno_comment_directive_text_changes(program));
+      analysis
+        .text_changes
+        .extend(get_import_exports_text_changes(
+          &GetImportExportsTextChangesParams {
+            specifier,
+            module_graph: params.module_graph,
+            mappings: params.mappings,
+            program,
+            package_specifier_mappings: params.package_specifier_mappings,
+            preserved_subpath_imports: params.preserved_subpath_imports,
+          },
+        )?);
+
+      Ok(analysis)
+    })
+    .with_context(|| {
+      format!(
+        "Issue getting text changes from {}",
+        parsed_source.specifier()
+      )
+    })
+}
+
+/// Gets the key of a module's analysis in the incremental cache from
+/// everything the analysis depends on.
+fn get_module_cache_key(params: &AnalyzeJsModuleParams) -> String {
+  let specifier = params.specifier;
+  let mappings = params.mappings;
+  let mut shim_global_names =
+    params.shim_global_names.iter().copied().collect::<Vec<_>>();
+  shim_global_names.sort();
+  let polyfills =
+    polyfills_for_target(params.target, params.platform, params.polyfills)
+      .iter()
+      .map(|p| p.name())
+      .collect::<Vec<_>>();
+  let mut parts = vec![
+    env!("CARGO_PKG_VERSION").to_string(),
+    specifier.to_string(),
+    params.module.media_type.to_string(),
+    params.parsed_source.text().to_string(),
+    params.is_test.to_string(),
+    format!("{:?}", params.platform),
+    polyfills.join(","),
+    shim_global_names.join(","),
+    mappings
+      .get_file_path(params.shim_file_specifier)
+      .display()
+      .to_string(),
+    format!("{:?}", params.preserved_subpath_imports.imports),
+    // the output paths of the module and the modules it imports
+    mappings.get_file_path(specifier).display().to_string(),
+  ];
+  for value in params.module.dependencies.keys() {
+    let resolved = params.module_graph.resolve_dependency(value, specifier);
+    let output = resolved.and_then(|resolved| {
+      match params.package_specifier_mappings.get(&resolved) {
+        Some(text) => Some(text.clone()),
+        None => mappings
+          .try_get_file_path(&resolved)
+          .map(|p| p.display().to_string()),
+      }
+    });
+    parts.push(format!("{value} {}", output.unwrap_or_default()));
+  }
+  for worker in params.module_graph.get_workers(specifier) {
+    parts.push(
+      mappings
+        .try_get_file_path(params.module_graph.resolve(worker))
+        .map(|p| p.display().to_string())
+        .unwrap_or_default(),
+    );
+  }
+  // warnings about remote modules say how they got in the output
+  if specifier.scheme() != "file" {
+    if let Some(chain) = params.import_chains.get(specifier) {
+      parts.push(format_import_chain(&chain));
+    }
+  }
+  get_cache_key(parts.iter().map(|p| p.as_str()))
+}
+
+struct TransformGeneratedModuleParams<'a, 'b> {
+  /// Specifier of the module in the graph that the module is generated for.
+  specifier: &'a ModuleSpecifier,
+  module_graph: &'a crate::graph::ModuleGraph,
+  mappings: &'a Mappings,
+  package_specifier_mappings: &'a HashMap<ModuleSpecifier, String>,
+  preserved_subpath_imports: &'a PreservedSubpathImports,
+  env_context: &'a mut EnvironmentContext<'b>,
+}
+
+/// Changes the import specifiers of a module that's generated for a module
+/// in the graph to the output's specifiers and finds the polyfills it needs,
+/// the same as is done for the modules in the graph.
+fn transform_generated_module(
+  params: &mut TransformGeneratedModuleParams,
+  text: String,
+  media_type: MediaType,
+) -> Result<String> {
+  let parsed_source = deno_ast::parse_program(deno_ast::ParseParams {
+    specifier: params.specifier.clone(),
+    text: text.into(),
+    media_type,
+    capture_tokens: true,
+    scope_analysis: true,
+    maybe_syntax: None,
+  })?;
+  let text_changes = parsed_source.with_view(|program| {
+    let top_level_decls =
+      get_top_level_decls(program, parsed_source.top_level_context());
+    fill_polyfills(&mut FillPolyfillsParams {
+      found_polyfills: &mut params.env_context.found_polyfills,
+      searching_polyfills: &mut params.env_context.searching_polyfills,
+      program,
+      unresolved_context: parsed_source.unresolved_context(),
+      top_level_decls: &top_level_decls,
+    });
+    get_import_exports_text_changes(&GetImportExportsTextChangesParams {
+      specifier: params.specifier,
+      module_graph: params.module_graph,
+      mappings: params.mappings,
+      program,
+      package_specifier_mappings: params.package_specifier_mappings,
+      preserved_subpath_imports: params.preserved_subpath_imports,
+    })
+  })?;
+  Ok(apply_text_changes(parsed_source.text(), text_changes))
+}
+
 fn check_add_polyfill_file_to_environment(
   env_context: &mut EnvironmentContext,
   polyfill_file_path: &Path,
-) {
+) -> Result<()> {
   if let Some(polyfill_file_text) =
     build_polyfill_file(&env_context.found_polyfills)
   {
     env_context.environment.files.push(OutputFile {
       file_path: polyfill_file_path.to_path_buf(),
       file_text: polyfill_file_text,
+      source_map: None,
+      specifier: env_context.polyfill_file_specifier.clone(),
+      media_type: MediaType::TypeScript,
+      kind: OutputFileKind::Synthetic,
+      content_hash: String::new(),
     });
 
-    for entry_point in env_context.environment.entry_points.iter() {
+    // the scripts of workers don't run after an entry point, so they need
+    // the polyfills as well
+    for entry_point in env_context
+      .environment
+      .entry_points
+      .iter()
+      .chain(env_context.environment.worker_entry_points.iter())
+    {
       if let Some(file) = env_context
         .environment
         .files
         .iter_mut()
         .find(|f| &f.file_path == entry_point)
       {
-        prepend_statement_to_text(
+        let original_text = file.file_text.clone();
+        let text_change = prepend_statement_to_text(
           &file.file_path,
           &mut file.file_text,
           &format!(
@@ -867,6 +1921,14 @@ fn check_add_polyfill_file_to_environment(
             get_relative_specifier(&file.file_path, polyfill_file_path)
           ),
         );
+        if let Some(source_map) = &mut file.source_map {
+          *source_map = update_source_map_for_insert(
+            source_map,
+            &original_text,
+            text_change.range.start,
+            &text_change.new_text,
+          )?;
+        }
       }
     }
   }
@@ -882,6 +1944,7 @@ fn check_add_polyfill_file_to_environment(
       }
     }
   }
+  Ok(())
 }
 
 fn check_add_shim_file_to_environment(
@@ -895,6 +1958,11 @@ fn check_add_shim_file_to_environment(
     env_context.environment.files.push(OutputFile {
       file_path: shim_file_path.to_path_buf(),
       file_text: shim_file_text,
+      source_map: None,
+      specifier: env_context.shim_file_specifier.clone(),
+      media_type: MediaType::TypeScript,
+      kind: OutputFileKind::Synthetic,
+      content_hash: String::new(),
     });
 
     for shim in env_context.shims.iter() {
@@ -909,7 +1977,7 @@ fn check_add_shim_file_to_environment(
             env_context.environment.dependencies.push(Dependency {
               name: shim.package.name.to_string(),
               version: version.clone(),
-              peer_dependency: shim.package.peer_dependency,
+              kind: shim.package.kind,
             });
           }
         }
@@ -1084,7 +2152,7 @@ async fn get_types_dependencies<TSys: WorkspaceFactorySys>(
       Some(Dependency {
         name: p.name,
         version: p.version?,
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       })
     })
     .collect()
@@ -1159,6 +2227,24 @@ fn get_bin_only_files(options: GetBinOnlyFilesOptions) -> Vec<PathBuf> {
   file_paths
 }
 
+/// Gets the dependencies of the environment along with the ones that it only
+/// imports for their types.
+fn get_environment_dependencies(
+  specifiers: EnvironmentSpecifiers,
+) -> (Vec<Dependency>, Vec<Dependency>) {
+  let (type_only, runtime): (BTreeMap<_, _>, BTreeMap<_, _>) = specifiers
+    .mapped
+    .into_iter()
+    .partition(|(specifier, _)| specifiers.type_only.contains(specifier));
+  let dependencies = get_dependencies(runtime);
+  let mut type_only_dependencies = get_dependencies(type_only);
+  // the package is still needed at runtime when the code imports it by
+  // another specifier (ex. a different sub path)
+  type_only_dependencies
+    .retain(|t| !dependencies.iter().any(|d| d.name == t.name));
+  (dependencies, type_only_dependencies)
+}
+
 fn get_dependencies(
   mappings: BTreeMap<ModuleSpecifier, PackageMappedSpecifier>,
 ) -> Vec<Dependency> {
@@ -1169,31 +2255,92 @@ fn get_dependencies(
         Some(Dependency {
           name: entry.1.name,
           version,
-          peer_dependency: entry.1.peer_dependency,
+          kind: entry.1.kind,
         })
       } else {
         None
       }
     })
     .collect::<Vec<_>>();
-  // a package.json lists a package as a peer dependency or a dependency, but
-  // never both, so a package mapped as a peer dependency stays one even when
-  // another specifier resolves to it (ex. a sub path of the same package,
-  // whose `npm:` specifier the user has no reason to map on its own)
-  let peer_names = dependencies
-    .iter()
-    .filter(|d| d.peer_dependency)
-    .map(|d| d.name.clone())
-    .collect::<HashSet<_>>();
+  // a package.json lists a package under only one kind of dependency, so a
+  // package mapped as a peer dependency stays one even when another specifier
+  // resolves to it (ex. a sub path of the same package, whose `npm:` specifier
+  // the user has no reason to map on its own), while a package that's
+  // needed at runtime isn't made optional
+  let mut kinds: HashMap<String, DependencyKind> = HashMap::new();
+  for dependency in dependencies.iter() {
+    kinds
+      .entry(dependency.name.clone())
+      .and_modify(|kind| *kind = (*kind).max(dependency.kind))
+      .or_insert(dependency.kind);
+  }
   for dependency in dependencies.iter_mut() {
-    dependency.peer_dependency |= peer_names.contains(&dependency.name);
+    dependency.kind = kinds[&dependency.name];
   }
   dependencies.sort_by(|a, b| a.name.cmp(&b.name));
   dependencies.dedup(); // only works after sorting
   dependencies
 }
 
-fn get_declaration_warnings(specifiers: &Specifiers) -> Vec<String> {
+/// Gets warnings about the code of a module that won't run in a browser.
+fn get_browser_platform_warnings(
+  module: &JsModule,
+  package_specifier_mappings: &HashMap<ModuleSpecifier, String>,
+  import_chains: &ImportChains,
+  uses_deno_global: bool,
+) -> Vec<String> {
+  let mut node_modules = BTreeSet::new();
+  for dep in module.dependencies.values() {
+    // type only imports are fine since they're removed from the output
+    if !dep.imports.iter().any(|i| i.kind.is_runtime()) {
+      continue;
+    }
+    let Some(specifier) = dep.maybe_code.maybe_specifier() else {
+      continue;
+    };
+    if specifier.scheme() == "node" {
+      node_modules.insert(specifier.path().to_string());
+    } else if let Some(text) = package_specifier_mappings.get(specifier) {
+      // ex. a deno.land/std/node module that's mapped to the built-in
+      let name = text.strip_prefix("node:").unwrap_or(text);
+      if DENO_SUPPORTED_BUILTIN_NODE_MODULES.contains(&name) {
+        node_modules.insert(name.to_string());
+      }
+    }
+  }
+
+  let mut warnings = node_modules
+    .into_iter()
+    .map(|name| {
+      format!(
+        "{} imports the Node.js built-in module \"node:{}\", which isn't available in the browser.",
+        display_specifier(&module.specifier),
+        name,
+      )
+    })
+    .collect::<Vec<_>>();
+  if uses_deno_global {
+    warnings.push(format!(
+      "{} uses the `Deno` global, which isn't available in the browser.",
+      display_specifier(&module.specifier),
+    ));
+  }
+  // say how a remote module got in the output since it's not obvious
+  if module.specifier.scheme() != "file" {
+    if let Some(chain) = import_chains.get(&module.specifier) {
+      let chain = format_import_chain(&chain);
+      for warning in warnings.iter_mut() {
+        warning.push_str(&format!("\n  Import chain: {chain}"));
+      }
+    }
+  }
+  warnings
+}
+
+fn get_declaration_warnings(
+  specifiers: &Specifiers,
+  import_chains: &ImportChains,
+) -> Vec<String> {
   let mut messages = Vec::new();
   for (code_specifier, d) in specifiers.types.iter() {
     if d.selected.referrer.scheme() == "file" {
@@ -1204,15 +2351,18 @@ fn get_declaration_warnings(specifiers: &Specifiers) -> Vec<String> {
           code_specifier,
           dep,
           &d.selected,
+          None,
           "Supress this warning by having only one local file specify the declaration file for this module.",
         ));
       }
     } else {
+      let import_chain = import_chains.get(code_specifier);
       for dep in d.ignored.iter() {
         messages.push(get_dep_warning(
           code_specifier,
           dep,
           &d.selected,
+          import_chain.as_deref(),
           "Supress this warning by specifying a declaration file for this module locally via `@deno-types`.",
         ));
       }
@@ -1224,9 +2374,16 @@ fn get_declaration_warnings(specifiers: &Specifiers) -> Vec<String> {
     code_specifier: &ModuleSpecifier,
     dep: &TypesDependency,
     selected_dep: &TypesDependency,
+    import_chain: Option<&[ModuleSpecifier]>,
     post_message: &str,
   ) -> String {
-    format!("Duplicate declaration file found for {}\n  Specified {} in {}\n  Selected {}\n  {}", code_specifier, dep.specifier, dep.referrer, selected_dep.specifier, post_message)
+    let import_chain = match import_chain {
+      Some(chain) => {
+        format!("  Import chain: {}\n", format_import_chain(chain))
+      }
+      None => String::new(),
+    };
+    format!("Duplicate declaration file found for {}\n  Specified {} in {}\n  Selected {}\n{}  {}", code_specifier, dep.specifier, dep.referrer, selected_dep.specifier, import_chain, post_message)
   }
 }
 
@@ -1259,7 +2416,7 @@ mod test {
         name: "package".to_string(),
         version: Some("*".to_string()),
         sub_path: None,
-        peer_dependency: false
+        kind: DependencyKind::Dependency
       })
     );
     assert_eq!(
@@ -1268,7 +2425,7 @@ mod test {
         name: "package".to_string(),
         version: Some("^2.1".to_string()),
         sub_path: None,
-        peer_dependency: false
+        kind: DependencyKind::Dependency
       })
     );
     assert_eq!(
@@ -1277,7 +2434,7 @@ mod test {
         name: "preact".to_string(),
         version: Some("*".to_string()),
         sub_path: Some("hooks".to_string()),
-        peer_dependency: false
+        kind: DependencyKind::Dependency
       })
     );
     assert_eq!(
@@ -1286,7 +2443,7 @@ mod test {
         name: "package".to_string(),
         version: Some("*".to_string()),
         sub_path: Some("sub/path".to_string()),
-        peer_dependency: false
+        kind: DependencyKind::Dependency
       })
     );
     assert_eq!(
@@ -1295,7 +2452,7 @@ mod test {
         name: "@scope/name".to_string(),
         version: Some("*".to_string()),
         sub_path: Some("path/sub".to_string()),
-        peer_dependency: false
+        kind: DependencyKind::Dependency
       })
     );
     assert_eq!(
@@ -1304,7 +2461,7 @@ mod test {
         name: "package".to_string(),
         version: Some("^2.1".to_string()),
         sub_path: Some("sub_path".to_string()),
-        peer_dependency: false
+        kind: DependencyKind::Dependency
       })
     );
     assert_eq!(
@@ -1313,7 +2470,7 @@ mod test {
         name: "@project/name".to_string(),
         version: Some("2.1.3".to_string()),
         sub_path: None,
-        peer_dependency: false
+        kind: DependencyKind::Dependency
       })
     );
     assert_eq!(
@@ -1322,8 +2479,64 @@ mod test {
         name: "@project/name".to_string(),
         version: Some("2.1.3".to_string()),
         sub_path: None,
-        peer_dependency: false
+        kind: DependencyKind::Dependency
       })
     );
   }
+
+  #[test]
+  fn test_get_dependencies_kind_precedence() {
+    fn mapped(name: &str, kind: DependencyKind) -> PackageMappedSpecifier {
+      PackageMappedSpecifier {
+        name: name.to_string(),
+        version: Some("^1.0.0".to_string()),
+        sub_path: None,
+        kind,
+      }
+    }
+    fn dependency(name: &str, kind: DependencyKind) -> Dependency {
+      Dependency {
+        name: name.to_string(),
+        version: "^1.0.0".to_string(),
+        kind,
+      }
+    }
+
+    let mappings = BTreeMap::from([
+      (
+        ModuleSpecifier::parse("npm:optional").unwrap(),
+        mapped("optional", DependencyKind::Optional),
+      ),
+      (
+        ModuleSpecifier::parse("npm:optional/sub").unwrap(),
+        mapped("optional", DependencyKind::Dependency),
+      ),
+      (
+        ModuleSpecifier::parse("npm:optional-peer").unwrap(),
+        mapped("optional-peer", DependencyKind::OptionalPeer),
+      ),
+      (
+        ModuleSpecifier::parse("npm:optional-peer/sub").unwrap(),
+        mapped("optional-peer", DependencyKind::Dependency),
+      ),
+      (
+        ModuleSpecifier::parse("npm:peer").unwrap(),
+        mapped("peer", DependencyKind::Peer),
+      ),
+      (
+        ModuleSpecifier::parse("npm:peer/sub").unwrap(),
+        mapped("peer", DependencyKind::Dependency),
+      ),
+    ]);
+    // a package that's required somewhere isn't demoted to a kind that
+    // npm might not install
+    assert_eq!(
+      get_dependencies(mappings),
+      vec![
+        dependency("optional", DependencyKind::Dependency),
+        dependency("optional-peer", DependencyKind::Dependency),
+        dependency("peer", DependencyKind::Peer),
+      ]
+    );
+  }
 }
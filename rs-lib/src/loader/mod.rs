@@ -14,6 +14,7 @@ mod specifier_mappers;
 pub use specifier_mappers::*;
 
 use crate::graph::JsrSpecifierMappings;
+use crate::graph::PrefixSpecifierMappings;
 use crate::MappedSpecifier;
 use crate::PackageMappedSpecifier;
 
@@ -29,6 +30,7 @@ pub struct SourceLoader<'a> {
   specifier_mappers: Vec<Box<dyn SpecifierMapper>>,
   specifier_mappings: &'a HashMap<ModuleSpecifier, MappedSpecifier>,
   jsr_specifier_mappings: &'a JsrSpecifierMappings,
+  prefix_specifier_mappings: &'a PrefixSpecifierMappings,
 }
 
 impl<'a> SourceLoader<'a> {
@@ -37,6 +39,7 @@ impl<'a> SourceLoader<'a> {
     specifier_mappers: Vec<Box<dyn SpecifierMapper>>,
     specifier_mappings: &'a HashMap<ModuleSpecifier, MappedSpecifier>,
     jsr_specifier_mappings: &'a JsrSpecifierMappings,
+    prefix_specifier_mappings: &'a PrefixSpecifierMappings,
   ) -> Self {
     Self {
       loader,
@@ -44,6 +47,7 @@ impl<'a> SourceLoader<'a> {
       specifier_mappers,
       specifier_mappings,
       jsr_specifier_mappings,
+      prefix_specifier_mappings,
     }
   }
 
@@ -54,15 +58,25 @@ impl<'a> SourceLoader<'a> {
   /// Gets the mapping the user provided for a specifier, if any.
   ///
   /// Mapped `jsr:` specifiers don't keep their scheme in the graph, so they
-  /// are looked up by the specifier they were resolved to instead.
+  /// are looked up by the specifier they were resolved to instead. A
+  /// specifier that isn't mapped on its own may be under a mapped directory.
   fn mapping(
     &self,
     specifier: &ModuleSpecifier,
   ) -> Option<Cow<MappedSpecifier>> {
-    match self.specifier_mappings.get(specifier) {
-      Some(mapping) => Some(Cow::Borrowed(mapping)),
-      None => self.jsr_specifier_mappings.get(specifier).map(Cow::Owned),
+    if let Some(mapping) = self.specifier_mappings.get(specifier) {
+      return Some(Cow::Borrowed(mapping));
     }
+    self
+      .jsr_specifier_mappings
+      .get(specifier)
+      .or_else(|| {
+        self
+          .prefix_specifier_mappings
+          .get(specifier)
+          .map(MappedSpecifier::Package)
+      })
+      .map(Cow::Owned)
   }
 }
 
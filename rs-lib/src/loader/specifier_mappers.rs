@@ -4,6 +4,7 @@ use deno_ast::ModuleSpecifier;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::DependencyKind;
 use crate::PackageMappedSpecifier;
 
 pub trait SpecifierMapper {
@@ -111,7 +112,7 @@ impl SpecifierMapper for SkypackMapper {
       name,
       version: Some(version),
       sub_path,
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     })
   }
 
@@ -164,7 +165,7 @@ impl SpecifierMapper for EsmShMapper {
       name: captures.get(2).unwrap().as_str().to_string(),
       version: Some(captures.get(3).unwrap().as_str().to_string()),
       sub_path,
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     })
   }
 
@@ -201,7 +202,7 @@ fn to_types_package(
     // the sub path is dropped because the package is only used as a
     // dependency in the package.json and never in a module specifier
     sub_path: None,
-    peer_dependency: false,
+    kind: DependencyKind::Dependency,
   })
 }
 
@@ -238,7 +239,7 @@ impl SpecifierMapper for DenoStdNodeSpecifierMapper {
         name: self.to_specifier.clone(),
         version: None,
         sub_path: None,
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       })
     } else {
       None
@@ -268,7 +269,7 @@ mod test {
       Some(PackageMappedSpecifier {
         name: "@project/name".to_string(),
         version: Some("5.6.2".to_string()),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
         sub_path: None,
       }),
     );
@@ -293,7 +294,7 @@ mod test {
       Some(PackageMappedSpecifier {
         name: "@project/name".to_string(),
         version: Some("5.6.2".to_string()),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
         sub_path: None,
       }),
     );
@@ -308,7 +309,7 @@ mod test {
         name: "@project/name".to_string(),
         version: Some("5.6.2".to_string()),
         sub_path: Some("es2022/name.js".to_string()),
-        peer_dependency: false
+        kind: DependencyKind::Dependency
       }),
     );
     assert_eq!(
@@ -319,7 +320,7 @@ mod test {
       Some(PackageMappedSpecifier {
         name: "nostr-tools".to_string(),
         version: Some("1.8.4".to_string()),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
         sub_path: None,
       }),
     );
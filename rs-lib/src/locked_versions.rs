@@ -0,0 +1,199 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+
+use deno_ast::ModuleSpecifier;
+use deno_lockfile::Lockfile;
+use deno_semver::jsr::JsrDepPackageReq;
+use deno_semver::npm::NpmPackageReqReference;
+use deno_semver::package::PackageReq;
+use deno_semver::Version;
+use deno_semver::VersionRangeSet;
+
+use crate::graph::display_specifier;
+use crate::graph::JsrSpecifierMappings;
+use crate::specifiers::PackageWarning;
+use crate::PackageMappedSpecifier;
+
+// make sure to update `LockedDependencyVersions` in the TS code when changing the names on this
+/// How to derive the version of a dependency from the version that the
+/// deno lockfile locks its specifier to.
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockedDependencyVersions {
+  /// The locked version (ex. `1.2.3`).
+  Exact,
+  /// Versions compatible with the locked version (ex. `^1.2.3`).
+  Caret,
+}
+
+/// Uses the versions that the deno lockfile locks the `npm:` and mapped `jsr:`
+/// specifiers to for the versions of their packages, returning warnings for
+/// any specifier whose version requirement has drifted from its locked
+/// version.
+///
+/// Only the versions written in the specifiers are replaced. A package that a
+/// mapping specifies the version of keeps that version.
+pub fn apply_locked_versions<'a>(
+  lockfile: &Lockfile,
+  jsr_specifier_mappings: &JsrSpecifierMappings,
+  mapped_specifiers: impl Iterator<
+    Item = (&'a ModuleSpecifier, &'a mut PackageMappedSpecifier),
+  >,
+  mode: Option<LockedDependencyVersions>,
+) -> Vec<PackageWarning> {
+  let Some(mode) = mode else {
+    return Vec::new();
+  };
+  let mut warnings = Vec::new();
+  let mut locked_by_name: BTreeMap<String, Vec<(Version, _)>> = BTreeMap::new();
+  let mut mapping_versioned_names = HashSet::new();
+  for (specifier, mapped) in mapped_specifiers {
+    let Some(dep_req) = source_package_req(jsr_specifier_mappings, specifier)
+      .or_else(|| npm_package_req(specifier, mapped))
+    else {
+      mapping_versioned_names.insert(mapped.name.clone());
+      continue;
+    };
+    let Some(version) = lockfile
+      .content
+      .packages
+      .specifiers
+      .get(&dep_req)
+      .and_then(|version| parse_locked_version(version))
+    else {
+      continue;
+    };
+    if let Some(range) = dep_req.req.version_req.range() {
+      if !range.satisfies(&version) {
+        warnings.push(PackageWarning {
+          package_name: mapped.name.clone(),
+          message: format!(
+            "The deno.lock file locks {} to {}, which does not match its version requirement of {}.",
+            display_specifier(specifier),
+            version,
+            dep_req.req.version_req.version_text(),
+          ),
+        });
+      } else if allows_distant_versions(range, &version) {
+        warnings.push(PackageWarning {
+          package_name: mapped.name.clone(),
+          message: format!(
+            "The version requirement of {} allows versions far from {}, which the deno.lock file locks it to. Consider narrowing it (ex. ^{}).",
+            display_specifier(specifier),
+            version,
+            version,
+          ),
+        });
+      }
+    }
+    locked_by_name
+      .entry(mapped.name.clone())
+      .or_default()
+      .push((version, mapped));
+  }
+
+  for (name, entries) in locked_by_name {
+    if mapping_versioned_names.contains(&name) {
+      continue;
+    }
+    // the specifiers are resolved to a single version in the output, so use
+    // the newest one that was tested with
+    let version = entries.iter().map(|(v, _)| v).max().unwrap().clone();
+    let version = match mode {
+      LockedDependencyVersions::Exact => version.to_string(),
+      LockedDependencyVersions::Caret => format!("^{version}"),
+    };
+    for (_, mapped) in entries {
+      mapped.version = Some(version.clone());
+    }
+  }
+  warnings
+}
+
+/// Gets if a version range allows versions that are far from the locked
+/// version, which are ones in an older minor release or a newer major
+/// release than it (ex. `5` or `*` locked at `5.3.0`), so the package may be
+/// resolved to a version that it wasn't tested with.
+fn allows_distant_versions(range: &VersionRangeSet, version: &Version) -> bool {
+  let first_of_major = (version.minor > 0)
+    .then(|| Version::parse_standard(&format!("{}.0.0", version.major)).ok())
+    .flatten();
+  let next_major =
+    Version::parse_standard(&format!("{}.0.0", version.major + 1)).ok();
+  first_of_major
+    .into_iter()
+    .chain(next_major)
+    .any(|v| range.satisfies(&v))
+}
+
+/// Package requirement of a mapped `jsr:` specifier in the module graph whose
+/// mapping gets its version from the specifier.
+fn source_package_req(
+  jsr_specifier_mappings: &JsrSpecifierMappings,
+  specifier: &ModuleSpecifier,
+) -> Option<JsrDepPackageReq> {
+  jsr_specifier_mappings
+    .specifier_package_req(specifier)
+    .map(JsrDepPackageReq::jsr)
+}
+
+/// Package requirement of an `npm:` specifier that's mapped to the package
+/// it refers to.
+fn npm_package_req(
+  specifier: &ModuleSpecifier,
+  mapped: &PackageMappedSpecifier,
+) -> Option<JsrDepPackageReq> {
+  let req: PackageReq = NpmPackageReqReference::from_specifier(specifier)
+    .ok()?
+    .into_inner()
+    .req;
+  (req.name.as_str() == mapped.name).then(|| JsrDepPackageReq::npm(req))
+}
+
+/// Parses a locked version, which for an npm package may have the versions
+/// of its peer dependencies appended (ex. `1.2.3_react@18.2.0`).
+fn parse_locked_version(text: &str) -> Option<Version> {
+  let text = text.split_once('_').map(|(v, _)| v).unwrap_or(text);
+  Version::parse_standard(text).ok()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_parse_locked_version() {
+    assert_eq!(
+      parse_locked_version("1.2.3"),
+      Some(Version::parse_standard("1.2.3").unwrap())
+    );
+    assert_eq!(
+      parse_locked_version("1.2.3-beta.1_react@18.2.0_react-dom@18.2.0"),
+      Some(Version::parse_standard("1.2.3-beta.1").unwrap())
+    );
+    assert_eq!(parse_locked_version("not a version"), None);
+  }
+
+  #[test]
+  fn test_allows_distant_versions() {
+    let allows = |req: &str, version: &str| {
+      let req = deno_semver::VersionReq::parse_from_npm(req).unwrap();
+      allows_distant_versions(
+        req.range().unwrap(),
+        &Version::parse_standard(version).unwrap(),
+      )
+    };
+    assert!(allows("5", "5.3.0"));
+    assert!(allows("^5", "5.3.0"));
+    assert!(allows("*", "5.0.0"));
+    assert!(allows(">=5.3.0", "5.3.0"));
+    assert!(!allows("^5.3", "5.3.2"));
+    assert!(!allows("^5.1", "5.2.0"));
+    assert!(!allows("5", "5.0.2"));
+    assert!(!allows("~2.0.0", "2.0.3"));
+    assert!(!allows("^0.3", "0.3.5"));
+  }
+}
@@ -2,6 +2,7 @@
 
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::path::Component;
@@ -39,8 +40,30 @@ pub static SYNTHETIC_TEST_SPECIFIERS: Lazy<SyntheticSpecifiers> =
     shims: ModuleSpecifier::parse("dnt://_dnt.test_shims.ts").unwrap(),
   });
 
+/// The output file paths of the modules.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Mappings {
   inner: HashMap<ModuleSpecifier, PathBuf>,
+  specifiers: HashMap<PathBuf, ModuleSpecifier>,
+}
+
+#[cfg(feature = "serialization")]
+impl serde::Serialize for Mappings {
+  fn serialize<S: serde::Serializer>(
+    &self,
+    serializer: S,
+  ) -> std::result::Result<S::Ok, S::Error> {
+    // serialized as the output file paths keyed by specifier text, sorted
+    // so the output is deterministic
+    let paths = self
+      .inner
+      .iter()
+      .map(|(specifier, file_path)| {
+        (specifier.as_str(), file_path.to_string_lossy())
+      })
+      .collect::<BTreeMap<_, _>>();
+    serde::Serialize::serialize(&paths, serializer)
+  }
 }
 
 impl Mappings {
@@ -92,10 +115,16 @@ impl Mappings {
             base_dir.display()
           )
         })?;
+      let media_type = if specifiers.assets.contains_key(local_file.specifier) {
+        // the module that has the file's content as its default export
+        MediaType::TypeScript
+      } else {
+        MediaType::from_path(relative_file_path)
+      };
       mappings.insert(
         local_file.specifier.clone(),
         get_mapped_file_path(
-          MediaType::from_path(relative_file_path),
+          media_type,
           relative_file_path,
           &mut mapped_filepaths_no_ext,
         ),
@@ -116,6 +145,9 @@ impl Mappings {
         Module::Js(esm) => esm.media_type,
         Module::Json(json) => json.media_type,
         Module::Wasm(_) => MediaType::Wasm,
+        Module::External(_) if specifiers.assets.contains_key(&specifier) => {
+          MediaType::TypeScript
+        }
         Module::Npm(_) | Module::Node(_) | Module::External(_) => {
           MediaType::Unknown
         }
@@ -207,7 +239,19 @@ impl Mappings {
       &SYNTHETIC_TEST_SPECIFIERS.shims,
     );
 
-    Ok(Mappings { inner: mappings })
+    // redirects share the path of the module they redirect to
+    let specifiers = mappings
+      .iter()
+      .filter(|(specifier, _)| {
+        !module_graph.redirects().contains_key(specifier)
+      })
+      .map(|(specifier, path)| (path.clone(), specifier.clone()))
+      .collect();
+
+    Ok(Mappings {
+      inner: mappings,
+      specifiers,
+    })
   }
 
   pub fn get_file_path(&self, specifier: &ModuleSpecifier) -> &PathBuf {
@@ -215,6 +259,22 @@ impl Mappings {
       panic!("Could not find file path for specifier: {}", specifier,);
     })
   }
+
+  /// Gets the output path of a module or redirect.
+  pub fn try_get_file_path(
+    &self,
+    specifier: &ModuleSpecifier,
+  ) -> Option<&PathBuf> {
+    self.inner.get(specifier)
+  }
+
+  /// Gets the module that's output at the path.
+  pub fn try_get_specifier(
+    &self,
+    file_path: &Path,
+  ) -> Option<&ModuleSpecifier> {
+    self.specifiers.get(file_path)
+  }
 }
 
 /// A local file with its path and test status resolved once up front, since
@@ -496,6 +556,8 @@ fn get_mapped_file_path(
   let extension = match media_type {
     MediaType::Json => "js",
     MediaType::Mjs | MediaType::Mts => "js",
+    // the module that instantiates the Wasm binary
+    MediaType::Wasm => "js",
     _ => &media_type.as_ts_extension()[1..],
   };
   with_extension(
@@ -0,0 +1,284 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::Result;
+use deno_semver::Version;
+use deno_semver::VersionReq;
+
+use crate::Dependency;
+
+/// Versions of a package published to the npm registry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NpmPackageInfo {
+  pub versions: Vec<Version>,
+  /// Dist tags (ex. `latest`) along with the version each points to.
+  pub dist_tags: HashMap<String, Version>,
+}
+
+/// Provides information about packages in the npm registry, which is used to
+/// check that the dependencies of the output resolve.
+#[async_trait::async_trait(?Send)]
+pub trait NpmPackageInfoProvider {
+  /// Gets the info of the package or `None` when it doesn't exist.
+  async fn get_package_info(
+    &self,
+    name: &str,
+  ) -> Result<Option<NpmPackageInfo>>;
+}
+
+/// Provides npm package info from a directory of packuments laid out like
+/// Deno's npm cache (ex. `<dir>/@scope/name/registry.json`), which works
+/// offline and with a stand-in registry.
+pub struct LocalNpmPackageInfoProvider<TSys: sys_traits::FsRead> {
+  sys: TSys,
+  dir: PathBuf,
+}
+
+impl<TSys: sys_traits::FsRead> LocalNpmPackageInfoProvider<TSys> {
+  pub fn new(sys: TSys, dir: PathBuf) -> Self {
+    Self { sys, dir }
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl<TSys: sys_traits::FsRead> NpmPackageInfoProvider
+  for LocalNpmPackageInfoProvider<TSys>
+{
+  async fn get_package_info(
+    &self,
+    name: &str,
+  ) -> Result<Option<NpmPackageInfo>> {
+    let path = self.dir.join(name).join("registry.json");
+    let text = match self.sys.fs_read_to_string(&path) {
+      Ok(text) => text,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+        return Ok(None);
+      }
+      Err(err) => {
+        return Err(err)
+          .with_context(|| format!("Failed reading {}", path.display()));
+      }
+    };
+    parse_packument(&text)
+      .with_context(|| format!("Failed parsing {}", path.display()))
+      .map(Some)
+  }
+}
+
+fn parse_packument(text: &str) -> Result<NpmPackageInfo> {
+  let value: serde_json::Value = serde_json::from_str(text)?;
+  let mut info = NpmPackageInfo::default();
+  if let Some(versions) = value.get("versions").and_then(|v| v.as_object()) {
+    for version in versions.keys() {
+      // ignore any version that isn't valid semver like the npm cli does
+      if let Ok(version) = Version::parse_from_npm(version) {
+        info.versions.push(version);
+      }
+    }
+  }
+  if let Some(tags) = value.get("dist-tags").and_then(|v| v.as_object()) {
+    for (tag, version) in tags {
+      if let Some(Ok(version)) = version.as_str().map(Version::parse_from_npm) {
+        info.dist_tags.insert(tag.clone(), version);
+      }
+    }
+  }
+  Ok(info)
+}
+
+/// Checks that the name and version range of each dependency resolves to a
+/// version published to the npm registry, optionally pinning each dependency
+/// to the version it resolves to.
+pub async fn resolve_dependency_versions<'a>(
+  provider: &dyn NpmPackageInfoProvider,
+  dependencies: impl Iterator<Item = &'a mut Dependency>,
+  pin: bool,
+) -> Result<()> {
+  let mut infos: HashMap<String, Option<NpmPackageInfo>> = HashMap::new();
+  let mut failures = Vec::new();
+  for dependency in dependencies {
+    if !infos.contains_key(&dependency.name) {
+      let info = provider
+        .get_package_info(&dependency.name)
+        .await
+        .with_context(|| {
+          format!("Failed getting npm package info for {}", dependency.name)
+        })?;
+      infos.insert(dependency.name.clone(), info);
+    }
+    let Some(info) = &infos[&dependency.name] else {
+      failures.push(format!(
+        "{}@{} - package not found",
+        dependency.name, dependency.version
+      ));
+      continue;
+    };
+    match resolve_version(info, &dependency.version) {
+      Ok(version) => {
+        if pin {
+          dependency.version = version.to_string();
+        }
+      }
+      Err(reason) => failures.push(format!(
+        "{}@{} - {}",
+        dependency.name, dependency.version, reason
+      )),
+    }
+  }
+
+  if failures.is_empty() {
+    Ok(())
+  } else {
+    failures.sort();
+    failures.dedup();
+    anyhow::bail!(
+      "The following dependencies were not found in the npm registry:\n  * {}",
+      failures.join("\n  * ")
+    )
+  }
+}
+
+/// Gets the greatest version of the package that satisfies the version
+/// range, or the reason why there isn't one.
+fn resolve_version<'a>(
+  info: &'a NpmPackageInfo,
+  version_text: &str,
+) -> Result<&'a Version, String> {
+  let Ok(version_req) = VersionReq::parse_from_npm(version_text) else {
+    return Err("invalid version range".to_string());
+  };
+  match version_req.tag() {
+    Some(tag) => info
+      .dist_tags
+      .get(tag)
+      .ok_or_else(|| format!("no \"{tag}\" dist tag")),
+    None => info
+      .versions
+      .iter()
+      .filter(|v| version_req.matches(v))
+      .max()
+      .ok_or_else(|| "no version satisfies the range".to_string()),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+  use sys_traits::impls::InMemorySys;
+  use sys_traits::FsCreateDirAll;
+  use sys_traits::FsWrite;
+
+  use crate::DependencyKind;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn test_local_provider() {
+    let sys = InMemorySys::default();
+    let dir = PathBuf::from("/npm");
+    sys.fs_create_dir_all(dir.join("@scope/pkg")).unwrap();
+    sys
+      .fs_write(
+        dir.join("@scope/pkg/registry.json"),
+        r#"{
+          "name": "@scope/pkg",
+          "dist-tags": { "latest": "1.1.0", "next": "2.0.0-rc.1" },
+          "versions": { "1.0.0": {}, "1.1.0": {}, "2.0.0-rc.1": {} }
+        }"#,
+      )
+      .unwrap();
+    let provider = LocalNpmPackageInfoProvider::new(sys, dir);
+    let info = provider.get_package_info("@scope/pkg").await.unwrap();
+    let info = info.unwrap();
+    assert_eq!(
+      info.versions,
+      vec![version("1.0.0"), version("1.1.0"), version("2.0.0-rc.1")]
+    );
+    assert_eq!(info.dist_tags["latest"], version("1.1.0"));
+    assert_eq!(info.dist_tags["next"], version("2.0.0-rc.1"));
+    assert!(provider.get_package_info("other").await.unwrap().is_none());
+  }
+
+  #[tokio::test]
+  async fn test_resolve_dependency_versions() {
+    let provider = TestProvider;
+    let mut dependencies = vec![
+      dependency("pkg", "^1.0.0"),
+      dependency("pkg", "latest"),
+      dependency("pkg", "~1.0.0"),
+    ];
+    resolve_dependency_versions(&provider, dependencies.iter_mut(), false)
+      .await
+      .unwrap();
+    assert_eq!(dependencies[0].version, "^1.0.0");
+
+    resolve_dependency_versions(&provider, dependencies.iter_mut(), true)
+      .await
+      .unwrap();
+    assert_eq!(
+      dependencies,
+      vec![
+        dependency("pkg", "1.2.0"),
+        dependency("pkg", "1.2.0"),
+        dependency("pkg", "1.0.1"),
+      ]
+    );
+
+    let mut dependencies = vec![
+      dependency("pkg", "^3.0.0"),
+      dependency("pkg", "next"),
+      dependency("pkg", "not a range!"),
+      dependency("pkgg", "^1.0.0"),
+    ];
+    let err =
+      resolve_dependency_versions(&provider, dependencies.iter_mut(), true)
+        .await
+        .unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      concat!(
+        "The following dependencies were not found in the npm registry:\n",
+        "  * pkg@^3.0.0 - no version satisfies the range\n",
+        "  * pkg@next - no \"next\" dist tag\n",
+        "  * pkg@not a range! - invalid version range\n",
+        "  * pkgg@^1.0.0 - package not found",
+      )
+    );
+  }
+
+  struct TestProvider;
+
+  #[async_trait::async_trait(?Send)]
+  impl NpmPackageInfoProvider for TestProvider {
+    async fn get_package_info(
+      &self,
+      name: &str,
+    ) -> Result<Option<NpmPackageInfo>> {
+      if name != "pkg" {
+        return Ok(None);
+      }
+      Ok(Some(NpmPackageInfo {
+        versions: ["1.0.0", "1.0.1", "1.2.0", "2.0.0"]
+          .into_iter()
+          .map(version)
+          .collect(),
+        dist_tags: HashMap::from([("latest".to_string(), version("1.2.0"))]),
+      }))
+    }
+  }
+
+  fn version(text: &str) -> Version {
+    Version::parse_from_npm(text).unwrap()
+  }
+
+  fn dependency(name: &str, version: &str) -> Dependency {
+    Dependency {
+      name: name.to_string(),
+      version: version.to_string(),
+      kind: DependencyKind::Dependency,
+    }
+  }
+}
@@ -0,0 +1,322 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use imara_diff::Algorithm;
+use imara_diff::BasicLineDiffPrinter;
+use imara_diff::Diff;
+use imara_diff::InternedInput;
+use imara_diff::UnifiedDiffConfig;
+use serde_json::Value;
+use sys_traits::FsDirEntry;
+
+use crate::emit::get_output_contents;
+use crate::emit::EmitManifest;
+use crate::emit::MANIFEST_FILE_NAME;
+use crate::Dependency;
+use crate::DependencyKind;
+use crate::TransformOutput;
+
+#[sys_traits::auto_impl]
+pub trait DiffOutputDirSys:
+  sys_traits::FsMetadata + sys_traits::FsRead + sys_traits::FsReadDir
+{
+}
+
+/// How emitting the output would change an existing output directory, with
+/// the paths relative to it.
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct OutputDirDiff {
+  /// Files in the output that aren't in the directory.
+  pub added_files: Vec<PathBuf>,
+  /// Files in the directory that aren't in the output.
+  pub removed_files: Vec<PathBuf>,
+  /// Files whose content in the output differs from the directory.
+  pub modified_files: Vec<ModifiedFile>,
+  pub added_dependencies: Vec<Dependency>,
+  pub removed_dependencies: Vec<Dependency>,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, PartialEq, Eq)]
+pub struct ModifiedFile {
+  pub file_path: PathBuf,
+  /// Unified diff from the file in the directory to the output, which is
+  /// `None` when either isn't text.
+  pub text_diff: Option<String>,
+}
+
+/// Compares the output with what was emitted to the directory.
+///
+/// The directory is either one that the output was emitted to, whose files
+/// and dependencies are known from its manifest, or one built by dnt's
+/// `build`. For the latter, the output is compared with the `src/` directory
+/// and the paths are relative to it, while the dependencies are read from the
+/// package.json file. A directory that doesn't exist is compared as if it
+/// were empty.
+pub fn diff_output_dir(
+  sys: &impl DiffOutputDirSys,
+  output: &TransformOutput,
+  out_dir: &Path,
+) -> Result<OutputDirDiff> {
+  let build_src_dir = out_dir.join("src");
+  let (compare_dir, existing_files, existing_dependencies, output_contents) =
+    if let Some(manifest) = EmitManifest::read(sys, out_dir) {
+      (
+        out_dir,
+        manifest.files,
+        manifest.dependencies,
+        get_output_contents(output).collect::<Vec<_>>(),
+      )
+    } else if sys.fs_is_dir_no_err(&build_src_dir) {
+      (
+        build_src_dir.as_path(),
+        read_dir_files(sys, &build_src_dir)?,
+        read_package_json_dependencies(sys, out_dir),
+        get_build_src_contents(output).collect(),
+      )
+    } else if !sys.fs_exists_no_err(out_dir) {
+      (
+        out_dir,
+        Vec::new(),
+        Vec::new(),
+        get_output_contents(output).collect(),
+      )
+    } else {
+      bail!(
+        "{} has neither a {} file from emitting the output nor the src directory of a build.",
+        out_dir.display(),
+        MANIFEST_FILE_NAME
+      );
+    };
+  let is_build_dir = compare_dir != out_dir;
+
+  let mut diff = OutputDirDiff::default();
+  let mut output_files = HashSet::new();
+  for (file_path, content) in output_contents {
+    let full_path = compare_dir.join(&file_path);
+    match sys.fs_read(&full_path) {
+      Ok(existing) => {
+        let existing = if is_build_dir && !content.starts_with(b"#!") {
+          strip_shebang(&existing)
+        } else {
+          existing.as_ref()
+        };
+        if existing != content.as_ref() {
+          diff.modified_files.push(ModifiedFile {
+            text_diff: get_text_diff(&file_path, existing, &content),
+            file_path: file_path.clone(),
+          });
+        }
+      }
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+        diff.added_files.push(file_path.clone());
+      }
+      Err(err) => {
+        return Err(err)
+          .with_context(|| format!("Failed reading {}", full_path.display()))
+      }
+    }
+    output_files.insert(file_path);
+  }
+  diff.removed_files = existing_files
+    .into_iter()
+    .filter(|file_path| {
+      !output_files.contains(file_path)
+        && sys.fs_is_file_no_err(compare_dir.join(file_path))
+    })
+    .collect();
+  diff.removed_files.sort();
+
+  let output_dependencies = output
+    .package_manifest
+    .dependencies
+    .iter()
+    .collect::<HashSet<_>>();
+  let existing_dependencies = existing_dependencies.iter().collect();
+  diff.added_dependencies = output_dependencies
+    .difference(&existing_dependencies)
+    .map(|dependency| (*dependency).clone())
+    .collect();
+  diff.added_dependencies.sort_by(|a, b| a.name.cmp(&b.name));
+  diff.removed_dependencies = existing_dependencies
+    .difference(&output_dependencies)
+    .map(|dependency| (*dependency).clone())
+    .collect();
+  diff
+    .removed_dependencies
+    .sort_by(|a, b| a.name.cmp(&b.name));
+  Ok(diff)
+}
+
+/// Gets the paths of the files in the directory relative to it, which
+/// skips the `node_modules` directory.
+fn read_dir_files(
+  sys: &impl DiffOutputDirSys,
+  out_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+  let mut files = Vec::new();
+  let mut pending_dirs = vec![PathBuf::new()];
+  while let Some(dir) = pending_dirs.pop() {
+    let entries = match sys.fs_read_dir(out_dir.join(&dir)) {
+      Ok(entries) => entries,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+      Err(err) => {
+        return Err(err).with_context(|| {
+          format!("Failed reading directory {}", out_dir.join(&dir).display())
+        })
+      }
+    };
+    for entry in entries {
+      let entry = entry?;
+      let file_name = entry.file_name();
+      let file_type = entry.file_type()?;
+      if file_type.is_dir() {
+        if file_name != OsStr::new("node_modules") {
+          pending_dirs.push(dir.join(&file_name));
+        }
+      } else if file_type.is_file()
+        && file_name != OsStr::new(MANIFEST_FILE_NAME)
+      {
+        files.push(dir.join(&file_name));
+      }
+    }
+  }
+  Ok(files)
+}
+
+/// Gets the dependencies listed in the directory's package.json file.
+fn read_package_json_dependencies(
+  sys: &impl DiffOutputDirSys,
+  out_dir: &Path,
+) -> Vec<Dependency> {
+  let Some(package_json) = sys
+    .fs_read_to_string(out_dir.join("package.json"))
+    .ok()
+    .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+  else {
+    return Vec::new();
+  };
+  let is_optional_peer = |name: &str| {
+    package_json
+      .get("peerDependenciesMeta")
+      .and_then(|meta| meta.get(name)?.get("optional")?.as_bool())
+      .unwrap_or(false)
+  };
+  let mut dependencies = Vec::new();
+  for (section, kind) in [
+    ("dependencies", DependencyKind::Dependency),
+    ("devDependencies", DependencyKind::Dev),
+    ("optionalDependencies", DependencyKind::Optional),
+    ("peerDependencies", DependencyKind::Peer),
+  ] {
+    let Some(section) = package_json.get(section).and_then(|s| s.as_object())
+    else {
+      continue;
+    };
+    for (name, version) in section {
+      let Some(version) = version.as_str() else {
+        continue;
+      };
+      let kind = if kind == DependencyKind::Peer && is_optional_peer(name) {
+        DependencyKind::OptionalPeer
+      } else {
+        kind
+      };
+      dependencies.push(Dependency {
+        name: name.clone(),
+        version: version.to_string(),
+        kind,
+      });
+    }
+  }
+  dependencies
+}
+
+/// Gets the contents of the files that `build` writes to its `src/`
+/// directory, which don't reference source maps.
+fn get_build_src_contents(
+  output: &TransformOutput,
+) -> impl Iterator<Item = (PathBuf, Cow<'_, [u8]>)> {
+  let files = output
+    .main
+    .files
+    .iter()
+    .chain(output.test.files.iter())
+    .map(|file| {
+      (
+        file.file_path.clone(),
+        Cow::Borrowed(file.file_text.as_bytes()),
+      )
+    });
+  let assets = output
+    .main
+    .assets
+    .iter()
+    .chain(output.test.assets.iter())
+    .map(|asset| {
+      (
+        asset.file_path.clone(),
+        Cow::Borrowed(asset.bytes.as_slice()),
+      )
+    });
+  files.chain(assets)
+}
+
+/// Removes the shebang that `build` adds to the scripts of the binaries.
+fn strip_shebang(bytes: &[u8]) -> &[u8] {
+  if !bytes.starts_with(b"#!") {
+    return bytes;
+  }
+  match bytes.iter().position(|b| *b == b'\n') {
+    Some(index) => &bytes[index + 1..],
+    None => &[],
+  }
+}
+
+fn get_text_diff(
+  file_path: &Path,
+  before: &[u8],
+  after: &[u8],
+) -> Option<String> {
+  let before = std::str::from_utf8(before).ok()?;
+  let after = std::str::from_utf8(after).ok()?;
+  let input = InternedInput::new(before, after);
+  let mut diff = Diff::compute(Algorithm::Histogram, &input);
+  diff.postprocess_lines(&input);
+  let file_path = file_path.to_string_lossy().replace('\\', "/");
+  Some(format!(
+    "--- a/{file_path}\n+++ b/{file_path}\n{}",
+    diff.unified_diff(
+      &BasicLineDiffPrinter(&input.interner),
+      UnifiedDiffConfig::default(),
+      &input,
+    )
+  ))
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn text_diff() {
+    assert_eq!(
+      get_text_diff(Path::new("mod.ts"), b"a\nb\nc\n", b"a\nd\nc\n",).unwrap(),
+      "--- a/mod.ts\n+++ b/mod.ts\n@@ -1,3 +1,3 @@\n a\n-b\n+d\n c\n"
+    );
+    assert_eq!(get_text_diff(Path::new("a.wasm"), &[0xff], b"a"), None);
+  }
+}
@@ -0,0 +1,292 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use deno_graph::Resolution;
+
+use crate::graph::display_specifier;
+use crate::graph::ModuleGraph;
+use crate::mappings::Mappings;
+use crate::specifiers::Specifiers;
+use crate::PackageMappedSpecifier;
+
+/// The modules of the transform and the dependencies between them, which
+/// shows why a module was included in the output.
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OutputGraph {
+  pub modules: Vec<OutputGraphModule>,
+  pub edges: Vec<OutputGraphEdge>,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputGraphModule {
+  pub specifier: String,
+  pub environment: OutputGraphEnvironment,
+  /// Path of the module in the output, which is `None` for a module that's
+  /// mapped to a package.
+  pub file_path: Option<PathBuf>,
+  pub mapped_package: Option<PackageMappedSpecifier>,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputGraphEnvironment {
+  Main,
+  Test,
+  /// A main module that's only reachable from a binary entry point.
+  BinOnly,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputGraphEdge {
+  pub from: String,
+  pub to: String,
+  pub kind: OutputGraphEdgeKind,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OutputGraphEdgeKind {
+  /// An import of the module's code.
+  Code,
+  /// An import of the module's types (ex. `@ts-types` or an
+  /// `X-TypeScript-Types` header).
+  Types,
+  /// The script of a worker created by the module.
+  Worker,
+}
+
+impl OutputGraph {
+  #[cfg(feature = "serialization")]
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self).unwrap()
+  }
+
+  /// Gets the graph in the Graphviz DOT language.
+  pub fn to_dot(&self) -> String {
+    let mut text = String::new();
+    writeln!(text, "digraph {{").unwrap();
+    writeln!(text, "  node [shape=box];").unwrap();
+    for module in &self.modules {
+      let description = match (&module.file_path, &module.mapped_package) {
+        (Some(file_path), _) => file_path.to_string_lossy().replace('\\', "/"),
+        (None, Some(package)) => package.module_specifier_text(),
+        (None, None) => String::new(),
+      };
+      let attributes = match module.environment {
+        OutputGraphEnvironment::Main => "",
+        OutputGraphEnvironment::Test => ", color=blue",
+        OutputGraphEnvironment::BinOnly => ", color=darkgreen",
+      };
+      writeln!(
+        text,
+        "  {} [label={}{attributes}];",
+        quote(&module.specifier),
+        quote(&format!("{}\n{description}", module.specifier)),
+      )
+      .unwrap();
+    }
+    for edge in &self.edges {
+      let attributes = match edge.kind {
+        OutputGraphEdgeKind::Code => "",
+        OutputGraphEdgeKind::Types => " [style=dashed]",
+        OutputGraphEdgeKind::Worker => " [label=\"worker\"]",
+      };
+      writeln!(
+        text,
+        "  {} -> {}{attributes};",
+        quote(&edge.from),
+        quote(&edge.to),
+      )
+      .unwrap();
+    }
+    writeln!(text, "}}").unwrap();
+    text
+  }
+}
+
+fn quote(text: &str) -> String {
+  let text = text
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n");
+  format!("\"{text}\"")
+}
+
+pub struct GetOutputGraphOptions<'a> {
+  pub module_graph: &'a ModuleGraph,
+  pub mappings: &'a Mappings,
+  pub specifiers: &'a Specifiers,
+  pub bin_only_files: &'a [PathBuf],
+}
+
+pub fn get_output_graph(options: GetOutputGraphOptions) -> OutputGraph {
+  let specifiers = options.specifiers;
+  let mapped = specifiers
+    .main
+    .mapped
+    .iter()
+    .chain(specifiers.test.mapped.iter())
+    .collect::<BTreeMap<_, _>>();
+  let module_specifiers = specifiers
+    .local
+    .iter()
+    .chain(specifiers.remote.iter())
+    .chain(specifiers.types.values().map(|d| &d.selected.specifier))
+    .collect::<BTreeSet<_>>();
+
+  let mut modules = Vec::new();
+  for specifier in module_specifiers.iter().copied() {
+    let file_path = options.mappings.get_file_path(specifier);
+    let environment = if specifiers.test_modules.contains(specifier) {
+      OutputGraphEnvironment::Test
+    } else if options.bin_only_files.contains(file_path) {
+      OutputGraphEnvironment::BinOnly
+    } else {
+      OutputGraphEnvironment::Main
+    };
+    modules.push(OutputGraphModule {
+      specifier: display_specifier(specifier),
+      environment,
+      file_path: Some(file_path.clone()),
+      mapped_package: None,
+    });
+  }
+  for (specifier, package) in mapped.iter() {
+    modules.push(OutputGraphModule {
+      specifier: display_specifier(specifier),
+      environment: if specifiers.main.mapped.contains_key(*specifier) {
+        OutputGraphEnvironment::Main
+      } else {
+        OutputGraphEnvironment::Test
+      },
+      file_path: None,
+      mapped_package: Some((*package).clone()),
+    });
+  }
+  modules.sort_by(|a, b| a.specifier.cmp(&b.specifier));
+
+  let mut edges = Vec::new();
+  for specifier in module_specifiers.iter().copied() {
+    let module = options.module_graph.get(specifier);
+    let mut targets = Vec::new();
+    for dep in module.dependencies().values() {
+      if let Some(code) = dep.get_code() {
+        targets.push((code, OutputGraphEdgeKind::Code));
+      }
+      if let Some(types) = dep.get_type() {
+        targets.push((types, OutputGraphEdgeKind::Types));
+      }
+    }
+    if let Some(module) = module.js() {
+      if let Some(deno_graph::TypesDependency {
+        dependency: Resolution::Ok(resolved),
+        ..
+      }) = &module.maybe_types_dependency
+      {
+        targets.push((&resolved.specifier, OutputGraphEdgeKind::Types));
+      }
+    }
+    for worker in options.module_graph.get_workers(specifier) {
+      targets.push((worker, OutputGraphEdgeKind::Worker));
+    }
+
+    let mut found = BTreeSet::new();
+    for (target, kind) in targets {
+      let target = options.module_graph.resolve(target);
+      // modules that aren't in the output aren't in the graph either
+      // (ex. declaration files that were replaced by another one)
+      let is_included =
+        module_specifiers.contains(target) || mapped.contains_key(target);
+      if is_included && found.insert((display_specifier(target), kind)) {
+        edges.push(OutputGraphEdge {
+          from: display_specifier(specifier),
+          to: display_specifier(target),
+          kind,
+        });
+      }
+    }
+  }
+  edges.sort_by(|a, b| (&a.from, &a.to, a.kind).cmp(&(&b.from, &b.to, b.kind)));
+
+  OutputGraph { modules, edges }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn dot() {
+    let graph = OutputGraph {
+      modules: vec![
+        OutputGraphModule {
+          specifier: "file:///mod.ts".to_string(),
+          environment: OutputGraphEnvironment::Main,
+          file_path: Some(PathBuf::from("mod.ts")),
+          mapped_package: None,
+        },
+        OutputGraphModule {
+          specifier: "file:///mod.test.ts".to_string(),
+          environment: OutputGraphEnvironment::Test,
+          file_path: Some(PathBuf::from("mod.test.ts")),
+          mapped_package: None,
+        },
+        OutputGraphModule {
+          specifier: "npm:chalk@^5.0.0".to_string(),
+          environment: OutputGraphEnvironment::Main,
+          file_path: None,
+          mapped_package: Some(PackageMappedSpecifier {
+            name: "chalk".to_string(),
+            version: Some("^5.0.0".to_string()),
+            sub_path: None,
+            kind: Default::default(),
+          }),
+        },
+      ],
+      edges: vec![
+        OutputGraphEdge {
+          from: "file:///mod.test.ts".to_string(),
+          to: "file:///mod.ts".to_string(),
+          kind: OutputGraphEdgeKind::Code,
+        },
+        OutputGraphEdge {
+          from: "file:///mod.ts".to_string(),
+          to: "npm:chalk@^5.0.0".to_string(),
+          kind: OutputGraphEdgeKind::Types,
+        },
+      ],
+    };
+    assert_eq!(
+      graph.to_dot(),
+      concat!(
+        "digraph {\n",
+        "  node [shape=box];\n",
+        "  \"file:///mod.ts\" [label=\"file:///mod.ts\\nmod.ts\"];\n",
+        "  \"file:///mod.test.ts\" [label=\"file:///mod.test.ts\\nmod.test.ts\", color=blue];\n",
+        "  \"npm:chalk@^5.0.0\" [label=\"npm:chalk@^5.0.0\\nchalk\"];\n",
+        "  \"file:///mod.test.ts\" -> \"file:///mod.ts\";\n",
+        "  \"file:///mod.ts\" -> \"npm:chalk@^5.0.0\" [style=dashed];\n",
+        "}\n",
+      )
+    );
+  }
+}
@@ -0,0 +1,755 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use anyhow::Result;
+use deno_ast::ModuleSpecifier;
+
+use crate::graph::display_specifier;
+use crate::ConfigPackage;
+use crate::Dependency;
+use crate::DependencyKind;
+use crate::Platform;
+use crate::SubpathImport;
+use crate::TransformOutputEnvironment;
+
+// make sure to update `PackageManifestEntryPointKind` in the TS code when changing the names on this
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageManifestEntryPointKind {
+  /// In the `exports`.
+  Export,
+  /// In the `bin`.
+  Bin,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Debug)]
+pub struct PackageManifestEntryPoint {
+  pub kind: PackageManifestEntryPointKind,
+  /// Subpath of an export (ex. `./sub`) or the command of a binary.
+  ///
+  /// An export without one is named `.` when it's the first export and by
+  /// its path otherwise. A single binary without one uses the package name
+  /// as the command.
+  pub name: Option<String>,
+  /// One of the entry points.
+  pub specifier: ModuleSpecifier,
+}
+
+/// Layout of the emitted package that the package manifest describes.
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(default, rename_all = "camelCase"))]
+#[derive(Clone, Debug, Default)]
+pub struct PackageManifestOptions {
+  /// The exports and binaries of the package.
+  ///
+  /// When empty, the exports are the entry points that aren't only binaries
+  /// and the binaries are the binary entry points. When the entry points are
+  /// derived from the config file, its exports are always used.
+  pub entry_points: Vec<PackageManifestEntryPoint>,
+  /// Directory that the ES modules are emitted to (ex. `./esm`), which is
+  /// used for the `import` condition.
+  pub es_module_dir: Option<String>,
+  /// Directory that the script modules are emitted to (ex. `./script`),
+  /// which is used for the `require` condition.
+  pub script_module_dir: Option<String>,
+  /// Directory that the declaration files are emitted to when separate from
+  /// the modules (ex. `./types`), which is used for the `types` condition.
+  ///
+  /// Type only dependencies are then also optional peer dependencies, since
+  /// the published declaration files import them.
+  pub types_dir: Option<String>,
+  /// Names of the packages that the package.json file already lists in its
+  /// `dependencies` or `peerDependencies`, so their types packages and type
+  /// only dependencies aren't also listed as dev dependencies.
+  pub specified_dependencies: Vec<String>,
+}
+
+/// The parts of the package.json file that are derived from the transform.
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PackageManifest {
+  /// Script module of the first export.
+  pub main: Option<String>,
+  /// ES module of the first export.
+  pub module: Option<String>,
+  /// Declaration file of the first export.
+  pub types: Option<String>,
+  /// Module of the first export for the `browser` field when the platform
+  /// is the browser.
+  pub browser: Option<String>,
+  /// Entries of the `exports` in the order of the entry points.
+  pub exports: Vec<PackageManifestExport>,
+  /// Entries of the `imports` for the subpath imports kept in the output.
+  ///
+  /// The ES module and script module directories each have a package.json
+  /// file that's the package scope of their modules, so these go in those.
+  pub imports: Vec<PackageManifestImport>,
+  /// Entries of the `bin` in the order of the entry points.
+  pub bin: Vec<PackageManifestBin>,
+  /// Dependencies of the package, which are listed in the section of the
+  /// package.json file that their kind says.
+  ///
+  /// A package may be listed in more than one section (ex. a type only
+  /// dependency that's both a dev and an optional peer dependency).
+  pub dependencies: Vec<Dependency>,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, PartialEq, Eq)]
+pub struct PackageManifestExport {
+  /// Subpath of the export (ex. `.` or `./sub`).
+  pub name: String,
+  /// Declaration file for the `types` condition, which applies to each of
+  /// the other conditions.
+  pub types: Option<String>,
+  /// Module for the `browser` condition when the platform is the browser,
+  /// which is the ES module when there is one.
+  pub browser: Option<String>,
+  pub import: Option<String>,
+  pub require: Option<String>,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, PartialEq, Eq)]
+pub struct PackageManifestImport {
+  /// Name of the subpath import (ex. `#internal/*`).
+  pub name: String,
+  /// Target relative to the ES module and script module directories
+  /// (ex. `./internal/*.js`).
+  pub target: String,
+  /// Target relative to the package for the `types` condition when the
+  /// declaration files are emitted separately (ex.
+  /// `./types/internal/*.d.ts`), since that directory is in the package's
+  /// scope.
+  pub types: Option<String>,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, PartialEq, Eq)]
+pub struct PackageManifestBin {
+  /// Command of the binary, which is the package name when `None`.
+  pub name: Option<String>,
+  pub path: String,
+}
+
+pub(crate) struct GetPackageManifestOptions<'a> {
+  pub options: &'a PackageManifestOptions,
+  pub entry_points: &'a [ModuleSpecifier],
+  pub bin_entry_points: &'a [ModuleSpecifier],
+  pub config_package: Option<&'a ConfigPackage>,
+  pub main: &'a TransformOutputEnvironment,
+  pub test: &'a TransformOutputEnvironment,
+  pub types_dependencies: &'a [Dependency],
+  pub subpath_imports: &'a [SubpathImport],
+  pub platform: Platform,
+}
+
+pub(crate) fn get_package_manifest(
+  options: GetPackageManifestOptions,
+  get_file_path: impl Fn(&ModuleSpecifier) -> PathBuf,
+) -> Result<PackageManifest> {
+  let manifest_options = options.options;
+  for entry_point in manifest_options.entry_points.iter() {
+    if !options.entry_points.contains(&entry_point.specifier) {
+      bail!(
+        "The package manifest entry point {} is not one of the entry points",
+        display_specifier(&entry_point.specifier),
+      );
+    }
+  }
+
+  let exports = get_exports(&options);
+  let bins = get_bins(&options)?;
+
+  let mut manifest = PackageManifest::default();
+  for (index, (name, specifier)) in exports.into_iter().enumerate() {
+    let file_path = get_file_path(specifier);
+    let name = name.unwrap_or_else(|| {
+      if index == 0 {
+        ".".to_string()
+      } else {
+        join_path(".", &to_js_file_path(&file_path))
+      }
+    });
+    let (types, import, require) =
+      get_condition_paths(manifest_options, &file_path);
+    let browser = match options.platform {
+      Platform::Node => None,
+      Platform::Browser => import.clone().or_else(|| require.clone()),
+    };
+    let export = PackageManifestExport {
+      name,
+      types,
+      browser,
+      import,
+      require,
+    };
+    if index == 0 {
+      manifest.main.clone_from(&export.require);
+      manifest.module.clone_from(&export.import);
+      manifest.types.clone_from(&export.types);
+      manifest.browser.clone_from(&export.browser);
+    }
+    manifest.exports.push(export);
+  }
+  // the `main` field is enough for a single export that's only emitted as
+  // a script module
+  if manifest_options.es_module_dir.is_none() && manifest.exports.len() < 2 {
+    manifest.exports.clear();
+  }
+  manifest.imports = options
+    .subpath_imports
+    .iter()
+    .map(|subpath_import| {
+      let file_path = Path::new(
+        subpath_import
+          .target
+          .strip_prefix("./")
+          .unwrap_or(&subpath_import.target),
+      );
+      PackageManifestImport {
+        name: subpath_import.name.clone(),
+        target: join_path(".", &to_js_file_path(file_path)),
+        types: manifest_options
+          .types_dir
+          .as_ref()
+          .map(|dir| join_path(dir, &to_dts_file_path(file_path))),
+      }
+    })
+    .collect();
+  let bin_dir = manifest_options
+    .es_module_dir
+    .as_ref()
+    .or(manifest_options.script_module_dir.as_ref());
+  if let Some(bin_dir) = bin_dir {
+    manifest.bin = bins
+      .into_iter()
+      .map(|(name, specifier)| PackageManifestBin {
+        name,
+        path: join_path(bin_dir, &to_js_file_path(&get_file_path(specifier))),
+      })
+      .collect();
+  }
+  manifest.dependencies = get_dependencies(
+    options.main,
+    options.test,
+    options.types_dependencies,
+    manifest_options,
+  );
+  Ok(manifest)
+}
+
+/// Gets the exports along with their names, which are `None` when they
+/// should be named by their position.
+fn get_exports<'a>(
+  options: &GetPackageManifestOptions<'a>,
+) -> Vec<(Option<String>, &'a ModuleSpecifier)> {
+  if let Some(config_package) = options.config_package {
+    // the entry points start with the config file's exports
+    return config_package
+      .exports
+      .iter()
+      .cloned()
+      .map(Some)
+      .zip(options.entry_points.iter())
+      .collect();
+  }
+  if options.options.entry_points.is_empty() {
+    options
+      .entry_points
+      .iter()
+      .filter(|e| !options.bin_entry_points.contains(e))
+      .map(|specifier| (None, specifier))
+      .collect()
+  } else {
+    options
+      .options
+      .entry_points
+      .iter()
+      .filter(|e| e.kind == PackageManifestEntryPointKind::Export)
+      .map(|e| (e.name.clone(), &e.specifier))
+      .collect()
+  }
+}
+
+fn get_bins<'a>(
+  options: &GetPackageManifestOptions<'a>,
+) -> Result<Vec<(Option<String>, &'a ModuleSpecifier)>> {
+  if options.options.entry_points.is_empty() {
+    let is_single = options.bin_entry_points.len() == 1;
+    return Ok(
+      options
+        .bin_entry_points
+        .iter()
+        .map(|specifier| {
+          // multiple binaries need a command each
+          let name = if is_single {
+            None
+          } else {
+            Path::new(specifier.path())
+              .file_stem()
+              .map(|s| s.to_string_lossy().into_owned())
+          };
+          (name, specifier)
+        })
+        .collect(),
+    );
+  }
+  let bins = options
+    .options
+    .entry_points
+    .iter()
+    .filter(|e| e.kind == PackageManifestEntryPointKind::Bin)
+    .collect::<Vec<_>>();
+  if bins.len() > 1 {
+    if let Some(bin) = bins.iter().find(|b| b.name.is_none()) {
+      bail!(
+        "The binary entry point {} requires a name because there are multiple binary entry points",
+        display_specifier(&bin.specifier),
+      );
+    }
+  }
+  Ok(
+    bins
+      .into_iter()
+      .map(|b| (b.name.clone(), &b.specifier))
+      .collect(),
+  )
+}
+
+fn get_dependencies(
+  main: &TransformOutputEnvironment,
+  test: &TransformOutputEnvironment,
+  types_dependencies: &[Dependency],
+  options: &PackageManifestOptions,
+) -> Vec<Dependency> {
+  let mut dependencies = main
+    .dependencies
+    .iter()
+    .filter(|d| d.kind != DependencyKind::Dev)
+    .cloned()
+    .collect::<Vec<_>>();
+  if options.types_dir.is_some() {
+    for dep in main.type_only_dependencies.iter() {
+      insert_dependency(&mut dependencies, dep, DependencyKind::OptionalPeer);
+    }
+  }
+  let is_specified =
+    |dep: &Dependency| options.specified_dependencies.contains(&dep.name);
+  // a types package is only a dev dependency when it isn't already needed
+  // by the published package
+  let types_dev_dependencies = types_dependencies
+    .iter()
+    .filter(|d| {
+      !dependencies.iter().any(|o| o.name == d.name) && !is_specified(d)
+    })
+    .cloned()
+    .collect::<Vec<_>>();
+  // in increasing precedence for when the same package is listed twice
+  let dev_dependencies = types_dev_dependencies
+    .iter()
+    .chain(test.dependencies.iter())
+    .chain(
+      main
+        .type_only_dependencies
+        .iter()
+        .filter(|d| !is_specified(d)),
+    )
+    .chain(
+      main
+        .dependencies
+        .iter()
+        .filter(|d| d.kind == DependencyKind::Dev),
+    );
+  for dep in dev_dependencies {
+    insert_dependency(&mut dependencies, dep, DependencyKind::Dev);
+  }
+  dependencies
+}
+
+/// Inserts the dependency with the provided kind, which replaces a
+/// dependency on the same package that has the same kind.
+fn insert_dependency(
+  dependencies: &mut Vec<Dependency>,
+  dep: &Dependency,
+  kind: DependencyKind,
+) {
+  let dep = Dependency {
+    name: dep.name.clone(),
+    version: dep.version.clone(),
+    kind,
+  };
+  match dependencies
+    .iter_mut()
+    .find(|d| d.name == dep.name && d.kind == kind)
+  {
+    Some(existing) => *existing = dep,
+    None => dependencies.push(dep),
+  }
+}
+
+/// Gets the paths of the `types`, `import`, and `require` conditions for
+/// the output file.
+fn get_condition_paths(
+  options: &PackageManifestOptions,
+  file_path: &Path,
+) -> (Option<String>, Option<String>, Option<String>) {
+  (
+    options
+      .types_dir
+      .as_ref()
+      .map(|dir| join_path(dir, &to_dts_file_path(file_path))),
+    options
+      .es_module_dir
+      .as_ref()
+      .map(|dir| join_path(dir, &to_js_file_path(file_path))),
+    options
+      .script_module_dir
+      .as_ref()
+      .map(|dir| join_path(dir, &to_js_file_path(file_path))),
+  )
+}
+
+fn join_path(dir: &str, file_path: &Path) -> String {
+  format!(
+    "{}/{}",
+    dir.trim_end_matches('/'),
+    file_path.to_string_lossy().replace('\\', "/")
+  )
+}
+
+/// Gets the path of the file the TypeScript compiler emits for the
+/// provided output file path.
+fn to_js_file_path(file_path: &Path) -> PathBuf {
+  if is_compiled_file_path(file_path) {
+    file_path.with_extension("js")
+  } else {
+    file_path.to_path_buf()
+  }
+}
+
+/// Gets the path of the declaration file the TypeScript compiler emits for
+/// the provided output file path.
+fn to_dts_file_path(file_path: &Path) -> PathBuf {
+  if is_compiled_file_path(file_path) {
+    file_path.with_extension("d.ts")
+  } else {
+    file_path.to_path_buf()
+  }
+}
+
+// the transform already outputs `.mts` and `.mjs` files as `.js`
+fn is_compiled_file_path(file_path: &Path) -> bool {
+  file_path
+    .extension()
+    .map(|ext| {
+      let ext = ext.to_string_lossy().to_lowercase();
+      matches!(ext.as_str(), "ts" | "tsx" | "jsx")
+    })
+    .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_get_package_manifest() {
+    let entry_points = vec![specifier("mod.ts"), specifier("sub/mod.ts")];
+    let main = TransformOutputEnvironment {
+      dependencies: vec![
+        dependency("dep", DependencyKind::Dependency),
+        dependency("peer", DependencyKind::Peer),
+        dependency("dev", DependencyKind::Dev),
+      ],
+      type_only_dependencies: vec![dependency(
+        "type-only",
+        DependencyKind::Dependency,
+      )],
+      ..Default::default()
+    };
+    let test = TransformOutputEnvironment {
+      dependencies: vec![dependency("test-dep", DependencyKind::Dependency)],
+      ..Default::default()
+    };
+    let types_dependencies = vec![
+      dependency("@types/dep", DependencyKind::Dependency),
+      dependency("peer", DependencyKind::Dependency),
+    ];
+    let get_for_platform = |options: &PackageManifestOptions,
+                            platform: Platform| {
+      get_package_manifest(
+        GetPackageManifestOptions {
+          options,
+          entry_points: &entry_points,
+          bin_entry_points: &[],
+          config_package: None,
+          main: &main,
+          test: &test,
+          types_dependencies: &types_dependencies,
+          subpath_imports: &[],
+          platform,
+        },
+        |specifier| PathBuf::from(&specifier.path()[1..]),
+      )
+    };
+    let get = |options: &PackageManifestOptions| {
+      get_for_platform(options, Platform::Node)
+    };
+
+    let manifest = get(&PackageManifestOptions {
+      entry_points: Vec::new(),
+      es_module_dir: Some("./esm".to_string()),
+      script_module_dir: Some("./script".to_string()),
+      types_dir: Some("./types".to_string()),
+      specified_dependencies: Vec::new(),
+    })
+    .unwrap();
+    assert_eq!(
+      manifest,
+      PackageManifest {
+        main: Some("./script/mod.js".to_string()),
+        module: Some("./esm/mod.js".to_string()),
+        types: Some("./types/mod.d.ts".to_string()),
+        browser: None,
+        exports: vec![
+          PackageManifestExport {
+            name: ".".to_string(),
+            types: Some("./types/mod.d.ts".to_string()),
+            browser: None,
+            import: Some("./esm/mod.js".to_string()),
+            require: Some("./script/mod.js".to_string()),
+          },
+          PackageManifestExport {
+            name: "./sub/mod.js".to_string(),
+            types: Some("./types/sub/mod.d.ts".to_string()),
+            browser: None,
+            import: Some("./esm/sub/mod.js".to_string()),
+            require: Some("./script/sub/mod.js".to_string()),
+          },
+        ],
+        imports: Vec::new(),
+        bin: Vec::new(),
+        dependencies: vec![
+          dependency("dep", DependencyKind::Dependency),
+          dependency("peer", DependencyKind::Peer),
+          dependency("type-only", DependencyKind::OptionalPeer),
+          dependency("@types/dep", DependencyKind::Dev),
+          dependency("test-dep", DependencyKind::Dev),
+          dependency("type-only", DependencyKind::Dev),
+          dependency("dev", DependencyKind::Dev),
+        ],
+      }
+    );
+
+    // named script module entry points with a binary
+    let manifest = get(&PackageManifestOptions {
+      entry_points: vec![
+        PackageManifestEntryPoint {
+          kind: PackageManifestEntryPointKind::Export,
+          name: Some(".".to_string()),
+          specifier: specifier("mod.ts"),
+        },
+        PackageManifestEntryPoint {
+          kind: PackageManifestEntryPointKind::Bin,
+          name: None,
+          specifier: specifier("mod.ts"),
+        },
+      ],
+      es_module_dir: None,
+      script_module_dir: Some("./script".to_string()),
+      types_dir: None,
+      specified_dependencies: Vec::new(),
+    })
+    .unwrap();
+    assert_eq!(manifest.main, Some("./script/mod.js".to_string()));
+    assert_eq!(manifest.module, None);
+    assert_eq!(manifest.exports, Vec::new());
+    assert_eq!(
+      manifest.bin,
+      vec![PackageManifestBin {
+        name: None,
+        path: "./script/mod.js".to_string(),
+      }]
+    );
+    assert!(!manifest
+      .dependencies
+      .iter()
+      .any(|d| d.kind == DependencyKind::OptionalPeer));
+
+    // the browser condition uses the es module when there is one
+    let manifest = get_for_platform(
+      &PackageManifestOptions {
+        entry_points: Vec::new(),
+        es_module_dir: Some("./esm".to_string()),
+        script_module_dir: Some("./script".to_string()),
+        types_dir: None,
+        specified_dependencies: Vec::new(),
+      },
+      Platform::Browser,
+    )
+    .unwrap();
+    assert_eq!(manifest.browser, Some("./esm/mod.js".to_string()));
+    assert_eq!(
+      manifest.exports[1].browser,
+      Some("./esm/sub/mod.js".to_string())
+    );
+    let manifest = get_for_platform(
+      &PackageManifestOptions {
+        entry_points: Vec::new(),
+        es_module_dir: None,
+        script_module_dir: Some("./script".to_string()),
+        types_dir: None,
+        specified_dependencies: Vec::new(),
+      },
+      Platform::Browser,
+    )
+    .unwrap();
+    assert_eq!(manifest.browser, Some("./script/mod.js".to_string()));
+
+    let err = get(&PackageManifestOptions {
+      entry_points: vec![PackageManifestEntryPoint {
+        kind: PackageManifestEntryPointKind::Export,
+        name: None,
+        specifier: specifier("other.ts"),
+      }],
+      ..Default::default()
+    })
+    .unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "The package manifest entry point file:///other.ts is not one of the entry points"
+    );
+  }
+
+  #[test]
+  fn test_get_package_manifest_layouts() {
+    let entry_points = vec![specifier("mod.ts")];
+    let get = |es_module: bool, script_module: bool, types: bool| {
+      let options = PackageManifestOptions {
+        es_module_dir: es_module.then(|| "./esm".to_string()),
+        script_module_dir: script_module.then(|| "./script".to_string()),
+        types_dir: types.then(|| "./types".to_string()),
+        ..Default::default()
+      };
+      get_manifest(&options, &entry_points, &Default::default(), &[])
+    };
+    let export =
+      |types: bool, import: bool, require: bool| PackageManifestExport {
+        name: ".".to_string(),
+        types: types.then(|| "./types/mod.d.ts".to_string()),
+        browser: None,
+        import: import.then(|| "./esm/mod.js".to_string()),
+        require: require.then(|| "./script/mod.js".to_string()),
+      };
+
+    let manifest = get(true, true, true);
+    assert_eq!(manifest.main, Some("./script/mod.js".to_string()));
+    assert_eq!(manifest.module, Some("./esm/mod.js".to_string()));
+    assert_eq!(manifest.types, Some("./types/mod.d.ts".to_string()));
+    assert_eq!(manifest.exports, vec![export(true, true, true)]);
+
+    let manifest = get(true, false, true);
+    assert_eq!(manifest.main, None);
+    assert_eq!(manifest.module, Some("./esm/mod.js".to_string()));
+    assert_eq!(manifest.types, Some("./types/mod.d.ts".to_string()));
+    assert_eq!(manifest.exports, vec![export(true, true, false)]);
+
+    // the `main` field is enough without the es modules
+    let manifest = get(false, true, true);
+    assert_eq!(manifest.main, Some("./script/mod.js".to_string()));
+    assert_eq!(manifest.module, None);
+    assert_eq!(manifest.types, Some("./types/mod.d.ts".to_string()));
+    assert_eq!(manifest.exports, Vec::new());
+
+    let manifest = get(true, false, false);
+    assert_eq!(manifest.main, None);
+    assert_eq!(manifest.module, Some("./esm/mod.js".to_string()));
+    assert_eq!(manifest.types, None);
+    assert_eq!(manifest.exports, vec![export(false, true, false)]);
+  }
+
+  #[test]
+  fn test_get_package_manifest_specified_dependencies() {
+    let entry_points = vec![specifier("mod.ts")];
+    let main = TransformOutputEnvironment {
+      dependencies: vec![dependency("dep", DependencyKind::Dependency)],
+      type_only_dependencies: vec![
+        dependency("type-only", DependencyKind::Dependency),
+        dependency("specified-type-only", DependencyKind::Dependency),
+      ],
+      ..Default::default()
+    };
+    let types_dependencies = vec![
+      dependency("@types/dep", DependencyKind::Dependency),
+      dependency("@types/specified", DependencyKind::Dependency),
+    ];
+    let options = PackageManifestOptions {
+      es_module_dir: Some("./esm".to_string()),
+      specified_dependencies: vec![
+        "specified-type-only".to_string(),
+        "@types/specified".to_string(),
+      ],
+      ..Default::default()
+    };
+    let manifest =
+      get_manifest(&options, &entry_points, &main, &types_dependencies);
+    // the package.json file lists the specified ones, so they aren't also
+    // dev dependencies
+    assert_eq!(
+      manifest.dependencies,
+      vec![
+        dependency("dep", DependencyKind::Dependency),
+        dependency("@types/dep", DependencyKind::Dev),
+        dependency("type-only", DependencyKind::Dev),
+      ]
+    );
+  }
+
+  fn get_manifest(
+    options: &PackageManifestOptions,
+    entry_points: &[ModuleSpecifier],
+    main: &TransformOutputEnvironment,
+    types_dependencies: &[Dependency],
+  ) -> PackageManifest {
+    get_package_manifest(
+      GetPackageManifestOptions {
+        options,
+        entry_points,
+        bin_entry_points: &[],
+        config_package: None,
+        main,
+        test: &Default::default(),
+        types_dependencies,
+        subpath_imports: &[],
+        platform: Platform::Node,
+      },
+      |specifier| PathBuf::from(&specifier.path()[1..]),
+    )
+    .unwrap()
+  }
+
+  fn dependency(name: &str, kind: DependencyKind) -> Dependency {
+    Dependency {
+      name: name.to_string(),
+      version: "^1.0.0".to_string(),
+      kind,
+    }
+  }
+
+  fn specifier(path: &str) -> ModuleSpecifier {
+    ModuleSpecifier::parse(&format!("file:///{path}")).unwrap()
+  }
+}
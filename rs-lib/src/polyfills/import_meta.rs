@@ -5,6 +5,7 @@ use deno_ast::view::Node;
 
 use super::Polyfill;
 use super::PolyfillVisitContext;
+use crate::Platform;
 use crate::ScriptTarget;
 
 pub struct ImportMetaPolyfill;
@@ -18,6 +19,11 @@ impl Polyfill for ImportMetaPolyfill {
     true
   }
 
+  fn use_for_platform(&self, platform: Platform) -> bool {
+    // the polyfill is implemented with Node.js modules
+    platform == Platform::Node
+  }
+
   fn visit_node(&self, node: Node, _context: &PolyfillVisitContext) -> bool {
     if let Node::MemberExpr(expr) = node {
       if let Expr::MetaProp(_meta) = expr.obj {
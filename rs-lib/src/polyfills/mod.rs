@@ -13,6 +13,7 @@ use deno_ast::view::PropName;
 use deno_ast::SourceRanged;
 
 use crate::Dependency;
+use crate::Platform;
 use crate::ScriptTarget;
 
 mod array_find_last;
@@ -30,6 +31,9 @@ pub trait Polyfill {
   /// changing these names.
   fn name(&self) -> &'static str;
   fn use_for_target(&self, target: ScriptTarget) -> bool;
+  fn use_for_platform(&self, _platform: Platform) -> bool {
+    true
+  }
   fn visit_node(
     &self,
     node: Node,
@@ -107,6 +111,7 @@ pub type PolyfillOverrides = HashMap<String, bool>;
 
 pub fn polyfills_for_target(
   target: ScriptTarget,
+  platform: Platform,
   overrides: &PolyfillOverrides,
 ) -> Vec<Box<dyn Polyfill>> {
   all_polyfills()
@@ -114,7 +119,9 @@ pub fn polyfills_for_target(
     .filter(|p| match overrides.get(p.name()) {
       Some(enabled) => *enabled,
       None => {
-        !matches!(target, ScriptTarget::Latest) && p.use_for_target(target)
+        !matches!(target, ScriptTarget::Latest)
+          && p.use_for_target(target)
+          && p.use_for_platform(platform)
       }
     })
     .collect()
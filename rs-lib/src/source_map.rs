@@ -0,0 +1,461 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use anyhow::Result;
+use deno_ast::apply_text_changes;
+use deno_ast::swc::sourcemap::SourceMap;
+use deno_ast::swc::sourcemap::SourceMapBuilder;
+use deno_ast::ModuleSpecifier;
+use deno_ast::TextChange;
+
+/// Source maps from the output files to the original modules.
+#[cfg_attr(feature = "serialization", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SourceMaps {
+  #[default]
+  None,
+  /// Source maps that reference the original modules by specifier.
+  Enabled,
+  /// Source maps that also embed the text of the original modules in
+  /// their `sourcesContent`.
+  WithSourcesContent,
+}
+
+/// A position in a text where the column is in UTF-16 code units, which is
+/// what source maps use.
+#[derive(Clone, Copy, Default)]
+struct Position {
+  line: u32,
+  column: u32,
+}
+
+impl Position {
+  fn advance(&mut self, text: &str) {
+    for c in text.chars() {
+      self.advance_char(c);
+    }
+  }
+
+  fn advance_char(&mut self, c: char) {
+    if c == '\n' {
+      self.line += 1;
+      self.column = 0;
+    } else {
+      self.column += c.len_utf16() as u32;
+    }
+  }
+}
+
+/// Applies the text changes to the module's text and gets a source map
+/// from the changed text back to the module.
+pub fn apply_text_changes_with_source_map(
+  specifier: &ModuleSpecifier,
+  source: &str,
+  mut text_changes: Vec<TextChange>,
+  source_maps: SourceMaps,
+) -> (String, Option<String>) {
+  if source_maps == SourceMaps::None {
+    return (apply_text_changes(source, text_changes), None);
+  }
+
+  // same order that the changes are applied in
+  text_changes.sort_by(|a, b| match a.range.start.cmp(&b.range.start) {
+    Ordering::Equal => a.range.end.cmp(&b.range.end),
+    ordering => ordering,
+  });
+
+  let mut builder = SourceMapBuilder::new(None);
+  let source_id = builder.add_source(specifier.to_string().into());
+  if source_maps == SourceMaps::WithSourcesContent {
+    builder.set_source_contents(source_id, Some(source.to_string().into()));
+  }
+  let mut mappings = MappingsBuilder {
+    builder,
+    source_id,
+    src: Position::default(),
+    dst: Position::default(),
+  };
+  let mut last_index = 0;
+  for change in text_changes.iter() {
+    if change.range.start > last_index {
+      mappings.add_unchanged_text(&source[last_index..change.range.start]);
+    }
+    mappings.add_new_text(&change.new_text);
+    mappings
+      .src
+      .advance(&source[change.range.start..change.range.end]);
+    last_index = change.range.end;
+  }
+  if last_index < source.len() {
+    mappings.add_unchanged_text(&source[last_index..]);
+  }
+
+  let text = apply_text_changes(source, text_changes);
+  (
+    text,
+    Some(source_map_to_string(mappings.builder.into_sourcemap())),
+  )
+}
+
+struct MappingsBuilder {
+  builder: SourceMapBuilder,
+  source_id: u32,
+  src: Position,
+  dst: Position,
+}
+
+impl MappingsBuilder {
+  fn add_mapping(&mut self) {
+    self.builder.add_raw(
+      self.dst.line,
+      self.dst.column,
+      self.src.line,
+      self.src.column,
+      Some(self.source_id),
+      None,
+      false,
+    );
+  }
+
+  /// Adds text that's the same in the output, which maps to the start of
+  /// each of its lines.
+  fn add_unchanged_text(&mut self, text: &str) {
+    self.add_mapping();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+      self.src.advance_char(c);
+      self.dst.advance_char(c);
+      if c == '\n' && chars.peek().is_some() {
+        self.add_mapping();
+      }
+    }
+  }
+
+  /// Adds text that's only in the output, which maps to the start of the
+  /// text it replaces.
+  fn add_new_text(&mut self, text: &str) {
+    if text.is_empty() {
+      return;
+    }
+    self.add_mapping();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+      self.dst.advance_char(c);
+      if c == '\n' && chars.peek().is_some() {
+        self.add_mapping();
+      }
+    }
+  }
+}
+
+/// Updates a source map for text that was inserted into the file it maps.
+pub fn update_source_map_for_insert(
+  source_map: &str,
+  text: &str,
+  index: usize,
+  inserted_text: &str,
+) -> Result<String> {
+  let source_map = SourceMap::from_slice(source_map.as_bytes())?;
+  let mut insert_pos = Position::default();
+  insert_pos.advance(&text[..index]);
+  let mut inserted_end_pos = insert_pos;
+  inserted_end_pos.advance(inserted_text);
+
+  let mut builder = SourceMapBuilder::new(None);
+  for (i, source) in source_map.sources().enumerate() {
+    let source_id = builder.add_source(source.clone());
+    builder.set_source_contents(
+      source_id,
+      source_map.get_source_contents(i as u32).cloned(),
+    );
+  }
+  for token in source_map.tokens() {
+    let (dst_line, dst_col) = if token.get_dst_line() > insert_pos.line {
+      (
+        token.get_dst_line() + inserted_end_pos.line - insert_pos.line,
+        token.get_dst_col(),
+      )
+    } else if token.get_dst_line() == insert_pos.line
+      && token.get_dst_col() >= insert_pos.column
+    {
+      (
+        inserted_end_pos.line,
+        token.get_dst_col() - insert_pos.column + inserted_end_pos.column,
+      )
+    } else {
+      (token.get_dst_line(), token.get_dst_col())
+    };
+    builder.add_raw(
+      dst_line,
+      dst_col,
+      token.get_src_line(),
+      token.get_src_col(),
+      Some(token.get_src_id()),
+      None,
+      false,
+    );
+  }
+  Ok(source_map_to_string(builder.into_sourcemap()))
+}
+
+/// Chains a source map to the output files (ex. one that the TypeScript
+/// compiler emits for them) with the source maps of the output files keyed
+/// by their URL, so that it maps to the original modules instead.
+///
+/// The sources of the map are resolved relative to its URL. A source
+/// without a source map is kept, and a local module is referenced relative
+/// to the map.
+pub fn chain_source_maps(
+  source_map: &str,
+  map_url: &ModuleSpecifier,
+  output_source_maps: &HashMap<ModuleSpecifier, String>,
+) -> Result<String> {
+  let source_map = SourceMap::from_slice(source_map.as_bytes())?;
+  let mut parsed_source_maps = HashMap::new();
+  for (i, source) in source_map.sources().enumerate() {
+    let Ok(specifier) = map_url.join(source) else {
+      continue;
+    };
+    if let Some(output_source_map) = output_source_maps.get(&specifier) {
+      parsed_source_maps.insert(
+        i as u32,
+        SourceMap::from_slice(output_source_map.as_bytes())?,
+      );
+    }
+  }
+
+  let mut builder = SourceMapBuilder::new(source_map.get_file().cloned());
+  for token in source_map.tokens() {
+    let name = token.get_name().cloned();
+    let original_token = parsed_source_maps
+      .get(&token.get_src_id())
+      .and_then(|output_source_map| {
+        output_source_map
+          .lookup_token(token.get_src_line(), token.get_src_col())
+      })
+      .filter(|original_token| original_token.has_source());
+    let (raw, contents) = match original_token {
+      Some(original_token) => {
+        let source = original_token.get_source().unwrap();
+        let raw = builder.add(
+          token.get_dst_line(),
+          token.get_dst_col(),
+          original_token.get_src_line(),
+          original_token.get_src_col(),
+          Some(get_relative_source(map_url, source).into()),
+          name,
+          false,
+        );
+        let contents = original_token
+          .get_source_view()
+          .map(|view| view.source().clone());
+        (raw, contents)
+      }
+      None => {
+        let raw = builder.add(
+          token.get_dst_line(),
+          token.get_dst_col(),
+          token.get_src_line(),
+          token.get_src_col(),
+          token.get_source().cloned(),
+          name,
+          false,
+        );
+        let contents = source_map.get_source_contents(token.get_src_id());
+        (raw, contents.cloned())
+      }
+    };
+    if let Some(contents) = contents {
+      if raw.src_id != !0 && !builder.has_source_contents(raw.src_id) {
+        builder.set_source_contents(raw.src_id, Some(contents));
+      }
+    }
+  }
+  Ok(source_map_to_string(builder.into_sourcemap()))
+}
+
+/// Gets the source of a map for a module, which is relative to the map
+/// when they're both local.
+fn get_relative_source(map_url: &ModuleSpecifier, source: &str) -> String {
+  match ModuleSpecifier::parse(source) {
+    Ok(specifier)
+      if specifier.scheme() == "file" && map_url.scheme() == "file" =>
+    {
+      map_url
+        .make_relative(&specifier)
+        .unwrap_or_else(|| source.to_string())
+    }
+    _ => source.to_string(),
+  }
+}
+
+fn source_map_to_string(source_map: SourceMap) -> String {
+  let mut bytes = Vec::new();
+  source_map.to_writer(&mut bytes).unwrap();
+  String::from_utf8(bytes).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  fn get_mappings(source_map: &str) -> Vec<(u32, u32, u32, u32)> {
+    SourceMap::from_slice(source_map.as_bytes())
+      .unwrap()
+      .tokens()
+      .map(|t| {
+        (
+          t.get_dst_line(),
+          t.get_dst_col(),
+          t.get_src_line(),
+          t.get_src_col(),
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn text_changes_source_map() {
+    let specifier = ModuleSpecifier::parse("file:///mod.ts").unwrap();
+    let source = "import './a.ts';\nconst a = 1;\nDeno.exit();\n";
+    let text_changes = vec![
+      TextChange {
+        range: 8..14,
+        new_text: "./a.js".to_string(),
+      },
+      TextChange {
+        range: 30..30,
+        new_text: "dntShim.".to_string(),
+      },
+      TextChange {
+        range: 0..0,
+        new_text: "import * as dntShim from \"./_dnt.shims.js\";\n".to_string(),
+      },
+    ];
+    let (text, source_map) = apply_text_changes_with_source_map(
+      &specifier,
+      source,
+      text_changes,
+      SourceMaps::Enabled,
+    );
+    let source_map = source_map.unwrap();
+    assert_eq!(
+      text,
+      concat!(
+        "import * as dntShim from \"./_dnt.shims.js\";\n",
+        "import './a.js';\n",
+        "const a = 1;\n",
+        "dntShim.Deno.exit();\n",
+      )
+    );
+    assert_eq!(
+      get_mappings(&source_map),
+      vec![
+        (0, 0, 0, 0),
+        (1, 0, 0, 0),
+        (1, 8, 0, 8),
+        (1, 14, 0, 14),
+        (2, 0, 1, 0),
+        (3, 0, 2, 0),
+        (3, 8, 2, 0),
+      ]
+    );
+    let parsed = SourceMap::from_slice(source_map.as_bytes()).unwrap();
+    assert_eq!(parsed.get_source(0).unwrap().as_str(), "file:///mod.ts");
+    assert!(parsed.get_source_contents(0).is_none());
+
+    // inserting text shifts the mappings after it
+    let source_map = update_source_map_for_insert(
+      &source_map,
+      &text,
+      0,
+      "import \"./_dnt.polyfills.js\";\n",
+    )
+    .unwrap();
+    assert_eq!(
+      get_mappings(&source_map),
+      vec![
+        (1, 0, 0, 0),
+        (2, 0, 0, 0),
+        (2, 8, 0, 8),
+        (2, 14, 0, 14),
+        (3, 0, 1, 0),
+        (4, 0, 2, 0),
+        (4, 8, 2, 0),
+      ]
+    );
+  }
+
+  #[test]
+  fn sources_content() {
+    let specifier =
+      ModuleSpecifier::parse("https://deno.land/x/mod.ts").unwrap();
+    let (_, source_map) = apply_text_changes_with_source_map(
+      &specifier,
+      "export {};",
+      Vec::new(),
+      SourceMaps::WithSourcesContent,
+    );
+    let source_map =
+      SourceMap::from_slice(source_map.unwrap().as_bytes()).unwrap();
+    assert_eq!(
+      source_map.get_source(0).unwrap().as_str(),
+      "https://deno.land/x/mod.ts"
+    );
+    assert_eq!(
+      source_map.get_source_contents(0).unwrap().as_str(),
+      "export {};"
+    );
+  }
+
+  #[test]
+  fn chain() {
+    let specifier = ModuleSpecifier::parse("file:///project/mod.ts").unwrap();
+    let (_, output_source_map) = apply_text_changes_with_source_map(
+      &specifier,
+      "const a = 1;\nDeno.exit();\n",
+      vec![TextChange {
+        range: 13..13,
+        new_text: "dntShim.".to_string(),
+      }],
+      SourceMaps::WithSourcesContent,
+    );
+    // ex. what the compiler emits for the output
+    let mut builder = SourceMapBuilder::new(Some("mod.js".into()));
+    builder.add(0, 0, 0, 0, Some("../src/mod.ts".into()), None, false);
+    builder.add(1, 0, 1, 8, Some("../src/mod.ts".into()), None, false);
+    builder.add(2, 0, 0, 0, Some("../src/other.ts".into()), None, false);
+    let source_map = chain_source_maps(
+      &source_map_to_string(builder.into_sourcemap()),
+      &ModuleSpecifier::parse("file:///project/npm/esm/mod.js.map").unwrap(),
+      &HashMap::from([(
+        ModuleSpecifier::parse("file:///project/npm/src/mod.ts").unwrap(),
+        output_source_map.unwrap(),
+      )]),
+    )
+    .unwrap();
+
+    assert_eq!(
+      get_mappings(&source_map),
+      vec![(0, 0, 0, 0), (1, 0, 1, 0), (2, 0, 0, 0)]
+    );
+    let source_map = SourceMap::from_slice(source_map.as_bytes()).unwrap();
+    assert_eq!(source_map.get_file().unwrap().as_str(), "mod.js");
+    // the original module is relative to the map and the output file
+    // without a source map is kept
+    assert_eq!(
+      source_map.sources().map(|s| s.as_str()).collect::<Vec<_>>(),
+      vec!["../../mod.ts", "../src/other.ts"]
+    );
+    assert_eq!(
+      source_map.get_source_contents(0).unwrap().as_str(),
+      "const a = 1;\nDeno.exit();\n"
+    );
+    assert!(source_map.get_source_contents(1).is_none());
+  }
+}
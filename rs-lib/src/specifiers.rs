@@ -1,21 +1,34 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 
 use anyhow::Result;
 use deno_ast::ModuleSpecifier;
+use deno_graph::ImportKind;
 use deno_graph::Module;
 use deno_graph::Resolution;
 use deno_semver::npm::NpmPackageReqReference;
+use deno_semver::RangeBound;
+use deno_semver::VersionBoundKind;
+use deno_semver::VersionRange;
+use deno_semver::VersionReq;
 
+use crate::analyze::get_runtime_import_specifiers;
+use crate::assets::get_asset_kinds;
+use crate::assets::AssetKind;
 use crate::declaration_file_resolution::resolve_declaration_file_mappings;
 use crate::declaration_file_resolution::DeclarationFileResolution;
 use crate::graph::display_specifier;
 use crate::graph::JsrSpecifierMappings;
 use crate::graph::ModuleGraph;
 use crate::loader::LoaderSpecifiers;
+use crate::locked_versions::apply_locked_versions;
+use crate::locked_versions::LockedDependencyVersions;
 use crate::PackageMappedSpecifier;
 
 #[derive(Debug)]
@@ -26,9 +39,13 @@ pub struct Specifiers {
   /// Packages that provide the declaration files of a mapped package,
   /// keyed by package name (ex. an `@types/` package served by a cdn).
   pub types_packages: BTreeMap<String, PackageMappedSpecifier>,
+  /// Files that are imported as text or bytes, which are also in the local
+  /// or remote specifiers.
+  pub assets: BTreeMap<ModuleSpecifier, AssetKind>,
   pub test_modules: HashSet<ModuleSpecifier>,
   pub main: EnvironmentSpecifiers,
   pub test: EnvironmentSpecifiers,
+  pub warnings: Vec<PackageWarning>,
 }
 
 impl Specifiers {
@@ -38,9 +55,34 @@ impl Specifiers {
   }
 }
 
-#[derive(Debug)]
+/// A warning about the version of a package, which says how the package got
+/// in the output once the chains of imports are known.
+#[derive(Clone, Debug)]
+pub struct PackageWarning {
+  pub package_name: String,
+  pub message: String,
+}
+
+impl PackageWarning {
+  /// Gets the message with the shortest chain of imports to the package.
+  pub fn display(&self, import_chains: &ImportChains) -> String {
+    match import_chains.get_for_package(&self.package_name) {
+      Some(chain) => format!(
+        "{}\n  Import chain: {}",
+        self.message,
+        format_import_chain(&chain)
+      ),
+      None => self.message.clone(),
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
 pub struct EnvironmentSpecifiers {
   pub mapped: BTreeMap<ModuleSpecifier, PackageMappedSpecifier>,
+  /// Mapped specifiers that are only imported for their types, so the
+  /// emitted JavaScript doesn't import them.
+  pub type_only: HashSet<ModuleSpecifier>,
 }
 
 pub fn get_specifiers<'a>(
@@ -49,6 +91,8 @@ pub fn get_specifiers<'a>(
   jsr_specifier_mappings: &JsrSpecifierMappings,
   module_graph: &ModuleGraph,
   modules: impl Iterator<Item = &'a Module>,
+  maybe_lockfile: Option<&deno_lockfile::Lockfile>,
+  locked_dependency_versions: Option<LockedDependencyVersions>,
 ) -> Result<Specifiers> {
   let mut local_specifiers = Vec::new();
   let mut remote_specifiers = Vec::new();
@@ -86,15 +130,17 @@ pub fn get_specifiers<'a>(
         } else {
           found_module_specifiers.push(module.specifier().clone());
 
-          if let Some(module) = module.js() {
-            for dep in module.dependencies.values() {
-              if let Some(specifier) = dep.get_code() {
-                pending.push(specifier);
-              }
-              if let Some(specifier) = dep.get_type() {
-                pending.push(specifier);
-              }
+          for dep in module.dependencies().values() {
+            if let Some(specifier) = dep.get_code() {
+              pending.push(specifier);
+            }
+            if let Some(specifier) = dep.get_type() {
+              pending.push(specifier);
             }
+          }
+          // workers run in the same environment as the module creating them
+          pending.extend(module_graph.get_workers(module.specifier()));
+          if let Some(module) = module.js() {
             if let Some(deno_graph::TypesDependency {
               dependency: Resolution::Ok(resolved),
               ..
@@ -123,7 +169,7 @@ pub fn get_specifiers<'a>(
 
   for module in all_modules.iter() {
     match module {
-      Module::Js(_) | Module::Json(_) => {
+      Module::Js(_) | Module::Json(_) | Module::Wasm(_) => {
         match module.specifier().scheme().to_lowercase().as_str() {
           "file" => local_specifiers.push(module.specifier().clone()),
           "http" | "https" => {
@@ -137,11 +183,14 @@ pub fn get_specifiers<'a>(
       Module::Npm(_) | Module::Node(_) => {
         // ignore
       }
-      Module::Wasm(_) => {
-        anyhow::bail!(
-          "Not implemented support for Wasm modules: {}",
-          module.specifier()
-        );
+      Module::External(module) if module.was_asset_load => {
+        match module.specifier.scheme().to_lowercase().as_str() {
+          "file" => local_specifiers.push(module.specifier.clone()),
+          "http" | "https" => remote_specifiers.push(module.specifier.clone()),
+          _ => {
+            anyhow::bail!("Unhandled scheme on url: {}", module.specifier);
+          }
+        }
       }
       Module::External(module) => {
         let specifier = &module.specifier;
@@ -159,6 +208,7 @@ pub fn get_specifiers<'a>(
     }
   }
 
+  let assets = get_asset_kinds(module_graph, all_modules.iter())?;
   let declaration_files = resolve_declaration_file_mappings(
     module_graph,
     &all_modules,
@@ -183,10 +233,36 @@ pub fn get_specifiers<'a>(
       .iter_mut()
       .chain(specifiers.mapped_packages.iter_mut()),
   );
+  let mut warnings = unify_npm_versions(
+    found_mapped_specifiers
+      .iter_mut()
+      .chain(specifiers.mapped_packages.iter_mut()),
+    &get_npm_referrers(module_graph, &all_modules),
+  );
+  if let Some(lockfile) = maybe_lockfile {
+    warnings.extend(apply_locked_versions(
+      lockfile,
+      jsr_specifier_mappings,
+      found_mapped_specifiers
+        .iter_mut()
+        .chain(specifiers.mapped_packages.iter_mut()),
+      locked_dependency_versions,
+    ));
+  }
   ensure_package_mapped_specifiers_valid(
     &found_mapped_specifiers,
     &specifiers.mapped_packages,
   )?;
+  let runtime_mapped_specifiers = get_runtime_mapped_specifiers(
+    module_graph,
+    entry_points,
+    &found_mapped_specifiers,
+  )?;
+  let type_only_mapped_specifiers = found_mapped_specifiers
+    .keys()
+    .filter(|s| !runtime_mapped_specifiers.contains(s))
+    .cloned()
+    .collect();
 
   Ok(Specifiers {
     local: local_specifiers
@@ -199,16 +275,20 @@ pub fn get_specifiers<'a>(
       .collect(),
     types,
     types_packages: declaration_files.types_packages,
+    assets,
     test_modules: test_modules
       .values()
       .map(|k| k.specifier().clone())
       .collect(),
     main: EnvironmentSpecifiers {
       mapped: found_mapped_specifiers,
+      type_only: type_only_mapped_specifiers,
     },
     test: EnvironmentSpecifiers {
       mapped: specifiers.mapped_packages,
+      type_only: Default::default(),
     },
+    warnings,
   })
 }
 
@@ -219,8 +299,8 @@ fn get_imported_specifiers(
   modules: &[&Module],
 ) -> HashSet<ModuleSpecifier> {
   let mut specifiers = HashSet::new();
-  for module in modules.iter().filter_map(|m| m.js()) {
-    for dep in module.dependencies.values() {
+  for module in modules.iter() {
+    for dep in module.dependencies().values() {
       if let Some(specifier) = dep.get_code() {
         specifiers.insert(module_graph.resolve(specifier).clone());
       }
@@ -229,6 +309,58 @@ fn get_imported_specifiers(
   specifiers
 }
 
+/// Gets the mapped specifiers that are imported by the code that runs when
+/// importing the entry points, which excludes the ones only imported in
+/// declaration files, with `import type`, or for what's used in types.
+fn get_runtime_mapped_specifiers(
+  module_graph: &ModuleGraph,
+  entry_points: &[ModuleSpecifier],
+  mapped_specifiers: &BTreeMap<ModuleSpecifier, PackageMappedSpecifier>,
+) -> Result<HashSet<ModuleSpecifier>> {
+  let mut found = HashSet::new();
+  let mut visited = HashSet::new();
+  let mut pending = entry_points.iter().collect::<Vec<_>>();
+  while let Some(specifier) = pending.pop() {
+    let specifier = module_graph.resolve(specifier);
+    if mapped_specifiers.contains_key(specifier) {
+      found.insert(specifier.clone());
+      continue;
+    }
+    if !visited.insert(specifier) {
+      continue;
+    }
+    let module = match module_graph.try_get(specifier) {
+      Some(Module::Js(module)) => module,
+      // everything a Wasm module imports is imported when instantiating it
+      Some(Module::Wasm(module)) => {
+        pending
+          .extend(module.dependencies.values().filter_map(|d| d.get_code()));
+        continue;
+      }
+      _ => continue,
+    };
+    if module.media_type.is_declaration() {
+      continue;
+    }
+    pending.extend(module_graph.get_workers(&module.specifier));
+    let runtime_imports = module_graph
+      .get_parsed_source(module)?
+      .with_view(get_runtime_import_specifiers);
+    for (value, dep) in module.dependencies.iter() {
+      let is_runtime = dep.imports.iter().any(|import| match import.kind {
+        ImportKind::Es if !import.is_dynamic => runtime_imports.contains(value),
+        kind => kind.is_runtime(),
+      });
+      if is_runtime {
+        if let Some(specifier) = dep.get_code() {
+          pending.push(specifier);
+        }
+      }
+    }
+  }
+  Ok(found)
+}
+
 /// Gets the local and remote modules that are only reachable from the
 /// provided roots, which is used to keep a binary entrypoint's modules out
 /// of the script output.
@@ -248,36 +380,311 @@ fn get_reachable(
   module_graph: &ModuleGraph,
   roots: &[ModuleSpecifier],
 ) -> HashSet<ModuleSpecifier> {
-  let mut found = HashSet::new();
-  let mut pending = roots.iter().cloned().collect::<Vec<_>>();
-  while let Some(specifier) = pending.pop() {
+  walk_reachable(module_graph, roots, |_| false)
+    .into_keys()
+    .collect()
+}
+
+/// Walks the modules reachable from the roots breadth first, which gets
+/// the referrer each module is first found from so that following the
+/// referrers gives the shortest chain of imports back to a root.
+fn walk_reachable(
+  module_graph: &ModuleGraph,
+  roots: &[ModuleSpecifier],
+  is_leaf: impl Fn(&ModuleSpecifier) -> bool,
+) -> HashMap<ModuleSpecifier, Option<ModuleSpecifier>> {
+  let mut found = HashMap::new();
+  let mut pending = roots
+    .iter()
+    .map(|s| (s.clone(), None))
+    .collect::<VecDeque<_>>();
+  while let Some((specifier, referrer)) = pending.pop_front() {
     let specifier = module_graph.resolve(&specifier).clone();
-    if !found.insert(specifier.clone()) {
+    if found.contains_key(&specifier) {
+      continue;
+    }
+    found.insert(specifier.clone(), referrer);
+    if is_leaf(&specifier) {
       continue;
     }
-    let Some(module) = module_graph.try_get(&specifier).and_then(|m| m.js())
-    else {
+    let Some(module) = module_graph.try_get(&specifier) else {
       continue;
     };
-    for dep in module.dependencies.values() {
+    let referrer = Some(specifier.clone());
+    for dep in module.dependencies().values() {
       if let Some(specifier) = dep.get_code() {
-        pending.push(specifier.clone());
+        pending.push_back((specifier.clone(), referrer.clone()));
       }
       if let Some(specifier) = dep.get_type() {
-        pending.push(specifier.clone());
+        pending.push_back((specifier.clone(), referrer.clone()));
       }
     }
     if let Some(deno_graph::TypesDependency {
       dependency: Resolution::Ok(resolved),
       ..
-    }) = &module.maybe_types_dependency
+    }) = module.maybe_types_dependency()
     {
-      pending.push(resolved.specifier.clone());
+      pending.push_back((resolved.specifier.clone(), referrer.clone()));
+    }
+    for worker in module_graph.get_workers(&specifier) {
+      pending.push_back((worker.clone(), referrer.clone()));
     }
   }
   found
 }
 
+/// The shortest chain of imports from the entry points to each module,
+/// which explains why a module or dependency is in the output.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportChains {
+  /// Module each module was first found from, which is `None` for the
+  /// entry points.
+  referrers: HashMap<ModuleSpecifier, Option<ModuleSpecifier>>,
+  /// Specifiers mapped to each package, keyed by package name.
+  packages: HashMap<String, Vec<ModuleSpecifier>>,
+}
+
+impl ImportChains {
+  pub fn new(
+    module_graph: &ModuleGraph,
+    roots: &[ModuleSpecifier],
+    specifiers: &Specifiers,
+  ) -> Self {
+    let mut packages: HashMap<String, Vec<ModuleSpecifier>> = HashMap::new();
+    for (specifier, mapped) in specifiers
+      .main
+      .mapped
+      .iter()
+      .chain(specifiers.test.mapped.iter())
+    {
+      packages
+        .entry(mapped.name.clone())
+        .or_default()
+        .push(specifier.clone());
+    }
+    Self {
+      // the modules of a mapped package aren't in the output
+      referrers: walk_reachable(module_graph, roots, |s| {
+        specifiers.has_mapped(s)
+      }),
+      packages,
+    }
+  }
+
+  /// Gets the chain of imports from an entry point to the module, which
+  /// starts with the entry point and ends with the module.
+  pub fn get(
+    &self,
+    specifier: &ModuleSpecifier,
+  ) -> Option<Vec<ModuleSpecifier>> {
+    let mut chain = vec![specifier.clone()];
+    let mut current = self.referrers.get(specifier)?;
+    while let Some(referrer) = current {
+      chain.push(referrer.clone());
+      current = self.referrers.get(referrer)?;
+    }
+    chain.reverse();
+    Some(chain)
+  }
+
+  /// Gets the shortest chain of imports from an entry point to a specifier
+  /// that's mapped to the package.
+  pub fn get_for_package(&self, name: &str) -> Option<Vec<ModuleSpecifier>> {
+    self
+      .packages
+      .get(name)?
+      .iter()
+      .filter_map(|s| self.get(s))
+      .min_by_key(|chain| chain.len())
+  }
+}
+
+/// Formats the chain of imports for a warning message.
+pub fn format_import_chain(chain: &[ModuleSpecifier]) -> String {
+  chain
+    .iter()
+    .map(display_specifier)
+    .collect::<Vec<_>>()
+    .join(" -> ")
+}
+
+/// Uses a single version requirement for each package that `npm:` specifiers
+/// with different version requirements resolve to (ex. `npm:chalk@5` and
+/// `npm:chalk@^5.3.0`), since a package.json can only list one of them.
+///
+/// When the requirements overlap, this is the requirement that only matches
+/// the versions all of them match. Otherwise, no version satisfies all of
+/// them, so the one requiring the newest versions is used and a warning says
+/// which files requested which requirement.
+fn unify_npm_versions<'a>(
+  mapped_specifiers: impl Iterator<
+    Item = (&'a ModuleSpecifier, &'a mut PackageMappedSpecifier),
+  >,
+  referrers: &HashMap<ModuleSpecifier, BTreeSet<ModuleSpecifier>>,
+) -> Vec<PackageWarning> {
+  type Entry<'a> = (
+    &'a ModuleSpecifier,
+    VersionRange,
+    &'a mut PackageMappedSpecifier,
+  );
+  let mut by_name: BTreeMap<String, Vec<Entry>> = BTreeMap::new();
+  for (specifier, mapped) in mapped_specifiers {
+    let Some(range) = npm_specifier_version_range(specifier, mapped) else {
+      continue;
+    };
+    by_name
+      .entry(mapped.name.clone())
+      .or_default()
+      .push((specifier, range, mapped));
+  }
+
+  let mut warnings = Vec::new();
+  for (name, mut entries) in by_name {
+    if entries.iter().all(|e| e.2.version == entries[0].2.version) {
+      continue;
+    }
+    let all_overlap = entries.iter().enumerate().all(|(i, (_, range, _))| {
+      entries[i + 1..]
+        .iter()
+        .all(|(_, other, _)| range.intersects_range(other))
+    });
+    let version = if all_overlap {
+      let intersection = entries[1..]
+        .iter()
+        .fold(entries[0].1.clone(), |intersection, (_, range, _)| {
+          intersection.clamp(range)
+        });
+      // keep the requirement that was written when one of them is the
+      // intersection, which is usually the case
+      entries
+        .iter()
+        .find(|(_, range, _)| *range == intersection)
+        .and_then(|(_, _, mapped)| mapped.version.clone())
+        .unwrap_or_else(|| npm_range_text(&intersection))
+    } else {
+      let version = entries
+        .iter()
+        .max_by(|a, b| compare_range_starts(&a.1.start, &b.1.start))
+        .and_then(|(_, _, mapped)| mapped.version.clone())
+        .unwrap();
+      let mut requested: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+      for (specifier, _, mapped) in entries.iter() {
+        let files = requested
+          .entry(mapped.version.as_deref().unwrap())
+          .or_default();
+        if let Some(referrers) = referrers.get(*specifier) {
+          files.extend(referrers.iter().map(display_specifier));
+        }
+      }
+      warnings.push(PackageWarning {
+        package_name: name.clone(),
+        message: format!(
+        "The npm package {} was requested with versions that don't overlap, so {} was used.\n{}",
+        name,
+        version,
+        requested
+          .into_iter()
+          .map(|(version, files)| if files.is_empty() {
+            format!("  * {version}")
+          } else {
+            format!(
+              "  * {} requested by {}",
+              version,
+              files.into_iter().collect::<Vec<_>>().join(", ")
+            )
+          })
+          .collect::<Vec<_>>()
+          .join("\n"),
+        ),
+      });
+      version
+    };
+    for (_, _, mapped) in entries.iter_mut() {
+      mapped.version = Some(version.clone());
+    }
+  }
+  warnings
+}
+
+/// Version requirement of an `npm:` specifier whose mapping is the package
+/// the specifier refers to, which is [`None`] when a mapping changed it or the
+/// requirement is not a single range of versions (ex. a dist tag).
+fn npm_specifier_version_range(
+  specifier: &ModuleSpecifier,
+  mapped: &PackageMappedSpecifier,
+) -> Option<VersionRange> {
+  let npm_specifier = NpmPackageReqReference::from_specifier(specifier).ok()?;
+  let req = npm_specifier.req();
+  if req.name.as_str() != mapped.name
+    || mapped.version.as_deref() != Some(req.version_req.version_text())
+  {
+    return None;
+  }
+  let version_req =
+    VersionReq::parse_from_npm(req.version_req.version_text()).ok()?;
+  match version_req.range()?.0.as_ref() {
+    [range] => Some(range.clone()),
+    _ => None,
+  }
+}
+
+/// Gets the range in the npm version requirement syntax (ex. `>=1.2.0 <1.5.0`).
+fn npm_range_text(range: &VersionRange) -> String {
+  let start = match &range.start {
+    RangeBound::Version(bound) => match bound.kind {
+      VersionBoundKind::Inclusive => Some(format!(">={}", bound.version)),
+      VersionBoundKind::Exclusive => Some(format!(">{}", bound.version)),
+    },
+    RangeBound::Unbounded => None,
+  };
+  let end = match &range.end {
+    RangeBound::Version(bound) => match bound.kind {
+      VersionBoundKind::Inclusive => Some(format!("<={}", bound.version)),
+      VersionBoundKind::Exclusive => Some(format!("<{}", bound.version)),
+    },
+    RangeBound::Unbounded => None,
+  };
+  match (start, end) {
+    (Some(start), Some(end)) => format!("{start} {end}"),
+    (Some(bound), None) | (None, Some(bound)) => bound,
+    (None, None) => "*".to_string(),
+  }
+}
+
+fn compare_range_starts(a: &RangeBound, b: &RangeBound) -> Ordering {
+  match (a, b) {
+    (RangeBound::Unbounded, RangeBound::Unbounded) => Ordering::Equal,
+    (RangeBound::Unbounded, RangeBound::Version(_)) => Ordering::Less,
+    (RangeBound::Version(_), RangeBound::Unbounded) => Ordering::Greater,
+    (RangeBound::Version(a), RangeBound::Version(b)) => {
+      a.version.cmp(&b.version)
+    }
+  }
+}
+
+/// Gets the modules that import each `npm:` specifier.
+fn get_npm_referrers(
+  module_graph: &ModuleGraph,
+  modules: &[&Module],
+) -> HashMap<ModuleSpecifier, BTreeSet<ModuleSpecifier>> {
+  let mut referrers: HashMap<ModuleSpecifier, BTreeSet<ModuleSpecifier>> =
+    HashMap::new();
+  for module in modules.iter().filter_map(|m| m.js()) {
+    for dep in module.dependencies.values() {
+      for specifier in dep.get_code().into_iter().chain(dep.get_type()) {
+        let specifier = module_graph.resolve(specifier);
+        if specifier.scheme() == "npm" {
+          referrers
+            .entry(specifier.clone())
+            .or_default()
+            .insert(module.specifier.clone());
+        }
+      }
+    }
+  }
+  referrers
+}
+
 fn ensure_package_mapped_specifiers_valid(
   mapped_specifiers: &BTreeMap<ModuleSpecifier, PackageMappedSpecifier>,
   test_mapped_specifiers: &BTreeMap<ModuleSpecifier, PackageMappedSpecifier>,
@@ -0,0 +1,243 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use anyhow::bail;
+use anyhow::Result;
+use deno_ast::ModuleSpecifier;
+use deno_graph::Module;
+
+use crate::graph::ModuleGraph;
+use crate::mappings::Mappings;
+
+/// A `#` prefixed subpath import of the package.json `imports` field
+/// (ex. `#internal/*`) that's kept in the output.
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubpathImport {
+  pub name: String,
+  /// Path of the target in the output (ex. `./internal/*.ts`).
+  pub target: String,
+}
+
+/// The subpath imports of the package.json whose targets are local modules.
+#[derive(Default)]
+pub(crate) struct PackageJsonImports {
+  /// Names along with the url that each targets, which has a `*` in it
+  /// when the name does.
+  entries: Vec<(String, String)>,
+}
+
+impl PackageJsonImports {
+  pub fn new(
+    pkg_json_url: &ModuleSpecifier,
+    imports: Option<&serde_json::Map<String, serde_json::Value>>,
+  ) -> Self {
+    let entries = imports
+      .into_iter()
+      .flatten()
+      .filter_map(|(name, value)| {
+        // conditional and package targets are resolved as usual
+        let target = value.as_str()?;
+        if !name.starts_with('#')
+          || !target.starts_with("./")
+          || name.contains('*') != target.contains('*')
+        {
+          return None;
+        }
+        let target_url = pkg_json_url.join(target).ok()?;
+        Some((name.clone(), target_url.to_string()))
+      })
+      .collect();
+    Self { entries }
+  }
+
+  /// Gets the entry that the specifier matches along with what the `*` in
+  /// its name matched.
+  fn get<'a>(&'a self, value: &'a str) -> Option<(&'a str, &'a str, &'a str)> {
+    self
+      .entries
+      .iter()
+      .find_map(|(name, target)| match name.split_once('*') {
+        Some((prefix, suffix)) => {
+          let matched = value
+            .strip_prefix(prefix)?
+            .strip_suffix(suffix)
+            .filter(|m| !m.is_empty())?;
+          Some((name.as_str(), target.as_str(), matched))
+        }
+        None => (name == value).then_some((name.as_str(), target.as_str(), "")),
+      })
+  }
+}
+
+/// The subpath imports that local modules use to import local modules.
+pub(crate) struct PreservedSubpathImports {
+  /// Specifier text of each preserved import along with what it resolves to.
+  pub resolutions: HashMap<String, ModuleSpecifier>,
+  pub imports: Vec<SubpathImport>,
+}
+
+impl PreservedSubpathImports {
+  /// Gets if the specifier text should be kept as-is in the output.
+  pub fn is_preserved(&self, value: &str, resolved: &ModuleSpecifier) -> bool {
+    self.resolutions.get(value) == Some(resolved)
+  }
+}
+
+pub(crate) fn get_preserved_subpath_imports(
+  package_json_imports: &PackageJsonImports,
+  module_graph: &ModuleGraph,
+  mappings: &Mappings,
+  local_specifiers: &[ModuleSpecifier],
+) -> Result<PreservedSubpathImports> {
+  let local = local_specifiers.iter().collect::<HashSet<_>>();
+  let mut resolutions = HashMap::new();
+  // the modules that each used entry resolves to along with what the `*`
+  // matched for them
+  let mut used: BTreeMap<&str, Vec<(&str, ModuleSpecifier)>> = BTreeMap::new();
+  for referrer in local_specifiers {
+    let Some(Module::Js(module)) = module_graph.try_get(referrer) else {
+      continue;
+    };
+    for value in module.dependencies.keys() {
+      let Some((name, target, matched)) = package_json_imports.get(value)
+      else {
+        continue;
+      };
+      let Some(resolved) = module_graph.resolve_dependency(value, referrer)
+      else {
+        continue;
+      };
+      // a target that doesn't end up in the output, like one that's mapped
+      // to a package, is resolved as usual
+      if !local.contains(&resolved)
+        || resolved.as_str() != target.replacen('*', matched, 1)
+      {
+        continue;
+      }
+      resolutions.insert(value.clone(), resolved.clone());
+      used.entry(name).or_default().push((matched, resolved));
+    }
+  }
+
+  let mut imports = Vec::with_capacity(used.len());
+  for (name, modules) in used {
+    let target = if name.contains('*') {
+      get_wildcard_target(name, &modules, mappings)?
+    } else {
+      relative_output_path(mappings, &modules[0].1)
+    };
+    imports.push(SubpathImport {
+      name: name.to_string(),
+      target,
+    });
+  }
+  Ok(PreservedSubpathImports {
+    resolutions,
+    imports,
+  })
+}
+
+/// Gets the target with a `*` that matches the output path of every module
+/// that the subpath import is used for.
+fn get_wildcard_target(
+  name: &str,
+  modules: &[(&str, ModuleSpecifier)],
+  mappings: &Mappings,
+) -> Result<String> {
+  let mut candidates: Option<BTreeSet<String>> = None;
+  for (matched, specifier) in modules {
+    let output_path = relative_output_path(mappings, specifier);
+    let module_candidates = output_path
+      .match_indices(matched)
+      .map(|(index, _)| {
+        format!(
+          "{}*{}",
+          &output_path[..index],
+          &output_path[index + matched.len()..]
+        )
+      })
+      .collect::<BTreeSet<_>>();
+    candidates = Some(match candidates {
+      Some(candidates) => candidates
+        .intersection(&module_candidates)
+        .cloned()
+        .collect(),
+      None => module_candidates,
+    });
+  }
+  // prefer the `*` to be as far to the end as possible
+  let target = candidates
+    .and_then(|c| c.into_iter().max_by_key(|t| t.find('*')))
+    .filter(|t| {
+      !t.ends_with('*') || !modules.iter().any(|m| is_compiled(&m.1))
+    });
+  match target {
+    Some(target) => Ok(target),
+    None => bail!(
+      "The subpath import \"{}\" can't be kept in the output because the modules it imports don't share a path with a `*` in the output. Include the file extension after the `*` or import the modules with relative specifiers instead.",
+      name,
+    ),
+  }
+}
+
+fn relative_output_path(
+  mappings: &Mappings,
+  specifier: &ModuleSpecifier,
+) -> String {
+  format!(
+    "./{}",
+    mappings
+      .get_file_path(specifier)
+      .to_string_lossy()
+      .replace('\\', "/")
+  )
+}
+
+/// Gets if the TypeScript compiler changes the extension of the module's
+/// output file.
+fn is_compiled(specifier: &ModuleSpecifier) -> bool {
+  let path = specifier.path().to_lowercase();
+  [".ts", ".tsx", ".jsx"]
+    .iter()
+    .any(|ext| path.ends_with(ext) && !path.ends_with(".d.ts"))
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_package_json_imports_get() {
+    let imports = serde_json::json!({
+      "#internal/*": "./internal/*.ts",
+      "#util": "./util.ts",
+      "#dep": "dep",
+      "#conditional": { "node": "./node.ts" },
+      "#mismatch/*": "./mismatch.ts",
+    });
+    let imports = PackageJsonImports::new(
+      &ModuleSpecifier::parse("file:///pkg/package.json").unwrap(),
+      imports.as_object(),
+    );
+    assert_eq!(
+      imports.get("#internal/a"),
+      Some(("#internal/*", "file:///pkg/internal/*.ts", "a"))
+    );
+    assert_eq!(
+      imports.get("#util"),
+      Some(("#util", "file:///pkg/util.ts", ""))
+    );
+    assert_eq!(imports.get("#internal/"), None);
+    assert_eq!(imports.get("#dep"), None);
+    assert_eq!(imports.get("#conditional"), None);
+    assert_eq!(imports.get("#mismatch/a"), None);
+  }
+}
@@ -0,0 +1,498 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use deno_ast::ModuleSpecifier;
+use deno_resolver::factory::WorkspaceFactorySys;
+
+use crate::build_transform_context;
+use crate::incremental_cache::IncrementalCache;
+use crate::transform_with_context;
+use crate::workspace::WorkspaceSiblings;
+use crate::Dependency;
+use crate::OutputAsset;
+use crate::OutputFile;
+use crate::TransformContext;
+use crate::TransformOptions;
+use crate::TransformOutput;
+use crate::TransformedJsModule;
+
+/// Names of the files that configure how the modules are resolved, which
+/// affect the whole output when changed.
+const CONFIG_FILE_NAMES: [&str; 4] =
+  ["deno.json", "deno.jsonc", "deno.lock", "package.json"];
+
+/// How the output changed from the last transform.
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TransformDiff {
+  /// Output files that weren't in the last output.
+  pub added_files: Vec<OutputFile>,
+  /// Output files whose text or source map changed.
+  pub changed_files: Vec<OutputFile>,
+  /// Paths of the output files that are no longer in the output.
+  pub removed_files: Vec<PathBuf>,
+  /// Assets that weren't in the last output.
+  pub added_assets: Vec<OutputAsset>,
+  /// Assets whose bytes changed.
+  pub changed_assets: Vec<OutputAsset>,
+  pub removed_assets: Vec<PathBuf>,
+  pub added_dependencies: Vec<Dependency>,
+  pub removed_dependencies: Vec<Dependency>,
+}
+
+impl TransformDiff {
+  pub fn is_empty(&self) -> bool {
+    self.added_files.is_empty()
+      && self.changed_files.is_empty()
+      && self.removed_files.is_empty()
+      && self.added_assets.is_empty()
+      && self.changed_assets.is_empty()
+      && self.removed_assets.is_empty()
+      && self.added_dependencies.is_empty()
+      && self.removed_dependencies.is_empty()
+  }
+}
+
+/// Transforms repeatedly (ex. when a file is saved in a dev loop), keeping
+/// the module graph, the mappings, and the result of each module from the
+/// last transform.
+///
+/// When only the text of local modules changed and their imports stayed the
+/// same, just those modules and the ones that import them are transformed
+/// again. Otherwise, the graph is built again and the analysis of each
+/// module is reused when nothing affecting it changed.
+pub struct Transformer<
+  TSys: WorkspaceFactorySys,
+  THttpClient: deno_cache_dir::file_fetcher::HttpClient + Clone + 'static,
+> {
+  sys: TSys,
+  http_client: THttpClient,
+  options: TransformOptions,
+  incremental_cache: IncrementalCache,
+  state: Option<TransformerState>,
+}
+
+/// What the last successful transform built.
+struct TransformerState {
+  context: TransformContext,
+  transformed_modules: HashMap<ModuleSpecifier, TransformedJsModule>,
+  output: TransformOutput,
+}
+
+impl<
+    TSys: WorkspaceFactorySys,
+    THttpClient: deno_cache_dir::file_fetcher::HttpClient + Clone + 'static,
+  > Transformer<TSys, THttpClient>
+{
+  pub fn new(
+    sys: TSys,
+    http_client: THttpClient,
+    options: TransformOptions,
+  ) -> Self {
+    let incremental_cache = match &options.incremental_cache_dir {
+      Some(dir_path) => IncrementalCache::load(&sys, dir_path),
+      None => IncrementalCache::default(),
+    };
+    Self {
+      sys,
+      http_client,
+      options,
+      incremental_cache,
+      state: None,
+    }
+  }
+
+  /// Output of the last successful transform.
+  pub fn output(&self) -> Option<&TransformOutput> {
+    self.state.as_ref().map(|state| &state.output)
+  }
+
+  /// Transforms everything, which replaces the last output.
+  pub async fn transform(&mut self) -> Result<&TransformOutput> {
+    let state = self.transform_all().await?;
+    Ok(&self.state.insert(state).output)
+  }
+
+  /// Transforms again after the files at the paths changed, were added,
+  /// or were removed, returning how the output changed.
+  ///
+  /// Nothing is transformed when none of the files affect the output.
+  pub async fn update(
+    &mut self,
+    changed_file_paths: &[PathBuf],
+  ) -> Result<TransformDiff> {
+    let Some(state) = &self.state else {
+      let state = self.transform_all().await?;
+      return Ok(get_diff(None, &self.state.insert(state).output));
+    };
+    let changed_file_paths = changed_file_paths
+      .iter()
+      .filter(|file_path| {
+        affects_output(&self.options, &state.output, file_path)
+      })
+      .collect::<Vec<_>>();
+    if changed_file_paths.is_empty() {
+      return Ok(TransformDiff::default());
+    }
+    let previous_output = match self.update_modules(&changed_file_paths).await?
+    {
+      Some(output) => {
+        let state = self.state.as_mut().unwrap();
+        std::mem::replace(&mut state.output, output)
+      }
+      None => {
+        let state = self.transform_all().await?;
+        self.state.replace(state).unwrap().output
+      }
+    };
+    Ok(get_diff(
+      Some(&previous_output),
+      &self.state.as_ref().unwrap().output,
+    ))
+  }
+
+  /// Transforms only the changed modules and the modules that import them,
+  /// which returns `None` when a change needs the graph to be built again
+  /// (ex. a module's imports changed or a file that isn't a module changed).
+  async fn update_modules(
+    &mut self,
+    changed_file_paths: &[&PathBuf],
+  ) -> Result<Option<TransformOutput>> {
+    let state = self.state.as_mut().unwrap();
+    let mut changed_specifiers = Vec::with_capacity(changed_file_paths.len());
+    for file_path in changed_file_paths {
+      if is_config_file(&self.options, file_path) {
+        return Ok(None);
+      }
+      let Ok(specifier) = deno_path_util::url_from_file_path(file_path) else {
+        return Ok(None);
+      };
+      // a removed file errors here, which the full transform reports
+      let Ok(text) = self.sys.fs_read_to_string(file_path) else {
+        return Ok(None);
+      };
+      if !state.context.specifiers.local.contains(&specifier)
+        || !state
+          .context
+          .module_graph
+          .try_update_module_text(&specifier, text.into_owned().into())
+      {
+        return Ok(None);
+      }
+      changed_specifiers.push(specifier);
+    }
+    for specifier in state
+      .context
+      .module_graph
+      .with_dependents(changed_specifiers)
+    {
+      state.transformed_modules.remove(&specifier);
+    }
+    let output = transform_with_context(
+      &state.context,
+      &self.sys,
+      &self.options,
+      &mut state.transformed_modules,
+      Some(&mut self.incremental_cache),
+    )
+    .await?;
+    Ok(Some(output))
+  }
+
+  async fn transform_all(&mut self) -> Result<TransformerState> {
+    let context = build_transform_context(
+      self.sys.clone(),
+      self.http_client.clone(),
+      &self.options,
+      &WorkspaceSiblings::default(),
+    )
+    .await?;
+    let mut transformed_modules = HashMap::new();
+    let output = transform_with_context(
+      &context,
+      &self.sys,
+      &self.options,
+      &mut transformed_modules,
+      Some(&mut self.incremental_cache),
+    )
+    .await?;
+    Ok(TransformerState {
+      context,
+      transformed_modules,
+      output,
+    })
+  }
+}
+
+fn affects_output(
+  options: &TransformOptions,
+  output: &TransformOutput,
+  file_path: &Path,
+) -> bool {
+  output.source_file_paths.contains(file_path)
+    || is_config_file(options, file_path)
+}
+
+/// Gets if the file configures how the modules are resolved, which affects
+/// the whole output.
+fn is_config_file(options: &TransformOptions, file_path: &Path) -> bool {
+  let is_config_file_name = file_path
+    .file_name()
+    .and_then(|name| name.to_str())
+    .is_some_and(|name| CONFIG_FILE_NAMES.contains(&name));
+  is_config_file_name
+    || [&options.config_file, &options.import_map]
+      .into_iter()
+      .flatten()
+      .any(|specifier| {
+        deno_path_util::url_to_file_path(specifier).ok().as_deref()
+          == Some(file_path)
+      })
+}
+
+fn get_diff(
+  previous: Option<&TransformOutput>,
+  current: &TransformOutput,
+) -> TransformDiff {
+  let mut diff = TransformDiff::default();
+  let previous_files = previous
+    .into_iter()
+    .flat_map(output_files)
+    .map(|file| (file.file_path.as_path(), file))
+    .collect::<HashMap<_, _>>();
+  for file in output_files(current) {
+    match previous_files.get(file.file_path.as_path()) {
+      None => diff.added_files.push(file.clone()),
+      Some(previous_file) => {
+        if previous_file.file_text != file.file_text
+          || previous_file.source_map != file.source_map
+        {
+          diff.changed_files.push(file.clone());
+        }
+      }
+    }
+  }
+  let current_file_paths = output_files(current)
+    .map(|file| file.file_path.as_path())
+    .collect::<HashSet<_>>();
+  diff.removed_files = previous_files
+    .keys()
+    .filter(|file_path| !current_file_paths.contains(*file_path))
+    .map(|file_path| file_path.to_path_buf())
+    .collect();
+  diff.removed_files.sort();
+
+  let previous_assets = previous
+    .into_iter()
+    .flat_map(output_assets)
+    .map(|asset| (asset.file_path.as_path(), asset))
+    .collect::<HashMap<_, _>>();
+  for asset in output_assets(current) {
+    match previous_assets.get(asset.file_path.as_path()) {
+      None => diff.added_assets.push(asset.clone()),
+      Some(previous_asset) => {
+        if previous_asset.bytes != asset.bytes {
+          diff.changed_assets.push(asset.clone());
+        }
+      }
+    }
+  }
+  let current_asset_paths = output_assets(current)
+    .map(|asset| asset.file_path.as_path())
+    .collect::<HashSet<_>>();
+  diff.removed_assets = previous_assets
+    .keys()
+    .filter(|file_path| !current_asset_paths.contains(*file_path))
+    .map(|file_path| file_path.to_path_buf())
+    .collect();
+  diff.removed_assets.sort();
+
+  let previous_dependencies =
+    previous.map(output_dependencies).unwrap_or_default();
+  let current_dependencies = output_dependencies(current);
+  diff.added_dependencies = current_dependencies
+    .difference(&previous_dependencies)
+    .map(|dependency| (*dependency).clone())
+    .collect();
+  diff.added_dependencies.sort_by(|a, b| a.name.cmp(&b.name));
+  diff.removed_dependencies = previous_dependencies
+    .difference(&current_dependencies)
+    .map(|dependency| (*dependency).clone())
+    .collect();
+  diff
+    .removed_dependencies
+    .sort_by(|a, b| a.name.cmp(&b.name));
+  diff
+}
+
+fn output_files(output: &TransformOutput) -> impl Iterator<Item = &OutputFile> {
+  output.main.files.iter().chain(output.test.files.iter())
+}
+
+fn output_assets(
+  output: &TransformOutput,
+) -> impl Iterator<Item = &OutputAsset> {
+  output.main.assets.iter().chain(output.test.assets.iter())
+}
+
+fn output_dependencies(output: &TransformOutput) -> HashSet<&Dependency> {
+  output
+    .main
+    .dependencies
+    .iter()
+    .chain(output.main.type_only_dependencies.iter())
+    .chain(output.test.dependencies.iter())
+    .chain(output.test.type_only_dependencies.iter())
+    .chain(output.types_dependencies.iter())
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use deno_cache_dir::file_fetcher::HeaderMap;
+  use deno_cache_dir::file_fetcher::SendError;
+  use deno_cache_dir::file_fetcher::SendResponse;
+  use pretty_assertions::assert_eq;
+  use sys_traits::impls::InMemorySys;
+  use sys_traits::EnvSetCurrentDir;
+  use sys_traits::EnvSetVar;
+  use sys_traits::FsCreateDirAll;
+  use sys_traits::FsWrite;
+
+  use super::*;
+  use crate::Platform;
+  use crate::ScriptTarget;
+  use crate::SourceMaps;
+
+  #[derive(Debug, Clone)]
+  struct NullHttpClient;
+
+  #[async_trait::async_trait(?Send)]
+  impl deno_cache_dir::file_fetcher::HttpClient for NullHttpClient {
+    async fn send_no_follow(
+      &self,
+      _specifier: &ModuleSpecifier,
+      _headers: HeaderMap,
+    ) -> Result<SendResponse, SendError> {
+      Err(SendError::NotFound)
+    }
+  }
+
+  #[tokio::test]
+  async fn update_transforms_affected_modules() {
+    let root_dir = PathBuf::from(if cfg!(windows) { "C:\\" } else { "/" });
+    let sys = InMemorySys::default();
+    sys.env_set_var("DENO_DIR", root_dir.join(".deno"));
+    sys.fs_create_dir_all(root_dir.join(".deno")).unwrap();
+    sys.env_set_current_dir(&root_dir).unwrap();
+    let write_file = |name: &str, text: &str| {
+      sys.fs_write(root_dir.join(name), text).unwrap();
+    };
+    write_file(
+      "mod.ts",
+      "import { value } from './dep.ts';\nimport './other.ts';\n",
+    );
+    write_file("dep.ts", "export const value = 5;");
+    write_file("other.ts", "console.log(1);");
+    let mut transformer = Transformer::new(
+      sys.clone(),
+      NullHttpClient,
+      TransformOptions {
+        entry_points: vec![deno_path_util::url_from_file_path(
+          &root_dir.join("mod.ts"),
+        )
+        .unwrap()],
+        entry_points_from_config: false,
+        bin_entry_points: Vec::new(),
+        test_entry_points: Vec::new(),
+        package_manifest: Default::default(),
+        shims: Vec::new(),
+        test_shims: Vec::new(),
+        specifier_mappings: Default::default(),
+        target: ScriptTarget::Latest,
+        polyfills: Default::default(),
+        platform: Platform::Node,
+        conditions: Vec::new(),
+        asset_inline_limit: 4096,
+        source_maps: SourceMaps::None,
+        incremental_cache_dir: None,
+        config_file: None,
+        no_config: true,
+        import_map: None,
+        frozen_lockfile: None,
+        locked_dependency_versions: None,
+        npm_package_info_provider: None,
+        pin_npm_versions: false,
+        cwd: root_dir.clone(),
+      },
+    );
+    transformer.transform().await.unwrap();
+    // tamper with the kept results to see which modules are transformed again
+    let tamper =
+      |transformer: &mut Transformer<InMemorySys, NullHttpClient>| {
+        for transformed in transformer
+          .state
+          .as_mut()
+          .unwrap()
+          .transformed_modules
+          .values_mut()
+        {
+          transformed.file_text = "tampered".to_string();
+        }
+      };
+    let get_changed_files = |diff: &TransformDiff| {
+      let mut files = diff
+        .changed_files
+        .iter()
+        .map(|file| {
+          (
+            file.file_path.to_string_lossy().to_string(),
+            file.file_text.clone(),
+          )
+        })
+        .collect::<Vec<_>>();
+      files.sort();
+      files
+    };
+    tamper(&mut transformer);
+
+    // the changed module and the one importing it are transformed again,
+    // which leaves the one that's unaffected with its tampered result
+    write_file("dep.ts", "export const value = 6;");
+    let diff = transformer
+      .update(&[root_dir.join("dep.ts")])
+      .await
+      .unwrap();
+    assert_eq!(
+      get_changed_files(&diff),
+      vec![
+        ("dep.ts".to_string(), "export const value = 6;".to_string()),
+        ("other.ts".to_string(), "tampered".to_string()),
+      ]
+    );
+
+    // changing what a module imports transforms everything again
+    write_file("dep.ts", "import './other.ts';\nexport const value = 6;");
+    tamper(&mut transformer);
+    let diff = transformer
+      .update(&[root_dir.join("dep.ts")])
+      .await
+      .unwrap();
+    assert_eq!(
+      get_changed_files(&diff),
+      vec![
+        (
+          "dep.ts".to_string(),
+          "import './other.js';\nexport const value = 6;".to_string()
+        ),
+        ("other.ts".to_string(), "console.log(1);".to_string()),
+      ]
+    );
+  }
+}
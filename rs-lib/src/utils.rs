@@ -128,11 +128,13 @@ pub fn partition_by_root_specifiers<'a>(
   root_specifiers
 }
 
+/// Prepends the statement after any leading comments, returning the
+/// change that was applied.
 pub fn prepend_statement_to_text(
   file_path: &Path,
   file_text: &mut String,
   statement_text: &str,
-) {
+) -> TextChange {
   // It's not great to have to reparse the file for this. Perhaps there is a utility
   // function in swc or maybe add one to deno_ast for parsing out the leading comments
   let text = std::mem::take(file_text);
@@ -150,17 +152,20 @@ pub fn prepend_statement_to_text(
     scope_analysis: false,
     text: text.clone(),
   });
-  match parsed_module {
+  let text_change = match parsed_module {
     Ok(parsed_module) => parsed_module.with_view(|program| {
-      let text_change =
-        text_change_for_prepend_statement_to_text(program, statement_text);
-      *file_text = apply_text_changes(text.as_ref(), vec![text_change]);
+      text_change_for_prepend_statement_to_text(program, statement_text)
     }),
     Err(_) => {
       // should never happen... fallback...
-      *file_text = format!("{}\n{}", statement_text, text);
+      TextChange {
+        range: 0..0,
+        new_text: format!("{}\n", statement_text),
+      }
     }
-  }
+  };
+  *file_text = apply_text_changes(text.as_ref(), vec![text_change.clone()]);
+  text_change
 }
 
 pub fn text_change_for_prepend_statement_to_text(
@@ -192,6 +197,24 @@ fn top_file_insert_pos(program: Program) -> usize {
   pos
 }
 
+/// Maps the items on the rayon thread pool when the `parallel` feature is
+/// enabled and otherwise one after another. Either way, the results are in
+/// the order of the items.
+pub fn map_in_parallel<T: Sync, R: Send>(
+  items: &[T],
+  f: impl Fn(&T) -> R + Sync + Send,
+) -> Vec<R> {
+  #[cfg(feature = "parallel")]
+  {
+    use rayon::prelude::*;
+    items.par_iter().map(f).collect()
+  }
+  #[cfg(not(feature = "parallel"))]
+  {
+    items.iter().map(f).collect()
+  }
+}
+
 /// `with_extension` that handles `.d.ts` files
 pub fn with_extension(path: &Path, ext: &str) -> PathBuf {
   let lower = path.to_string_lossy().to_lowercase();
@@ -376,6 +399,15 @@ mod test {
     assert_eq!(output, expected);
   }
 
+  #[test]
+  fn test_map_in_parallel() {
+    let items = (0..100).collect::<Vec<_>>();
+    assert_eq!(
+      map_in_parallel(&items, |i| i * 2),
+      items.iter().map(|i| i * 2).collect::<Vec<_>>()
+    );
+  }
+
   #[test]
   fn test_with_extension() {
     assert_eq!(
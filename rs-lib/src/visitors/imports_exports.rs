@@ -15,6 +15,7 @@ use deno_ast::TextChange;
 
 use crate::graph::ModuleGraph;
 use crate::mappings::Mappings;
+use crate::subpath_imports::PreservedSubpathImports;
 use crate::utils::get_relative_specifier;
 
 pub struct GetImportExportsTextChangesParams<'a> {
@@ -23,6 +24,7 @@ pub struct GetImportExportsTextChangesParams<'a> {
   pub mappings: &'a Mappings,
   pub program: Program<'a>,
   pub package_specifier_mappings: &'a HashMap<ModuleSpecifier, String>,
+  pub preserved_subpath_imports: &'a PreservedSubpathImports,
 }
 
 struct Context<'a> {
@@ -33,6 +35,7 @@ struct Context<'a> {
   output_file_path: &'a PathBuf,
   text_changes: Vec<TextChange>,
   package_specifier_mappings: &'a HashMap<ModuleSpecifier, String>,
+  preserved_subpath_imports: &'a PreservedSubpathImports,
 }
 
 pub fn get_import_exports_text_changes(
@@ -46,6 +49,7 @@ pub fn get_import_exports_text_changes(
     output_file_path: params.mappings.get_file_path(params.specifier),
     text_changes: Vec::new(),
     package_specifier_mappings: params.package_specifier_mappings,
+    preserved_subpath_imports: params.preserved_subpath_imports,
   };
 
   visit_children(params.program.as_node(), &mut context)?;
@@ -126,6 +130,13 @@ fn visit_module_specifier(str: &Str, context: &mut Context) {
     Some(s) => s,
     None => return,
   };
+  // keep a subpath import so the package.json `imports` resolves it
+  if context
+    .preserved_subpath_imports
+    .is_preserved(&value, &specifier)
+  {
+    return;
+  }
 
   let new_text = if let Some(bare_specifier) =
     context.package_specifier_mappings.get(&specifier)
@@ -0,0 +1,195 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::Context;
+use anyhow::Result;
+use deno_graph::WasmModule;
+use wasm_dep_analyzer::ExportType;
+use wasm_dep_analyzer::ParseOptions;
+use wasm_dep_analyzer::WasmDeps;
+
+use crate::utils::get_relative_path;
+use crate::Platform;
+
+/// Gets the text of the JavaScript module that instantiates the Wasm module
+/// and exports what it exports.
+///
+/// The import specifiers are the ones the Wasm module uses, so they still
+/// need to be changed to the output's specifiers.
+pub fn get_wasm_module_wrapper_text(
+  module: &WasmModule,
+  wrapper_file_path: &Path,
+  asset_file_path: &Path,
+  platform: Platform,
+) -> Result<String> {
+  let wasm_deps = WasmDeps::parse(
+    &module.source,
+    ParseOptions {
+      // only the kind of each export is necessary
+      skip_types: true,
+    },
+  )
+  .with_context(|| {
+    format!("Failed parsing Wasm module {}", module.specifier)
+  })?;
+  let asset_specifier = format!(
+    "./{}",
+    get_relative_path(wrapper_file_path, asset_file_path)
+      .to_string_lossy()
+      .replace('\\', "/")
+  );
+
+  let mut import_modules = Vec::<&str>::new();
+  for import in &wasm_deps.imports {
+    if !import_modules.contains(&import.module) {
+      import_modules.push(import.module);
+    }
+  }
+
+  let mut text = String::new();
+  if platform == Platform::Node {
+    text.push_str("import { readFileSync } from \"node:fs\";\n");
+  }
+  for (i, import_module) in import_modules.iter().enumerate() {
+    writeln!(text, "import * as wasmImport{i} from {import_module:?};")
+      .unwrap();
+  }
+  text.push('\n');
+
+  let mut imports_object = String::from("{\n");
+  for (i, import_module) in import_modules.iter().enumerate() {
+    writeln!(imports_object, "    {import_module:?}: wasmImport{i},").unwrap();
+  }
+  imports_object.push_str("  }");
+  let url = format!("new URL({asset_specifier:?}, import.meta.url)");
+  match platform {
+    Platform::Node => {
+      writeln!(
+        text,
+        "const wasmInstance = new WebAssembly.Instance(\n  new WebAssembly.Module(readFileSync({url})),\n  {imports_object},\n);"
+      )
+      .unwrap();
+    }
+    Platform::Browser => {
+      writeln!(
+        text,
+        "const {{ instance: wasmInstance }} = await WebAssembly.instantiateStreaming(\n  fetch({url}),\n  {imports_object},\n);"
+      )
+      .unwrap();
+    }
+  }
+
+  for (i, export) in wasm_deps.exports.iter().enumerate() {
+    let mut value = format!("wasmInstance.exports[{:?}]", export.name);
+    // the declaration file has the value of a global rather than the
+    // `WebAssembly.Global`
+    if matches!(export.export_type, ExportType::Global(_)) {
+      value.push_str(".value");
+    }
+    if is_valid_ident(export.name) {
+      writeln!(text, "export const {} = {value};", export.name).unwrap();
+    } else {
+      writeln!(
+        text,
+        "const wasmExport{i} = {value};\nexport {{ wasmExport{i} as {:?} }};",
+        export.name
+      )
+      .unwrap();
+    }
+  }
+
+  Ok(text)
+}
+
+fn is_valid_ident(name: &str) -> bool {
+  !name.is_empty() && deno_ast::swc::ast::Ident::verify_symbol(name).is_ok()
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::PathBuf;
+
+  use deno_ast::ModuleSpecifier;
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  /// A module that imports `log` from `./mod.ts` and exports an `add`
+  /// function, a memory, and a global named `weird-name`.
+  const WASM_BYTES: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    0x01, 0x0b, 0x02, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, 0x60, 0x01, 0x7f,
+    0x00, // types
+    0x02, 0x10, 0x01, 0x08, b'.', b'/', b'm', b'o', b'd', b'.', b't', b's',
+    0x03, b'l', b'o', b'g', 0x00, 0x01, // imports
+    0x03, 0x02, 0x01, 0x00, // functions
+    0x05, 0x03, 0x01, 0x00, 0x01, // memory
+    0x06, 0x06, 0x01, 0x7f, 0x00, 0x41, 0x01, 0x0b, // globals
+    0x07, 0x1d, 0x03, 0x03, b'a', b'd', b'd', 0x00, 0x01, 0x06, b'm', b'e',
+    b'm', b'o', b'r', b'y', 0x02, 0x00, 0x0a, b'w', b'e', b'i', b'r', b'd',
+    b'-', b'n', b'a', b'm', b'e', 0x03, 0x00, // exports
+    0x0a, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a,
+    0x0b, // code
+  ];
+
+  fn get_text(platform: Platform) -> String {
+    let module = WasmModule {
+      specifier: ModuleSpecifier::parse("file:///math.wasm").unwrap(),
+      mtime: None,
+      dependencies: Default::default(),
+      source: WASM_BYTES.into(),
+      source_dts: "".into(),
+      maybe_cache_info: None,
+    };
+    get_wasm_module_wrapper_text(
+      &module,
+      &PathBuf::from("math.js"),
+      &PathBuf::from("math.wasm"),
+      platform,
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn node_wrapper() {
+    assert_eq!(
+      get_text(Platform::Node),
+      r#"import { readFileSync } from "node:fs";
+import * as wasmImport0 from "./mod.ts";
+
+const wasmInstance = new WebAssembly.Instance(
+  new WebAssembly.Module(readFileSync(new URL("./math.wasm", import.meta.url))),
+  {
+    "./mod.ts": wasmImport0,
+  },
+);
+export const add = wasmInstance.exports["add"];
+export const memory = wasmInstance.exports["memory"];
+const wasmExport2 = wasmInstance.exports["weird-name"].value;
+export { wasmExport2 as "weird-name" };
+"#
+    );
+  }
+
+  #[test]
+  fn browser_wrapper() {
+    assert_eq!(
+      get_text(Platform::Browser),
+      r#"import * as wasmImport0 from "./mod.ts";
+
+const { instance: wasmInstance } = await WebAssembly.instantiateStreaming(
+  fetch(new URL("./math.wasm", import.meta.url)),
+  {
+    "./mod.ts": wasmImport0,
+  },
+);
+export const add = wasmInstance.exports["add"];
+export const memory = wasmInstance.exports["memory"];
+const wasmExport2 = wasmInstance.exports["weird-name"].value;
+export { wasmExport2 as "weird-name" };
+"#
+    );
+  }
+}
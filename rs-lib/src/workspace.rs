@@ -0,0 +1,265 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use deno_ast::ModuleSpecifier;
+use deno_config::deno_json::ConfigFile;
+use deno_resolver::factory::ConfigDiscoveryOption;
+use deno_resolver::factory::WorkspaceFactorySys;
+
+use crate::config_package::resolve_config_package;
+use crate::graph::display_specifier;
+use crate::incremental_cache::IncrementalCache;
+use crate::transform_with_siblings;
+use crate::workspace_factory_options;
+use crate::DependencyKind;
+use crate::LockedDependencyVersions;
+use crate::MappedSpecifier;
+use crate::NpmPackageInfoProvider;
+use crate::PackageManifestOptions;
+use crate::PackageMappedSpecifier;
+use crate::Platform;
+use crate::PolyfillOverrides;
+use crate::ScriptTarget;
+use crate::Shim;
+use crate::SourceMaps;
+use crate::TransformOptions;
+use crate::TransformOutput;
+
+pub struct TransformWorkspaceOptions {
+  /// Layout of each emitted member package, whose entry points are ignored
+  /// since the exports of each member are used.
+  pub package_manifest: PackageManifestOptions,
+  pub shims: Vec<Shim>,
+  pub test_shims: Vec<Shim>,
+  /// Maps specifiers to an npm package or module in every member, which
+  /// takes precedence over the exports of the other members.
+  pub specifier_mappings: HashMap<String, MappedSpecifier>,
+  pub target: ScriptTarget,
+  pub polyfills: PolyfillOverrides,
+  pub platform: Platform,
+  pub conditions: Vec<String>,
+  pub asset_inline_limit: usize,
+  pub source_maps: SourceMaps,
+  /// Directory of the incremental cache, which has a directory for each
+  /// member named after it.
+  pub incremental_cache_dir: Option<PathBuf>,
+  /// Config file of the workspace. When not provided, the workspace is
+  /// discovered from the cwd.
+  pub config_file: Option<ModuleSpecifier>,
+  pub frozen_lockfile: Option<bool>,
+  pub locked_dependency_versions: Option<LockedDependencyVersions>,
+  pub npm_package_info_provider: Option<Rc<dyn NpmPackageInfoProvider>>,
+  pub pin_npm_versions: bool,
+  pub cwd: PathBuf,
+}
+
+#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, PartialEq, Eq)]
+pub struct TransformWorkspaceMemberOutput {
+  pub name: String,
+  pub version: String,
+  /// Directory of the member's config file.
+  pub dir_path: PathBuf,
+  /// Names of the member's exports (ex. `.` or `./sub`), which are in the
+  /// same order as the entry points of the main environment.
+  pub exports: Vec<String>,
+  pub output: TransformOutput,
+}
+
+/// Transforms each member of the workspace that's a package (has a name and
+/// exports) using its exports as the entry points.
+///
+/// An import of another member becomes a dependency on that member's package
+/// at its version rather than copying its modules into the output.
+pub async fn transform_workspace(
+  sys: impl WorkspaceFactorySys,
+  http_client: impl deno_cache_dir::file_fetcher::HttpClient + Clone + 'static,
+  options: TransformWorkspaceOptions,
+) -> Result<Vec<TransformWorkspaceMemberOutput>> {
+  if options.pin_npm_versions && options.npm_package_info_provider.is_none() {
+    bail!("pinning npm versions requires an npm package info provider");
+  }
+  let config_discovery = match &options.config_file {
+    Some(config_file) => ConfigDiscoveryOption::Path(
+      deno_path_util::url_to_file_path(config_file)?,
+    ),
+    None => ConfigDiscoveryOption::DiscoverCwd,
+  };
+  let factory = deno_resolver::factory::WorkspaceFactory::new(
+    sys.clone(),
+    options.cwd.clone(),
+    workspace_factory_options(config_discovery, options.frozen_lockfile),
+  );
+  let workspace = factory.workspace_directory()?.workspace.clone();
+  let members = workspace
+    .jsr_packages()
+    .map(|package| WorkspaceMember::new(package.name, &package.config_file))
+    .collect::<Result<Vec<_>>>()?;
+  if members.is_empty() {
+    bail!(
+      "No workspace members with a name and exports were found in {}",
+      display_specifier(workspace.root_dir_url()),
+    );
+  }
+
+  let mut outputs = Vec::with_capacity(members.len());
+  for member in members.iter() {
+    let siblings = WorkspaceSiblings {
+      member_name: member.name.clone(),
+      mappings: members
+        .iter()
+        .filter(|m| m.name != member.name)
+        .flat_map(|m| m.export_mappings())
+        .collect(),
+      dirs: members
+        .iter()
+        .map(|m| (m.dir_url.clone(), m.name.clone()))
+        .collect(),
+    };
+    let incremental_cache_dir = options
+      .incremental_cache_dir
+      .as_ref()
+      .map(|dir_path| dir_path.join(&member.name));
+    let mut incremental_cache = incremental_cache_dir
+      .as_ref()
+      .map(|dir_path| IncrementalCache::load(&sys, dir_path));
+    let output = transform_with_siblings(
+      sys.clone(),
+      http_client.clone(),
+      TransformOptions {
+        entry_points: Vec::new(),
+        entry_points_from_config: true,
+        bin_entry_points: Vec::new(),
+        test_entry_points: Vec::new(),
+        package_manifest: PackageManifestOptions {
+          entry_points: Vec::new(),
+          ..options.package_manifest.clone()
+        },
+        shims: options.shims.clone(),
+        test_shims: options.test_shims.clone(),
+        specifier_mappings: options.specifier_mappings.clone(),
+        target: options.target,
+        polyfills: options.polyfills.clone(),
+        platform: options.platform,
+        conditions: options.conditions.clone(),
+        asset_inline_limit: options.asset_inline_limit,
+        source_maps: options.source_maps,
+        incremental_cache_dir,
+        config_file: Some(member.config_specifier.clone()),
+        no_config: false,
+        import_map: None,
+        frozen_lockfile: options.frozen_lockfile,
+        locked_dependency_versions: options.locked_dependency_versions,
+        npm_package_info_provider: options.npm_package_info_provider.clone(),
+        pin_npm_versions: options.pin_npm_versions,
+        cwd: options.cwd.clone(),
+      },
+      &siblings,
+      incremental_cache.as_mut(),
+    )
+    .await
+    .with_context(|| {
+      format!("Failed transforming workspace member {}", member.name)
+    })?;
+    outputs.push(TransformWorkspaceMemberOutput {
+      name: member.name.clone(),
+      version: member.version.clone(),
+      dir_path: deno_path_util::url_to_file_path(&member.dir_url)?,
+      exports: member.exports.iter().map(|(k, _)| k.clone()).collect(),
+      output,
+    });
+  }
+  Ok(outputs)
+}
+
+struct WorkspaceMember {
+  name: String,
+  version: String,
+  config_specifier: ModuleSpecifier,
+  dir_url: ModuleSpecifier,
+  /// Export names along with the module each resolves to.
+  exports: Vec<(String, ModuleSpecifier)>,
+}
+
+impl WorkspaceMember {
+  fn new(name: String, config_file: &ConfigFile) -> Result<Self> {
+    let Some(version) = config_file.json.version.clone() else {
+      bail!(
+        "The workspace member {} must have a version in {} in order to be depended on",
+        name,
+        display_specifier(&config_file.specifier),
+      );
+    };
+    let (package, export_specifiers) = resolve_config_package(config_file)?;
+    Ok(Self {
+      name,
+      version,
+      config_specifier: config_file.specifier.clone(),
+      dir_url: config_file.specifier.join("./")?,
+      exports: package.exports.into_iter().zip(export_specifiers).collect(),
+    })
+  }
+
+  fn export_mappings(
+    &self,
+  ) -> impl Iterator<Item = (ModuleSpecifier, PackageMappedSpecifier)> + '_ {
+    self.exports.iter().map(|(key, specifier)| {
+      let sub_path = key.strip_prefix("./").map(|p| p.to_string());
+      (
+        specifier.clone(),
+        PackageMappedSpecifier {
+          name: self.name.clone(),
+          version: Some(self.version.clone()),
+          sub_path,
+          kind: DependencyKind::Dependency,
+        },
+      )
+    })
+  }
+}
+
+/// The other members of the workspace that a member is transformed with.
+#[derive(Default)]
+pub(crate) struct WorkspaceSiblings {
+  member_name: String,
+  /// Exports of the other members mapped to their packages.
+  pub mappings: HashMap<ModuleSpecifier, PackageMappedSpecifier>,
+  /// Directory of every member along with its name.
+  dirs: Vec<(ModuleSpecifier, String)>,
+}
+
+impl WorkspaceSiblings {
+  /// Errors when a module of another member that isn't one of its exports
+  /// is imported, since it would otherwise be copied into the output.
+  pub fn ensure_not_imported(
+    &self,
+    local_specifiers: &[ModuleSpecifier],
+  ) -> Result<()> {
+    for specifier in local_specifiers {
+      // use the innermost member for a member nested in another one
+      let maybe_owner = self
+        .dirs
+        .iter()
+        .filter(|(dir_url, _)| specifier.as_str().starts_with(dir_url.as_str()))
+        .max_by_key(|(dir_url, _)| dir_url.as_str().len());
+      if let Some((_, name)) = maybe_owner {
+        if *name != self.member_name {
+          bail!(
+            "{} imports {}, which is not one of the exports of the workspace member {}",
+            self.member_name,
+            display_specifier(specifier),
+            name,
+          );
+        }
+      }
+    }
+    Ok(())
+  }
+}
@@ -47,6 +47,14 @@ impl InMemoryLoader {
     &mut self,
     path: impl AsRef<str>,
     text: impl AsRef<str>,
+  ) -> &mut Self {
+    self.add_local_file_bytes(path, text.as_ref().as_bytes())
+  }
+
+  pub fn add_local_file_bytes(
+    &mut self,
+    path: impl AsRef<str>,
+    bytes: &[u8],
   ) -> &mut Self {
     let path = path.as_ref();
     let path = if cfg!(windows) && path.starts_with("/") {
@@ -56,7 +64,7 @@ impl InMemoryLoader {
     };
     let parent_dir = path.parent().unwrap();
     self.sys.fs_create_dir_all(parent_dir).unwrap();
-    self.sys.fs_write(path, text.as_ref().as_bytes()).unwrap();
+    self.sys.fs_write(path, bytes).unwrap();
     self
   }
 
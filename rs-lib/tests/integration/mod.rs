@@ -8,28 +8,32 @@ pub use test_builder::*;
 
 macro_rules! assert_files {
   ($actual: expr, $expected: expr) => {{
-    let mut actual = $actual;
+    // the origin metadata is tested separately, but the source map is
+    // compared since it should only exist when source maps are enabled
+    let mut actual = $actual
+      .into_iter()
+      .map(|file| (file.file_path, file.file_text, file.source_map))
+      .collect::<Vec<_>>();
     let expected = $expected;
     #[cfg(target_os = "windows")]
-    for file in actual.iter_mut() {
+    for (file_path, _, _) in actual.iter_mut() {
       // normalize this on windows to forward slashes
-      file.file_path = std::path::PathBuf::from(
-        file
-          .file_path
-          .to_string_lossy()
-          .to_string()
-          .replace("\\", "/"),
+      *file_path = std::path::PathBuf::from(
+        file_path.to_string_lossy().to_string().replace("\\", "/"),
       );
     }
-    actual.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    actual.sort();
     let mut expected = expected
       .iter()
-      .map(|(file_path, file_text)| deno_node_transform::OutputFile {
-        file_path: std::path::PathBuf::from(file_path),
-        file_text: file_text.to_string(),
+      .map(|(file_path, file_text)| {
+        (
+          std::path::PathBuf::from(file_path),
+          file_text.to_string(),
+          None::<String>,
+        )
       })
       .collect::<Vec<_>>();
-    expected.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    expected.sort();
 
     pretty_assertions::assert_eq!(actual, expected);
   }};
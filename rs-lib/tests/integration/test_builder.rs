@@ -1,19 +1,33 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
 
 use anyhow::Result;
 use deno_node_transform::transform;
+use deno_node_transform::transform_workspace;
+use deno_node_transform::DependencyKind;
 use deno_node_transform::GlobalName;
+use deno_node_transform::LocalNpmPackageInfoProvider;
+use deno_node_transform::LockedDependencyVersions;
 use deno_node_transform::MappedSpecifier;
 use deno_node_transform::ModuleSpecifier;
+use deno_node_transform::NpmPackageInfoProvider;
+use deno_node_transform::PackageManifestOptions;
 use deno_node_transform::PackageMappedSpecifier;
 use deno_node_transform::PackageShim;
+use deno_node_transform::Platform;
 use deno_node_transform::PolyfillOverrides;
 use deno_node_transform::ScriptTarget;
 use deno_node_transform::Shim;
+use deno_node_transform::SourceMaps;
 use deno_node_transform::TransformOptions;
 use deno_node_transform::TransformOutput;
+use deno_node_transform::TransformWorkspaceMemberOutput;
+use deno_node_transform::TransformWorkspaceOptions;
+use deno_node_transform::Transformer;
+use sys_traits::impls::InMemorySys;
 use sys_traits::EnvCurrentDir;
 
 use super::InMemoryLoader;
@@ -21,18 +35,28 @@ use super::InMemoryLoader;
 pub struct TestBuilder {
   loader: InMemoryLoader,
   entry_point: String,
+  entry_points_from_config: bool,
   additional_entry_points: Vec<String>,
   bin_entry_points: Vec<String>,
   test_entry_points: Vec<String>,
+  package_manifest: PackageManifestOptions,
   specifier_mappings: HashMap<String, MappedSpecifier>,
   shims: Vec<Shim>,
   test_shims: Vec<Shim>,
   target: ScriptTarget,
   polyfills: PolyfillOverrides,
+  platform: Platform,
+  conditions: Vec<String>,
+  asset_inline_limit: usize,
+  source_maps: SourceMaps,
+  incremental_cache_dir: Option<PathBuf>,
   config_file: Option<ModuleSpecifier>,
   no_config: bool,
   import_map: Option<ModuleSpecifier>,
   frozen_lockfile: Option<bool>,
+  locked_dependency_versions: Option<LockedDependencyVersions>,
+  npm_registry_dir: Option<PathBuf>,
+  pin_npm_versions: bool,
 }
 
 impl TestBuilder {
@@ -44,18 +68,28 @@ impl TestBuilder {
       } else {
         "file:///mod.ts".to_string()
       },
+      entry_points_from_config: false,
       additional_entry_points: Vec::new(),
       bin_entry_points: Vec::new(),
       test_entry_points: Vec::new(),
+      package_manifest: Default::default(),
       specifier_mappings: Default::default(),
       shims: Default::default(),
       test_shims: Default::default(),
       target: ScriptTarget::ES5,
       polyfills: Default::default(),
+      platform: Platform::Node,
+      conditions: Vec::new(),
+      asset_inline_limit: 4096,
+      source_maps: SourceMaps::None,
+      incremental_cache_dir: None,
       config_file: None,
       no_config: false,
       import_map: None,
       frozen_lockfile: None,
+      locked_dependency_versions: None,
+      npm_registry_dir: None,
+      pin_npm_versions: false,
     }
   }
 
@@ -67,11 +101,30 @@ impl TestBuilder {
     self
   }
 
+  pub fn sys(&self) -> &InMemorySys {
+    &self.loader.sys
+  }
+
   pub fn entry_point(&mut self, value: impl AsRef<str>) -> &mut Self {
     self.entry_point = normalize_urls(value.as_ref());
     self
   }
 
+  /// Derives the entry points from the config file's exports instead of
+  /// using the default entry point.
+  pub fn set_entry_points_from_config(&mut self) -> &mut Self {
+    self.entry_points_from_config = true;
+    self
+  }
+
+  pub fn set_package_manifest(
+    &mut self,
+    options: PackageManifestOptions,
+  ) -> &mut Self {
+    self.package_manifest = options;
+    self
+  }
+
   pub fn add_entry_point(&mut self, value: impl AsRef<str>) -> &mut Self {
     self
       .additional_entry_points
@@ -114,7 +167,7 @@ impl TestBuilder {
         name: "@deno/shim-deno".to_string(),
         version: Some("^0.1.0".to_string()),
         sub_path: None,
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       },
       types_package: None,
       global_names: vec![GlobalName {
@@ -130,7 +183,7 @@ impl TestBuilder {
         name: "@deno/shim-timers".to_string(),
         version: Some("^0.1.0".to_string()),
         sub_path: None,
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       },
       types_package: None,
       global_names: vec![
@@ -174,17 +227,18 @@ impl TestBuilder {
         name: bare_specifier.as_ref().to_string(),
         version: version.map(|v| v.to_string()),
         sub_path: path.map(|v| v.to_string()),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       }),
     );
     self
   }
 
-  pub fn add_peer_package_specifier_mapping(
+  pub fn add_package_specifier_mapping_with_kind(
     &mut self,
     specifier: impl AsRef<str>,
     bare_specifier: impl AsRef<str>,
     version: Option<&str>,
+    kind: DependencyKind,
   ) -> &mut Self {
     self.specifier_mappings.insert(
       normalize_urls(specifier.as_ref()),
@@ -192,7 +246,7 @@ impl TestBuilder {
         name: bare_specifier.as_ref().to_string(),
         version: version.map(|v| v.to_string()),
         sub_path: None,
-        peer_dependency: true,
+        kind,
       }),
     );
     self
@@ -217,6 +271,30 @@ impl TestBuilder {
     self
   }
 
+  pub fn set_locked_dependency_versions(
+    &mut self,
+    value: LockedDependencyVersions,
+  ) -> &mut Self {
+    self.locked_dependency_versions = Some(value);
+    self
+  }
+
+  /// Sets the directory of packuments in the loader's file system that's
+  /// used to resolve the npm dependencies.
+  pub fn set_npm_registry_dir(&mut self, path: &str) -> &mut Self {
+    self.npm_registry_dir = Some(if cfg!(windows) && path.starts_with('/') {
+      PathBuf::from(format!("C:{path}"))
+    } else {
+      PathBuf::from(path)
+    });
+    self
+  }
+
+  pub fn set_pin_npm_versions(&mut self, value: bool) -> &mut Self {
+    self.pin_npm_versions = value;
+    self
+  }
+
   pub fn set_target(&mut self, target: ScriptTarget) -> &mut Self {
     self.target = target;
     self
@@ -227,39 +305,125 @@ impl TestBuilder {
     self
   }
 
+  pub fn set_platform(&mut self, platform: Platform) -> &mut Self {
+    self.platform = platform;
+    self
+  }
+
+  pub fn add_condition(&mut self, condition: &str) -> &mut Self {
+    self.conditions.push(condition.to_string());
+    self
+  }
+
+  pub fn set_asset_inline_limit(&mut self, value: usize) -> &mut Self {
+    self.asset_inline_limit = value;
+    self
+  }
+
+  pub fn set_source_maps(&mut self, value: SourceMaps) -> &mut Self {
+    self.source_maps = value;
+    self
+  }
+
+  pub fn set_incremental_cache_dir(&mut self, path: &str) -> &mut Self {
+    self.incremental_cache_dir = Some(PathBuf::from(path));
+    self
+  }
+
   pub async fn transform(&self) -> Result<TransformOutput> {
-    let mut entry_points =
-      vec![ModuleSpecifier::parse(&self.entry_point).unwrap()];
+    transform(
+      self.loader.sys.clone(),
+      self.loader.clone(),
+      self.transform_options(),
+    )
+    .await
+  }
+
+  pub fn transformer(&self) -> Transformer<InMemorySys, InMemoryLoader> {
+    Transformer::new(
+      self.loader.sys.clone(),
+      self.loader.clone(),
+      self.transform_options(),
+    )
+  }
+
+  fn transform_options(&self) -> TransformOptions {
+    let mut entry_points = if self.entry_points_from_config {
+      Vec::new()
+    } else {
+      vec![ModuleSpecifier::parse(&self.entry_point).unwrap()]
+    };
     entry_points.extend(
       self
         .additional_entry_points
         .iter()
         .map(|p| ModuleSpecifier::parse(p).unwrap()),
     );
-    transform(
+    TransformOptions {
+      entry_points,
+      entry_points_from_config: self.entry_points_from_config,
+      bin_entry_points: self
+        .bin_entry_points
+        .iter()
+        .map(|p| ModuleSpecifier::parse(p).unwrap())
+        .collect(),
+      test_entry_points: self
+        .test_entry_points
+        .iter()
+        .map(|p| ModuleSpecifier::parse(p).unwrap())
+        .collect(),
+      package_manifest: self.package_manifest.clone(),
+      shims: self.shims.clone(),
+      test_shims: self.test_shims.clone(),
+      specifier_mappings: self.specifier_mappings.clone(),
+      target: self.target,
+      polyfills: self.polyfills.clone(),
+      platform: self.platform,
+      conditions: self.conditions.clone(),
+      asset_inline_limit: self.asset_inline_limit,
+      source_maps: self.source_maps,
+      incremental_cache_dir: self.incremental_cache_dir.clone(),
+      config_file: self.config_file.clone(),
+      no_config: self.no_config,
+      import_map: self.import_map.clone(),
+      frozen_lockfile: self.frozen_lockfile,
+      locked_dependency_versions: self.locked_dependency_versions,
+      npm_package_info_provider: self.npm_registry_dir.as_ref().map(|dir| {
+        Rc::new(LocalNpmPackageInfoProvider::new(
+          self.loader.sys.clone(),
+          dir.clone(),
+        )) as Rc<dyn NpmPackageInfoProvider>
+      }),
+      pin_npm_versions: self.pin_npm_versions,
+      cwd: self.loader.sys.env_current_dir().unwrap(),
+    }
+  }
+
+  /// Transforms each member of the workspace discovered from the cwd or
+  /// the config file, ignoring the entry points.
+  pub async fn transform_workspace(
+    &self,
+  ) -> Result<Vec<TransformWorkspaceMemberOutput>> {
+    transform_workspace(
       self.loader.sys.clone(),
       self.loader.clone(),
-      TransformOptions {
-        entry_points,
-        bin_entry_points: self
-          .bin_entry_points
-          .iter()
-          .map(|p| ModuleSpecifier::parse(p).unwrap())
-          .collect(),
-        test_entry_points: self
-          .test_entry_points
-          .iter()
-          .map(|p| ModuleSpecifier::parse(p).unwrap())
-          .collect(),
+      TransformWorkspaceOptions {
+        package_manifest: self.package_manifest.clone(),
         shims: self.shims.clone(),
         test_shims: self.test_shims.clone(),
         specifier_mappings: self.specifier_mappings.clone(),
         target: self.target,
         polyfills: self.polyfills.clone(),
+        platform: self.platform,
+        conditions: self.conditions.clone(),
+        asset_inline_limit: self.asset_inline_limit,
+        source_maps: self.source_maps,
+        incremental_cache_dir: self.incremental_cache_dir.clone(),
         config_file: self.config_file.clone(),
-        no_config: self.no_config,
-        import_map: self.import_map.clone(),
         frozen_lockfile: self.frozen_lockfile,
+        locked_dependency_versions: self.locked_dependency_versions,
+        npm_package_info_provider: None,
+        pin_npm_versions: false,
         cwd: self.loader.sys.env_current_dir().unwrap(),
       },
     )
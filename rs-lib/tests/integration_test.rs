@@ -1,14 +1,39 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
+use std::path::Path;
 use std::path::PathBuf;
 
+use deno_node_transform::ConfigPackage;
 use deno_node_transform::Dependency;
+use deno_node_transform::DependencyKind;
 use deno_node_transform::GlobalName;
+use deno_node_transform::LoaderChecksum;
+use deno_node_transform::LockedDependencyVersions;
+use deno_node_transform::MediaType;
+use deno_node_transform::ModifiedFile;
 use deno_node_transform::ModuleShim;
+use deno_node_transform::ModuleSpecifier;
+use deno_node_transform::OutputAsset;
+use deno_node_transform::OutputDirDiff;
+use deno_node_transform::OutputFileKind;
+use deno_node_transform::OutputGraphEdge;
+use deno_node_transform::OutputGraphEdgeKind;
+use deno_node_transform::OutputGraphEnvironment;
+use deno_node_transform::OutputGraphModule;
+use deno_node_transform::PackageManifest;
+use deno_node_transform::PackageManifestBin;
+use deno_node_transform::PackageManifestEntryPoint;
+use deno_node_transform::PackageManifestEntryPointKind;
+use deno_node_transform::PackageManifestExport;
+use deno_node_transform::PackageManifestImport;
+use deno_node_transform::PackageManifestOptions;
 use deno_node_transform::PackageMappedSpecifier;
 use deno_node_transform::PackageShim;
+use deno_node_transform::Platform;
 use deno_node_transform::ScriptTarget;
 use deno_node_transform::Shim;
+use deno_node_transform::SourceMaps;
+use deno_node_transform::SubpathImport;
 use deno_node_transform::TransformOutput;
 use pretty_assertions::assert_eq;
 
@@ -154,7 +179,7 @@ async fn transform_shim_custom_shims() {
         name: "node-fetch".to_string(),
         version: Some("~3.1.0".to_string()),
         sub_path: None,
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       },
       types_package: None,
       global_names: vec![GlobalName {
@@ -168,7 +193,7 @@ async fn transform_shim_custom_shims() {
         name: "node-fetch".to_string(),
         version: Some("~3.1.0".to_string()),
         sub_path: Some("test".to_string()),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       },
       types_package: None,
       global_names: vec![GlobalName {
@@ -182,12 +207,12 @@ async fn transform_shim_custom_shims() {
         name: "domexception".to_string(),
         version: Some("^4.0.0".to_string()),
         sub_path: None,
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       },
       types_package: Some(Dependency {
         name: "@types/domexception".to_string(),
         version: "^2.0.1".to_string(),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       }),
       global_names: vec![GlobalName {
         name: "DOMException".to_string(),
@@ -200,7 +225,7 @@ async fn transform_shim_custom_shims() {
         name: "buffer".to_string(),
         version: None,
         sub_path: None,
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       },
       types_package: None,
       global_names: vec![
@@ -221,7 +246,7 @@ async fn transform_shim_custom_shims() {
         name: "type-only".to_string(),
         version: None,
         sub_path: None,
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       },
       types_package: None,
       global_names: vec![GlobalName {
@@ -324,12 +349,12 @@ async fn transform_shim_custom_shims() {
       Dependency {
         name: "node-fetch".to_string(),
         version: "~3.1.0".to_string(),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       },
       Dependency {
         name: "domexception".to_string(),
         version: "^4.0.0".to_string(),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       }
     ]
   );
@@ -341,7 +366,7 @@ async fn transform_shim_custom_shims() {
     vec![Dependency {
       name: "@types/domexception".to_string(),
       version: "^2.0.1".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
 }
@@ -951,7 +976,7 @@ async fn transform_types_package_from_types_header_of_mapped_module() {
     &[Dependency {
       name: "svg-path-parser".to_string(),
       version: "1.1.0".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
   // the declaration files are provided by an npm package, so it's
@@ -961,7 +986,7 @@ async fn transform_types_package_from_types_header_of_mapped_module() {
     &[Dependency {
       name: "@types/svg-path-parser".to_string(),
       version: "~1.1.6".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
 }
@@ -1004,7 +1029,7 @@ export * from 'svg-path-parser';"
     &[Dependency {
       name: "@types/svg-path-parser".to_string(),
       version: "1.1.3".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
 }
@@ -1240,12 +1265,14 @@ async fn transform_deno_types_and_type_ref_for_different_remote_file() {
         "Duplicate declaration file found for http://localhost/file.js\n",
         "  Specified http://localhost/declarations.d.ts in http://localhost/mod.ts\n",
         "  Selected http://localhost/declarations3.d.ts\n",
+        "  Import chain: file:///mod.ts -> http://localhost/mod.ts -> http://localhost/file.js\n",
         "  Supress this warning by specifying a declaration file for this module locally via `@deno-types`.",
       ),
       concat!(
         "Duplicate declaration file found for http://localhost/file.js\n",
         "  Specified http://localhost/declarations2.d.ts in http://localhost/other.ts\n",
         "  Selected http://localhost/declarations3.d.ts\n",
+        "  Import chain: file:///mod.ts -> http://localhost/mod.ts -> http://localhost/file.js\n",
         "  Supress this warning by specifying a declaration file for this module locally via `@deno-types`.",
       ),
     ]
@@ -1368,12 +1395,12 @@ async fn transform_specifier_mappings() {
       Dependency {
         name: "mod".to_string(),
         version: "~0.1.0".to_string(),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       },
       Dependency {
         name: "remote-module".to_string(),
         version: "1.0.0".to_string(),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       }
     ]
   );
@@ -1442,12 +1469,12 @@ async fn transform_jsr_specifier_mappings() {
       Dependency {
         name: "scope-inherited".to_string(),
         version: "~2.0.0".to_string(),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       },
       Dependency {
         name: "scope-name".to_string(),
         version: "^1.0.0".to_string(),
-        peer_dependency: false,
+        kind: DependencyKind::Dependency,
       }
     ]
   );
@@ -1493,7 +1520,7 @@ async fn transform_jsr_specifier_mapping_different_version_reqs() {
     &[Dependency {
       name: "scope-name".to_string(),
       version: "^0.3.3".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
 }
@@ -1518,7 +1545,7 @@ async fn transform_jsr_specifier_mapping_different_version_reqs_in_test() {
     &[Dependency {
       name: "scope-name".to_string(),
       version: "^0.3.3".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
   // ...so the tests don't need a dev dependency of their own
@@ -1581,7 +1608,7 @@ async fn transform_jsr_specifier_mapping_via_config_file() {
     &[Dependency {
       name: "scope-name".to_string(),
       version: "^1.0.0".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
 }
@@ -1617,7 +1644,7 @@ async fn transform_jsr_specifier_mapping_via_import_map() {
     &[Dependency {
       name: "scope-name".to_string(),
       version: "^1.0.0".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
 }
@@ -1661,7 +1688,7 @@ async fn transform_specifier_mapping_of_bare_specifier() {
     &[Dependency {
       name: "npm-my-lib".to_string(),
       version: "^39.0.0".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
 }
@@ -1695,7 +1722,7 @@ async fn transform_specifier_mapping_of_bare_specifier_to_jsr() {
     &[Dependency {
       name: "scope-name".to_string(),
       version: "^1.0.0".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
 }
@@ -1852,11 +1879,146 @@ async fn transform_specifier_mapping_of_redirected_specifier() {
     &[Dependency {
       name: "remote-module".to_string(),
       version: "1.0.0".to_string(),
-      peer_dependency: false,
+      kind: DependencyKind::Dependency,
     }]
   );
 }
 
+#[tokio::test]
+async fn transform_npm_registry_dependencies() {
+  let mut builder = TestBuilder::new();
+  builder
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import 'https://localhost/a.ts';\n",
+            "import 'npm:@scope/b@latest';\n",
+          ),
+        )
+        .add_remote_file("https://localhost/a.ts", "")
+        .add_local_file(
+          "/npm/a/registry.json",
+          r#"{ "versions": { "1.0.0": {}, "1.1.0": {}, "2.0.0": {} } }"#,
+        )
+        .add_local_file(
+          "/npm/@scope/b/registry.json",
+          r#"{ "dist-tags": { "latest": "0.2.0" }, "versions": { "0.2.0": {} } }"#,
+        );
+    })
+    .add_package_specifier_mapping(
+      "https://localhost/a.ts",
+      "a",
+      Some("^1.0.0"),
+      None,
+    )
+    .set_npm_registry_dir("/npm");
+
+  let result = builder.transform().await.unwrap();
+  assert_eq!(
+    result.main.dependencies,
+    &[
+      Dependency {
+        name: "@scope/b".to_string(),
+        version: "latest".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "a".to_string(),
+        version: "^1.0.0".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+    ]
+  );
+
+  let result = builder
+    .set_pin_npm_versions(true)
+    .transform()
+    .await
+    .unwrap();
+  assert_eq!(
+    result.main.dependencies,
+    &[
+      Dependency {
+        name: "@scope/b".to_string(),
+        version: "0.2.0".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "a".to_string(),
+        version: "1.1.0".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+    ]
+  );
+}
+
+#[tokio::test]
+async fn transform_npm_registry_dependencies_not_found() {
+  let error_message = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import 'https://localhost/a.ts';\n",
+            "import 'https://localhost/b.ts';\n",
+          ),
+        )
+        .add_remote_file("https://localhost/a.ts", "")
+        .add_remote_file("https://localhost/b.ts", "")
+        .add_local_file(
+          "/npm/a/registry.json",
+          r#"{ "versions": { "1.0.0": {} } }"#,
+        );
+    })
+    .add_package_specifier_mapping(
+      "https://localhost/a.ts",
+      "a",
+      Some("^2.0.0"),
+      None,
+    )
+    .add_package_specifier_mapping(
+      "https://localhost/b.ts",
+      "bb",
+      Some("^1.0.0"),
+      None,
+    )
+    .set_npm_registry_dir("/npm")
+    .transform()
+    .await
+    .err()
+    .unwrap();
+
+  assert_eq!(
+    error_message.to_string(),
+    concat!(
+      "The following dependencies were not found in the npm registry:\n",
+      "  * a@^2.0.0 - no version satisfies the range\n",
+      "  * bb@^1.0.0 - package not found",
+    )
+  );
+}
+
+#[tokio::test]
+async fn transform_pin_npm_versions_no_provider() {
+  let error_message = TestBuilder::new()
+    .with_loader(|loader| {
+      loader.add_local_file("/mod.ts", "");
+    })
+    .set_pin_npm_versions(true)
+    .transform()
+    .await
+    .err()
+    .unwrap();
+
+  assert_eq!(
+    error_message.to_string(),
+    "pinning npm versions requires an npm package info provider"
+  );
+}
+
 #[tokio::test]
 async fn transform_not_found_mappings() {
   let error_message = TestBuilder::new()
@@ -1887,19 +2049,35 @@ async fn transform_not_found_mappings() {
 }
 
 #[tokio::test]
-async fn node_module_mapping() {
+async fn transform_prefix_specifier_mapping() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
         .add_local_file(
           "/mod.ts",
           concat!(
-            "import * as path from 'https://deno.land/std@0.181.0/node/path.ts';\n",
-            "import { performance } from 'https://deno.land/std@0.156.0/node/perf_hooks.ts';\n",
-            "import * as fs from 'https://deno.land/std/node/fs/promises.ts';",
+            "import * as fs from 'https://localhost/lib@1.0/fs/mod.ts';\n",
+            "import * as path from 'https://localhost/lib@1.0/path/mod.ts';\n",
+            "import * as hooks from 'https://localhost/lib@1.0/hooks.ts';\n",
           ),
-        );
+        )
+        .add_remote_file("https://localhost/lib@1.0/fs/mod.ts", "")
+        .add_remote_file("https://localhost/lib@1.0/path/mod.ts", "")
+        .add_remote_file("https://localhost/lib@1.0/hooks.ts", "");
     })
+    .add_package_specifier_mapping(
+      "https://localhost/lib@1.0/",
+      "lib",
+      Some("^1.0.0"),
+      Some("esm/*"),
+    )
+    // a mapping of a module under the prefix takes precedence
+    .add_package_specifier_mapping(
+      "https://localhost/lib@1.0/hooks.ts",
+      "lib",
+      Some("^1.0.0"),
+      Some("hooks"),
+    )
     .transform()
     .await
     .unwrap();
@@ -1909,104 +2087,64 @@ async fn node_module_mapping() {
     &[(
       "mod.ts",
       concat!(
-        "import * as path from 'path';\n",
-        "import { performance } from 'perf_hooks';\n",
-        "import * as fs from 'fs/promises';",
+        "import * as fs from 'lib/esm/fs/mod.ts';\n",
+        "import * as path from 'lib/esm/path/mod.ts';\n",
+        "import * as hooks from 'lib/hooks';\n",
       )
-    ),]
+    )]
+  );
+  assert_eq!(
+    result.main.dependencies,
+    &[Dependency {
+      name: "lib".to_string(),
+      version: "^1.0.0".to_string(),
+      kind: DependencyKind::Dependency,
+    }]
   );
 }
 
 #[tokio::test]
-async fn skypack_esm_module_mapping() {
-  let result = TestBuilder::new()
+async fn transform_prefix_specifier_mapping_not_found() {
+  let error_message = TestBuilder::new()
     .with_loader(|loader| {
       loader
         .add_local_file(
           "/mod.ts",
-          concat!(
-            "import package1 from 'https://cdn.skypack.dev/preact@^10.5.0';\n",
-            "import package2 from 'https://cdn.skypack.dev/@scope/package-name@1';\n",
-            "import package3 from 'https://esm.sh/react@17.0.2';\n",
-            // custom esm.sh stuff like this should download the dependency
-            "import package4 from 'https://esm.sh/swr?deps=react@16.14.0';\n",
-            "import package5 from 'https://esm.sh/test@1.2.5?deps=react@16.14.0';\n",
-            "import package6 from 'https://cdn.skypack.dev/preact@^10.5.0/hooks?dts';\n",
-            "import package7 from 'https://esm.sh/react-dom@17.0.2/server';\n",
-          ),
-        )
-        .add_remote_file_with_headers(
-          "https://esm.sh/swr?deps=react@16.14.0", "",
-          &[("content-type", "application/typescript")]
+          "import 'https://localhost/other@1.0/mod.ts';",
         )
-        .add_remote_file_with_headers(
-          "https://esm.sh/test@1.2.5?deps=react@16.14.0",
-          "",
-          &[("content-type", "application/typescript")]
-       );
+        .add_remote_file("https://localhost/other@1.0/mod.ts", "");
     })
+    .add_package_specifier_mapping(
+      "https://localhost/lib@1.0/",
+      "lib",
+      Some("^1.0.0"),
+      None,
+    )
     .transform()
     .await
+    .err()
     .unwrap();
 
-  assert_files!(
-    result.main.files,
-    &[
-      (
-        "mod.ts",
-        concat!(
-          "import package1 from 'preact';\n",
-          "import package2 from '@scope/package-name';\n",
-          "import package3 from 'react';\n",
-          "import package4 from './deps/esm.sh/swr.js';\n",
-          "import package5 from './deps/esm.sh/test@1.2.5.js';\n",
-          "import package6 from 'preact/hooks';\n",
-          "import package7 from 'react-dom/server';\n",
-        )
-      ),
-      ("deps/esm.sh/swr.ts", "",),
-      ("deps/esm.sh/test@1.2.5.ts", "",)
-    ]
-  );
+  // the prefix is found when any module under it is
   assert_eq!(
-    result.main.dependencies,
-    &[
-      Dependency {
-        name: "@scope/package-name".to_string(),
-        version: "1".to_string(),
-        peer_dependency: false,
-      },
-      Dependency {
-        name: "preact".to_string(),
-        version: "^10.5.0".to_string(),
-        peer_dependency: false,
-      },
-      Dependency {
-        name: "react".to_string(),
-        version: "17.0.2".to_string(),
-        peer_dependency: false,
-      },
-      Dependency {
-        name: "react-dom".to_string(),
-        version: "17.0.2".to_string(),
-        peer_dependency: false,
-      }
-    ]
+    error_message.to_string(),
+    concat!(
+      "The following specifiers were indicated to be mapped to a package, but were not found:\n",
+      "  * https://localhost/lib@1.0/",
+    )
   );
 }
 
 #[tokio::test]
-async fn skypack_module_mapping_different_versions() {
+async fn transform_prefix_specifier_mapping_to_module() {
   let error_message = TestBuilder::new()
     .with_loader(|loader| {
-      loader.add_local_file(
-        "/mod.ts",
-        concat!(
-          "import package1 from 'https://cdn.skypack.dev/preact@^10.5.0';\n",
-          "import package2 from 'https://cdn.skypack.dev/preact@^10.5.2';",
-        ),
-      );
+      loader.add_local_file("/mod.ts", "test");
     })
+    .add_module_specifier_mapping(
+      "https://localhost/lib@1.0/",
+      "file:///other/",
+    )
     .transform()
     .await
     .err()
@@ -2014,550 +2152,578 @@ async fn skypack_module_mapping_different_versions() {
 
   assert_eq!(
     error_message.to_string(),
-    "Specifier https://cdn.skypack.dev/preact@^10.5.0 with version ^10.5.0 did not match specifier https://cdn.skypack.dev/preact@^10.5.2 with version ^10.5.2."
+    "The mapping \"https://localhost/lib@1.0/\" ends with a slash, so it must map to a package rather than a module"
   );
 }
 
 #[tokio::test]
-async fn esm_module_with_deno_types() {
+async fn transform_entry_points_from_config() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
         .add_local_file(
-          "/mod.ts",
-          concat!(
-            "// @deno-types=\"https://localhost/mod.d.ts\"\n",
-            "import {test} from 'https://esm.sh/test@0.0.1/lib/mod.js';\n",
-          ),
-        )
-        .add_remote_file_with_headers(
-          "https://esm.sh/test@0.0.1/lib/mod.js",
-          "export function test() {return 5;}",
-          &[("content-type", "application/typescript")],
+          "/deno.json",
+          r#"{
+  "name": "@scope/pkg",
+  "version": "1.0.0",
+  "exports": { ".": "./mod.ts", "./sub": "./sub/mod.ts" }
+}"#,
         )
-        .add_remote_file_with_headers(
-          "https://localhost/mod.d.ts",
-          "declare function test(): number;",
-          &[("content-type", "application/typescript")],
-        );
+        .add_local_file("/mod.ts", "export * from './sub/mod.ts';")
+        .add_local_file("/sub/mod.ts", "export const sub = 1;")
+        .add_local_file("/cli.ts", "import './mod.ts';");
     })
+    .set_entry_points_from_config()
+    .add_bin_entry_point("file:///cli.ts")
     .transform()
     .await
     .unwrap();
 
-  assert_files!(
-    result.main.files,
+  assert_eq!(
+    result.main.entry_points,
     &[
-      // this is a bug... it should create a proxy here instead,
-      // but will wait for someone to open this as it's probably
-      // rare for this to occur in the wild
-      ("mod.ts", "\nimport {test} from 'test/lib/mod.js';\n"),
-      (
-        "deps/localhost/mod.d.ts",
-        "declare function test(): number;",
-      )
+      PathBuf::from("mod.ts"),
+      PathBuf::from("sub/mod.ts"),
+      PathBuf::from("cli.ts"),
     ]
   );
+  assert_eq!(
+    result.config_package,
+    Some(ConfigPackage {
+      name: Some("@scope/pkg".to_string()),
+      version: Some("1.0.0".to_string()),
+      exports: vec![".".to_string(), "./sub".to_string()],
+    })
+  );
 }
 
 #[tokio::test]
-async fn transform_import_map() {
+async fn transform_entry_points_from_config_string_exports() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file(
-          "/mod.ts",
-          "import * as remote from 'localhost/mod.ts';",
-        )
-        .add_local_file(
-          "/import_map.json",
-          r#"{
-  // test comments
-  "imports": {
-    "localhost/": "/subdir/"
-  }
-}"#,
-        )
-        .add_local_file(
-          "/subdir/mod.ts",
-          "import * as myOther from './other.ts';",
-        )
-        .add_local_file("/subdir/other.ts", "export function test() {}");
+        .add_local_file("/deno.json", r#"{ "exports": "./mod.ts" }"#)
+        .add_local_file("/mod.ts", "export const value = 1;");
     })
-    .set_import_map("file:///import_map.json")
+    .set_entry_points_from_config()
+    .add_entry_point("file:///mod.ts")
     .transform()
     .await
     .unwrap();
 
-  // the import map is used as the config file, so nothing was auto-discovered
-  assert_eq!(result.discovered_config_file, None);
-  assert_files!(
-    result.main.files,
-    &[
-      ("mod.ts", "import * as remote from './subdir/mod.js';",),
-      ("subdir/mod.ts", "import * as myOther from './other.js';",),
-      ("subdir/other.ts", "export function test() {}",)
-    ]
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
+  assert_eq!(
+    result.config_package,
+    Some(ConfigPackage {
+      name: None,
+      version: None,
+      exports: vec![".".to_string()],
+    })
   );
 }
 
 #[tokio::test]
-async fn transform_import_map_uneven_sibling_depths() {
-  let result = TestBuilder::new()
-    .entry_point("file:///project-one/mod.ts")
+async fn transform_entry_points_from_config_mismatch() {
+  let error_message = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file(
-          "/project-one/mod.ts",
-          "import value from 'project-two/deeper/mod.ts';",
-        )
-        .add_local_file(
-          "/project-one/deno.json",
-          r#"{
-  "imports": {
-    "project-two/": "../project-two/"
-  }
-}"#,
-        )
-        .add_local_file(
-          "/project-two/deeper/mod.ts",
-          "export default 'hello world';",
-        );
+        .add_local_file("/deno.json", r#"{ "exports": "./mod.ts" }"#)
+        .add_local_file("/mod.ts", "export const value = 1;")
+        .add_local_file("/other.ts", "export const other = 1;");
     })
-    .set_import_map("file:///project-one/deno.json")
+    .set_entry_points_from_config()
+    .add_entry_point("file:///other.ts")
     .transform()
     .await
+    .err()
     .unwrap();
 
-  // the sibling is at a different depth, so the shared root moves up to
-  // encompass both projects, the same as it already does for siblings at
-  // equal depths
-  assert_files!(
-    result.main.files,
-    &[
-      (
-        "project-one/mod.ts",
-        "import value from '../project-two/deeper/mod.js';",
-      ),
-      ("project-two/deeper/mod.ts", "export default 'hello world';",),
-    ]
-  );
   assert_eq!(
-    result.main.entry_points,
-    &[PathBuf::from("project-one/mod.ts")]
+    error_message.to_string(),
+    concat!(
+      "The entry points don't match the exports of file:///deno.json:\n",
+      "  * file:///other.ts is an entry point, but not an export\n",
+      "  * file:///mod.ts is the \".\" export, but not an entry point",
+    )
   );
 }
 
 #[tokio::test]
-async fn transform_import_map_local_sibling_and_remote() {
+async fn transform_subpath_imports() {
   let result = TestBuilder::new()
-    .entry_point("file:///example-project/mod.ts")
     .with_loader(|loader| {
       loader
         .add_local_file(
-          "/example-project/mod.ts",
-          "export * from './src/mod.ts';",
-        )
-        .add_local_file(
-          "/example-project/src/mod.ts",
-          "export * from 'example-shared/mod.ts';\nexport * from 'https://example.com/mod.ts';",
-        )
-        .add_local_file(
-          "/example-project/deno.json",
-          r#"{
+          "/package.json",
+          r##"{
   "imports": {
-    "example-shared/": "../example-shared/"
+    "#internal/*": "./internal/*.ts",
+    "#util": "./util.ts",
+    "#unused": "./unused.ts"
   }
-}"#,
+}"##,
         )
-        .add_local_file("/example-shared/mod.ts", "export const shared = 'shared';")
-        .add_remote_file("https://example.com/mod.ts", "export const remote = 'remote';");
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import { a } from '#internal/a';\n",
+            "import { b } from '#internal/nested/b';\n",
+            "import { u } from './util.ts';\n",
+            "export * from '#util';\n",
+            "console.log(a, b, u);\n",
+          ),
+        )
+        .add_local_file("/internal/a.ts", "export const a = 1;")
+        .add_local_file("/internal/nested/b.ts", "export const b = 1;")
+        .add_local_file("/util.ts", "export const u = 1;");
+    })
+    .set_package_manifest(PackageManifestOptions {
+      es_module_dir: Some("./esm".to_string()),
+      script_module_dir: Some("./script".to_string()),
+      types_dir: Some("./types".to_string()),
+      ..Default::default()
     })
-    .set_import_map("file:///example-project/deno.json")
     .transform()
     .await
     .unwrap();
 
-  // the local sibling shares a root with the project, while the remote module
-  // keeps going to `deps`
   assert_files!(
     result.main.files,
     &[
       (
-        "example-project/mod.ts",
-        "export * from './src/mod.js';",
-      ),
-      (
-        "example-project/src/mod.ts",
-        "export * from '../../example-shared/mod.js';\nexport * from '../../deps/example.com/mod.js';",
-      ),
-      (
-        "example-shared/mod.ts",
-        "export const shared = 'shared';",
-      ),
-      (
-        "deps/example.com/mod.ts",
-        "export const remote = 'remote';",
+        "mod.ts",
+        concat!(
+          "import { a } from '#internal/a';\n",
+          "import { b } from '#internal/nested/b';\n",
+          "import { u } from './util.js';\n",
+          "export * from '#util';\n",
+          "console.log(a, b, u);\n",
+        )
       ),
+      ("internal/a.ts", "export const a = 1;"),
+      ("internal/nested/b.ts", "export const b = 1;"),
+      ("util.ts", "export const u = 1;"),
     ]
   );
   assert_eq!(
-    result.main.entry_points,
-    &[PathBuf::from("example-project/mod.ts")]
+    result.subpath_imports,
+    vec![
+      SubpathImport {
+        name: "#internal/*".to_string(),
+        target: "./internal/*.ts".to_string(),
+      },
+      SubpathImport {
+        name: "#util".to_string(),
+        target: "./util.ts".to_string(),
+      },
+    ]
+  );
+  assert_eq!(
+    result.package_manifest.imports,
+    vec![
+      PackageManifestImport {
+        name: "#internal/*".to_string(),
+        target: "./internal/*.js".to_string(),
+        types: Some("./types/internal/*.d.ts".to_string()),
+      },
+      PackageManifestImport {
+        name: "#util".to_string(),
+        target: "./util.js".to_string(),
+        types: Some("./types/util.d.ts".to_string()),
+      },
+    ]
   );
 }
 
 #[tokio::test]
-async fn transform_specifier_not_in_import_map() {
-  let err_message = TestBuilder::new()
+async fn transform_subpath_imports_wildcard_extension() {
+  let error_message = TestBuilder::new()
     .with_loader(|loader| {
       loader
         .add_local_file(
-          "/mod.ts",
-          "import a from 'other';\nimport b from 'other/sub.ts';",
-        )
-        .add_local_file(
-          "/import_map.json",
-          r#"{
-  "imports": {
-    "other": "./other/mod.ts"
-  }
-}"#,
+          "/package.json",
+          r##"{ "imports": { "#internal/*": "./internal/*" } }"##,
         )
-        .add_local_file("/other/mod.ts", "export default 1;")
-        .add_local_file("/other/sub.ts", "export default 2;");
+        .add_local_file("/mod.ts", "import '#internal/a.ts';")
+        .add_local_file("/internal/a.ts", "export const a = 1;");
     })
-    .set_import_map("file:///import_map.json")
     .transform()
     .await
     .err()
     .unwrap();
 
-  // the sub path is not mapped, so surface it rather than emitting the
-  // bare specifier into the output as-is
   assert_eq!(
-    err_message.to_string(),
-    normalize_urls(
-      concat!(
-        "Import \"other/sub.ts\" not a dependency and not in import map from \"file:///mod.ts\"",
-        "\n    at file:///mod.ts:2:15",
-      )
+    error_message.to_string(),
+    concat!(
+      "The subpath import \"#internal/*\" can't be kept in the output because ",
+      "the modules it imports don't share a path with a `*` in the output. ",
+      "Include the file extension after the `*` or import the modules with ",
+      "relative specifiers instead.",
     )
   );
 }
 
 #[tokio::test]
-async fn transform_entry_point_with_sibling_dir_in_project() {
+async fn transform_package_manifest() {
   let result = TestBuilder::new()
-    .entry_point("file:///project/src/mod.ts")
     .with_loader(|loader| {
       loader
         .add_local_file(
-          "/project/src/mod.ts",
-          "export * from '../lib/util.ts';",
+          "/deno.json",
+          r#"{ "exports": { ".": "./mod.ts", "./sub": "./sub/mod.ts" } }"#,
         )
-        .add_local_file("/project/lib/util.ts", "export const util = 1;");
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import * as pkg from 'npm:using-statement@^0.4';\n",
+            "export * from './sub/mod.ts';\n",
+            "console.log(pkg);\n",
+          ),
+        )
+        .add_local_file("/sub/mod.ts", "export const sub = 1;")
+        .add_local_file("/cli.ts", "import './mod.ts';");
+    })
+    .set_entry_points_from_config()
+    .add_bin_entry_point("file:///cli.ts")
+    .set_package_manifest(PackageManifestOptions {
+      entry_points: vec![PackageManifestEntryPoint {
+        kind: PackageManifestEntryPointKind::Bin,
+        name: Some("cmd".to_string()),
+        specifier: ModuleSpecifier::parse("file:///cli.ts").unwrap(),
+      }],
+      es_module_dir: Some("./esm".to_string()),
+      script_module_dir: Some("./script".to_string()),
+      types_dir: None,
+      specified_dependencies: Vec::new(),
     })
     .transform()
     .await
     .unwrap();
 
-  // a sibling directory of the entry point is still part of the project, so
-  // it must not be treated as a dependency
-  assert_files!(
-    result.main.files,
-    &[
-      ("src/mod.ts", "export * from '../lib/util.js';"),
-      ("lib/util.ts", "export const util = 1;"),
-    ]
+  assert_eq!(
+    result.package_manifest,
+    PackageManifest {
+      main: Some("./script/mod.js".to_string()),
+      module: Some("./esm/mod.js".to_string()),
+      types: None,
+      browser: None,
+      exports: vec![
+        PackageManifestExport {
+          name: ".".to_string(),
+          types: None,
+          browser: None,
+          import: Some("./esm/mod.js".to_string()),
+          require: Some("./script/mod.js".to_string()),
+        },
+        PackageManifestExport {
+          name: "./sub".to_string(),
+          types: None,
+          browser: None,
+          import: Some("./esm/sub/mod.js".to_string()),
+          require: Some("./script/sub/mod.js".to_string()),
+        },
+      ],
+      imports: Vec::new(),
+      bin: vec![PackageManifestBin {
+        name: Some("cmd".to_string()),
+        path: "./esm/cli.js".to_string(),
+      }],
+      dependencies: vec![Dependency {
+        name: "using-statement".to_string(),
+        version: "^0.4".to_string(),
+        kind: DependencyKind::Dependency,
+      }],
+    }
   );
-  assert_eq!(result.main.entry_points, &[PathBuf::from("src/mod.ts")]);
 }
 
 #[tokio::test]
-async fn transform_test_entry_point_uses_test_root_without_moving_main() {
-  let result = TestBuilder::new()
-    .entry_point("file:///example-project/src/mod.ts")
+async fn transform_package_manifest_unknown_entry_point() {
+  let error_message = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file(
-          "/example-project/src/mod.ts",
-          "export const value = 'value';",
-        )
-        .add_local_file(
-          "/example-project/tests/integration/functions/mod.test.ts",
-          "import '../../../src/mod.ts';",
-        );
+        .add_local_file("/mod.ts", "export const value = 1;")
+        .add_local_file("/other.ts", "export const other = 1;");
+    })
+    .set_package_manifest(PackageManifestOptions {
+      entry_points: vec![PackageManifestEntryPoint {
+        kind: PackageManifestEntryPointKind::Export,
+        name: Some("./other".to_string()),
+        specifier: ModuleSpecifier::parse("file:///other.ts").unwrap(),
+      }],
+      ..Default::default()
     })
-    .add_test_entry_point(
-      "file:///example-project/tests/integration/functions/mod.test.ts",
-    )
     .transform()
     .await
+    .err()
     .unwrap();
 
-  // the tests are in a directory outside the main files' root, which must not
-  // push the main files down a directory
-  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
   assert_eq!(
-    result.test.entry_points,
-    &[PathBuf::from("tests/integration/functions/mod.test.ts")]
-  );
-  assert_files!(
-    result.test.files,
-    &[(
-      "tests/integration/functions/mod.test.ts",
-      "import '../../../mod.js';",
-    )]
+    error_message.to_string(),
+    "The package manifest entry point file:///other.ts is not one of the entry points"
   );
 }
 
 #[tokio::test]
-async fn transform_config_file() {
-  let result = TestBuilder::new()
+async fn transform_workspace_members() {
+  let mut result = TestBuilder::new()
     .with_loader(|loader| {
-      loader
-        .add_local_file(
-          "/mod.ts",
-          "import * as remote from 'localhost/mod.ts';",
-        )
+      add_workspace_files(loader)
         .add_local_file(
-          "/deno.json",
-          r#"{
-  // test comments
-  "imports": {
-    "localhost/": "/subdir/"
-  }
-}"#,
-        )
-        .add_local_file(
-          "/subdir/mod.ts",
-          "import * as myOther from './other.ts';",
+          "/b/mod.ts",
+          concat!(
+            "import { a } from '@scope/a';\n",
+            "import { sub } from '@scope/a/sub';\n",
+            "import { shared } from './shared.ts';\n",
+            "export const b = a + sub + shared;\n",
+          ),
         )
-        .add_local_file("/subdir/other.ts", "export function test() {}");
+        .add_local_file("/b/shared.ts", "export const shared = 1;");
     })
-    .set_config_file("file:///deno.json")
-    .transform()
+    .transform_workspace()
     .await
     .unwrap();
 
-  // it was provided, so it wasn't auto-discovered
-  assert_eq!(result.discovered_config_file, None);
+  assert_eq!(result.len(), 2);
+  let b = result.pop().unwrap();
+  let a = result.pop().unwrap();
+  assert_eq!(a.name, "@scope/a");
+  assert_eq!(a.version, "1.2.0");
+  assert_eq!(a.exports, vec![".".to_string(), "./sub".to_string()]);
   assert_files!(
-    result.main.files,
+    a.output.main.files,
     &[
-      ("mod.ts", "import * as remote from './subdir/mod.js';",),
-      ("subdir/mod.ts", "import * as myOther from './other.js';",),
-      ("subdir/other.ts", "export function test() {}",)
+      ("mod.ts", "export const a = 1;"),
+      ("sub.ts", "export const sub = 2;"),
     ]
   );
-}
-
-#[tokio::test]
-async fn transform_auto_discovered_config_file() {
-  let result = TestBuilder::new()
-    .with_loader(|loader| {
-      add_config_discovery_files(loader);
-    })
-    .entry_point("file:///sub_dir/mod.ts")
-    .transform()
-    .await
-    .unwrap();
+  assert!(a.output.main.dependencies.is_empty());
 
-  assert_eq!(
-    result.discovered_config_file,
-    Some(PathBuf::from(if cfg!(windows) {
-      "C:\\deno.json"
-    } else {
-      "/deno.json"
-    }))
-  );
+  // the other member is imported from its package rather than copied
+  assert_eq!(b.name, "@scope/b");
+  assert_eq!(b.exports, vec![".".to_string()]);
   assert_files!(
-    result.main.files,
+    b.output.main.files,
     &[
-      ("sub_dir/mod.ts", "import * as remote from '../other.js';",),
-      ("other.ts", "export function test() {}",)
+      (
+        "mod.ts",
+        concat!(
+          "import { a } from '@scope/a';\n",
+          "import { sub } from '@scope/a/sub';\n",
+          "import { shared } from './shared.js';\n",
+          "export const b = a + sub + shared;\n",
+        )
+      ),
+      ("shared.ts", "export const shared = 1;"),
     ]
   );
+  assert_eq!(
+    b.output.main.dependencies,
+    &[Dependency {
+      name: "@scope/a".to_string(),
+      version: "1.2.0".to_string(),
+      kind: DependencyKind::Dependency,
+    }]
+  );
 }
 
 #[tokio::test]
-async fn transform_no_config() {
-  let err_message = TestBuilder::new()
+async fn transform_workspace_member_non_export_import() {
+  let error_message = TestBuilder::new()
     .with_loader(|loader| {
-      add_config_discovery_files(loader);
+      add_workspace_files(loader)
+        .add_local_file("/a/internal.ts", "export const internal = 3;")
+        .add_local_file("/b/mod.ts", "import '../a/internal.ts';");
     })
-    .entry_point("file:///sub_dir/mod.ts")
-    .set_no_config(true)
-    .transform()
+    .transform_workspace()
     .await
     .err()
     .unwrap();
 
-  // the config file should not have been discovered, so its
-  // import mapping should not have been applied
   assert_eq!(
-    err_message.to_string(),
-    normalize_urls(
-      "Import \"localhost/mod.ts\" not a dependency
-    at file:///sub_dir/mod.ts:1:25"
+    format!("{error_message:#}"),
+    concat!(
+      "Failed transforming workspace member @scope/b: ",
+      "@scope/b imports file:///a/internal.ts, which is not one of the ",
+      "exports of the workspace member @scope/a",
     )
   );
 }
 
+fn add_workspace_files(loader: &mut InMemoryLoader) -> &mut InMemoryLoader {
+  loader
+    .add_local_file("/deno.json", r#"{ "workspace": ["./a", "./b"] }"#)
+    .add_local_file(
+      "/a/deno.json",
+      r#"{
+  "name": "@scope/a",
+  "version": "1.2.0",
+  "exports": { ".": "./mod.ts", "./sub": "./sub.ts" }
+}"#,
+    )
+    .add_local_file("/a/mod.ts", "export const a = 1;")
+    .add_local_file("/a/sub.ts", "export const sub = 2;")
+    .add_local_file(
+      "/b/deno.json",
+      r#"{ "name": "@scope/b", "version": "0.1.0", "exports": "./mod.ts" }"#,
+    )
+}
+
 #[tokio::test]
-async fn transform_multiple_entry_points() {
+async fn node_module_mapping() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file("/mod.ts", "import './ref.ts';mod1;")
-        .add_local_file("/mod2.ts", "import './ref.ts';mod2;")
-        .add_local_file("/ref.ts", "export const test = 5;");
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import * as path from 'https://deno.land/std@0.181.0/node/path.ts';\n",
+            "import { performance } from 'https://deno.land/std@0.156.0/node/perf_hooks.ts';\n",
+            "import * as fs from 'https://deno.land/std/node/fs/promises.ts';",
+          ),
+        );
     })
-    .add_entry_point("file:///mod2.ts")
     .transform()
     .await
     .unwrap();
 
   assert_files!(
     result.main.files,
-    &[
-      ("mod.ts", "import './ref.js';mod1;"),
-      ("mod2.ts", "import './ref.js';mod2;"),
-      ("ref.ts", "export const test = 5;"),
-    ]
+    &[(
+      "mod.ts",
+      concat!(
+        "import * as path from 'path';\n",
+        "import { performance } from 'perf_hooks';\n",
+        "import * as fs from 'fs/promises';",
+      )
+    ),]
   );
 }
 
 #[tokio::test]
-async fn test_entry_points() {
+async fn skypack_esm_module_mapping() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
         .add_local_file(
           "/mod.ts",
-          "import package1 from 'https://cdn.skypack.dev/preact@^10.5.0';\n",
-        )
-        .add_local_file(
-          "/mod.test.ts",
           concat!(
-            "import './mod.ts';\n",
             "import package1 from 'https://cdn.skypack.dev/preact@^10.5.0';\n",
+            "import package2 from 'https://cdn.skypack.dev/@scope/package-name@1';\n",
             "import package3 from 'https://esm.sh/react@17.0.2';\n",
-            "Deno.writeTextFile('test', 'test')",
+            // custom esm.sh stuff like this should download the dependency
+            "import package4 from 'https://esm.sh/swr?deps=react@16.14.0';\n",
+            "import package5 from 'https://esm.sh/test@1.2.5?deps=react@16.14.0';\n",
+            "import package6 from 'https://cdn.skypack.dev/preact@^10.5.0/hooks?dts';\n",
+            "import package7 from 'https://esm.sh/react-dom@17.0.2/server';\n",
           ),
-        );
+        )
+        .add_remote_file_with_headers(
+          "https://esm.sh/swr?deps=react@16.14.0", "",
+          &[("content-type", "application/typescript")]
+        )
+        .add_remote_file_with_headers(
+          "https://esm.sh/test@1.2.5?deps=react@16.14.0",
+          "",
+          &[("content-type", "application/typescript")]
+       );
     })
-    .add_test_entry_point("file:///mod.test.ts")
-    .add_default_shims()
     .transform()
     .await
     .unwrap();
 
   assert_files!(
     result.main.files,
-    &[("mod.ts", "import package1 from 'preact';\n",)]
-  );
-  assert_eq!(
-    result.main.dependencies,
-    &[Dependency {
-      name: "preact".to_string(),
-      version: "^10.5.0".to_string(),
-      peer_dependency: false,
-    },]
-  );
-  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
-
-  assert_files!(
-    result.test.files,
     &[
       (
-        "mod.test.ts",
+        "mod.ts",
         concat!(
-          "import * as dntShim from \"./_dnt.test_shims.js\";\n",
-          "import './mod.js';\n",
           "import package1 from 'preact';\n",
+          "import package2 from '@scope/package-name';\n",
           "import package3 from 'react';\n",
-          "dntShim.Deno.writeTextFile('test', 'test')"
+          "import package4 from './deps/esm.sh/swr.js';\n",
+          "import package5 from './deps/esm.sh/test@1.2.5.js';\n",
+          "import package6 from 'preact/hooks';\n",
+          "import package7 from 'react-dom/server';\n",
         )
-        .to_string(),
       ),
-      (
-        "_dnt.test_shims.ts",
-        get_shim_file_text(
-          concat!(
-            "import { Deno } from \"@deno/shim-deno\";\n",
-            "export { Deno } from \"@deno/shim-deno\";\n",
-            "import { setTimeout, setInterval } from \"@deno/shim-timers\";\n",
-            "export { setTimeout, setInterval } from \"@deno/shim-timers\";\n",
-            "\n",
-            "const dntGlobals = {\n",
-            "  Deno,\n",
-            "  setTimeout,\n",
-            "  setInterval,\n",
-            "};\n",
-            "export const dntGlobalThis = createMergeProxy(globalThis, dntGlobals);\n",
-          )
-          .to_string(),
-        ),
-      )
+      ("deps/esm.sh/swr.ts", "",),
+      ("deps/esm.sh/test@1.2.5.ts", "",)
     ]
   );
   assert_eq!(
-    result.test.dependencies,
+    result.main.dependencies,
     &[
       Dependency {
-        name: "react".to_string(),
-        version: "17.0.2".to_string(),
-        peer_dependency: false,
+        name: "@scope/package-name".to_string(),
+        version: "1".to_string(),
+        kind: DependencyKind::Dependency,
       },
       Dependency {
-        name: "@deno/shim-deno".to_string(),
-        version: "^0.1.0".to_string(),
-        peer_dependency: false,
+        name: "preact".to_string(),
+        version: "^10.5.0".to_string(),
+        kind: DependencyKind::Dependency,
       },
       Dependency {
-        name: "@deno/shim-timers".to_string(),
-        version: "^0.1.0".to_string(),
-        peer_dependency: false,
+        name: "react".to_string(),
+        version: "17.0.2".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "react-dom".to_string(),
+        version: "17.0.2".to_string(),
+        kind: DependencyKind::Dependency,
       }
     ]
   );
-  assert_eq!(result.test.entry_points, &[PathBuf::from("mod.test.ts")]);
 }
 
 #[tokio::test]
-async fn test_entry_points_same_module_multiple_places() {
+async fn skypack_module_mapping_different_versions() {
+  let error_message = TestBuilder::new()
+    .with_loader(|loader| {
+      loader.add_local_file(
+        "/mod.ts",
+        concat!(
+          "import package1 from 'https://cdn.skypack.dev/preact@^10.5.0';\n",
+          "import package2 from 'https://cdn.skypack.dev/preact@^10.5.2';",
+        ),
+      );
+    })
+    .transform()
+    .await
+    .err()
+    .unwrap();
+
+  assert_eq!(
+    error_message.to_string(),
+    "Specifier https://cdn.skypack.dev/preact@^10.5.0 with version ^10.5.0 did not match specifier https://cdn.skypack.dev/preact@^10.5.2 with version ^10.5.2."
+  );
+}
+
+#[tokio::test]
+async fn esm_module_with_deno_types() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
         .add_local_file(
           "/mod.ts",
           concat!(
-            "export * from 'https://deno.land/std@0.181.0/path.ts';\n",
-            "import * as deps from './deps.ts';",
-          ),
-        )
-        // ensure that the path.ts in this file being already analyzed
-        // doesn't cause flags.ts to not be analyzed
-        .add_local_file(
-          "/deps.ts",
-          concat!(
-            "export * from 'https://deno.land/std@0.181.0/path.ts';\n",
-            "export * from 'https://deno.land/std@0.181.0/flags.ts';",
+            "// @deno-types=\"https://localhost/mod.d.ts\"\n",
+            "import {test} from 'https://esm.sh/test@0.0.1/lib/mod.js';\n",
           ),
         )
-        .add_remote_file(
-          "https://deno.land/std@0.181.0/flags.ts",
-          "export class Flags {}",
-        )
-        .add_remote_file(
-          "https://deno.land/std@0.181.0/path.ts",
-          "export class Path {}",
+        .add_remote_file_with_headers(
+          "https://esm.sh/test@0.0.1/lib/mod.js",
+          "export function test() {return 5;}",
+          &[("content-type", "application/typescript")],
         )
-        .add_local_file("/mod.test.ts", "import * as deps from './deps.ts';");
+        .add_remote_file_with_headers(
+          "https://localhost/mod.d.ts",
+          "declare function test(): number;",
+          &[("content-type", "application/typescript")],
+        );
     })
-    .add_test_entry_point("file:///mod.test.ts")
     .transform()
     .await
     .unwrap();
@@ -2565,408 +2731,373 @@ async fn test_entry_points_same_module_multiple_places() {
   assert_files!(
     result.main.files,
     &[
+      // this is a bug... it should create a proxy here instead,
+      // but will wait for someone to open this as it's probably
+      // rare for this to occur in the wild
+      ("mod.ts", "\nimport {test} from 'test/lib/mod.js';\n"),
       (
-        "mod.ts",
-        concat!(
-          "export * from './deps/deno.land/std@0.181.0/path.js';\n",
-          "import * as deps from './deps.js';",
-        )
-      ),
-      (
-        "deps.ts",
-        concat!(
-          "export * from './deps/deno.land/std@0.181.0/path.js';\n",
-          "export * from './deps/deno.land/std@0.181.0/flags.js';",
-        )
-      ),
-      (
-        "deps/deno.land/std@0.181.0/flags.ts",
-        "export class Flags {}"
-      ),
-      ("deps/deno.land/std@0.181.0/path.ts", "export class Path {}")
+        "deps/localhost/mod.d.ts",
+        "declare function test(): number;",
+      )
     ]
   );
-  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
-
-  assert_files!(
-    result.test.files,
-    &[("mod.test.ts", "import * as deps from './deps.js';",)]
-  );
-  assert_eq!(result.test.entry_points, &[PathBuf::from("mod.test.ts")]);
 }
 
 #[tokio::test]
-async fn polyfills_all() {
+async fn transform_import_map() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
         .add_local_file(
           "/mod.ts",
-          concat!(
-            "export const test = (obj) => Object.hasOwn(obj, 'test');\n",
-            "try {\n",
-            "} catch (err) {\n",
-            "  err.cause = new Error();\n",
-            "}\n",
-            "''.replaceAll('test', 'other');\n",
-            "[].findLast(() => true);\n",
-            "import.meta.main;\n",
-          ),
+          "import * as remote from 'localhost/mod.ts';",
         )
-        .add_local_file("/mod.test.ts", "import * as mod from './mod.ts';");
+        .add_local_file(
+          "/import_map.json",
+          r#"{
+  // test comments
+  "imports": {
+    "localhost/": "/subdir/"
+  }
+}"#,
+        )
+        .add_local_file(
+          "/subdir/mod.ts",
+          "import * as myOther from './other.ts';",
+        )
+        .add_local_file("/subdir/other.ts", "export function test() {}");
     })
-    .add_test_entry_point("file:///mod.test.ts")
+    .set_import_map("file:///import_map.json")
     .transform()
     .await
     .unwrap();
 
+  // the import map is used as the config file, so nothing was auto-discovered
+  assert_eq!(result.discovered_config_file, None);
   assert_files!(
     result.main.files,
     &[
-      (
-        "mod.ts",
-        concat!(
-          "import \"./_dnt.polyfills.js\";\n",
-          "export const test = (obj) => Object.hasOwn(obj, 'test');\n",
-          "try {\n",
-          "} catch (err) {\n",
-          "  err.cause = new Error();\n",
-          "}\n",
-          "''.replaceAll('test', 'other');\n",
-          "[].findLast(() => true);\n",
-          "import.meta.main;\n",
-        ),
-      ),
-      (
-        "_dnt.polyfills.ts",
-        concat!(
-          include_str!("../src/polyfills/scripts/esnext.object-has-own.ts"),
-          include_str!("../src/polyfills/scripts/esnext.error-cause.ts"),
-          include_str!("../src/polyfills/scripts/es2021.string-replaceAll.ts"),
-          include_str!("../src/polyfills/scripts/esnext.array-findLast.ts"),
-          include_str!("../src/polyfills/scripts/deno.import-meta.ts"),
-        )
-      ),
+      ("mod.ts", "import * as remote from './subdir/mod.js';",),
+      ("subdir/mod.ts", "import * as myOther from './other.js';",),
+      ("subdir/other.ts", "export function test() {}",)
     ]
   );
-  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
-
-  assert_files!(
-    result.test.files,
-    &[("mod.test.ts", concat!("import * as mod from './mod.js';",),)]
-  );
-  assert_eq!(result.test.entry_points, &[PathBuf::from("mod.test.ts")]);
 }
 
 #[tokio::test]
-async fn polyfills_string_replaceall_target() {
-  test_string_replace_all_polyfill(ScriptTarget::ES2020, true).await;
-  test_string_replace_all_polyfill(ScriptTarget::ES2021, false).await;
-}
-
-async fn test_string_replace_all_polyfill(
-  target: ScriptTarget,
-  should_have_polyfill: bool,
-) {
+async fn transform_import_map_uneven_sibling_depths() {
   let result = TestBuilder::new()
+    .entry_point("file:///project-one/mod.ts")
     .with_loader(|loader| {
       loader
-        .add_local_file("/mod.ts", "''.replaceAll('test', 'other');\n")
-        .add_local_file("/mod.test.ts", "import * as mod from './mod.ts';");
+        .add_local_file(
+          "/project-one/mod.ts",
+          "import value from 'project-two/deeper/mod.ts';",
+        )
+        .add_local_file(
+          "/project-one/deno.json",
+          r#"{
+  "imports": {
+    "project-two/": "../project-two/"
+  }
+}"#,
+        )
+        .add_local_file(
+          "/project-two/deeper/mod.ts",
+          "export default 'hello world';",
+        );
     })
-    .add_test_entry_point("file:///mod.test.ts")
-    .set_target(target)
+    .set_import_map("file:///project-one/deno.json")
     .transform()
     .await
     .unwrap();
 
-  if should_have_polyfill {
-    assert_files!(
-      result.main.files,
-      &[
-        (
-          "mod.ts",
-          concat!(
-            "import \"./_dnt.polyfills.js\";\n",
-            "''.replaceAll('test', 'other');\n",
-          ),
-        ),
-        (
-          "_dnt.polyfills.ts",
-          concat!(include_str!(
-            "../src/polyfills/scripts/es2021.string-replaceAll.ts"
-          ),)
-        ),
-      ]
-    );
-  } else {
-    assert_files!(
-      result.main.files,
-      &[("mod.ts", "''.replaceAll('test', 'other');\n",)]
-    );
-  }
-  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
-}
-
-#[tokio::test]
-async fn polyfills_override_disables_for_target() {
-  // ES2020 would normally get the polyfill, but the override wins
-  let result = build_string_replace_all_polyfill_test(
-    ScriptTarget::ES2020,
-    Some(("stringReplaceAll", false)),
-  )
-  .await;
-
-  assert_files!(
-    result.main.files,
-    &[("mod.ts", "''.replaceAll('test', 'other');\n")]
-  );
-}
-
-#[tokio::test]
-async fn polyfills_override_enables_for_latest_target() {
-  // `Latest` normally excludes every polyfill, but the override wins
-  let result = build_string_replace_all_polyfill_test(
-    ScriptTarget::Latest,
-    Some(("stringReplaceAll", true)),
-  )
-  .await;
-
+  // the sibling is at a different depth, so the shared root moves up to
+  // encompass both projects, the same as it already does for siblings at
+  // equal depths
   assert_files!(
     result.main.files,
     &[
       (
-        "mod.ts",
-        concat!(
-          "import \"./_dnt.polyfills.js\";\n",
-          "''.replaceAll('test', 'other');\n",
-        ),
-      ),
-      (
-        "_dnt.polyfills.ts",
-        include_str!("../src/polyfills/scripts/es2021.string-replaceAll.ts")
+        "project-one/mod.ts",
+        "import value from '../project-two/deeper/mod.js';",
       ),
+      ("project-two/deeper/mod.ts", "export default 'hello world';",),
     ]
   );
+  assert_eq!(
+    result.main.entry_points,
+    &[PathBuf::from("project-one/mod.ts")]
+  );
 }
 
 #[tokio::test]
-async fn polyfills_override_only_affects_named_polyfill() {
-  // disabling one polyfill leaves the others resolving by target
+async fn transform_import_map_local_sibling_and_remote() {
   let result = TestBuilder::new()
+    .entry_point("file:///example-project/mod.ts")
     .with_loader(|loader| {
-      loader.add_local_file(
-        "/mod.ts",
-        "''.replaceAll('test', 'other');\nObject.hasOwn({}, 'test');\n",
-      );
+      loader
+        .add_local_file(
+          "/example-project/mod.ts",
+          "export * from './src/mod.ts';",
+        )
+        .add_local_file(
+          "/example-project/src/mod.ts",
+          "export * from 'example-shared/mod.ts';\nexport * from 'https://example.com/mod.ts';",
+        )
+        .add_local_file(
+          "/example-project/deno.json",
+          r#"{
+  "imports": {
+    "example-shared/": "../example-shared/"
+  }
+}"#,
+        )
+        .add_local_file("/example-shared/mod.ts", "export const shared = 'shared';")
+        .add_remote_file("https://example.com/mod.ts", "export const remote = 'remote';");
     })
-    .set_target(ScriptTarget::ES2020)
-    .set_polyfill("stringReplaceAll", false)
+    .set_import_map("file:///example-project/deno.json")
     .transform()
     .await
     .unwrap();
 
+  // the local sibling shares a root with the project, while the remote module
+  // keeps going to `deps`
   assert_files!(
     result.main.files,
     &[
       (
-        "mod.ts",
-        concat!(
-          "import \"./_dnt.polyfills.js\";\n",
-          "''.replaceAll('test', 'other');\n",
-          "Object.hasOwn({}, 'test');\n",
-        ),
+        "example-project/mod.ts",
+        "export * from './src/mod.js';",
       ),
       (
-        "_dnt.polyfills.ts",
-        include_str!("../src/polyfills/scripts/esnext.object-has-own.ts")
+        "example-project/src/mod.ts",
+        "export * from '../../example-shared/mod.js';\nexport * from '../../deps/example.com/mod.js';",
+      ),
+      (
+        "example-shared/mod.ts",
+        "export const shared = 'shared';",
+      ),
+      (
+        "deps/example.com/mod.ts",
+        "export const remote = 'remote';",
       ),
     ]
   );
+  assert_eq!(
+    result.main.entry_points,
+    &[PathBuf::from("example-project/mod.ts")]
+  );
 }
 
-async fn build_string_replace_all_polyfill_test(
-  target: ScriptTarget,
-  polyfill_override: Option<(&str, bool)>,
-) -> TransformOutput {
-  let mut builder = TestBuilder::new();
-  builder
+#[tokio::test]
+async fn transform_specifier_not_in_import_map() {
+  let err_message = TestBuilder::new()
     .with_loader(|loader| {
-      loader.add_local_file("/mod.ts", "''.replaceAll('test', 'other');\n");
-    })
-    .set_target(target);
-  if let Some((name, enabled)) = polyfill_override {
-    builder.set_polyfill(name, enabled);
+      loader
+        .add_local_file(
+          "/mod.ts",
+          "import a from 'other';\nimport b from 'other/sub.ts';",
+        )
+        .add_local_file(
+          "/import_map.json",
+          r#"{
+  "imports": {
+    "other": "./other/mod.ts"
   }
-  builder.transform().await.unwrap()
+}"#,
+        )
+        .add_local_file("/other/mod.ts", "export default 1;")
+        .add_local_file("/other/sub.ts", "export default 2;");
+    })
+    .set_import_map("file:///import_map.json")
+    .transform()
+    .await
+    .err()
+    .unwrap();
+
+  // the sub path is not mapped, so surface it rather than emitting the
+  // bare specifier into the output as-is
+  assert_eq!(
+    err_message.to_string(),
+    normalize_urls(
+      concat!(
+        "Import \"other/sub.ts\" not a dependency and not in import map from \"file:///mod.ts\"",
+        "\n    at file:///mod.ts:2:15",
+      )
+    )
+  );
 }
 
 #[tokio::test]
-async fn polyfills_test_files() {
+async fn transform_entry_point_with_sibling_dir_in_project() {
   let result = TestBuilder::new()
+    .entry_point("file:///project/src/mod.ts")
     .with_loader(|loader| {
-      loader.add_local_file("/mod.ts", "").add_local_file(
-        "/mod.test.ts",
-        "// Some copyright text\nObject.hasOwn({}, 'prop');",
-      );
+      loader
+        .add_local_file(
+          "/project/src/mod.ts",
+          "export * from '../lib/util.ts';",
+        )
+        .add_local_file("/project/lib/util.ts", "export const util = 1;");
     })
-    .add_test_entry_point("file:///mod.test.ts")
     .transform()
     .await
     .unwrap();
 
-  assert_files!(result.main.files, &[("mod.ts", "",)]);
-  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
-
+  // a sibling directory of the entry point is still part of the project, so
+  // it must not be treated as a dependency
   assert_files!(
-    result.test.files,
+    result.main.files,
     &[
-      (
-        "mod.test.ts",
-        concat!(
-          "// Some copyright text\n",
-          "import \"./_dnt.test_polyfills.js\";\n\n",
-          "Object.hasOwn({}, 'prop');"
-        )
-      ),
-      (
-        "_dnt.test_polyfills.ts",
-        include_str!("../src/polyfills/scripts/esnext.object-has-own.ts"),
-      )
+      ("src/mod.ts", "export * from '../lib/util.js';"),
+      ("lib/util.ts", "export const util = 1;"),
     ]
   );
-  assert_eq!(result.test.entry_points, &[PathBuf::from("mod.test.ts")]);
+  assert_eq!(result.main.entry_points, &[PathBuf::from("src/mod.ts")]);
 }
 
 #[tokio::test]
-async fn polyfills_object_has_own_conflict() {
-  // should not do a polyfill because of Object
+async fn transform_test_entry_point_uses_test_root_without_moving_main() {
   let result = TestBuilder::new()
+    .entry_point("file:///example-project/src/mod.ts")
     .with_loader(|loader| {
       loader
-        .add_local_file("/mod.ts", "export class Object {} Object.hasOwn();");
+        .add_local_file(
+          "/example-project/src/mod.ts",
+          "export const value = 'value';",
+        )
+        .add_local_file(
+          "/example-project/tests/integration/functions/mod.test.ts",
+          "import '../../../src/mod.ts';",
+        );
     })
+    .add_test_entry_point(
+      "file:///example-project/tests/integration/functions/mod.test.ts",
+    )
     .transform()
     .await
     .unwrap();
 
+  // the tests are in a directory outside the main files' root, which must not
+  // push the main files down a directory
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
+  assert_eq!(
+    result.test.entry_points,
+    &[PathBuf::from("tests/integration/functions/mod.test.ts")]
+  );
   assert_files!(
-    result.main.files,
-    &[("mod.ts", "export class Object {} Object.hasOwn();")]
+    result.test.files,
+    &[(
+      "tests/integration/functions/mod.test.ts",
+      "import '../../../mod.js';",
+    )]
   );
 }
 
 #[tokio::test]
-async fn module_specifier_mapping_general() {
+async fn transform_config_file() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file("/mod.ts", "import './other.deno.ts';")
-        .add_local_file("/other.deno.ts", "console.log(5);")
         .add_local_file(
-          "/other.node.ts",
-          concat!(
-            "import * as fs from 'fs';\n",
-            "import { myFunction } from './myFunction.ts'\n",
-            "export function test() {\n",
-            "  // dnt-shim-ignore\n",
-            "  Deno.readFileSync('test');\n",
-            "  Object.hasOwn({}, 'prop');\n",
-            "}",
-          ),
+          "/mod.ts",
+          "import * as remote from 'localhost/mod.ts';",
         )
-        .add_local_file("/myFunction.ts", "export function myFunction() {}");
+        .add_local_file(
+          "/deno.json",
+          r#"{
+  // test comments
+  "imports": {
+    "localhost/": "/subdir/"
+  }
+}"#,
+        )
+        .add_local_file(
+          "/subdir/mod.ts",
+          "import * as myOther from './other.ts';",
+        )
+        .add_local_file("/subdir/other.ts", "export function test() {}");
     })
-    .add_module_specifier_mapping(
-      "file:///other.deno.ts",
-      "file:///other.node.ts",
-    )
+    .set_config_file("file:///deno.json")
     .transform()
     .await
     .unwrap();
 
+  // it was provided, so it wasn't auto-discovered
+  assert_eq!(result.discovered_config_file, None);
   assert_files!(
     result.main.files,
     &[
-      (
-        "mod.ts",
-        concat!(
-          "import \"./_dnt.polyfills.js\";\n",
-          "import './other.node.js';"
-        ),
-      ),
-      (
-        "other.node.ts",
-        concat!(
-          "import * as fs from 'fs';\n",
-          "import { myFunction } from './myFunction.js'\n",
-          "export function test() {\n",
-          "  // dnt-shim-ignore\n",
-          "  Deno.readFileSync('test');\n",
-          "  Object.hasOwn({}, 'prop');\n",
-          "}",
-        )
-      ),
-      ("myFunction.ts", "export function myFunction() {}",),
-      (
-        "_dnt.polyfills.ts",
-        include_str!("../src/polyfills/scripts/esnext.object-has-own.ts")
-      ),
+      ("mod.ts", "import * as remote from './subdir/mod.js';",),
+      ("subdir/mod.ts", "import * as myOther from './other.js';",),
+      ("subdir/other.ts", "export function test() {}",)
     ]
   );
-  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
 }
 
 #[tokio::test]
-async fn redirect_entrypoint() {
+async fn transform_auto_discovered_config_file() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
-      loader
-        .add_local_file("/mod.deno.ts", "console.log(5);")
-        .add_local_file("/mod.node.ts", "5;");
+      add_config_discovery_files(loader);
     })
-    .entry_point("file:///mod.deno.ts")
-    .add_module_specifier_mapping("file:///mod.deno.ts", "file:///mod.node.ts")
+    .entry_point("file:///sub_dir/mod.ts")
     .transform()
     .await
     .unwrap();
 
-  assert_files!(result.main.files, &[("mod.node.ts", "5;")]);
-  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.node.ts")]);
+  assert_eq!(
+    result.discovered_config_file,
+    Some(PathBuf::from(if cfg!(windows) {
+      "C:\\deno.json"
+    } else {
+      "/deno.json"
+    }))
+  );
+  assert_files!(
+    result.main.files,
+    &[
+      ("sub_dir/mod.ts", "import * as remote from '../other.js';",),
+      ("other.ts", "export function test() {}",)
+    ]
+  );
 }
 
 #[tokio::test]
-async fn redirect_not_found() {
+async fn transform_no_config() {
   let err_message = TestBuilder::new()
     .with_loader(|loader| {
-      loader.add_local_file("/mod.ts", "console.log(5);");
+      add_config_discovery_files(loader);
     })
-    .add_module_specifier_mapping("file:///mod.deno.ts", "file:///mod.node.ts")
+    .entry_point("file:///sub_dir/mod.ts")
+    .set_no_config(true)
     .transform()
     .await
     .err()
     .unwrap();
 
+  // the config file should not have been discovered, so its
+  // import mapping should not have been applied
   assert_eq!(
     err_message.to_string(),
-    normalize_urls(concat!(
-      "The following specifiers were indicated to be mapped to a module, but were not found:\n",
-      "  * file:///mod.deno.ts",
-    )),
+    normalize_urls(
+      "Import \"localhost/mod.ts\" not a dependency
+    at file:///sub_dir/mod.ts:1:25"
+    )
   );
 }
 
 #[tokio::test]
-async fn json_module_import_default() {
+async fn transform_multiple_entry_points() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file(
-          "/mod.ts",
-          r#"import jsonData from './data.json' assert { type: 'json' };"#,
-        )
-        .add_local_file("/data.json", "\u{FEFF}{ \"prop\": 5 }");
+        .add_local_file("/mod.ts", "import './ref.ts';mod1;")
+        .add_local_file("/mod2.ts", "import './ref.ts';mod2;")
+        .add_local_file("/ref.ts", "export const test = 5;");
     })
+    .add_entry_point("file:///mod2.ts")
     .transform()
     .await
     .unwrap();
@@ -2974,50 +3105,142 @@ async fn json_module_import_default() {
   assert_files!(
     result.main.files,
     &[
-      ("mod.ts", r#"import jsonData from './data.js';"#),
-      ("data.js", r#"export default { "prop": 5 };"#)
+      ("mod.ts", "import './ref.js';mod1;"),
+      ("mod2.ts", "import './ref.js';mod2;"),
+      ("ref.ts", "export const test = 5;"),
     ]
   );
 }
 
 #[tokio::test]
-async fn json_module_dynamic_import() {
+async fn test_entry_points() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
         .add_local_file(
           "/mod.ts",
-          r#"const jsonData = (await import('./data.json', { assert: { type: 'json' } })).default;"#
+          "import package1 from 'https://cdn.skypack.dev/preact@^10.5.0';\n",
         )
-        .add_local_file("/data.json", r#"{ "prop": 5 }"#);
+        .add_local_file(
+          "/mod.test.ts",
+          concat!(
+            "import './mod.ts';\n",
+            "import package1 from 'https://cdn.skypack.dev/preact@^10.5.0';\n",
+            "import package3 from 'https://esm.sh/react@17.0.2';\n",
+            "Deno.writeTextFile('test', 'test')",
+          ),
+        );
     })
+    .add_test_entry_point("file:///mod.test.ts")
+    .add_default_shims()
     .transform()
     .await
     .unwrap();
 
   assert_files!(
     result.main.files,
+    &[("mod.ts", "import package1 from 'preact';\n",)]
+  );
+  assert_eq!(
+    result.main.dependencies,
+    &[Dependency {
+      name: "preact".to_string(),
+      version: "^10.5.0".to_string(),
+      kind: DependencyKind::Dependency,
+    },]
+  );
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
+
+  assert_files!(
+    result.test.files,
     &[
       (
-        "mod.ts",
-        r#"const jsonData = (await import('./data.js')).default;"#
+        "mod.test.ts",
+        concat!(
+          "import * as dntShim from \"./_dnt.test_shims.js\";\n",
+          "import './mod.js';\n",
+          "import package1 from 'preact';\n",
+          "import package3 from 'react';\n",
+          "dntShim.Deno.writeTextFile('test', 'test')"
+        )
+        .to_string(),
       ),
-      ("data.js", r#"export default { "prop": 5 };"#)
+      (
+        "_dnt.test_shims.ts",
+        get_shim_file_text(
+          concat!(
+            "import { Deno } from \"@deno/shim-deno\";\n",
+            "export { Deno } from \"@deno/shim-deno\";\n",
+            "import { setTimeout, setInterval } from \"@deno/shim-timers\";\n",
+            "export { setTimeout, setInterval } from \"@deno/shim-timers\";\n",
+            "\n",
+            "const dntGlobals = {\n",
+            "  Deno,\n",
+            "  setTimeout,\n",
+            "  setInterval,\n",
+            "};\n",
+            "export const dntGlobalThis = createMergeProxy(globalThis, dntGlobals);\n",
+          )
+          .to_string(),
+        ),
+      )
+    ]
+  );
+  assert_eq!(
+    result.test.dependencies,
+    &[
+      Dependency {
+        name: "react".to_string(),
+        version: "17.0.2".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "@deno/shim-deno".to_string(),
+        version: "^0.1.0".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "@deno/shim-timers".to_string(),
+        version: "^0.1.0".to_string(),
+        kind: DependencyKind::Dependency,
+      }
     ]
   );
+  assert_eq!(result.test.entry_points, &[PathBuf::from("mod.test.ts")]);
 }
 
 #[tokio::test]
-async fn json_module_re_export() {
+async fn test_entry_points_same_module_multiple_places() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
         .add_local_file(
           "/mod.ts",
-          r#"export { default as Test } from './data.json' with { type: "json" };"#
+          concat!(
+            "export * from 'https://deno.land/std@0.181.0/path.ts';\n",
+            "import * as deps from './deps.ts';",
+          ),
         )
-        .add_local_file("/data.json", r#"{ "prop": 5 }"#);
+        // ensure that the path.ts in this file being already analyzed
+        // doesn't cause flags.ts to not be analyzed
+        .add_local_file(
+          "/deps.ts",
+          concat!(
+            "export * from 'https://deno.land/std@0.181.0/path.ts';\n",
+            "export * from 'https://deno.land/std@0.181.0/flags.ts';",
+          ),
+        )
+        .add_remote_file(
+          "https://deno.land/std@0.181.0/flags.ts",
+          "export class Flags {}",
+        )
+        .add_remote_file(
+          "https://deno.land/std@0.181.0/path.ts",
+          "export class Path {}",
+        )
+        .add_local_file("/mod.test.ts", "import * as deps from './deps.ts';");
     })
+    .add_test_entry_point("file:///mod.test.ts")
     .transform()
     .await
     .unwrap();
@@ -3025,41 +3248,57 @@ async fn json_module_re_export() {
   assert_files!(
     result.main.files,
     &[
-      ("mod.ts", r#"export { default as Test } from './data.js';"#),
-      ("data.js", r#"export default { "prop": 5 };"#)
+      (
+        "mod.ts",
+        concat!(
+          "export * from './deps/deno.land/std@0.181.0/path.js';\n",
+          "import * as deps from './deps.js';",
+        )
+      ),
+      (
+        "deps.ts",
+        concat!(
+          "export * from './deps/deno.land/std@0.181.0/path.js';\n",
+          "export * from './deps/deno.land/std@0.181.0/flags.js';",
+        )
+      ),
+      (
+        "deps/deno.land/std@0.181.0/flags.ts",
+        "export class Flags {}"
+      ),
+      ("deps/deno.land/std@0.181.0/path.ts", "export class Path {}")
     ]
   );
-}
-
-#[tokio::test]
-async fn issue_104() {
-  let result = TestBuilder::new()
-    .with_loader(|loader| {
-      loader
-        .add_local_file("/mod.ts", "import type { other } from './test.ts'; import { test } from './test.ts'; test();")
-        .add_local_file("/test.ts", "export function test() {} export type other = string;");
-    })
-    .transform()
-    .await
-    .unwrap();
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
 
   assert_files!(
-    result.main.files,
-    &[
-      ("mod.ts", "import type { other } from './test.js'; import { test } from './test.js'; test();"),
-      ("test.ts", "export function test() {} export type other = string;"),
-    ]
+    result.test.files,
+    &[("mod.test.ts", "import * as deps from './deps.js';",)]
   );
+  assert_eq!(result.test.entry_points, &[PathBuf::from("mod.test.ts")]);
 }
 
 #[tokio::test]
-async fn local_declaration_file_import() {
+async fn polyfills_all() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file("/mod.ts", "import type { A } from './types.d.ts';")
-        .add_local_file("/types.d.ts", "export interface A {}");
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "export const test = (obj) => Object.hasOwn(obj, 'test');\n",
+            "try {\n",
+            "} catch (err) {\n",
+            "  err.cause = new Error();\n",
+            "}\n",
+            "''.replaceAll('test', 'other');\n",
+            "[].findLast(() => true);\n",
+            "import.meta.main;\n",
+          ),
+        )
+        .add_local_file("/mod.test.ts", "import * as mod from './mod.ts';");
     })
+    .add_test_entry_point("file:///mod.test.ts")
     .transform()
     .await
     .unwrap();
@@ -3067,315 +3306,349 @@ async fn local_declaration_file_import() {
   assert_files!(
     result.main.files,
     &[
-      ("mod.ts", "import type { A } from './types';"),
-      ("types.d.ts", "export interface A {}"),
+      (
+        "mod.ts",
+        concat!(
+          "import \"./_dnt.polyfills.js\";\n",
+          "export const test = (obj) => Object.hasOwn(obj, 'test');\n",
+          "try {\n",
+          "} catch (err) {\n",
+          "  err.cause = new Error();\n",
+          "}\n",
+          "''.replaceAll('test', 'other');\n",
+          "[].findLast(() => true);\n",
+          "import.meta.main;\n",
+        ),
+      ),
+      (
+        "_dnt.polyfills.ts",
+        concat!(
+          include_str!("../src/polyfills/scripts/esnext.object-has-own.ts"),
+          include_str!("../src/polyfills/scripts/esnext.error-cause.ts"),
+          include_str!("../src/polyfills/scripts/es2021.string-replaceAll.ts"),
+          include_str!("../src/polyfills/scripts/esnext.array-findLast.ts"),
+          include_str!("../src/polyfills/scripts/deno.import-meta.ts"),
+        )
+      ),
     ]
   );
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
+
+  assert_files!(
+    result.test.files,
+    &[("mod.test.ts", concat!("import * as mod from './mod.js';",),)]
+  );
+  assert_eq!(result.test.entry_points, &[PathBuf::from("mod.test.ts")]);
 }
 
 #[tokio::test]
-async fn remote_declaration_file_import() {
+async fn polyfills_string_replaceall_target() {
+  test_string_replace_all_polyfill(ScriptTarget::ES2020, true).await;
+  test_string_replace_all_polyfill(ScriptTarget::ES2021, false).await;
+}
+
+async fn test_string_replace_all_polyfill(
+  target: ScriptTarget,
+  should_have_polyfill: bool,
+) {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file(
-          "/mod.ts",
-          concat!(
-            "import type { RawSourceMap } from 'https://esm.sh/source-map@0.7.3/source-map.d.ts';\n",
-            "import type { Other } from 'https://localhost/source-map.d.ts';",
-          )
-        )
-        .add_remote_file("https://esm.sh/source-map@0.7.3/source-map.d.ts", "export interface RawSourceMap {}")
-        .add_remote_file("https://localhost/source-map.d.ts", "export interface Other {}");
+        .add_local_file("/mod.ts", "''.replaceAll('test', 'other');\n")
+        .add_local_file("/mod.test.ts", "import * as mod from './mod.ts';");
     })
+    .add_test_entry_point("file:///mod.test.ts")
+    .set_target(target)
     .transform()
     .await
     .unwrap();
 
-  assert_files!(result.main.files, &[
-    (
-      "mod.ts",
-      concat!(
-        "import type { RawSourceMap } from './deps/esm.sh/source-map@0.7.3/source-map';\n",
-        "import type { Other } from './deps/localhost/source-map';",
-    )),
-    ("deps/esm.sh/source-map@0.7.3/source-map.d.ts", "export interface RawSourceMap {}"),
-    ("deps/localhost/source-map.d.ts", "export interface Other {}"),
-  ]);
+  if should_have_polyfill {
+    assert_files!(
+      result.main.files,
+      &[
+        (
+          "mod.ts",
+          concat!(
+            "import \"./_dnt.polyfills.js\";\n",
+            "''.replaceAll('test', 'other');\n",
+          ),
+        ),
+        (
+          "_dnt.polyfills.ts",
+          concat!(include_str!(
+            "../src/polyfills/scripts/es2021.string-replaceAll.ts"
+          ),)
+        ),
+      ]
+    );
+  } else {
+    assert_files!(
+      result.main.files,
+      &[("mod.ts", "''.replaceAll('test', 'other');\n",)]
+    );
+  }
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
 }
 
 #[tokio::test]
-async fn import_type_change_specifier() {
-  let result = TestBuilder::new()
-    .with_loader(|loader| {
-      loader
-        .add_local_file(
-          "/mod.ts",
-          r#"export type Test = import('./other.ts').Test"#,
-        )
-        .add_local_file("/other.ts", "export type Test = string;");
-    })
-    .transform()
-    .await
-    .unwrap();
+async fn polyfills_override_disables_for_target() {
+  // ES2020 would normally get the polyfill, but the override wins
+  let result = build_string_replace_all_polyfill_test(
+    ScriptTarget::ES2020,
+    Some(("stringReplaceAll", false)),
+  )
+  .await;
 
   assert_files!(
     result.main.files,
-    &[
-      ("mod.ts", r#"export type Test = import('./other.js').Test"#),
-      ("other.ts", "export type Test = string;")
-    ]
+    &[("mod.ts", "''.replaceAll('test', 'other');\n")]
   );
 }
 
 #[tokio::test]
-async fn module_decl_string_literal_change_specifier() {
-  let result = TestBuilder::new()
-    .with_loader(|loader| {
-      loader
-        .add_local_file(
-          "/mod.ts",
-          r#"import Test from './other.ts'; declare module './other.ts' {}"#,
-        )
-        .add_local_file("/other.ts", "export type Test = string;");
-    })
-    .transform()
-    .await
-    .unwrap();
+async fn polyfills_override_enables_for_latest_target() {
+  // `Latest` normally excludes every polyfill, but the override wins
+  let result = build_string_replace_all_polyfill_test(
+    ScriptTarget::Latest,
+    Some(("stringReplaceAll", true)),
+  )
+  .await;
 
   assert_files!(
     result.main.files,
     &[
       (
         "mod.ts",
-        r#"import Test from './other.js'; declare module './other.js' {}"#
+        concat!(
+          "import \"./_dnt.polyfills.js\";\n",
+          "''.replaceAll('test', 'other');\n",
+        ),
+      ),
+      (
+        "_dnt.polyfills.ts",
+        include_str!("../src/polyfills/scripts/es2021.string-replaceAll.ts")
       ),
-      ("other.ts", "export type Test = string;")
     ]
   );
 }
 
 #[tokio::test]
-async fn node_specifier() {
+async fn polyfills_override_only_affects_named_polyfill() {
+  // disabling one polyfill leaves the others resolving by target
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader.add_local_file(
         "/mod.ts",
-        "import * as fs from 'node:fs'; console.log(fs);",
+        "''.replaceAll('test', 'other');\nObject.hasOwn({}, 'test');\n",
       );
     })
+    .set_target(ScriptTarget::ES2020)
+    .set_polyfill("stringReplaceAll", false)
     .transform()
     .await
     .unwrap();
 
   assert_files!(
     result.main.files,
-    &[("mod.ts", "import * as fs from 'node:fs'; console.log(fs);"),]
+    &[
+      (
+        "mod.ts",
+        concat!(
+          "import \"./_dnt.polyfills.js\";\n",
+          "''.replaceAll('test', 'other');\n",
+          "Object.hasOwn({}, 'test');\n",
+        ),
+      ),
+      (
+        "_dnt.polyfills.ts",
+        include_str!("../src/polyfills/scripts/esnext.object-has-own.ts")
+      ),
+    ]
   );
 }
 
-#[tokio::test]
-async fn transform_bin_only_files() {
-  let result = TestBuilder::new()
-    .with_loader(|loader| {
-      loader
-        .add_local_file("/mod.ts", "export * from './shared.ts';")
-        .add_local_file(
-          "/cli.ts",
-          "import './shared.ts';
-import './cli_only.ts';",
-        )
-        .add_local_file("/shared.ts", "export const a = 1;")
-        .add_local_file("/cli_only.ts", "export const b = 2;");
+async fn build_string_replace_all_polyfill_test(
+  target: ScriptTarget,
+  polyfill_override: Option<(&str, bool)>,
+) -> TransformOutput {
+  let mut builder = TestBuilder::new();
+  builder
+    .with_loader(|loader| {
+      loader.add_local_file("/mod.ts", "''.replaceAll('test', 'other');\n");
     })
-    .add_bin_entry_point("file:///cli.ts")
-    .transform()
-    .await
-    .unwrap();
-
-  // only the modules that nothing else uses are binary only
-  assert_eq!(
-    result.bin_only_files,
-    &[PathBuf::from("cli.ts"), PathBuf::from("cli_only.ts")]
-  );
+    .set_target(target);
+  if let Some((name, enabled)) = polyfill_override {
+    builder.set_polyfill(name, enabled);
+  }
+  builder.transform().await.unwrap()
 }
 
 #[tokio::test]
-async fn npm_specifier() {
+async fn polyfills_test_files() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
-      loader.add_local_file(
-        "/mod.ts",
-        "import * as pkg from 'npm:using-statement@^0.4'; console.log(pkg);",
+      loader.add_local_file("/mod.ts", "").add_local_file(
+        "/mod.test.ts",
+        "// Some copyright text\nObject.hasOwn({}, 'prop');",
       );
     })
+    .add_test_entry_point("file:///mod.test.ts")
     .transform()
     .await
     .unwrap();
 
+  assert_files!(result.main.files, &[("mod.ts", "",)]);
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
+
   assert_files!(
-    result.main.files,
-    &[(
-      "mod.ts",
-      "import * as pkg from 'using-statement'; console.log(pkg);"
-    )]
+    result.test.files,
+    &[
+      (
+        "mod.test.ts",
+        concat!(
+          "// Some copyright text\n",
+          "import \"./_dnt.test_polyfills.js\";\n\n",
+          "Object.hasOwn({}, 'prop');"
+        )
+      ),
+      (
+        "_dnt.test_polyfills.ts",
+        include_str!("../src/polyfills/scripts/esnext.object-has-own.ts"),
+      )
+    ]
   );
+  assert_eq!(result.test.entry_points, &[PathBuf::from("mod.test.ts")]);
 }
 
 #[tokio::test]
-async fn npm_specifier_peer_dependency_with_sub_path() {
+async fn polyfills_object_has_own_conflict() {
+  // should not do a polyfill because of Object
   let result = TestBuilder::new()
     .with_loader(|loader| {
-      loader.add_local_file(
-        "/mod.ts",
-        concat!(
-          "import * as pkg from 'npm:using-statement@^0.4';\n",
-          "import * as sub from 'npm:using-statement@^0.4/sub';\n",
-          "console.log(pkg, sub);\n",
-        ),
-      );
+      loader
+        .add_local_file("/mod.ts", "export class Object {} Object.hasOwn();");
     })
-    .add_peer_package_specifier_mapping(
-      "npm:using-statement@^0.4",
-      "using-statement",
-      Some("^0.4"),
-    )
     .transform()
     .await
     .unwrap();
 
-  // the sub path resolves to the same package, so it must not also
-  // show up as a non-peer dependency
-  assert_eq!(
-    result.main.dependencies,
-    &[Dependency {
-      name: "using-statement".to_string(),
-      version: "^0.4".to_string(),
-      peer_dependency: true,
-    }]
+  assert_files!(
+    result.main.files,
+    &[("mod.ts", "export class Object {} Object.hasOwn();")]
   );
 }
 
 #[tokio::test]
-async fn npm_types_specifier() {
+async fn module_specifier_mapping_general() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
+        .add_local_file("/mod.ts", "import './other.deno.ts';")
+        .add_local_file("/other.deno.ts", "console.log(5);")
         .add_local_file(
-          "/mod.ts",
+          "/other.node.ts",
           concat!(
-            "import type { Node } from 'unist';
-",
-            "import type { Core } from 'babel-core';
-",
-            "export type { Core, Node };
-",
+            "import * as fs from 'fs';\n",
+            "import { myFunction } from './myFunction.ts'\n",
+            "export function test() {\n",
+            "  // dnt-shim-ignore\n",
+            "  Deno.readFileSync('test');\n",
+            "  Object.hasOwn({}, 'prop');\n",
+            "}",
           ),
         )
-        .add_local_file(
-          "/deno.json",
-          r#"{
-  "imports": {
-    "unist": "npm:@types/unist@3.0.3",
-    "babel-core": "npm:@types/babel__core@7.20.5"
-  }
-}"#,
-        );
+        .add_local_file("/myFunction.ts", "export function myFunction() {}");
     })
+    .add_module_specifier_mapping(
+      "file:///other.deno.ts",
+      "file:///other.node.ts",
+    )
     .transform()
     .await
     .unwrap();
 
-  // a declaration file package is imported by the name of the package
-  // it provides the declarations for
   assert_files!(
     result.main.files,
-    &[(
-      "mod.ts",
-      concat!(
-        "import type { Node } from 'unist';
-",
-        "import type { Core } from '@babel/core';
-",
-        "export type { Core, Node };
-",
-      )
-    )]
-  );
-  assert_eq!(
-    result.main.dependencies,
     &[
-      Dependency {
-        name: "@types/babel__core".to_string(),
-        version: "7.20.5".to_string(),
-        peer_dependency: false,
-      },
-      Dependency {
-        name: "@types/unist".to_string(),
-        version: "3.0.3".to_string(),
-        peer_dependency: false,
-      }
+      (
+        "mod.ts",
+        concat!(
+          "import \"./_dnt.polyfills.js\";\n",
+          "import './other.node.js';"
+        ),
+      ),
+      (
+        "other.node.ts",
+        concat!(
+          "import * as fs from 'fs';\n",
+          "import { myFunction } from './myFunction.js'\n",
+          "export function test() {\n",
+          "  // dnt-shim-ignore\n",
+          "  Deno.readFileSync('test');\n",
+          "  Object.hasOwn({}, 'prop');\n",
+          "}",
+        )
+      ),
+      ("myFunction.ts", "export function myFunction() {}",),
+      (
+        "_dnt.polyfills.ts",
+        include_str!("../src/polyfills/scripts/esnext.object-has-own.ts")
+      ),
     ]
   );
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
 }
 
 #[tokio::test]
-async fn transform_uses_lockfile_remote_checksum() {
-  // a deno.lock with a non-matching remote checksum should cause an
-  // integrity error, proving the lockfile is being used
+async fn redirect_entrypoint() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file("/deno.json", "{}")
-        .add_local_file(
-          "/deno.lock",
-          concat!(
-            "{\n",
-            "  \"version\": \"5\",\n",
-            "  \"remote\": {\n",
-            "    \"https://localhost/mod.ts\": \"0000000000000000000000000000000000000000000000000000000000000000\"\n",
-            "  }\n",
-            "}\n"
-          ),
-        )
-        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
-        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+        .add_local_file("/mod.deno.ts", "console.log(5);")
+        .add_local_file("/mod.node.ts", "5;");
+    })
+    .entry_point("file:///mod.deno.ts")
+    .add_module_specifier_mapping("file:///mod.deno.ts", "file:///mod.node.ts")
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(result.main.files, &[("mod.node.ts", "5;")]);
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.node.ts")]);
+}
+
+#[tokio::test]
+async fn redirect_not_found() {
+  let err_message = TestBuilder::new()
+    .with_loader(|loader| {
+      loader.add_local_file("/mod.ts", "console.log(5);");
     })
+    .add_module_specifier_mapping("file:///mod.deno.ts", "file:///mod.node.ts")
     .transform()
     .await
     .err()
     .unwrap();
 
-  assert!(
-    result
-      .to_string()
-      .to_lowercase()
-      .contains("integrity check failed"),
-    "unexpected error: {:#}",
-    result
+  assert_eq!(
+    err_message.to_string(),
+    normalize_urls(concat!(
+      "The following specifiers were indicated to be mapped to a module, but were not found:\n",
+      "  * file:///mod.deno.ts",
+    )),
   );
 }
 
 #[tokio::test]
-async fn transform_uses_lockfile_matching_remote_checksum() {
-  // a deno.lock with a matching remote checksum should transform successfully
+async fn json_module_import_default() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file("/deno.json", "{}")
         .add_local_file(
-          "/deno.lock",
-          concat!(
-            "{\n",
-            "  \"version\": \"5\",\n",
-            "  \"remote\": {\n",
-            "    \"https://localhost/mod.ts\": \"35c146f76e129477c64061bc84511e1090f3d4d8059713e6663dd4b35b1f7642\"\n",
-            "  }\n",
-            "}\n"
-          ),
+          "/mod.ts",
+          r#"import jsonData from './data.json' assert { type: 'json' };"#,
         )
-        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
-        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+        .add_local_file("/data.json", "\u{FEFF}{ \"prop\": 5 }");
     })
     .transform()
     .await
@@ -3384,116 +3657,147 @@ async fn transform_uses_lockfile_matching_remote_checksum() {
   assert_files!(
     result.main.files,
     &[
-      ("mod.ts", "import './deps/localhost/mod.js';"),
-      ("deps/localhost/mod.ts", "console.log(1);"),
+      ("mod.ts", r#"import jsonData from './data.js';"#),
+      ("data.js", r#"export default { "prop": 5 };"#)
     ]
   );
 }
 
 #[tokio::test]
-async fn transform_ignores_unrelated_lockfile_redirects() {
-  // the lockfile is for the whole project, so it may have redirects for
-  // modules that aren't reachable from the entry points being transformed
+async fn json_module_dynamic_import() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file("/deno.json", "{}")
         .add_local_file(
-          "/deno.lock",
-          concat!(
-            "{\n",
-            "  \"version\": \"5\",\n",
-            "  \"redirects\": {\n",
-            "    \"https://localhost/unrelated.ts\": \"https://localhost/unrelated/mod.ts\"\n",
-            "  }\n",
-            "}\n"
-          ),
+          "/mod.ts",
+          r#"const jsonData = (await import('./data.json', { assert: { type: 'json' } })).default;"#
         )
-        .add_local_file("/mod.ts", "console.log(1);");
+        .add_local_file("/data.json", r#"{ "prop": 5 }"#);
     })
     .transform()
     .await
     .unwrap();
 
-  assert_files!(result.main.files, &[("mod.ts", "console.log(1);")]);
+  assert_files!(
+    result.main.files,
+    &[
+      (
+        "mod.ts",
+        r#"const jsonData = (await import('./data.js')).default;"#
+      ),
+      ("data.js", r#"export default { "prop": 5 };"#)
+    ]
+  );
 }
 
 #[tokio::test]
-async fn transform_frozen_lockfile_errors_for_missing_remote() {
-  // a frozen lockfile should error rather than resolve a dependency
-  // that's not in it
+async fn json_module_re_export() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file("/deno.json", "{}")
         .add_local_file(
-          "/deno.lock",
-          concat!("{\n", "  \"version\": \"5\"\n", "}\n"),
+          "/mod.ts",
+          r#"export { default as Test } from './data.json' with { type: "json" };"#
         )
-        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
-        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+        .add_local_file("/data.json", r#"{ "prop": 5 }"#);
     })
-    .set_frozen_lockfile(true)
     .transform()
     .await
-    .err()
     .unwrap();
 
-  assert!(
-    result.to_string().contains("The lockfile is out of date."),
-    "unexpected error: {:#}",
-    result
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", r#"export { default as Test } from './data.js';"#),
+      ("data.js", r#"export default { "prop": 5 };"#)
+    ]
   );
 }
 
 #[tokio::test]
-async fn transform_frozen_lockfile_via_config_file() {
-  // the `lock.frozen` setting in the deno.json should be respected
+async fn issue_104() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file("/deno.json", r#"{ "lock": { "frozen": true } }"#)
-        .add_local_file(
-          "/deno.lock",
-          concat!("{\n", "  \"version\": \"5\"\n", "}\n"),
-        )
-        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
-        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+        .add_local_file("/mod.ts", "import type { other } from './test.ts'; import { test } from './test.ts'; test();")
+        .add_local_file("/test.ts", "export function test() {} export type other = string;");
     })
     .transform()
     .await
-    .err()
     .unwrap();
 
-  assert!(
-    result.to_string().contains("The lockfile is out of date."),
-    "unexpected error: {:#}",
-    result
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "import type { other } from './test.js'; import { test } from './test.js'; test();"),
+      ("test.ts", "export function test() {} export type other = string;"),
+    ]
   );
 }
 
 #[tokio::test]
-async fn transform_frozen_lockfile_up_to_date() {
-  // a frozen lockfile that has all the dependencies should transform
+async fn local_declaration_file_import() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/mod.ts", "import type { A } from './types.d.ts';")
+        .add_local_file("/types.d.ts", "export interface A {}");
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "import type { A } from './types';"),
+      ("types.d.ts", "export interface A {}"),
+    ]
+  );
+}
+
+#[tokio::test]
+async fn remote_declaration_file_import() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file("/deno.json", "{}")
         .add_local_file(
-          "/deno.lock",
+          "/mod.ts",
           concat!(
-            "{\n",
-            "  \"version\": \"5\",\n",
-            "  \"remote\": {\n",
-            "    \"https://localhost/mod.ts\": \"35c146f76e129477c64061bc84511e1090f3d4d8059713e6663dd4b35b1f7642\"\n",
-            "  }\n",
-            "}\n"
-          ),
+            "import type { RawSourceMap } from 'https://esm.sh/source-map@0.7.3/source-map.d.ts';\n",
+            "import type { Other } from 'https://localhost/source-map.d.ts';",
+          )
         )
-        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
-        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+        .add_remote_file("https://esm.sh/source-map@0.7.3/source-map.d.ts", "export interface RawSourceMap {}")
+        .add_remote_file("https://localhost/source-map.d.ts", "export interface Other {}");
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(result.main.files, &[
+    (
+      "mod.ts",
+      concat!(
+        "import type { RawSourceMap } from './deps/esm.sh/source-map@0.7.3/source-map';\n",
+        "import type { Other } from './deps/localhost/source-map';",
+    )),
+    ("deps/esm.sh/source-map@0.7.3/source-map.d.ts", "export interface RawSourceMap {}"),
+    ("deps/localhost/source-map.d.ts", "export interface Other {}"),
+  ]);
+}
+
+#[tokio::test]
+async fn import_type_change_specifier() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          r#"export type Test = import('./other.ts').Test"#,
+        )
+        .add_local_file("/other.ts", "export type Test = string;");
     })
-    .set_frozen_lockfile(true)
     .transform()
     .await
     .unwrap();
@@ -3501,27 +3805,23 @@ async fn transform_frozen_lockfile_up_to_date() {
   assert_files!(
     result.main.files,
     &[
-      ("mod.ts", "import './deps/localhost/mod.js';"),
-      ("deps/localhost/mod.ts", "console.log(1);"),
+      ("mod.ts", r#"export type Test = import('./other.js').Test"#),
+      ("other.ts", "export type Test = string;")
     ]
   );
 }
 
 #[tokio::test]
-async fn transform_frozen_lockfile_false_overrides_config_file() {
-  // explicitly opting out should win over the deno.json setting
+async fn module_decl_string_literal_change_specifier() {
   let result = TestBuilder::new()
     .with_loader(|loader| {
       loader
-        .add_local_file("/deno.json", r#"{ "lock": { "frozen": true } }"#)
         .add_local_file(
-          "/deno.lock",
-          concat!("{\n", "  \"version\": \"5\"\n", "}\n"),
+          "/mod.ts",
+          r#"import Test from './other.ts'; declare module './other.ts' {}"#,
         )
-        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
-        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+        .add_local_file("/other.ts", "export type Test = string;");
     })
-    .set_frozen_lockfile(false)
     .transform()
     .await
     .unwrap();
@@ -3529,36 +3829,2096 @@ async fn transform_frozen_lockfile_false_overrides_config_file() {
   assert_files!(
     result.main.files,
     &[
-      ("mod.ts", "import './deps/localhost/mod.js';"),
-      ("deps/localhost/mod.ts", "console.log(1);"),
+      (
+        "mod.ts",
+        r#"import Test from './other.js'; declare module './other.js' {}"#
+      ),
+      ("other.ts", "export type Test = string;")
     ]
   );
 }
 
-fn get_shim_file_text(mut text: String) -> String {
-  text.push('\n');
-  text.push_str(
-    &include_str!("../src/scripts/createMergeProxy.ts")
-      .replace("export function", "function"),
+#[tokio::test]
+async fn node_specifier() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader.add_local_file(
+        "/mod.ts",
+        "import * as fs from 'node:fs'; console.log(fs);",
+      );
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(
+    result.main.files,
+    &[("mod.ts", "import * as fs from 'node:fs'; console.log(fs);"),]
   );
-  text
 }
 
-/// Adds a config file in a parent directory of the `/sub_dir/mod.ts`
-/// entry point so that it's only found by searching upwards from it.
-fn add_config_discovery_files(loader: &mut InMemoryLoader) {
-  loader
-    .add_local_file(
-      "/sub_dir/mod.ts",
-      "import * as remote from 'localhost/mod.ts';",
+#[tokio::test]
+async fn transform_bin_only_files() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/mod.ts", "export * from './shared.ts';")
+        .add_local_file(
+          "/cli.ts",
+          "import './shared.ts';
+import './cli_only.ts';",
+        )
+        .add_local_file("/shared.ts", "export const a = 1;")
+        .add_local_file("/cli_only.ts", "export const b = 2;");
+    })
+    .add_bin_entry_point("file:///cli.ts")
+    .transform()
+    .await
+    .unwrap();
+
+  // only the modules that nothing else uses are binary only
+  assert_eq!(
+    result.bin_only_files,
+    &[PathBuf::from("cli.ts"), PathBuf::from("cli_only.ts")]
+  );
+}
+
+#[tokio::test]
+async fn npm_specifier() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader.add_local_file(
+        "/mod.ts",
+        "import * as pkg from 'npm:using-statement@^0.4'; console.log(pkg);",
+      );
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(
+    result.main.files,
+    &[(
+      "mod.ts",
+      "import * as pkg from 'using-statement'; console.log(pkg);"
+    )]
+  );
+}
+
+#[tokio::test]
+async fn npm_specifier_peer_dependency_with_sub_path() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader.add_local_file(
+        "/mod.ts",
+        concat!(
+          "import * as pkg from 'npm:using-statement@^0.4';\n",
+          "import * as sub from 'npm:using-statement@^0.4/sub';\n",
+          "console.log(pkg, sub);\n",
+        ),
+      );
+    })
+    .add_package_specifier_mapping_with_kind(
+      "npm:using-statement@^0.4",
+      "using-statement",
+      Some("^0.4"),
+      DependencyKind::Peer,
     )
-    .add_local_file(
-      "/deno.json",
-      r#"{
-  "imports": {
-    "localhost/mod.ts": "./other.ts"
+    .transform()
+    .await
+    .unwrap();
+
+  // the sub path resolves to the same package, so it must not also
+  // show up as a non-peer dependency
+  assert_eq!(
+    result.main.dependencies,
+    &[Dependency {
+      name: "using-statement".to_string(),
+      version: "^0.4".to_string(),
+      kind: DependencyKind::Peer,
+    }]
+  );
+}
+
+#[tokio::test]
+async fn npm_specifier_dependency_kinds() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader.add_local_file(
+        "/mod.ts",
+        concat!(
+          "import 'npm:accelerator@^1';\n",
+          "import 'npm:plugin@^2';\n",
+          "import 'npm:plugin@^2/sub';\n",
+          "import 'npm:helper@^3';\n",
+        ),
+      );
+    })
+    .add_package_specifier_mapping_with_kind(
+      "npm:accelerator@^1",
+      "accelerator",
+      Some("^1"),
+      DependencyKind::Optional,
+    )
+    .add_package_specifier_mapping_with_kind(
+      "npm:plugin@^2",
+      "plugin",
+      Some("^2"),
+      DependencyKind::OptionalPeer,
+    )
+    .add_package_specifier_mapping_with_kind(
+      "npm:helper@^3",
+      "helper",
+      Some("^3"),
+      DependencyKind::Dev,
+    )
+    .transform()
+    .await
+    .unwrap();
+
+  // the sub path that isn't mapped is needed at runtime, so the package
+  // isn't made an optional peer dependency
+  assert_eq!(
+    result.main.dependencies,
+    &[
+      Dependency {
+        name: "accelerator".to_string(),
+        version: "^1".to_string(),
+        kind: DependencyKind::Optional,
+      },
+      Dependency {
+        name: "helper".to_string(),
+        version: "^3".to_string(),
+        kind: DependencyKind::Dev,
+      },
+      Dependency {
+        name: "plugin".to_string(),
+        version: "^2".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+    ]
+  );
+}
+
+#[tokio::test]
+async fn npm_specifier_dev_dependency_also_used_at_runtime() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader.add_local_file(
+        "/mod.ts",
+        concat!(
+          "import 'npm:helper@^3';\n",
+          "import 'npm:helper@^3/testing';\n",
+        ),
+      );
+    })
+    .add_package_specifier_mapping_with_kind(
+      "npm:helper@^3/testing",
+      "helper",
+      Some("^3"),
+      DependencyKind::Dev,
+    )
+    .transform()
+    .await
+    .unwrap();
+
+  // the package is needed at runtime, so it isn't demoted to a dev dependency
+  assert_eq!(
+    result.main.dependencies,
+    &[Dependency {
+      name: "helper".to_string(),
+      version: "^3".to_string(),
+      kind: DependencyKind::Dependency,
+    }]
+  );
+}
+
+#[tokio::test]
+async fn npm_specifier_type_only_dependencies() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import type { A } from 'npm:import-type@^1';\n",
+            "import { B } from 'npm:type-position@^2';\n",
+            "import { C, type D } from 'npm:runtime@^3';\n",
+            "import type { E } from 'npm:sub-path@^4/types';\n",
+            "import 'npm:sub-path@^4';\n",
+            "import { f } from 'npm:non-null@^5';\n",
+            "import { h } from 'npm:const-assertion@^8';\n",
+            "import type { T } from './types.ts';\n",
+            "export function g(a: A, b: B, d: D, e: E, t: T) {\n",
+            "  f!.run(new C(), await import('npm:dynamic@^6'));\n",
+            "}\n",
+            "export const values = [h] as const;\n",
+          ),
+        )
+        .add_local_file(
+          "/types.ts",
+          concat!(
+            "import { value } from 'npm:types-module@^7';\n",
+            "export const other = value;\n",
+            "export type T = typeof other;\n",
+          ),
+        );
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  let dependency = |name: &str, version: &str| Dependency {
+    name: name.to_string(),
+    version: version.to_string(),
+    kind: DependencyKind::Dependency,
+  };
+  assert_eq!(
+    result.main.dependencies,
+    &[
+      dependency("const-assertion", "^8"),
+      dependency("dynamic", "^6"),
+      dependency("non-null", "^5"),
+      dependency("runtime", "^3"),
+      dependency("sub-path", "^4"),
+    ]
+  );
+  // a module that's only imported for its types doesn't import its
+  // dependencies at runtime either
+  assert_eq!(
+    result.main.type_only_dependencies,
+    &[
+      dependency("import-type", "^1"),
+      dependency("type-position", "^2"),
+      dependency("types-module", "^7"),
+    ]
+  );
+}
+
+#[tokio::test]
+async fn npm_specifier_unify_versions() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/mod.ts", "import 'npm:chalk@5';\nimport './a.ts';\n")
+        .add_local_file("/a.ts", "import 'npm:chalk@^5.3.0/ansi-styles';\n")
+        .add_local_file("/mod.test.ts", "import 'npm:chalk@~5.3';\n");
+    })
+    .add_test_entry_point("file:///mod.test.ts")
+    .transform()
+    .await
+    .unwrap();
+
+  assert_eq!(result.warnings, Vec::<String>::new());
+  assert_eq!(
+    result.main.dependencies,
+    &[Dependency {
+      name: "chalk".to_string(),
+      version: "~5.3".to_string(),
+      kind: DependencyKind::Dependency,
+    }]
+  );
+  assert_eq!(result.test.dependencies, &[]);
+}
+
+#[tokio::test]
+async fn npm_specifier_unify_partially_overlapping_versions() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          "import 'npm:chalk@~5.2.3';\nimport './a.ts';\n",
+        )
+        .add_local_file("/a.ts", "import 'npm:chalk@^5.2.5';\n");
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  // neither range contains the other, so the intersection is written in the
+  // npm syntax
+  assert_eq!(result.warnings, Vec::<String>::new());
+  assert_eq!(
+    result.main.dependencies,
+    &[Dependency {
+      name: "chalk".to_string(),
+      version: ">=5.2.5 <5.3.0".to_string(),
+      kind: DependencyKind::Dependency,
+    }]
+  );
+}
+
+#[tokio::test]
+async fn npm_specifier_non_overlapping_versions() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          "import 'npm:chalk@4';\nimport './a.ts';\nimport './b.ts';\n",
+        )
+        .add_local_file("/a.ts", "import 'npm:chalk@^5.3.0';\n")
+        .add_local_file("/b.ts", "import 'npm:chalk@^5.3.0';\n");
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  assert_eq!(
+    result.main.dependencies,
+    &[Dependency {
+      name: "chalk".to_string(),
+      version: "^5.3.0".to_string(),
+      kind: DependencyKind::Dependency,
+    }]
+  );
+  assert_eq!(
+    result.warnings,
+    &[concat!(
+      "The npm package chalk was requested with versions that don't overlap, so ^5.3.0 was used.\n",
+      "  * 4 requested by file:///mod.ts\n",
+      "  * ^5.3.0 requested by file:///a.ts, file:///b.ts\n",
+      "  Import chain: file:///mod.ts -> npm:chalk@4",
+    )]
+  );
+}
+
+#[tokio::test]
+async fn npm_types_specifier() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import type { Node } from 'unist';
+",
+            "import type { Core } from 'babel-core';
+",
+            "export type { Core, Node };
+",
+          ),
+        )
+        .add_local_file(
+          "/deno.json",
+          r#"{
+  "imports": {
+    "unist": "npm:@types/unist@3.0.3",
+    "babel-core": "npm:@types/babel__core@7.20.5"
+  }
+}"#,
+        );
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  // a declaration file package is imported by the name of the package
+  // it provides the declarations for
+  assert_files!(
+    result.main.files,
+    &[(
+      "mod.ts",
+      concat!(
+        "import type { Node } from 'unist';
+",
+        "import type { Core } from '@babel/core';
+",
+        "export type { Core, Node };
+",
+      )
+    )]
+  );
+  // only imported with `import type`
+  assert!(result.main.dependencies.is_empty());
+  assert_eq!(
+    result.main.type_only_dependencies,
+    &[
+      Dependency {
+        name: "@types/babel__core".to_string(),
+        version: "7.20.5".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "@types/unist".to_string(),
+        version: "3.0.3".to_string(),
+        kind: DependencyKind::Dependency,
+      }
+    ]
+  );
+}
+
+#[tokio::test]
+async fn transform_uses_lockfile_remote_checksum() {
+  // a deno.lock with a non-matching remote checksum should cause an
+  // integrity error, proving the lockfile is being used
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/deno.json", "{}")
+        .add_local_file(
+          "/deno.lock",
+          concat!(
+            "{\n",
+            "  \"version\": \"5\",\n",
+            "  \"remote\": {\n",
+            "    \"https://localhost/mod.ts\": \"0000000000000000000000000000000000000000000000000000000000000000\"\n",
+            "  }\n",
+            "}\n"
+          ),
+        )
+        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
+        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+    })
+    .transform()
+    .await
+    .err()
+    .unwrap();
+
+  assert!(
+    result
+      .to_string()
+      .to_lowercase()
+      .contains("integrity check failed"),
+    "unexpected error: {:#}",
+    result
+  );
+}
+
+#[tokio::test]
+async fn transform_uses_lockfile_matching_remote_checksum() {
+  // a deno.lock with a matching remote checksum should transform successfully
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/deno.json", "{}")
+        .add_local_file(
+          "/deno.lock",
+          concat!(
+            "{\n",
+            "  \"version\": \"5\",\n",
+            "  \"remote\": {\n",
+            "    \"https://localhost/mod.ts\": \"35c146f76e129477c64061bc84511e1090f3d4d8059713e6663dd4b35b1f7642\"\n",
+            "  }\n",
+            "}\n"
+          ),
+        )
+        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
+        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "import './deps/localhost/mod.js';"),
+      ("deps/localhost/mod.ts", "console.log(1);"),
+    ]
+  );
+}
+
+#[tokio::test]
+async fn transform_ignores_unrelated_lockfile_redirects() {
+  // the lockfile is for the whole project, so it may have redirects for
+  // modules that aren't reachable from the entry points being transformed
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/deno.json", "{}")
+        .add_local_file(
+          "/deno.lock",
+          concat!(
+            "{\n",
+            "  \"version\": \"5\",\n",
+            "  \"redirects\": {\n",
+            "    \"https://localhost/unrelated.ts\": \"https://localhost/unrelated/mod.ts\"\n",
+            "  }\n",
+            "}\n"
+          ),
+        )
+        .add_local_file("/mod.ts", "console.log(1);");
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(result.main.files, &[("mod.ts", "console.log(1);")]);
+}
+
+#[tokio::test]
+async fn transform_frozen_lockfile_errors_for_missing_remote() {
+  // a frozen lockfile should error rather than resolve a dependency
+  // that's not in it
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/deno.json", "{}")
+        .add_local_file(
+          "/deno.lock",
+          concat!("{\n", "  \"version\": \"5\"\n", "}\n"),
+        )
+        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
+        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+    })
+    .set_frozen_lockfile(true)
+    .transform()
+    .await
+    .err()
+    .unwrap();
+
+  assert!(
+    result.to_string().contains("The lockfile is out of date."),
+    "unexpected error: {:#}",
+    result
+  );
+}
+
+#[tokio::test]
+async fn transform_frozen_lockfile_via_config_file() {
+  // the `lock.frozen` setting in the deno.json should be respected
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/deno.json", r#"{ "lock": { "frozen": true } }"#)
+        .add_local_file(
+          "/deno.lock",
+          concat!("{\n", "  \"version\": \"5\"\n", "}\n"),
+        )
+        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
+        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+    })
+    .transform()
+    .await
+    .err()
+    .unwrap();
+
+  assert!(
+    result.to_string().contains("The lockfile is out of date."),
+    "unexpected error: {:#}",
+    result
+  );
+}
+
+#[tokio::test]
+async fn transform_locked_dependency_versions() {
+  let mut builder = TestBuilder::new();
+  builder
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/deno.json", "{}")
+        .add_local_file(
+          "/deno.lock",
+          r#"{
+  "version": "5",
+  "specifiers": {
+    "jsr:@scope/inherited@~2.0.0": "2.0.3",
+    "jsr:@scope/name@^1.0.0": "1.4.0",
+    "npm:chalk@5": "5.3.0",
+    "npm:chalk@^5.1": "5.2.0"
+  },
+  "jsr": {
+    "@scope/inherited@2.0.3": { "integrity": "abc" },
+    "@scope/name@1.4.0": { "integrity": "abc" }
+  },
+  "npm": {
+    "chalk@5.2.0": { "integrity": "sha512-abc" },
+    "chalk@5.3.0": { "integrity": "sha512-abc" }
+  }
+}"#,
+        )
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import 'npm:chalk@5';\n",
+            "import 'npm:chalk@^5.1';\n",
+            "import 'jsr:@scope/name@^1.0.0';\n",
+            "import 'jsr:@scope/inherited@~2.0.0';\n",
+          ),
+        );
+    })
+    // the mapping specifies the version, so it's kept
+    .add_package_specifier_mapping(
+      "jsr:@scope/name",
+      "scope-name",
+      Some("^1.0.0"),
+      None,
+    )
+    .add_package_specifier_mapping(
+      "jsr:@scope/inherited",
+      "scope-inherited",
+      None,
+      None,
+    );
+
+  let result = builder.transform().await.unwrap();
+  // the lockfile is only checked when the versions are derived from it
+  assert_eq!(result.warnings, Vec::<String>::new());
+  assert_eq!(
+    result.main.dependencies,
+    &[
+      Dependency {
+        name: "chalk".to_string(),
+        version: "^5.1".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "scope-inherited".to_string(),
+        version: "~2.0.0".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "scope-name".to_string(),
+        version: "^1.0.0".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+    ]
+  );
+
+  let result = builder
+    .set_locked_dependency_versions(LockedDependencyVersions::Caret)
+    .transform()
+    .await
+    .unwrap();
+  assert_eq!(
+    result.main.dependencies,
+    &[
+      Dependency {
+        name: "chalk".to_string(),
+        version: "^5.3.0".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "scope-inherited".to_string(),
+        version: "^2.0.3".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "scope-name".to_string(),
+        version: "^1.0.0".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+    ]
+  );
+
+  let result = builder
+    .set_locked_dependency_versions(LockedDependencyVersions::Exact)
+    .transform()
+    .await
+    .unwrap();
+  // the version requirement allows older minor releases than were tested
+  assert_eq!(
+    result.warnings,
+    &[concat!(
+      "The version requirement of npm:chalk@5 allows versions far from 5.3.0, which the deno.lock file locks it to. Consider narrowing it (ex. ^5.3.0).\n",
+      "  Import chain: file:///mod.ts -> npm:chalk@5",
+    )]
+  );
+  assert_eq!(
+    result.main.dependencies,
+    &[
+      Dependency {
+        name: "chalk".to_string(),
+        version: "5.3.0".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "scope-inherited".to_string(),
+        version: "2.0.3".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+      Dependency {
+        name: "scope-name".to_string(),
+        version: "^1.0.0".to_string(),
+        kind: DependencyKind::Dependency,
+      },
+    ]
+  );
+}
+
+#[tokio::test]
+async fn transform_locked_dependency_versions_drift() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/deno.json", "{}")
+        .add_local_file(
+          "/deno.lock",
+          r#"{
+  "version": "5",
+  "specifiers": {
+    "npm:chalk@5": "4.1.2"
+  },
+  "npm": {
+    "chalk@4.1.2": { "integrity": "sha512-abc" }
+  }
+}"#,
+        )
+        .add_local_file("/mod.ts", "import 'npm:chalk@5';\n");
+    })
+    .set_locked_dependency_versions(LockedDependencyVersions::Caret)
+    .transform()
+    .await
+    .unwrap();
+
+  assert_eq!(
+    result.warnings,
+    &[concat!(
+      "The deno.lock file locks npm:chalk@5 to 4.1.2, which does not match its version requirement of 5.\n",
+      "  Import chain: file:///mod.ts -> npm:chalk@5",
+    )]
+  );
+  assert_eq!(
+    result.main.dependencies,
+    &[Dependency {
+      name: "chalk".to_string(),
+      version: "^4.1.2".to_string(),
+      kind: DependencyKind::Dependency,
+    }]
+  );
+}
+
+#[tokio::test]
+async fn transform_locked_dependency_versions_no_lockfile() {
+  let error_message = TestBuilder::new()
+    .with_loader(|loader| {
+      loader.add_local_file("/mod.ts", "import 'npm:chalk@5';\n");
+    })
+    .set_locked_dependency_versions(LockedDependencyVersions::Exact)
+    .transform()
+    .await
+    .err()
+    .unwrap();
+
+  assert_eq!(
+    error_message.to_string(),
+    "Deriving the dependency versions from the lockfile requires a deno.lock file"
+  );
+}
+
+#[tokio::test]
+async fn transform_frozen_lockfile_up_to_date() {
+  // a frozen lockfile that has all the dependencies should transform
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/deno.json", "{}")
+        .add_local_file(
+          "/deno.lock",
+          concat!(
+            "{\n",
+            "  \"version\": \"5\",\n",
+            "  \"remote\": {\n",
+            "    \"https://localhost/mod.ts\": \"35c146f76e129477c64061bc84511e1090f3d4d8059713e6663dd4b35b1f7642\"\n",
+            "  }\n",
+            "}\n"
+          ),
+        )
+        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
+        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+    })
+    .set_frozen_lockfile(true)
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "import './deps/localhost/mod.js';"),
+      ("deps/localhost/mod.ts", "console.log(1);"),
+    ]
+  );
+}
+
+#[tokio::test]
+async fn transform_frozen_lockfile_false_overrides_config_file() {
+  // explicitly opting out should win over the deno.json setting
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/deno.json", r#"{ "lock": { "frozen": true } }"#)
+        .add_local_file(
+          "/deno.lock",
+          concat!("{\n", "  \"version\": \"5\"\n", "}\n"),
+        )
+        .add_local_file("/mod.ts", "import 'https://localhost/mod.ts';")
+        .add_remote_file("https://localhost/mod.ts", "console.log(1);");
+    })
+    .set_frozen_lockfile(false)
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "import './deps/localhost/mod.js';"),
+      ("deps/localhost/mod.ts", "console.log(1);"),
+    ]
+  );
+}
+
+fn get_shim_file_text(mut text: String) -> String {
+  text.push('\n');
+  text.push_str(
+    &include_str!("../src/scripts/createMergeProxy.ts")
+      .replace("export function", "function"),
+  );
+  text
+}
+
+/// Adds a config file in a parent directory of the `/sub_dir/mod.ts`
+/// entry point so that it's only found by searching upwards from it.
+fn add_config_discovery_files(loader: &mut InMemoryLoader) {
+  loader
+    .add_local_file(
+      "/sub_dir/mod.ts",
+      "import * as remote from 'localhost/mod.ts';",
+    )
+    .add_local_file(
+      "/deno.json",
+      r#"{
+  "imports": {
+    "localhost/mod.ts": "./other.ts"
+  }
+}"#,
+    )
+    .add_local_file("/other.ts", "export function test() {}");
+}
+
+#[tokio::test]
+async fn transform_platform_and_conditions() {
+  fn get_builder() -> TestBuilder {
+    let mut builder = TestBuilder::new();
+    builder.with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/package.json",
+          r##"{
+  "imports": {
+    "#env": {
+      "react-server": "./server.ts",
+      "browser": "./browser.ts",
+      "default": "./node.ts"
+    }
+  }
+}"##,
+        )
+        .add_local_file("/server.ts", "export const env = 'server';")
+        .add_local_file("/browser.ts", "export const env = 'browser';")
+        .add_local_file("/node.ts", "export const env = 'node';")
+        .add_local_file("/mod.ts", "export * from '#env';");
+    });
+    builder
+  }
+
+  let result = get_builder().transform().await.unwrap();
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "export * from './node.js';"),
+      ("node.ts", "export const env = 'node';"),
+    ]
+  );
+
+  let result = get_builder()
+    .set_platform(Platform::Browser)
+    .transform()
+    .await
+    .unwrap();
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "export * from './browser.js';"),
+      ("browser.ts", "export const env = 'browser';"),
+    ]
+  );
+
+  // custom conditions are used by the mapping keys as well
+  let result = get_builder()
+    .set_platform(Platform::Browser)
+    .add_condition("react-server")
+    .add_package_specifier_mapping("#env", "env-server", Some("1.0.0"), None)
+    .transform()
+    .await
+    .unwrap();
+  assert_files!(
+    result.main.files,
+    &[("mod.ts", "export * from 'env-server';")]
+  );
+  assert_eq!(
+    result.main.dependencies,
+    vec![Dependency {
+      name: "env-server".to_string(),
+      version: "1.0.0".to_string(),
+      kind: DependencyKind::Dependency,
+    }]
+  );
+}
+
+#[tokio::test]
+async fn transform_browser_platform() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import * as fs from 'node:fs';\n",
+            "import type { ParsedPath } from 'node:path';\n",
+            "export function read(p: ParsedPath): string {\n",
+            "  if (typeof Deno !== 'undefined') {\n",
+            "    return Deno.readTextFileSync(p.base);\n",
+            "  }\n",
+            "  return fs.readFileSync(p.base, 'utf8');\n",
+            "}\n",
+            "export const url = import.meta.url;\n",
+          ),
+        )
+        .add_local_file(
+          "/mod.test.ts",
+          "import { url } from './mod.ts';\nDeno.test('url', () => {});",
+        );
+    })
+    .add_test_entry_point("file:///mod.test.ts")
+    .set_platform(Platform::Browser)
+    .transform()
+    .await
+    .unwrap();
+
+  assert_eq!(
+    result.warnings,
+    vec![
+      concat!(
+        "file:///mod.ts imports the Node.js built-in module \"node:fs\", ",
+        "which isn't available in the browser.",
+      ),
+      "file:///mod.ts uses the `Deno` global, which isn't available in the browser.",
+    ]
+  );
+  // the import.meta polyfill is only for Node.js
+  assert_eq!(
+    result
+      .main
+      .files
+      .iter()
+      .map(|f| f.file_path.to_string_lossy().into_owned())
+      .collect::<Vec<_>>(),
+    vec!["mod.ts"]
+  );
+}
+
+/// A module that imports `log` from `./log.ts` and exports an `add`
+/// function, a memory, and a global named `weird-name`.
+const WASM_BYTES: &[u8] = &[
+  0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+  0x01, 0x0b, 0x02, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, 0x60, 0x01, 0x7f,
+  0x00, // types
+  0x02, 0x10, 0x01, 0x08, b'.', b'/', b'l', b'o', b'g', b'.', b't', b's', 0x03,
+  b'l', b'o', b'g', 0x00, 0x01, // imports
+  0x03, 0x02, 0x01, 0x00, // functions
+  0x05, 0x03, 0x01, 0x00, 0x01, // memory
+  0x06, 0x06, 0x01, 0x7f, 0x00, 0x41, 0x01, 0x0b, // globals
+  0x07, 0x1d, 0x03, 0x03, b'a', b'd', b'd', 0x00, 0x01, 0x06, b'm', b'e', b'm',
+  b'o', b'r', b'y', 0x02, 0x00, 0x0a, b'w', b'e', b'i', b'r', b'd', b'-', b'n',
+  b'a', b'm', b'e', 0x03, 0x00, // exports
+  0x0a, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a,
+  0x0b, // code
+];
+
+#[tokio::test]
+async fn transform_wasm_module() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          "import { add } from './math.wasm';\nexport const sum = add(1, 2);",
+        )
+        .add_local_file_bytes("/math.wasm", WASM_BYTES)
+        .add_local_file("/log.ts", "export function log(value: number) {}");
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(
+    result.main.files,
+    &[
+      (
+        "mod.ts",
+        concat!(
+          "import \"./_dnt.polyfills.js\";\n",
+          "import { add } from './math.js';\n",
+          "export const sum = add(1, 2);",
+        ),
+      ),
+      ("log.ts", "export function log(value: number) {}"),
+      (
+        "math.js",
+        concat!(
+          "import { readFileSync } from \"node:fs\";\n",
+          "import * as wasmImport0 from \"./log.js\";\n",
+          "\n",
+          "const wasmInstance = new WebAssembly.Instance(\n",
+          "  new WebAssembly.Module(readFileSync(new URL(\"./math.wasm\", import.meta.url))),\n",
+          "  {\n",
+          "    \"./log.ts\": wasmImport0,\n",
+          "  },\n",
+          ");\n",
+          "export const add = wasmInstance.exports[\"add\"];\n",
+          "export const memory = wasmInstance.exports[\"memory\"];\n",
+          "const wasmExport2 = wasmInstance.exports[\"weird-name\"].value;\n",
+          "export { wasmExport2 as \"weird-name\" };\n",
+        ),
+      ),
+      (
+        "math.d.ts",
+        concat!(
+          "import { \"log\" as __deno_wasm_import_0__ } from \"./log.js\";\n",
+          "export declare function add(arg0: number, arg1: number): number;\n",
+          "export declare const memory: WebAssembly.Memory;\n",
+          "declare const __deno_wasm_export_2__: number;\n",
+          "export { __deno_wasm_export_2__ as \"weird-name\" };\n",
+        ),
+      ),
+      (
+        "_dnt.polyfills.ts",
+        include_str!("../src/polyfills/scripts/deno.import-meta.ts"),
+      ),
+    ]
+  );
+  assert_eq!(
+    result.main.assets,
+    vec![OutputAsset {
+      file_path: PathBuf::from("math.wasm"),
+      bytes: WASM_BYTES.to_vec(),
+    }]
+  );
+}
+
+#[tokio::test]
+async fn transform_text_and_bytes_imports() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import text from './data.txt' with { type: 'text' };\n",
+            "import bytes from './image.bin' with { type: 'bytes' };\n",
+            "export { bytes, text };",
+          ),
+        )
+        .add_local_file("/data.txt", "hello")
+        .add_local_file_bytes("/image.bin", &[0, 1, 2, 3, 4, 5]);
+    })
+    .set_asset_inline_limit(5)
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(
+    result.main.files,
+    &[
+      (
+        "mod.ts",
+        concat!(
+          "import \"./_dnt.polyfills.js\";\n",
+          "import text from './data.txt.js';\n",
+          "import bytes from './image.bin.js';\n",
+          "export { bytes, text };",
+        ),
+      ),
+      (
+        "data.txt.ts",
+        "const text: string = \"hello\";\nexport default text;\n",
+      ),
+      (
+        "image.bin.ts",
+        concat!(
+          "import { readFileSync } from \"node:fs\";\n\n",
+          "const bytes: Uint8Array = new Uint8Array(readFileSync(new URL(\"./image.bin\", import.meta.url)));\n",
+          "export default bytes;\n",
+        ),
+      ),
+      (
+        "_dnt.polyfills.ts",
+        include_str!("../src/polyfills/scripts/deno.import-meta.ts"),
+      ),
+    ]
+  );
+  assert_eq!(
+    result.main.assets,
+    vec![OutputAsset {
+      file_path: PathBuf::from("image.bin"),
+      bytes: vec![0, 1, 2, 3, 4, 5],
+    }]
+  );
+}
+
+#[tokio::test]
+async fn transform_text_import_also_imported_as_module() {
+  let err_message = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import text from './data.ts' with { type: 'text' };\n",
+            "import './data.ts';\n",
+          ),
+        )
+        .add_local_file("/data.ts", "export {};");
+    })
+    .transform()
+    .await
+    .err()
+    .unwrap();
+
+  assert_eq!(
+    err_message.to_string(),
+    concat!(
+      "file:///data.ts is imported with a type of \"text\" and also as a module, which isn't supported.\n",
+      "    at file:///mod.ts:1:18",
+    )
+  );
+}
+
+#[tokio::test]
+async fn transform_import_meta_url_references() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import './sub/mod.ts';\n",
+            "const template = new URL('./templates/page.html', import.meta.url);\n",
+            "const missing = new URL('./missing.txt', import.meta.url);\n",
+          ),
+        )
+        .add_local_file(
+          "/sub/mod.ts",
+          concat!(
+            "new URL('../templates/page.html', import.meta.url);\n",
+            "new URL('./data.bin', import.meta.url);\n",
+            "new URL(`./ignored.txt`, import.meta.url);\n",
+            "new URL('./ignored.txt', 'https://deno.land');\n",
+            "{\n",
+            "  class URL { constructor(a: string, b: string) {} }\n",
+            "  new URL('./ignored.txt', import.meta.url);\n",
+            "}\n",
+          ),
+        )
+        .add_local_file("/templates/page.html", "<html></html>")
+        .add_local_file_bytes("/sub/data.bin", &[0, 1, 2])
+        .add_local_file("/sub/ignored.txt", "");
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  assert_eq!(
+    result.main.assets,
+    vec![
+      OutputAsset {
+        file_path: PathBuf::from("templates/page.html"),
+        bytes: b"<html></html>".to_vec(),
+      },
+      OutputAsset {
+        file_path: PathBuf::from("sub/data.bin"),
+        bytes: vec![0, 1, 2],
+      },
+    ]
+  );
+  assert_eq!(
+    result.warnings,
+    vec![concat!(
+      "file:///mod.ts references file:///missing.txt with `new URL`, but it wasn't found.\n",
+      "    at file:///mod.ts:3:17",
+    )]
+  );
+}
+
+#[tokio::test]
+async fn transform_worker_scripts() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "export const worker = new Worker(new URL('./workers/worker.ts', import.meta.url), { type: 'module' });\n",
+            "new URL('./data.txt', import.meta.url);\n",
+          ),
+        )
+        .add_local_file(
+          "/workers/worker.ts",
+          concat!(
+            "import { value } from './dep.ts';\n",
+            "Deno.writeTextFileSync('test', value);\n",
+          ),
+        )
+        .add_local_file("/workers/dep.ts", "export const value = '';")
+        .add_local_file("/data.txt", "");
+    })
+    .add_default_shims()
+    .transform()
+    .await
+    .unwrap();
+
+  assert_files!(
+    result.main.files,
+    &[
+      (
+        "mod.ts",
+        concat!(
+          "import \"./_dnt.polyfills.js\";\n",
+          "export const worker = new Worker(new URL('./workers/worker.js', import.meta.url), { type: 'module' });\n",
+          "new URL('./data.txt', import.meta.url);\n",
+        ),
+      ),
+      (
+        "workers/worker.ts",
+        concat!(
+          "import \"../_dnt.polyfills.js\";\n",
+          "import * as dntShim from \"../_dnt.shims.js\";\n",
+          "import { value } from './dep.js';\n",
+          "dntShim.Deno.writeTextFileSync('test', value);\n",
+        ),
+      ),
+      ("workers/dep.ts", "export const value = '';"),
+      (
+        "_dnt.polyfills.ts",
+        include_str!("../src/polyfills/scripts/deno.import-meta.ts"),
+      ),
+      (
+        "_dnt.shims.ts",
+        &get_shim_file_text(
+          concat!(
+            "import { Deno } from \"@deno/shim-deno\";\n",
+            "export { Deno } from \"@deno/shim-deno\";\n",
+            "import { setTimeout, setInterval } from \"@deno/shim-timers\";\n",
+            "export { setTimeout, setInterval } from \"@deno/shim-timers\";\n",
+            "\n",
+            "const dntGlobals = {\n",
+            "  Deno,\n",
+            "  setTimeout,\n",
+            "  setInterval,\n",
+            "};\n",
+            "export const dntGlobalThis = createMergeProxy(globalThis, dntGlobals);\n",
+          )
+          .to_string(),
+        ),
+      ),
+    ]
+  );
+  // the worker isn't an entry point of the package
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
+  assert_eq!(
+    result.main.worker_entry_points,
+    &[PathBuf::from("workers/worker.ts")]
+  );
+  assert!(result.test.files.is_empty());
+}
+
+#[tokio::test]
+async fn transform_source_maps() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "// comment\n",
+            "import { value } from './dep.ts';\n",
+            "console.log(import.meta.url, value);\n",
+          ),
+        )
+        .add_local_file("/dep.ts", "export const value = 5;");
+    })
+    .set_source_maps(SourceMaps::WithSourcesContent)
+    .transform()
+    .await
+    .unwrap();
+
+  let get_source_map = |file_path: &str| {
+    let file = result
+      .main
+      .files
+      .iter()
+      .find(|f| f.file_path == PathBuf::from(file_path))
+      .unwrap();
+    serde_json::from_str::<serde_json::Value>(file.source_map.as_ref().unwrap())
+      .unwrap()
+  };
+  let source_map = get_source_map("mod.ts");
+  assert_eq!(source_map["sources"], serde_json::json!(["file:///mod.ts"]));
+  assert_eq!(
+    source_map["sourcesContent"],
+    serde_json::json!([concat!(
+      "// comment\n",
+      "import { value } from './dep.ts';\n",
+      "console.log(import.meta.url, value);\n",
+    )])
+  );
+  // the lines after the prepended polyfill import are shifted down
+  assert_eq!(source_map["mappings"], "AAAA;;;AACA,uBAAuB,QAAQ;AAC/B");
+  let source_map = get_source_map("dep.ts");
+  assert_eq!(source_map["sources"], serde_json::json!(["file:///dep.ts"]));
+  assert_eq!(source_map["mappings"], "AAAA");
+  // generated files don't have a source map
+  assert!(result
+    .main
+    .files
+    .iter()
+    .find(|f| f.file_path == PathBuf::from("_dnt.polyfills.ts"))
+    .unwrap()
+    .source_map
+    .is_none());
+}
+
+#[tokio::test]
+async fn transform_output_file_metadata() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import * as other from 'http://localhost/mod.js';\n",
+            "console.log(import.meta.url, other);\n",
+          ),
+        )
+        .add_remote_file_with_headers(
+          "http://localhost/mod.js",
+          "export {}",
+          &[("x-typescript-types", "./mod.d.ts")],
+        )
+        .add_remote_file("http://localhost/mod.d.ts", "export {};");
+    })
+    .transform()
+    .await
+    .unwrap();
+
+  let mut files = result
+    .main
+    .files
+    .iter()
+    .map(|f| {
+      (
+        f.file_path.to_string_lossy().replace('\\', "/"),
+        f.specifier.to_string(),
+        f.media_type,
+        f.kind,
+      )
+    })
+    .collect::<Vec<_>>();
+  files.sort_by(|a, b| a.0.cmp(&b.0));
+  assert_eq!(
+    files,
+    vec![
+      (
+        "_dnt.polyfills.ts".to_string(),
+        "dnt://_dnt.polyfills.ts".to_string(),
+        MediaType::TypeScript,
+        OutputFileKind::Synthetic,
+      ),
+      (
+        "deps/localhost/mod.d.ts".to_string(),
+        "http://localhost/mod.d.ts".to_string(),
+        MediaType::Dts,
+        OutputFileKind::Types,
+      ),
+      (
+        "deps/localhost/mod.js".to_string(),
+        "http://localhost/mod.js".to_string(),
+        MediaType::JavaScript,
+        OutputFileKind::Remote,
+      ),
+      (
+        "mod.ts".to_string(),
+        "file:///mod.ts".to_string(),
+        MediaType::TypeScript,
+        OutputFileKind::Local,
+      ),
+    ]
+  );
+  for file in &result.main.files {
+    assert_eq!(
+      file.content_hash,
+      LoaderChecksum::r#gen(file.file_text.as_bytes()),
+    );
+    assert_eq!(file.content_hash.len(), 64);
+  }
+
+  let specifier = ModuleSpecifier::parse("http://localhost/mod.js").unwrap();
+  let file_path = PathBuf::from("deps/localhost/mod.js");
+  assert_eq!(
+    result.mappings.try_get_file_path(&specifier),
+    Some(&file_path)
+  );
+  assert_eq!(
+    result.mappings.try_get_specifier(&file_path),
+    Some(&specifier)
+  );
+  let unknown = ModuleSpecifier::parse("http://localhost/other.js").unwrap();
+  assert_eq!(result.mappings.try_get_file_path(&unknown), None);
+  assert_eq!(
+    result
+      .mappings
+      .try_get_specifier(&PathBuf::from("other.js")),
+    None
+  );
+  // serialized as an object of file paths keyed by specifier
+  assert_eq!(
+    serde_json::to_value(&result.mappings).unwrap(),
+    serde_json::json!({
+      "dnt://_dnt.polyfills.ts": "_dnt.polyfills.ts",
+      "dnt://_dnt.shims.ts": "_dnt.shims.ts",
+      "dnt://_dnt.test_polyfills.ts": "_dnt.test_polyfills.ts",
+      "dnt://_dnt.test_shims.ts": "_dnt.test_shims.ts",
+      "file:///mod.ts": "mod.ts",
+      "http://localhost/mod.d.ts": "deps/localhost/mod.d.ts",
+      "http://localhost/mod.js": "deps/localhost/mod.js",
+    })
+  );
+}
+
+#[tokio::test]
+async fn transform_output_graph() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          concat!(
+            "import 'http://localhost/pkg.ts';\n",
+            "import 'http://localhost/remote.ts';\n",
+          ),
+        )
+        .add_local_file("/cli.ts", "import './mod.ts';")
+        .add_local_file("/mod.test.ts", "import './mod.ts';")
+        .add_remote_file("http://localhost/pkg.ts", "export {};")
+        .add_remote_file("http://localhost/remote.ts", "export {};");
+    })
+    .add_package_specifier_mapping(
+      "http://localhost/pkg.ts",
+      "pkg",
+      Some("^1.0.0"),
+      None,
+    )
+    .add_bin_entry_point("file:///cli.ts")
+    .add_test_entry_point("file:///mod.test.ts")
+    .transform()
+    .await
+    .unwrap();
+
+  let package = PackageMappedSpecifier {
+    name: "pkg".to_string(),
+    version: Some("^1.0.0".to_string()),
+    sub_path: None,
+    kind: DependencyKind::Dependency,
+  };
+  assert_eq!(
+    result.graph.modules,
+    vec![
+      OutputGraphModule {
+        specifier: "file:///cli.ts".to_string(),
+        environment: OutputGraphEnvironment::BinOnly,
+        file_path: Some(PathBuf::from("cli.ts")),
+        mapped_package: None,
+      },
+      OutputGraphModule {
+        specifier: "file:///mod.test.ts".to_string(),
+        environment: OutputGraphEnvironment::Test,
+        file_path: Some(PathBuf::from("mod.test.ts")),
+        mapped_package: None,
+      },
+      OutputGraphModule {
+        specifier: "file:///mod.ts".to_string(),
+        environment: OutputGraphEnvironment::Main,
+        file_path: Some(PathBuf::from("mod.ts")),
+        mapped_package: None,
+      },
+      OutputGraphModule {
+        specifier: "http://localhost/pkg.ts".to_string(),
+        environment: OutputGraphEnvironment::Main,
+        file_path: None,
+        mapped_package: Some(package),
+      },
+      OutputGraphModule {
+        specifier: "http://localhost/remote.ts".to_string(),
+        environment: OutputGraphEnvironment::Main,
+        file_path: Some(PathBuf::from("deps/localhost/remote.ts")),
+        mapped_package: None,
+      },
+    ]
+  );
+  let edge = |from: &str, to: &str| OutputGraphEdge {
+    from: from.to_string(),
+    to: to.to_string(),
+    kind: OutputGraphEdgeKind::Code,
+  };
+  assert_eq!(
+    result.graph.edges,
+    vec![
+      edge("file:///cli.ts", "file:///mod.ts"),
+      edge("file:///mod.test.ts", "file:///mod.ts"),
+      edge("file:///mod.ts", "http://localhost/pkg.ts"),
+      edge("file:///mod.ts", "http://localhost/remote.ts"),
+    ]
+  );
+  assert_eq!(
+    result.graph.to_dot(),
+    concat!(
+      "digraph {\n",
+      "  node [shape=box];\n",
+      "  \"file:///cli.ts\" [label=\"file:///cli.ts\\ncli.ts\", color=darkgreen];\n",
+      "  \"file:///mod.test.ts\" [label=\"file:///mod.test.ts\\nmod.test.ts\", color=blue];\n",
+      "  \"file:///mod.ts\" [label=\"file:///mod.ts\\nmod.ts\"];\n",
+      "  \"http://localhost/pkg.ts\" [label=\"http://localhost/pkg.ts\\npkg\"];\n",
+      "  \"http://localhost/remote.ts\" [label=\"http://localhost/remote.ts\\ndeps/localhost/remote.ts\"];\n",
+      "  \"file:///cli.ts\" -> \"file:///mod.ts\";\n",
+      "  \"file:///mod.test.ts\" -> \"file:///mod.ts\";\n",
+      "  \"file:///mod.ts\" -> \"http://localhost/pkg.ts\";\n",
+      "  \"file:///mod.ts\" -> \"http://localhost/remote.ts\";\n",
+      "}\n",
+    )
+  );
+  let json =
+    serde_json::from_str::<serde_json::Value>(&result.graph.to_json()).unwrap();
+  assert_eq!(json["modules"][0]["environment"], "binOnly");
+  assert_eq!(json["modules"][3]["mappedPackage"]["name"], "pkg");
+}
+
+#[tokio::test]
+async fn transform_import_chains() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/mod.ts", "export * from './a.ts';")
+        .add_local_file(
+          "/a.ts",
+          concat!(
+            "export * from 'http://localhost/remote.ts';\n",
+            "export * from 'http://localhost/pkg.ts';\n",
+          ),
+        )
+        .add_local_file("/mod.test.ts", "import 'http://localhost/pkg.ts';")
+        .add_remote_file(
+          "http://localhost/remote.ts",
+          "import * as fs from 'node:fs';\nexport const value = fs;",
+        )
+        .add_remote_file("http://localhost/pkg.ts", "export {};");
+    })
+    .add_package_specifier_mapping(
+      "http://localhost/pkg.ts",
+      "pkg",
+      Some("^1.0.0"),
+      None,
+    )
+    .add_test_entry_point("file:///mod.test.ts")
+    .set_platform(Platform::Browser)
+    .transform()
+    .await
+    .unwrap();
+
+  let chain = |specifiers: &[&str]| {
+    specifiers
+      .iter()
+      .map(|s| ModuleSpecifier::parse(s).unwrap())
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(
+    result.get_import_chain(&PathBuf::from("deps/localhost/remote.ts")),
+    Some(chain(&[
+      "file:///mod.ts",
+      "file:///a.ts",
+      "http://localhost/remote.ts"
+    ]))
+  );
+  assert_eq!(
+    result.get_import_chain(&PathBuf::from("mod.ts")),
+    Some(chain(&["file:///mod.ts"]))
+  );
+  assert_eq!(result.get_import_chain(&PathBuf::from("other.ts")), None);
+  // the shortest chain is from the test entry point
+  assert_eq!(
+    result.get_dependency_import_chain("pkg"),
+    Some(chain(&["file:///mod.test.ts", "http://localhost/pkg.ts"]))
+  );
+  assert_eq!(result.get_dependency_import_chain("other"), None);
+  // the warnings about remote modules say how they got in the output
+  assert_eq!(
+    result.warnings,
+    vec![concat!(
+      "http://localhost/remote.ts imports the Node.js built-in module ",
+      "\"node:fs\", which isn't available in the browser.\n",
+      "  Import chain: file:///mod.ts -> file:///a.ts -> ",
+      "http://localhost/remote.ts",
+    )]
+  );
+}
+
+#[tokio::test]
+async fn transform_incremental_cache() {
+  use sys_traits::FsRead;
+  use sys_traits::FsWrite;
+
+  fn tamper_cache(loader: &mut InMemoryLoader) {
+    let text = loader.sys.fs_read_to_string("/cache/modules.json").unwrap();
+    assert!(text.contains("./dep.js"));
+    loader
+      .sys
+      .fs_write(
+        "/cache/modules.json",
+        text.replace("./dep.js", "./cached.js"),
+      )
+      .unwrap();
   }
+
+  let mut test_builder = TestBuilder::new();
+  test_builder
+    .with_loader(|loader| {
+      loader
+        .add_local_file("/mod.ts", "import { value } from './dep.ts';")
+        .add_local_file("/dep.ts", "export const value = 5;");
+    })
+    .set_incremental_cache_dir("/cache");
+  let result = test_builder.transform().await.unwrap();
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "import { value } from './dep.js';"),
+      ("dep.ts", "export const value = 5;"),
+    ]
+  );
+
+  // the cached analysis is used when nothing affecting the module changed
+  test_builder.with_loader(tamper_cache);
+  let result = test_builder.transform().await.unwrap();
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "import { value } from './cached.js';"),
+      ("dep.ts", "export const value = 5;"),
+    ]
+  );
+  test_builder.with_loader(|loader| {
+    loader.add_local_file("/dep.ts", "export const value = 6;");
+  });
+  let result = test_builder.transform().await.unwrap();
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "import { value } from './cached.js';"),
+      ("dep.ts", "export const value = 6;"),
+    ]
+  );
+
+  // changing the module's text analyzes it again
+  test_builder.with_loader(|loader| {
+    loader.add_local_file("/mod.ts", "import { value } from './dep.ts';\n");
+  });
+  let result = test_builder.transform().await.unwrap();
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "import { value } from './dep.js';\n"),
+      ("dep.ts", "export const value = 6;"),
+    ]
+  );
+
+  // so does changing an option that affects it
+  test_builder.with_loader(tamper_cache).add_default_shims();
+  let result = test_builder.transform().await.unwrap();
+  assert_files!(
+    result.main.files,
+    &[
+      ("mod.ts", "import { value } from './dep.js';\n"),
+      ("dep.ts", "export const value = 6;"),
+    ]
+  );
+}
+
+#[tokio::test]
+async fn transformer_update() {
+  let mut test_builder = TestBuilder::new();
+  test_builder.with_loader(|loader| {
+    loader
+      .add_local_file("/mod.ts", "import { value } from './dep.ts';")
+      .add_local_file("/dep.ts", "export const value = 5;")
+      .add_local_file("/other.ts", "export {};");
+  });
+  let mut transformer = test_builder.transformer();
+  let output = transformer.transform().await.unwrap();
+  assert_eq!(output.main.files.len(), 2);
+
+  // files that aren't in the output don't affect it
+  test_builder.with_loader(|loader| {
+    loader.add_local_file("/other.ts", "export const other = 5;");
+  });
+  let diff = transformer
+    .update(&[PathBuf::from("/other.ts")])
+    .await
+    .unwrap();
+  assert!(diff.is_empty());
+
+  test_builder.with_loader(|loader| {
+    loader
+      .add_local_file(
+        "/dep.ts",
+        concat!(
+          "import 'npm:using-statement@^0.4';\n",
+          "import './added.ts';\n",
+          "export const value = 5;",
+        ),
+      )
+      .add_local_file("/added.ts", "export {};");
+  });
+  let diff = transformer
+    .update(&[PathBuf::from("/dep.ts"), PathBuf::from("/added.ts")])
+    .await
+    .unwrap();
+  assert_files!(diff.added_files, &[("added.ts", "export {};")]);
+  assert_files!(
+    diff.changed_files,
+    &[(
+      "dep.ts",
+      concat!(
+        "import 'using-statement';\n",
+        "import './added.js';\n",
+        "export const value = 5;",
+      ),
+    )]
+  );
+  assert!(diff.removed_files.is_empty());
+  assert_eq!(
+    diff.added_dependencies,
+    vec![Dependency {
+      name: "using-statement".to_string(),
+      version: "^0.4".to_string(),
+      kind: DependencyKind::Dependency,
+    }]
+  );
+  assert!(diff.removed_dependencies.is_empty());
+
+  test_builder.with_loader(|loader| {
+    loader.add_local_file("/mod.ts", "export {};");
+  });
+  let diff = transformer
+    .update(&[PathBuf::from("/mod.ts")])
+    .await
+    .unwrap();
+  assert!(diff.added_files.is_empty());
+  assert_files!(diff.changed_files, &[("mod.ts", "export {};")]);
+  assert_eq!(
+    diff.removed_files,
+    vec![PathBuf::from("added.ts"), PathBuf::from("dep.ts")]
+  );
+  assert!(diff.added_dependencies.is_empty());
+  assert_eq!(
+    diff
+      .removed_dependencies
+      .iter()
+      .map(|d| d.name.as_str())
+      .collect::<Vec<_>>(),
+    vec!["using-statement"]
+  );
+  assert_files!(
+    transformer.output().unwrap().main.files.clone(),
+    &[("mod.ts", "export {};")]
+  );
+}
+
+#[tokio::test]
+async fn transform_emit() {
+  use sys_traits::FsMetadata;
+  use sys_traits::FsRead;
+  use sys_traits::FsWrite;
+
+  let mut test_builder = TestBuilder::new();
+  test_builder.with_loader(|loader| {
+    loader
+      .add_local_file("/mod.ts", "import './sub/dep.ts';")
+      .add_local_file("/sub/dep.ts", "export {};");
+  });
+  let sys = test_builder.sys().clone();
+  let output = test_builder.transform().await.unwrap();
+  let result = output.emit(&sys, Path::new("/out")).unwrap();
+  assert_eq!(
+    result.written_files,
+    vec![PathBuf::from("mod.ts"), PathBuf::from("sub/dep.ts")]
+  );
+  assert!(result.unchanged_files.is_empty());
+  assert!(result.removed_files.is_empty());
+  assert_eq!(
+    sys.fs_read_to_string("/out/mod.ts").unwrap(),
+    "import './sub/dep.js';"
+  );
+
+  // a file that wasn't emitted is left alone
+  sys.fs_write("/out/other.txt", "other").unwrap();
+  test_builder.with_loader(|loader| {
+    loader.add_local_file("/mod.ts", "export const value = 5;");
+  });
+  let output = test_builder.transform().await.unwrap();
+  let result = output.emit(&sys, Path::new("/out")).unwrap();
+  assert_eq!(result.written_files, vec![PathBuf::from("mod.ts")]);
+  assert!(result.unchanged_files.is_empty());
+  assert_eq!(result.removed_files, vec![PathBuf::from("sub/dep.ts")]);
+  assert!(!sys.fs_exists_no_err("/out/sub"));
+  assert!(sys.fs_exists_no_err("/out/other.txt"));
+
+  let result = output.emit(&sys, Path::new("/out")).unwrap();
+  assert!(result.written_files.is_empty());
+  assert_eq!(result.unchanged_files, vec![PathBuf::from("mod.ts")]);
+  assert!(result.removed_files.is_empty());
+}
+
+#[tokio::test]
+async fn transform_emit_source_maps() {
+  use sys_traits::FsRead;
+
+  let mut test_builder = TestBuilder::new();
+  test_builder
+    .with_loader(|loader| {
+      loader.add_local_file("/mod.ts", "export const value = 5;");
+    })
+    .set_source_maps(SourceMaps::Enabled);
+  let sys = test_builder.sys().clone();
+  let output = test_builder.transform().await.unwrap();
+  let result = output.emit(&sys, Path::new("/out")).unwrap();
+  assert!(result.written_files.contains(&PathBuf::from("mod.ts.map")));
+  // the emitted file points at the map beside it
+  assert_eq!(
+    sys.fs_read_to_string("/out/mod.ts").unwrap(),
+    "export const value = 5;\n//# sourceMappingURL=mod.ts.map"
+  );
+  let file = output
+    .main
+    .files
+    .iter()
+    .find(|f| f.file_path == Path::new("mod.ts"))
+    .unwrap();
+  assert_eq!(
+    sys.fs_read_to_string("/out/mod.ts.map").unwrap(),
+    file.source_map.as_deref().unwrap()
+  );
+  // and it's unchanged when emitted again
+  let result = output.emit(&sys, Path::new("/out")).unwrap();
+  assert!(result.written_files.is_empty());
+}
+
+#[tokio::test]
+async fn transform_diff_output_dir() {
+  use sys_traits::FsMetadata;
+  use sys_traits::FsRead;
+  use sys_traits::FsWrite;
+
+  let mut test_builder = TestBuilder::new();
+  test_builder.with_loader(|loader| {
+    loader
+      .add_local_file("/mod.ts", "import './sub/dep.ts';")
+      .add_local_file("/sub/dep.ts", "export {};");
+  });
+  let sys = test_builder.sys().clone();
+  let output = test_builder.transform().await.unwrap();
+  let diff = output.diff_output_dir(&sys, Path::new("/out")).unwrap();
+  assert_eq!(
+    diff.added_files,
+    vec![PathBuf::from("mod.ts"), PathBuf::from("sub/dep.ts")]
+  );
+  output.emit(&sys, Path::new("/out")).unwrap();
+  assert_eq!(
+    output.diff_output_dir(&sys, Path::new("/out")).unwrap(),
+    OutputDirDiff::default()
+  );
+
+  sys.fs_write("/out/other.txt", "other").unwrap();
+  test_builder.with_loader(|loader| {
+    loader
+      .add_local_file(
+        "/mod.ts",
+        concat!(
+          "import * as pkg from 'npm:using-statement@^0.4';\n",
+          "import './new.ts';\n",
+          "console.log(pkg);\n",
+        ),
+      )
+      .add_local_file("/new.ts", "export {};");
+  });
+  let output = test_builder.transform().await.unwrap();
+  let diff = output.diff_output_dir(&sys, Path::new("/out")).unwrap();
+  assert_eq!(
+    diff,
+    OutputDirDiff {
+      added_files: vec![PathBuf::from("new.ts")],
+      // a file that wasn't emitted isn't removed
+      removed_files: vec![PathBuf::from("sub/dep.ts")],
+      modified_files: vec![ModifiedFile {
+        file_path: PathBuf::from("mod.ts"),
+        text_diff: Some(
+          concat!(
+            "--- a/mod.ts\n",
+            "+++ b/mod.ts\n",
+            "@@ -1,1 +1,3 @@\n",
+            "-import './sub/dep.js';\n",
+            "+import * as pkg from 'using-statement';\n",
+            "+import './new.js';\n",
+            "+console.log(pkg);\n",
+          )
+          .to_string()
+        ),
+      }],
+      added_dependencies: vec![Dependency {
+        name: "using-statement".to_string(),
+        version: "^0.4".to_string(),
+        kind: DependencyKind::Dependency,
+      }],
+      removed_dependencies: Vec::new(),
+    }
+  );
+  // nothing was written
+  assert_eq!(
+    sys.fs_read_to_string("/out/mod.ts").unwrap(),
+    "import './sub/dep.js';"
+  );
+  assert!(!sys.fs_exists_no_err("/out/new.ts"));
+}
+
+#[tokio::test]
+async fn transform_worker_script_mapped_to_package() {
+  let result = TestBuilder::new()
+    .with_loader(|loader| {
+      loader
+        .add_local_file(
+          "/mod.ts",
+          "new Worker(new URL('./worker.ts', import.meta.url), { type: 'module' });",
+        )
+        .add_local_file("/worker.ts", "");
+    })
+    .add_package_specifier_mapping("file:///worker.ts", "worker-pkg", Some("^1"), None)
+    .transform()
+    .await
+    .unwrap();
+
+  // the url is left alone since the worker isn't in the output
+  assert_eq!(
+    result.warnings,
+    vec![concat!(
+      "file:///mod.ts creates a worker with file:///worker.ts, but it's mapped to a package so its script isn't in the output.\n",
+      "    at file:///mod.ts:1:12",
+    )]
+  );
+  let file = result
+    .main
+    .files
+    .iter()
+    .find(|f| f.file_path == Path::new("mod.ts"))
+    .unwrap();
+  assert_eq!(
+    file.file_text,
+    concat!(
+      "import \"./_dnt.polyfills.js\";\n",
+      "new Worker(new URL('./worker.ts', import.meta.url), { type: 'module' });",
+    )
+  );
+  assert_eq!(result.main.entry_points, &[PathBuf::from("mod.ts")]);
+}
+
+#[tokio::test]
+async fn transform_diff_output_dir_build() {
+  use sys_traits::FsCreateDirAll;
+  use sys_traits::FsWrite;
+
+  let mut test_builder = TestBuilder::new();
+  test_builder.with_loader(|loader| {
+    loader
+      .add_local_file(
+        "/mod.ts",
+        concat!(
+          "import * as pkg from 'npm:using-statement@^0.4';\n",
+          "import './dep.ts';\n",
+          "console.log(pkg);\n",
+        ),
+      )
+      .add_local_file("/dep.ts", "export const value = 2;");
+  });
+  let sys = test_builder.sys().clone();
+  let output = test_builder.transform().await.unwrap();
+  // a dnt build's output dir, which wasn't emitted to
+  sys.fs_create_dir_all("/npm/src").unwrap();
+  sys.fs_create_dir_all("/npm/esm").unwrap();
+  sys
+    .fs_write(
+      "/npm/src/mod.ts",
+      concat!(
+        // the shebang that's added to a binary is ignored
+        "#!/usr/bin/env node\n",
+        "import * as pkg from 'using-statement';\n",
+        "import './dep.js';\n",
+        "console.log(pkg);\n",
+      ),
+    )
+    .unwrap();
+  sys
+    .fs_write("/npm/src/dep.ts", "export const value = 1;")
+    .unwrap();
+  sys.fs_write("/npm/src/old.ts", "export {};").unwrap();
+  sys.fs_write("/npm/esm/mod.js", "export {};").unwrap();
+  sys
+    .fs_write(
+      "/npm/package.json",
+      r#"{
+  "name": "pkg",
+  "dependencies": { "using-statement": "^0.4", "chalk": "^5" }
 }"#,
     )
-    .add_local_file("/other.ts", "export function test() {}");
+    .unwrap();
+  let diff = output.diff_output_dir(&sys, Path::new("/npm")).unwrap();
+  assert_eq!(
+    diff,
+    OutputDirDiff {
+      added_files: Vec::new(),
+      removed_files: vec![PathBuf::from("old.ts")],
+      modified_files: vec![ModifiedFile {
+        file_path: PathBuf::from("dep.ts"),
+        text_diff: Some(
+          concat!(
+            "--- a/dep.ts\n",
+            "+++ b/dep.ts\n",
+            "@@ -1,1 +1,1 @@\n",
+            "-export const value = 1;\n",
+            "+export const value = 2;\n",
+          )
+          .to_string()
+        ),
+      }],
+      added_dependencies: Vec::new(),
+      removed_dependencies: vec![Dependency {
+        name: "chalk".to_string(),
+        version: "^5".to_string(),
+        kind: DependencyKind::Dependency,
+      }],
+    }
+  );
+
+  // a directory that's neither emitted to nor built can't be compared
+  sys.fs_create_dir_all("/other").unwrap();
+  let err = output
+    .diff_output_dir(&sys, Path::new("/other"))
+    .unwrap_err();
+  assert_eq!(
+    err.to_string(),
+    concat!(
+      "/other has neither a .dnt-manifest.json file from emitting the ",
+      "output nor the src directory of a build."
+    )
+  );
 }
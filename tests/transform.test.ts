@@ -0,0 +1,38 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+import * as path from "@std/path";
+import { assert, assertEquals } from "@std/assert";
+import { transform } from "../transform.ts";
+
+Deno.test("transform should return the output as plain objects", async () => {
+  const rootDir = await Deno.makeTempDir();
+  try {
+    await Deno.writeTextFile(
+      path.join(rootDir, "mod.ts"),
+      "export const value = 5;\n",
+    );
+    const emitDir = path.join(rootDir, "out");
+    for (const options of [{}, { emitDir }]) {
+      const result = await transform({
+        entryPoints: ["mod.ts"],
+        target: "ES2022",
+        configFile: false,
+        cwd: rootDir,
+        ...options,
+      });
+      assert(!(result instanceof Map));
+      assertEquals(
+        result.main.files.map((f) => [f.filePath, f.fileText]),
+        [["mod.ts", "export const value = 5;\n"]],
+      );
+      assertEquals(result.warnings, []);
+      assert(Object.keys(result.mappings).length > 0);
+    }
+    assertEquals(
+      await Deno.readTextFile(path.join(emitDir, "mod.ts")),
+      "export const value = 5;\n",
+    );
+  } finally {
+    await Deno.remove(rootDir, { recursive: true });
+  }
+});
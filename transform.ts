@@ -43,10 +43,33 @@ export interface PackageMappedSpecifier {
    * name in the module specifier like so: `<package-name>/<sub-path>`
    */
   subPath?: string;
-  /** If this should be a peer dependency. */
+  /** Where the dependency is listed in the package.json file.
+   * @default "dependency"
+   */
+  kind?: DependencyKind;
+  /** If this should be a peer dependency.
+   * @deprecated Use `kind: "peer"` instead.
+   */
   peerDependency?: boolean;
 }
 
+// make sure to update `DependencyKind` in the Rust code when changing this
+/** Where a dependency is listed in the package.json file.
+ *
+ * - `"dependency"` - In `dependencies`.
+ * - `"dev"` - In `devDependencies`.
+ * - `"optional"` - In `optionalDependencies` (ex. a native accelerator).
+ * - `"optionalPeer"` - In `peerDependencies` and marked optional in
+ *   `peerDependenciesMeta` (ex. a plugin that can be swapped out).
+ * - `"peer"` - In `peerDependencies`.
+ */
+export type DependencyKind =
+  | "dependency"
+  | "dev"
+  | "optional"
+  | "optionalPeer"
+  | "peer";
+
 export interface GlobalName {
   /** Name to use as the global name. */
   name: string;
@@ -76,12 +99,33 @@ export interface ModuleShim {
   globalNames: (GlobalName | string)[];
 }
 
+// make sure to update `LockedDependencyVersions` in the Rust code when changing this
+export type LockedDependencyVersions = "exact" | "caret";
+
+// make sure to update `Platform` in the Rust code when changing this
+export type Platform = "node" | "browser";
+
+/** Source maps from the output files to the original modules.
+ *
+ * `"withSourcesContent"` also embeds the text of the original modules.
+ */
+export type SourceMaps = "none" | "enabled" | "withSourcesContent";
+
 export interface TransformOptions {
+  /** Entry points, which may be left empty when `entryPointsFromConfig`
+   * is set. */
   entryPoints: string[];
+  /** Uses the `exports` of the deno.json as the entry points, which must
+   * be the same modules as any non-binary `entryPoints` that are provided.
+   */
+  entryPointsFromConfig?: boolean;
   /** Entry points that are only used as an npm binary, which is a subset
    * of the entry points. */
   binEntryPoints?: string[];
   testEntryPoints?: string[];
+  /** Layout of the emitted package that the output's `packageManifest`
+   * describes. */
+  packageManifest?: PackageManifestOptions;
   shims?: Shim[];
   testShims?: Shim[];
   mappings?: SpecifierMappings;
@@ -90,6 +134,47 @@ export interface TransformOptions {
    * over what `target` implies.
    */
   polyfills?: Partial<Record<PolyfillName, boolean>>;
+  /** Platform that the output runs on, which controls the conditions used
+   * to resolve the `exports` and `imports` of packages.
+   * @default "node"
+   */
+  platform?: Platform;
+  /** Additional conditions to use when resolving the `exports` and `imports`
+   * of packages (ex. `"worker"` or `"react-server"`). */
+  conditions?: string[];
+  /** Files imported as text or bytes that are at most this many bytes are
+   * inlined in the output. Larger files are copied and read at runtime.
+   * @default 4096
+   */
+  assetInlineLimit?: number;
+  /** Generates a source map from each transformed module's output back to
+   * its original specifier.
+   *
+   * The maps are returned on the output files, and `emitDir` writes them
+   * beside the files. Use `chainSourceMaps` to chain them with the maps that
+   * are generated from the output (ex. by the TypeScript compiler), which
+   * is what the dnt build does when source or declaration maps are enabled.
+   * @default "none"
+   */
+  sourceMaps?: SourceMaps;
+  /** Directory to cache the analysis of each module in, which a later
+   * transform reuses for the modules where nothing affecting them changed. */
+  incrementalCacheDir?: string;
+  /** Directory to write the output files and assets to.
+   *
+   * A file that's unchanged isn't written again and the files that a
+   * previous transform wrote there, but that aren't in the output anymore,
+   * are removed.
+   */
+  emitDir?: string;
+  /** Existing output directory to compare the output with, without writing
+   * anything to it. The differences are returned in `outputDirDiff`.
+   *
+   * This is either a directory that the output was emitted to with `emitDir`
+   * or one built by dnt's `build`, whose `src` directory is compared with the
+   * output and whose package.json provides the dependencies. A directory that
+   * doesn't exist yet is compared as if it were empty. */
+  diffDir?: string;
   /// Path or url to the import map.
   importMap?: string;
   /** Path or url to a deno.json.
@@ -108,20 +193,82 @@ export interface TransformOptions {
    * Leave this undefined to use the `lock.frozen` setting in the deno.json file.
    */
   frozenLockfile?: boolean;
+  /** Uses the versions that the deno lock file locks the `npm:` and `jsr:`
+   * specifiers to for the versions of the dependencies, either exactly
+   * (ex. `1.2.3`) or as a caret range (ex. `^1.2.3`).
+   *
+   * A warning is output for a specifier whose version requirement doesn't
+   * match its locked version or allows versions far from it (ex. `npm:pkg@5`
+   * locked at `5.3.0`).
+   *
+   * Leave this undefined to use the version requirements in the specifiers.
+   */
+  lockedDependencyVersions?: LockedDependencyVersions;
+  /** Path or file url to a directory of npm packuments laid out like Deno's
+   * npm cache (ex. `<dir>/@scope/name/registry.json`) that's used to check
+   * that the name and version range of each dependency resolves.
+   */
+  npmRegistryDir?: string;
+  /** Pins each dependency to the exact version its range resolves to.
+   *
+   * This requires `npmRegistryDir`.
+   */
+  pinNpmVersions?: boolean;
   /** Path or file url to the directory that the relative paths in these
    * options resolve from and that a config file, `deno.lock`, and
    * `node_modules` directory are discovered relative to. */
   cwd: string;
 }
 
+// make sure to update `PackageManifestEntryPointKind` in the Rust code when changing this
+export type PackageManifestEntryPointKind = "export" | "bin";
+
+export interface PackageManifestEntryPoint {
+  kind: PackageManifestEntryPointKind;
+  /** Subpath of an export (ex. `./sub`) or the command of a binary.
+   *
+   * An export without one is named `.` when it's the first export and by
+   * its path otherwise. A single binary without one uses the package name
+   * as the command.
+   */
+  name?: string;
+  /** Path or url of one of the entry points. */
+  path: string;
+}
+
+/** Layout of the emitted package that the package manifest describes. */
+export interface PackageManifestOptions {
+  /** The exports and binaries of the package.
+   *
+   * When not specified, the exports are the entry points that aren't only
+   * binaries and the binaries are the binary entry points. When the entry
+   * points are derived from the config file, its exports are always used.
+   */
+  entryPoints?: PackageManifestEntryPoint[];
+  /** Directory that the ES modules are emitted to (ex. `./esm`), which is
+   * used for the `import` condition. */
+  esModuleDir?: string;
+  /** Directory that the script modules are emitted to (ex. `./script`),
+   * which is used for the `require` condition. */
+  scriptModuleDir?: string;
+  /** Directory that the declaration files are emitted to when separate
+   * from the modules (ex. `./types`), which is used for the `types`
+   * condition. */
+  typesDir?: string;
+  /** Names of the packages that the package.json file already lists in its
+   * `dependencies` or `peerDependencies`, so their types packages and type
+   * only dependencies aren't also listed as dev dependencies. */
+  specifiedDependencies?: string[];
+}
+
 /** Dependency in a package.json file. */
 export interface Dependency {
   /** Name of the package. */
   name: string;
   /** Version specifier (ex. `^1.0.0`). */
   version: string;
-  /** If this is suggested to be a peer dependency. */
-  peerDependency?: boolean;
+  /** Where the dependency is suggested to be listed in the package.json file. */
+  kind: DependencyKind;
 }
 
 export interface TransformOutput {
@@ -141,17 +288,198 @@ export interface TransformOutput {
   typesDependencies: Dependency[];
   /** Output files that are only reachable from a binary entrypoint. */
   binOnlyFiles: string[];
+  /** Package described by the deno.json when the entry points were derived
+   * from its `exports`. */
+  configPackage?: ConfigPackage;
+  /** `#` prefixed subpath imports of the package.json file that are kept in
+   * the output because they import local modules. */
+  subpathImports: SubpathImport[];
+  /** The `exports`, `imports`, `bin`, and dependencies of the package.json
+   * file. */
+  packageManifest: PackageManifest;
+  /** Output file paths of the modules keyed by specifier. */
+  mappings: Record<string, string>;
+  /** Modules in the output and the dependencies between them, which shows
+   * why a module was included. Use `graphToDot` to visualize it. */
+  graph: OutputGraph;
+  /** What writing the output to the `emitDir` did. */
+  emitResult?: EmitResult;
+  /** How emitting the output would change the `diffDir`. */
+  outputDirDiff?: OutputDirDiff;
+}
+
+/** Paths relative to the `emitDir` of what emitting the output did. */
+export interface EmitResult {
+  /** Files that were new or whose content changed. */
+  writtenFiles: string[];
+  /** Files that already had the same content, so they weren't written. */
+  unchangedFiles: string[];
+  /** Files that a previous transform wrote, but that aren't in the output. */
+  removedFiles: string[];
+}
+
+/** Paths relative to the `diffDir` (or its `src` directory for a build) of
+ * how emitting the output would change it. */
+export interface OutputDirDiff {
+  /** Files in the output that aren't in the directory. */
+  addedFiles: string[];
+  /** Files in the directory that aren't in the output. */
+  removedFiles: string[];
+  /** Files whose content in the output differs from the directory. */
+  modifiedFiles: ModifiedFile[];
+  addedDependencies: Dependency[];
+  removedDependencies: Dependency[];
+}
+
+export interface ModifiedFile {
+  filePath: string;
+  /** Unified diff from the file in the directory to the output, which is
+   * `undefined` when either isn't text. */
+  textDiff?: string;
+}
+
+export interface OutputGraph {
+  modules: OutputGraphModule[];
+  edges: OutputGraphEdge[];
+}
+
+export interface OutputGraphModule {
+  specifier: string;
+  /** `"binOnly"` is a main module that's only reachable from a binary
+   * entry point. */
+  environment: "main" | "test" | "binOnly";
+  /** Path of the module in the output, which is not set for a module
+   * that's mapped to a package. */
+  filePath?: string;
+  mappedPackage?: PackageMappedSpecifier;
+}
+
+export interface OutputGraphEdge {
+  from: string;
+  to: string;
+  /** `"types"` is an import of the module's types (ex. `@ts-types` or an
+   * `X-TypeScript-Types` header) and `"worker"` is the script of a worker
+   * created by the module. */
+  kind: "code" | "types" | "worker";
+}
+
+export interface SubpathImport {
+  /** Name of the subpath import (ex. `#internal/*`). */
+  name: string;
+  /** Path of the target in the output (ex. `./internal/*.ts`). */
+  target: string;
+}
+
+/** The parts of the package.json file that are derived from the transform. */
+export interface PackageManifest {
+  /** Script module of the first export. */
+  main?: string;
+  /** ES module of the first export. */
+  module?: string;
+  /** Declaration file of the first export. */
+  types?: string;
+  /** Module of the first export for the `browser` field when the platform
+   * is the browser. */
+  browser?: string;
+  /** Entries of the `exports` in the order of the entry points. */
+  exports: PackageManifestExport[];
+  /** Entries of the `imports` for the subpath imports kept in the output.
+   *
+   * The ES module and script module directories each have a package.json
+   * file that's the package scope of their modules, so these go in those.
+   */
+  imports: PackageManifestImport[];
+  /** Entries of the `bin` in the order of the entry points. */
+  bin: PackageManifestBin[];
+  /** Dependencies of the package, which are listed in the section of the
+   * package.json file that their kind says.
+   *
+   * A package may be listed in more than one section (ex. a type only
+   * dependency that's both a dev and an optional peer dependency).
+   */
+  dependencies: Dependency[];
+}
+
+export interface PackageManifestExport {
+  /** Subpath of the export (ex. `.` or `./sub`). */
+  name: string;
+  /** Declaration file for the `types` condition, which applies to each of
+   * the other conditions. */
+  types?: string;
+  /** Module for the `browser` condition when the platform is the browser,
+   * which is the ES module when there is one. */
+  browser?: string;
+  import?: string;
+  require?: string;
+}
+
+export interface PackageManifestImport {
+  /** Name of the subpath import (ex. `#internal/*`). */
+  name: string;
+  /** Target relative to the ES module and script module directories
+   * (ex. `./internal/*.js`). */
+  target: string;
+  /** Target relative to the package for the `types` condition when the
+   * declaration files are emitted separately
+   * (ex. `./types/internal/*.d.ts`). */
+  types?: string;
+}
+
+export interface PackageManifestBin {
+  /** Command of the binary, which is the package name when undefined. */
+  name?: string;
+  path: string;
+}
+
+export interface ConfigPackage {
+  name?: string;
+  version?: string;
+  /** Names of the exports (ex. `.` or `./sub`), which are in the same order
+   * as the entry points of the main environment. */
+  exports: string[];
 }
 
 export interface TransformOutputEnvironment {
   entryPoints: string[];
+  /** Output paths of the scripts of the workers that the modules create,
+   * which are run on their own like an entry point. */
+  workerEntryPoints: string[];
   dependencies: Dependency[];
+  /** Dependencies that are only imported for their types (ex. with
+   * `import type`), so the emitted JavaScript never imports them.
+   */
+  typeOnlyDependencies: Dependency[];
   files: OutputFile[];
+  /** Files that aren't modules, which go beside the emitted modules. */
+  assets: OutputAsset[];
 }
 
 export interface OutputFile {
   filePath: string;
   fileText: string;
+  /** Module the file was transformed from, which is a `dnt://` specifier for
+   * the files that dnt generates. */
+  specifier: string;
+  /** Media type of the module the file was transformed from
+   * (ex. `TypeScript`). */
+  mediaType: string;
+  kind: OutputFileKind;
+  /** SHA-256 checksum of the file's text. */
+  contentHash: string;
+  /** Source map from the file to the module it was transformed from when
+   * `sourceMaps` is enabled. */
+  sourceMap?: string;
+}
+
+/** Where an output file comes from. `"synthetic"` files are generated by
+ * dnt (ex. the polyfills or shims) and `"types"` files are declaration files
+ * that provide the types of another module. */
+export type OutputFileKind = "local" | "remote" | "synthetic" | "types";
+
+/** A file that's copied to the output as-is (ex. a Wasm binary). */
+export interface OutputAsset {
+  filePath: string;
+  bytes: Uint8Array;
 }
 
 /** Analyzes the provided entry point to get all the dependended on modules and
@@ -160,7 +488,7 @@ export interface OutputFile {
 export function transform(
   options: TransformOptions,
 ): Promise<TransformOutput> {
-  if (options.entryPoints.length === 0) {
+  if (options.entryPoints.length === 0 && !options.entryPointsFromConfig) {
     throw new Error("Specify one or more entry points.");
   }
   // all the relative paths in the options resolve from here
@@ -173,16 +501,31 @@ export function transform(
       }),
     ),
     entryPoints: options.entryPoints.map((e) => valueToUrl(e, cwd)),
+    entryPointsFromConfig: options.entryPointsFromConfig ?? false,
     binEntryPoints: (options.binEntryPoints ?? []).map((e) =>
       valueToUrl(e, cwd)
     ),
     testEntryPoints: (options.testEntryPoints ?? []).map((e) =>
       valueToUrl(e, cwd)
     ),
+    packageManifest: mapPackageManifestOptions(options.packageManifest, cwd),
     shims: (options.shims ?? []).map((s) => mapShim(s, cwd)),
     testShims: (options.testShims ?? []).map((s) => mapShim(s, cwd)),
     target: options.target,
     polyfills: options.polyfills ?? {},
+    platform: options.platform ?? "node",
+    conditions: options.conditions ?? [],
+    assetInlineLimit: options.assetInlineLimit ?? 4096,
+    sourceMaps: options.sourceMaps ?? "none",
+    incrementalCacheDir: options.incrementalCacheDir == null
+      ? undefined
+      : valueToUrl(options.incrementalCacheDir, cwd),
+    emitDir: options.emitDir == null
+      ? undefined
+      : valueToUrl(options.emitDir, cwd),
+    diffDir: options.diffDir == null
+      ? undefined
+      : valueToUrl(options.diffDir, cwd),
     importMap: options.importMap == null
       ? undefined
       : valueToUrl(options.importMap, cwd),
@@ -190,15 +533,133 @@ export function transform(
       ? valueToUrl(options.configFile, cwd)
       : undefined,
     noConfig: options.configFile === false,
+    npmRegistryDir: options.npmRegistryDir == null
+      ? undefined
+      : valueToUrl(options.npmRegistryDir, cwd),
+    pinNpmVersions: options.pinNpmVersions ?? false,
     cwd: path.toFileUrl(cwd).toString(),
   };
   return wasm.transform(newOptions);
 }
 
+/** Chains a source map to the output files (ex. one that the TypeScript
+ * compiler emits for them) with the source maps of the output files keyed
+ * by their URL, so that it maps to the original modules instead.
+ *
+ * @param sourceMap The source map to chain.
+ * @param mapUrl URL of the file that the source map is in, which its
+ * sources are relative to.
+ * @param outputSourceMaps Source map of each output file keyed by the URL
+ * that the output file was written to.
+ */
+export function chainSourceMaps(
+  sourceMap: string,
+  mapUrl: string,
+  outputSourceMaps: Record<string, string>,
+): string {
+  return wasm.chainSourceMaps(sourceMap, mapUrl, outputSourceMaps);
+}
+
+/** Gets the graph of a transform in the Graphviz DOT language. */
+export function graphToDot(graph: OutputGraph): string {
+  return wasm.graphToDot(graph);
+}
+
+export interface TransformWorkspaceOptions {
+  /** Layout of each emitted member package. */
+  packageManifest?: Omit<PackageManifestOptions, "entryPoints">;
+  shims?: Shim[];
+  testShims?: Shim[];
+  /** Mappings used in every member, which take precedence over the
+   * exports of the other members. */
+  mappings?: SpecifierMappings;
+  target: ScriptTarget;
+  polyfills?: Partial<Record<PolyfillName, boolean>>;
+  platform?: Platform;
+  conditions?: string[];
+  assetInlineLimit?: number;
+  sourceMaps?: SourceMaps;
+  /** Directory to cache the analysis of each module in, which has a
+   * directory for each member. */
+  incrementalCacheDir?: string;
+  /** Path or url to the workspace's deno.json.
+   *
+   * When not specified, the workspace is discovered from the cwd.
+   */
+  configFile?: string;
+  frozenLockfile?: boolean;
+  lockedDependencyVersions?: LockedDependencyVersions;
+  npmRegistryDir?: string;
+  pinNpmVersions?: boolean;
+  cwd: string;
+}
+
+export interface TransformWorkspaceMemberOutput {
+  name: string;
+  version: string;
+  /** Directory of the member's config file. */
+  dirPath: string;
+  /** Names of the member's exports (ex. `.` or `./sub`), which are in the
+   * same order as the entry points of the main environment. */
+  exports: string[];
+  output: TransformOutput;
+}
+
+/** Transforms each member of a Deno workspace that has a name and exports,
+ * using its exports as the entry points.
+ *
+ * An import of another member becomes a dependency on that member's package
+ * at its version rather than copying its modules into the output. */
+export function transformWorkspace(
+  options: TransformWorkspaceOptions,
+): Promise<TransformWorkspaceMemberOutput[]> {
+  const cwd = standardizePath(options.cwd, Deno.cwd());
+  return wasm.transformWorkspace({
+    ...options,
+    mappings: Object.fromEntries(
+      Object.entries(options.mappings ?? {}).map(([key, value]) => {
+        return [mapMappingKey(key, cwd), mapMappedSpecifier(value, cwd)];
+      }),
+    ),
+    shims: (options.shims ?? []).map((s) => mapShim(s, cwd)),
+    testShims: (options.testShims ?? []).map((s) => mapShim(s, cwd)),
+    polyfills: options.polyfills ?? {},
+    platform: options.platform ?? "node",
+    conditions: options.conditions ?? [],
+    assetInlineLimit: options.assetInlineLimit ?? 4096,
+    sourceMaps: options.sourceMaps ?? "none",
+    incrementalCacheDir: options.incrementalCacheDir == null
+      ? undefined
+      : valueToUrl(options.incrementalCacheDir, cwd),
+    configFile: options.configFile == null
+      ? undefined
+      : valueToUrl(options.configFile, cwd),
+    npmRegistryDir: options.npmRegistryDir == null
+      ? undefined
+      : valueToUrl(options.npmRegistryDir, cwd),
+    pinNpmVersions: options.pinNpmVersions ?? false,
+    cwd: path.toFileUrl(cwd).toString(),
+  });
+}
+
+function mapPackageManifestOptions(
+  options: PackageManifestOptions | undefined,
+  cwd: string,
+) {
+  return {
+    ...options,
+    entryPoints: (options?.entryPoints ?? []).map((e) => ({
+      kind: e.kind,
+      name: e.name,
+      specifier: valueToUrl(e.path, cwd),
+    })),
+  };
+}
+
 function mapMappingKey(key: string, cwd: string) {
   key = key.trim();
   if (/^[a-z]+:/i.test(key) || isRelativeOrAbsolutePath(key)) {
-    return valueToUrl(key, cwd);
+    return withTrailingSlashOf(key, valueToUrl(key, cwd));
   }
   // fall back to a path for a key like `mod.ts` that resolved to
   // one before bare specifiers were supported
@@ -210,6 +671,12 @@ function mapMappingKey(key: string, cwd: string) {
   return key;
 }
 
+/** Keeps the trailing slash of a directory mapping key, which
+ * resolving the path would otherwise remove. */
+function withTrailingSlashOf(key: string, url: string) {
+  return /[\\/]$/.test(key) && !url.endsWith("/") ? url + "/" : url;
+}
+
 function isRelativeOrAbsolutePath(value: string) {
   return /^\.\.?[\\/]/.test(value) || path.isAbsolute(value);
 }
@@ -243,11 +710,24 @@ function mapMappedSpecifier(
   } else {
     return {
       kind: "package",
-      value,
+      value: withDependencyKind(value),
     };
   }
 }
 
+/** Fills in the dependency kind from the deprecated `peerDependency`. */
+function withDependencyKind<
+  T extends { kind?: DependencyKind; peerDependency?: boolean },
+>(value: T): T {
+  if (value.kind != null || value.peerDependency == null) {
+    return value;
+  }
+  return {
+    ...value,
+    kind: value.peerDependency ? "peer" : "dependency",
+  };
+}
+
 type SerializableShim = { kind: "package"; value: PackageShim } | {
   kind: "module";
   value: ModuleShim;
@@ -259,7 +739,15 @@ function mapShim(value: Shim, cwd: string): SerializableShim {
     globalNames: value.globalNames.map(mapToGlobalName),
   };
   if (isPackageShim(newValue)) {
-    return { kind: "package", value: newValue };
+    return {
+      kind: "package",
+      value: {
+        ...newValue,
+        package: withDependencyKind(newValue.package),
+        typesPackage: newValue.typesPackage &&
+          withDependencyKind(newValue.typesPackage),
+      },
+    };
   } else {
     return {
       kind: "module",
@@ -3,14 +3,25 @@
 mod utils;
 
 use std::collections::HashMap;
+use std::rc::Rc;
 
 use anyhow::Context;
 use anyhow::Result;
+use dnt::EmitResult;
+use dnt::LocalNpmPackageInfoProvider;
+use dnt::LockedDependencyVersions;
 use dnt::MappedSpecifier;
 use dnt::ModuleSpecifier;
+use dnt::NpmPackageInfoProvider;
+use dnt::OutputDirDiff;
+use dnt::OutputGraph;
+use dnt::PackageManifestOptions;
+use dnt::Platform;
 use dnt::ScriptTarget;
 use dnt::Shim;
+use dnt::SourceMaps;
 use serde::Deserialize;
+use serde::Serialize;
 use utils::set_panic_hook;
 
 use deno_cache_dir::file_fetcher::HeaderMap;
@@ -198,19 +209,38 @@ fn response_headers_to_headermap(headers: JsValue) -> HeaderMap {
 pub struct TransformOptions {
   pub entry_points: Vec<String>,
   #[serde(default)]
+  pub entry_points_from_config: bool,
+  #[serde(default)]
   pub bin_entry_points: Vec<String>,
   pub test_entry_points: Vec<String>,
+  #[serde(default)]
+  pub package_manifest: PackageManifestOptions,
   pub shims: Vec<Shim>,
   pub test_shims: Vec<Shim>,
   pub mappings: HashMap<String, MappedSpecifier>,
   pub target: ScriptTarget,
   #[serde(default)]
   pub polyfills: HashMap<String, bool>,
+  #[serde(default)]
+  pub platform: Platform,
+  #[serde(default)]
+  pub conditions: Vec<String>,
+  #[serde(default = "default_asset_inline_limit")]
+  pub asset_inline_limit: usize,
+  #[serde(default)]
+  pub source_maps: SourceMaps,
+  pub incremental_cache_dir: Option<ModuleSpecifier>,
+  pub emit_dir: Option<ModuleSpecifier>,
+  pub diff_dir: Option<ModuleSpecifier>,
   pub import_map: Option<ModuleSpecifier>,
   pub config_file: Option<ModuleSpecifier>,
   #[serde(default)]
   pub no_config: bool,
   pub frozen_lockfile: Option<bool>,
+  pub locked_dependency_versions: Option<LockedDependencyVersions>,
+  pub npm_registry_dir: Option<ModuleSpecifier>,
+  #[serde(default)]
+  pub pin_npm_versions: bool,
   pub cwd: ModuleSpecifier,
 }
 
@@ -232,27 +262,200 @@ async fn transform_inner(options: JsValue) -> Result<JsValue, anyhow::Error> {
   // where it errored.
   // let options: TransformOptions = serde_wasm_bindgen::from_value(options)?;
 
+  let npm_package_info_provider =
+    npm_package_info_provider(options.npm_registry_dir.as_ref())?;
   let result = dnt::transform(
     sys_traits::impls::RealSys,
     WasmHttpClient { cached_only: false },
     dnt::TransformOptions {
       entry_points: parse_module_specifiers(options.entry_points)?,
+      entry_points_from_config: options.entry_points_from_config,
       bin_entry_points: parse_module_specifiers(options.bin_entry_points)?,
       test_entry_points: parse_module_specifiers(options.test_entry_points)?,
+      package_manifest: options.package_manifest,
       shims: options.shims,
       test_shims: options.test_shims,
       specifier_mappings: options.mappings,
       target: options.target,
       polyfills: options.polyfills,
+      platform: options.platform,
+      conditions: options.conditions,
+      asset_inline_limit: options.asset_inline_limit,
+      source_maps: options.source_maps,
+      incremental_cache_dir: options
+        .incremental_cache_dir
+        .as_ref()
+        .map(deno_path_util::url_to_file_path)
+        .transpose()?,
       import_map: options.import_map,
       config_file: options.config_file,
       no_config: options.no_config,
       frozen_lockfile: options.frozen_lockfile,
+      locked_dependency_versions: options.locked_dependency_versions,
+      npm_package_info_provider,
+      pin_npm_versions: options.pin_npm_versions,
+      cwd: deno_path_util::url_to_file_path(&options.cwd)?,
+    },
+  )
+  .await?;
+  // compared before emitting so the diff is against what was there before
+  let output_dir_diff = match &options.diff_dir {
+    Some(diff_dir) => Some(result.diff_output_dir(
+      &sys_traits::impls::RealSys,
+      &deno_path_util::url_to_file_path(diff_dir)?,
+    )?),
+    None => None,
+  };
+  let emit_result = match &options.emit_dir {
+    Some(emit_dir) => Some(result.emit(
+      &sys_traits::impls::RealSys,
+      &deno_path_util::url_to_file_path(emit_dir)?,
+    )?),
+    None => None,
+  };
+  Ok(to_js_value(&TransformResult {
+    output: result,
+    emit_result,
+    output_dir_diff,
+  }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TransformResult {
+  // flattening serializes this as a map, so it relies on `to_js_value`
+  // serializing maps as objects
+  #[serde(flatten)]
+  output: dnt::TransformOutput,
+  emit_result: Option<EmitResult>,
+  output_dir_diff: Option<OutputDirDiff>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransformWorkspaceOptions {
+  #[serde(default)]
+  pub package_manifest: PackageManifestOptions,
+  pub shims: Vec<Shim>,
+  pub test_shims: Vec<Shim>,
+  pub mappings: HashMap<String, MappedSpecifier>,
+  pub target: ScriptTarget,
+  #[serde(default)]
+  pub polyfills: HashMap<String, bool>,
+  #[serde(default)]
+  pub platform: Platform,
+  #[serde(default)]
+  pub conditions: Vec<String>,
+  #[serde(default = "default_asset_inline_limit")]
+  pub asset_inline_limit: usize,
+  #[serde(default)]
+  pub source_maps: SourceMaps,
+  pub incremental_cache_dir: Option<ModuleSpecifier>,
+  pub config_file: Option<ModuleSpecifier>,
+  pub frozen_lockfile: Option<bool>,
+  pub locked_dependency_versions: Option<LockedDependencyVersions>,
+  pub npm_registry_dir: Option<ModuleSpecifier>,
+  #[serde(default)]
+  pub pin_npm_versions: bool,
+  pub cwd: ModuleSpecifier,
+}
+
+#[wasm_bindgen(js_name = transformWorkspace)]
+pub async fn transform_workspace(options: JsValue) -> Result<JsValue, JsValue> {
+  set_panic_hook();
+
+  transform_workspace_inner(options)
+    .await
+    // need to include the anyhow context
+    .map_err(|err| format!("{err:#}").into())
+}
+
+async fn transform_workspace_inner(
+  options: JsValue,
+) -> Result<JsValue, anyhow::Error> {
+  #[allow(deprecated)]
+  let options: TransformWorkspaceOptions = options.into_serde()?;
+
+  let result = dnt::transform_workspace(
+    sys_traits::impls::RealSys,
+    WasmHttpClient { cached_only: false },
+    dnt::TransformWorkspaceOptions {
+      package_manifest: options.package_manifest,
+      shims: options.shims,
+      test_shims: options.test_shims,
+      specifier_mappings: options.mappings,
+      target: options.target,
+      polyfills: options.polyfills,
+      platform: options.platform,
+      conditions: options.conditions,
+      asset_inline_limit: options.asset_inline_limit,
+      source_maps: options.source_maps,
+      incremental_cache_dir: options
+        .incremental_cache_dir
+        .as_ref()
+        .map(deno_path_util::url_to_file_path)
+        .transpose()?,
+      config_file: options.config_file,
+      frozen_lockfile: options.frozen_lockfile,
+      locked_dependency_versions: options.locked_dependency_versions,
+      npm_package_info_provider: npm_package_info_provider(
+        options.npm_registry_dir.as_ref(),
+      )?,
+      pin_npm_versions: options.pin_npm_versions,
       cwd: deno_path_util::url_to_file_path(&options.cwd)?,
     },
   )
   .await?;
-  Ok(serde_wasm_bindgen::to_value(&result).unwrap())
+  Ok(to_js_value(&result))
+}
+
+/// Serializes the maps as plain objects, which is what the TS code declares.
+fn to_js_value(value: &impl Serialize) -> JsValue {
+  value
+    .serialize(
+      &serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true),
+    )
+    .unwrap()
+}
+
+#[wasm_bindgen(js_name = graphToDot)]
+pub fn graph_to_dot(graph: JsValue) -> Result<String, JsValue> {
+  #[allow(deprecated)]
+  let graph: OutputGraph = graph.into_serde().map_err(|err| err.to_string())?;
+  Ok(graph.to_dot())
+}
+
+#[wasm_bindgen(js_name = chainSourceMaps)]
+pub fn chain_source_maps(
+  source_map: String,
+  map_url: String,
+  output_source_maps: JsValue,
+) -> Result<String, JsValue> {
+  #[allow(deprecated)]
+  let output_source_maps: HashMap<ModuleSpecifier, String> = output_source_maps
+    .into_serde()
+    .map_err(|err| err.to_string())?;
+  let map_url =
+    ModuleSpecifier::parse(&map_url).map_err(|err| err.to_string())?;
+  dnt::chain_source_maps(&source_map, &map_url, &output_source_maps)
+    .map_err(|err| format!("{:#}", err).into())
+}
+
+/// Same as the default of the option in the TS code.
+fn default_asset_inline_limit() -> usize {
+  4096
+}
+
+fn npm_package_info_provider(
+  npm_registry_dir: Option<&ModuleSpecifier>,
+) -> Result<Option<Rc<dyn NpmPackageInfoProvider>>, anyhow::Error> {
+  match npm_registry_dir {
+    Some(url) => Ok(Some(Rc::new(LocalNpmPackageInfoProvider::new(
+      sys_traits::impls::RealSys,
+      deno_path_util::url_to_file_path(url)?,
+    )) as Rc<dyn NpmPackageInfoProvider>)),
+    None => Ok(None),
+  }
 }
 
 fn parse_module_specifiers(